@@ -1,61 +1,133 @@
-mod day01;
+use crate::util::error::{AocError, AocResult};
+
+pub mod day01;
 use day01::DAY1;
-mod day02;
+pub mod day02;
 use day02::DAY2;
-mod day03;
+pub mod day03;
 use day03::DAY3;
-mod day04;
+pub mod day04;
 use day04::DAY4;
-mod day05;
+pub mod day05;
 use day05::DAY5;
-mod day06;
+pub mod day06;
 use day06::DAY6;
-mod day07;
+pub mod day07;
 use day07::DAY7;
-mod day08;
+pub mod day08;
 use day08::DAY8;
-mod day09;
+pub mod day09;
 use day09::DAY9;
-mod day10;
+pub mod day10;
 use day10::DAY10;
-mod day11;
+pub mod day11;
 use day11::DAY11;
-mod day12;
+pub mod day12;
 use day12::DAY12;
-mod day13;
+pub mod day13;
 use day13::DAY13;
-mod day14;
+pub mod day14;
 use day14::DAY14;
-mod day15;
+pub mod day15;
 use day15::DAY15;
-mod day16;
+pub mod day16;
 use day16::DAY16;
-mod day18;
+pub mod day18;
 use day18::DAY18;
-mod day17;
+pub mod day17;
 use day17::DAY17;
-mod day19;
+pub mod day19;
 use day19::DAY19;
-mod day20;
+pub mod day20;
 use day20::DAY20;
-mod day21;
+pub mod day21;
 use day21::DAY21;
-mod day22;
+pub mod day22;
 use day22::DAY22;
-mod day23;
+pub mod day23;
 use day23::DAY23;
-mod day24;
+pub mod day24;
 use day24::DAY24;
-mod day25;
+pub mod day25;
 use day25::DAY25;
 // « add day import »
 
 pub struct Day {
-    pub puzzle1: fn(input: &String),
-    pub puzzle2: fn(input: &String)
+    // Parses the input into something both puzzles can be solved from, usually parse::<Solution> (see StructuredDay).
+    pub parse: fn(input: &String) -> AocResult<Box<dyn ParsedInput>>,
+    // Example inputs from the puzzle text, checked against the puzzle entry points by the examples test suite.
+    #[cfg(test)]
+    pub examples: &'static [Example],
+}
+
+impl Day {
+    // Parses the input and solves the first puzzle. Use parse directly to solve both without parsing twice.
+    pub fn puzzle1(&self, input: &String) -> AocResult<String> {
+        (self.parse)(input)?.part1()
+    }
+
+    pub fn puzzle2(&self, input: &String) -> AocResult<String> {
+        (self.parse)(input)?.part2()
+    }
+}
+
+// A day's solution, split in parsing the input and solving both puzzles from what was parsed. That way the runner only
+// needs to parse an input once, and the puzzles don't each repeat the parsing.
+pub trait StructuredDay {
+    type Parsed;
+
+    fn parse(input: &str) -> AocResult<Self::Parsed>;
+    fn part1(parsed: &Self::Parsed) -> AocResult<String>;
+    fn part2(parsed: &Self::Parsed) -> AocResult<String>;
 }
 
-pub fn get_day(day: i32) -> Result<Day, String> {
+// A parsed input, ready to solve the puzzles for. (What Day::parse returns, so days can be used without knowing what
+// they parse into.)
+pub trait ParsedInput {
+    fn part1(&self) -> AocResult<String>;
+    fn part2(&self) -> AocResult<String>;
+
+    fn part(&self, part: u8) -> AocResult<String> {
+        match part {
+            1 => self.part1(),
+            2 => self.part2(),
+            _ => Err(AocError::Unsupported(format!("Invalid part {}, expected 1 or 2", part)))
+        }
+    }
+}
+
+struct Parsed<D: StructuredDay>(D::Parsed);
+
+impl<D: StructuredDay> ParsedInput for Parsed<D> {
+    fn part1(&self) -> AocResult<String> {
+        D::part1(&self.0)
+    }
+
+    fn part2(&self) -> AocResult<String> {
+        D::part2(&self.0)
+    }
+}
+
+// Anything that goes wrong while parsing is a problem with the input. Most parsers still report String errors, which
+// would otherwise end up as logic errors.
+pub fn parse<D: StructuredDay + 'static>(input: &String) -> AocResult<Box<dyn ParsedInput>> {
+    let parsed = D::parse(input).map_err(|e| match e {
+        AocError::Logic(message) => AocError::Parse(message),
+        e => e,
+    })?;
+    Ok(Box::new(Parsed::<D>(parsed)))
+}
+
+// An example input with the expected answers (None for puzzles the example does not apply to, e.g. because the
+// solution depends on the shape of the real input).
+#[cfg(test)]
+pub struct Example {
+    pub input: &'static str,
+    pub puzzle1: Option<&'static str>,
+    pub puzzle2: Option<&'static str>,
+}
+
+pub fn get_day(day: i32) -> AocResult<Day> {
     match day {
         1 => Ok(DAY1),
         2 => Ok(DAY2),
@@ -83,6 +155,49 @@ pub fn get_day(day: i32) -> Result<Day, String> {
         24 => Ok(DAY24),
         25 => Ok(DAY25),
         // « add day match »
-        _ => Err(format!("No implementation yet for day {}", day))
+        _ => Err(AocError::Unsupported(format!("No implementation yet for day {}", day)))
     }
-}
\ No newline at end of file
+}
+
+// All days that have an implementation.
+pub fn implemented_days() -> Vec<i32> {
+    (1..=25).filter(|d| get_day(*d).is_ok()).collect()
+}
+
+#[cfg(test)]
+mod examples {
+    use crate::days::{get_day, implemented_days};
+
+    // Checks if the answer is one of the 'words' of the puzzle output (as the output also contains a description).
+    fn contains_answer(output: &str, answer: &str) -> bool {
+        output.split(|c: char| !c.is_alphanumeric() && c != '-').any(|word| word == answer)
+    }
+
+    #[test]
+    fn test_examples() {
+        let mut failures = vec![];
+
+        for day_num in implemented_days() {
+            let day = get_day(day_num).unwrap();
+
+            for (index, example) in day.examples.iter().enumerate() {
+                let input = example.input.to_string();
+                for (puzzle_num, expected) in [(1, example.puzzle1), (2, example.puzzle2)] {
+                    if let Some(answer) = expected {
+                        let output = match puzzle_num {
+                            1 => day.puzzle1(&input),
+                            _ => day.puzzle2(&input),
+                        };
+                        match output {
+                            Ok(output) if contains_answer(&output, answer) => {}
+                            Ok(output) => failures.push(format!("Day {} example {} puzzle {}: expected {}, got '{}'", day_num, index + 1, puzzle_num, answer, output)),
+                            Err(err) => failures.push(format!("Day {} example {} puzzle {}: expected {}, failed with {}", day_num, index + 1, puzzle_num, answer, err)),
+                        }
+                    }
+                }
+            }
+        }
+
+        assert!(failures.is_empty(), "Example failures:\n{}", failures.join("\n"));
+    }
+}
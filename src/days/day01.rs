@@ -1,18 +1,50 @@
-use crate::days::Day;
-use crate::util::number::{parse_i32};
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::number::parse_num;
+use crate::util::scanner::WordScanner;
 
 pub const DAY1: Day = Day {
-    puzzle1,
-    puzzle2
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let result: i32 = input.lines().map(|l| parse_calibration_line(l).unwrap()).sum();
-    println!("Puzzle 1: {}", result);
+struct Solution;
+
+impl StructuredDay for Solution {
+    type Parsed = Vec<CalibrationLine>;
+
+    fn parse(input: &str) -> AocResult<Vec<CalibrationLine>> {
+        Ok(input.lines().map(CalibrationLine::parse).collect())
+    }
+
+    fn part1(lines: &Vec<CalibrationLine>) -> AocResult<String> {
+        let result = lines.iter().enumerate()
+            .map(|(i, l)| l.digits.ok_or(format!("No digits in line {}", i + 1)))
+            .sum::<Result<i32, _>>()?;
+        Ok(format!("Puzzle 1: {}", result))
+    }
+
+    fn part2(lines: &Vec<CalibrationLine>) -> AocResult<String> {
+        let result = lines.iter().enumerate()
+            .map(|(i, l)| l.spelled.ok_or(format!("No digits in line {}", i + 1)))
+            .sum::<Result<i32, _>>()?;
+        Ok(format!("Puzzle 1: {}", result))
+    }
 }
-fn puzzle2(input: &String) {
-    let result: i32 = input.lines().map(|l| parse_calibration_line_v2(l).unwrap()).sum();
-    println!("Puzzle 1: {}", result);
+
+// The calibration value of a line, reading only the digits (puzzle 1) and reading spelled out digits as well (puzzle 2).
+// A line without any (spelled out) digits has no value, which only fails the puzzle that needs it.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+struct CalibrationLine {
+    digits: Option<i32>,
+    spelled: Option<i32>,
+}
+
+impl CalibrationLine {
+    fn parse(line: &str) -> Self {
+        Self { digits: parse_calibration_line(line).ok(), spelled: parse_calibration_line_v2(line).ok() }
+    }
 }
 
 // The newly-improved calibration document consists of lines of text;
@@ -21,62 +53,39 @@ fn puzzle2(input: &String) {
 // (in that order) to form a single two-digit number.
 fn parse_calibration_line(line: &str) -> Result<i32, String> {
     let digits: Vec<char> = line.chars().filter(|c| c.is_digit(10)).collect();
-    parse_i32(format!("{}{}", digits[0], digits.last().ok_or(format!("{}", "No digits in input"))?).as_str())
+    let first = digits.first().ok_or(format!("No digits in '{}'", line))?;
+    let last = digits.last().ok_or(format!("No digits in '{}'", line))?;
+    parse_num(format!("{}{}", first, last).as_str()).map_err(Into::into)
 }
 
 // Your calculation isn't quite right. It looks like some of the digits are actually spelled out
 // with letters: one, two, three, four, five, six, seven, eight, and nine also count as valid "digits".
-fn parse_calibration_line_v2(line: &str) -> Result<i32, String> {
-    let mut result: i32 = 0;
-
-    // Walk over the line and find the first (spelled out) digit
-    for i in 0..line.len() {
-        let chars: Vec<_> = line.chars().collect();
-        if chars[i].is_digit(10) {
-            result += chars[i].to_string().parse::<i32>().map_err(|e| format!("{}", e))?;
-            break;
-        }
-
-        let (_, check) = line.split_at(i);
-        if check.starts_with("one") { result = 1; break; }
-        else if check.starts_with("two") { result = 2; break; }
-        else if check.starts_with("three") { result = 3; break; }
-        else if check.starts_with("four") { result = 4; break; }
-        else if check.starts_with("five") { result = 5; break; }
-        else if check.starts_with("six") { result = 6; break; }
-        else if check.starts_with("seven") { result = 7; break; }
-        else if check.starts_with("eight") { result = 8; break; }
-        else if check.starts_with("nine") { result = 9; break; }
-    }
+const DIGITS: &[(&str, i32)] = &[
+    ("1", 1), ("2", 2), ("3", 3), ("4", 4), ("5", 5), ("6", 6), ("7", 7), ("8", 8), ("9", 9),
+];
+const SPELLED_DIGITS: &[(&str, i32)] = &[
+    ("one", 1), ("two", 2), ("three", 3), ("four", 4), ("five", 5), ("six", 6), ("seven", 7), ("eight", 8), ("nine", 9),
+];
 
-    result *= 10;
-
-    // Walk backwards and find the last (spelled out) digit
-    for i in (0..line.len()).rev() {
-        let chars: Vec<_> = line.chars().collect();
-        if chars[i].is_digit(10) {
-            result += chars[i].to_string().parse::<i32>().map_err(|e| format!("{}", e))?;
-            break;
-        }
-
-        let (check, _) = line.split_at(i + 1);
-        if check.ends_with("one") { result += 1; break; }
-        else if check.ends_with("two") { result += 2; break; }
-        else if check.ends_with("three") { result += 3; break; }
-        else if check.ends_with("four") { result += 4; break; }
-        else if check.ends_with("five") { result += 5; break; }
-        else if check.ends_with("six") { result += 6; break; }
-        else if check.ends_with("seven") { result += 7; break; }
-        else if check.ends_with("eight") { result += 8; break; }
-        else if check.ends_with("nine") { result += 9; break; }
-    }
+fn parse_calibration_line_v2(line: &str) -> Result<i32, String> {
+    parse_calibration_line_with(&WordScanner::from_tables(&[DIGITS, SPELLED_DIGITS]), line)
+}
 
-    Ok(result)
+fn parse_calibration_line_with(scanner: &WordScanner<i32>, line: &str) -> Result<i32, String> {
+    let (first, last) = scanner.first_and_last(line).ok_or(format!("No digits in '{}'", line))?;
+    Ok(first * 10 + last)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day01::{parse_calibration_line, parse_calibration_line_v2};
+    use crate::days::day01::{CalibrationLine, DIGITS, parse_calibration_line, parse_calibration_line_v2, parse_calibration_line_with};
+    use crate::util::scanner::WordScanner;
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT_1, puzzle1: Some("142"), puzzle2: None },
+        Example { input: TEST_INPUT_2, puzzle1: None, puzzle2: Some("281") },
+    ];
 
     #[test]
     fn test_parse_calibration_line() {
@@ -84,6 +93,14 @@ mod tests {
         assert_eq!(parse_calibration_line("pqr3stu8vwx"), Ok(38));
         assert_eq!(parse_calibration_line("a1b2c3d4e5f"), Ok(15));
         assert_eq!(parse_calibration_line("treb7uchet"), Ok(77));
+        assert_eq!(parse_calibration_line("nodigits"), Err("No digits in 'nodigits'".to_string()));
+    }
+
+    #[test]
+    fn test_calibration_line_parse() {
+        assert_eq!(CalibrationLine::parse("xtwone3four"), CalibrationLine { digits: Some(33), spelled: Some(24) });
+        assert_eq!(CalibrationLine::parse("eightwothree"), CalibrationLine { digits: None, spelled: Some(83) });
+        assert_eq!(CalibrationLine::parse("nodigits"), CalibrationLine { digits: None, spelled: None });
     }
 
     #[test]
@@ -98,5 +115,30 @@ mod tests {
 
         // Unsure if this should work, but I want it to.
         assert_eq!(parse_calibration_line_v2("oneight"), Ok(18));
+        assert!(parse_calibration_line_v2("nodigits").is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_calibration_line_with() {
+        let dutch = WordScanner::from_tables(&[DIGITS, &[("een", 1), ("twee", 2), ("drie", 3), ("acht", 8)]]);
+        assert_eq!(parse_calibration_line_with(&dutch, "xtweeacht"), Ok(28));
+        assert_eq!(parse_calibration_line_with(&dutch, "driexone"), Ok(33));
+    }
+
+    const TEST_INPUT_1: &str = "\
+        1abc2\n\
+        pqr3stu8vwx\n\
+        a1b2c3d4e5f\n\
+        treb7uchet\
+    ";
+
+    const TEST_INPUT_2: &str = "\
+        two1nine\n\
+        eightwothree\n\
+        abcone2threexyz\n\
+        xtwone3four\n\
+        4nineeightseven2\n\
+        zoneight234\n\
+        7pqrstsixteen\
+    ";
+}
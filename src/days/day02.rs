@@ -1,56 +1,83 @@
-use crate::days::Day;
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::multiset::MultiSet;
 use crate::util::parser::Parser;
 
 pub const DAY2: Day = Day {
-    puzzle1,
-    puzzle2
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let games = parse_input(input).unwrap();
-    let bag = Bag { red: 12, green: 13, blue: 14 };
+struct Solution;
 
-    let possible_games = filter_possible_games(games, &bag);
-    let result = possible_games.iter().map(|g| g.id).sum::<isize>();
+impl StructuredDay for Solution {
+    type Parsed = Vec<Game>;
 
-    println!("Sum of possible game IDs: {}", result);
-}
-fn puzzle2(input: &String) {
-    let games = parse_input(input).unwrap();
+    fn parse(input: &str) -> AocResult<Vec<Game>> {
+        parse_input(input).map_err(Into::into)
+    }
+
+    fn part1(games: &Vec<Game>) -> AocResult<String> {
+        let bag = cubes(&[(Colour::Red, 12), (Colour::Green, 13), (Colour::Blue, 14)]);
+
+        let possible_games = filter_possible_games(games.clone(), &bag);
+        let result = possible_games.iter().map(|g| g.id).sum::<isize>();
+
+        Ok(format!("Sum of possible game IDs: {}", result))
+    }
 
-    let minimum_bags: Vec<_> = games.iter().map(|g| get_smallest_bag_for_game(g).unwrap()).collect();
-    let result: isize = minimum_bags.iter().map(|b| b.get_power()).sum();
+    fn part2(games: &Vec<Game>) -> AocResult<String> {
+        let minimum_bags = games.iter()
+            .map(|g| get_smallest_bag_for_game(g).ok_or(format!("Game {} has no pulls", g.id)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let result: usize = minimum_bags.iter().map(CubeSet::power).sum();
 
-    println!("Sum of power of minimum bags: {}", result);
+        Ok(format!("Sum of power of minimum bags: {}", result))
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Default, Debug)]
 struct Game {
     id: isize,
-    pulls: Vec<Pull>
+    pulls: Vec<CubeSet>
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
-struct Pull {
-    red: isize,
-    green: isize,
-    blue: isize
+// Any colour name goes, even though the puzzle only uses red, green and blue.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+enum Colour {
+    Red,
+    Green,
+    Blue,
+    Other(String),
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
-struct Bag {
-    red: isize,
-    green: isize,
-    blue: isize
+impl Colour {
+    fn from_name(name: String) -> Colour {
+        match name.as_str() {
+            "red" => Colour::Red,
+            "green" => Colour::Green,
+            "blue" => Colour::Blue,
+            _ => Colour::Other(name),
+        }
+    }
 }
 
-impl Bag {
-    // The power of a set of cubes is equal to the numbers of red, green, and blue cubes multiplied together.
-    pub fn get_power(&self) -> isize {
-        self.red * self.green * self.blue
+// Cubes by colour.
+type CubeSet = MultiSet<Colour>;
+
+impl CubeSet {
+    // The power of a set of cubes is equal to the numbers of red, green, and blue cubes multiplied together. (So a bag
+    // without any cubes of one of those colours has a power of 0, and other colours don't count.)
+    fn power(&self) -> usize {
+        self.count(&Colour::Red) * self.count(&Colour::Green) * self.count(&Colour::Blue)
     }
 }
 
+fn cubes(counts: &[(Colour, usize)]) -> CubeSet {
+    counts.iter().cloned().collect()
+}
+
 fn parse_input(input: &str) -> Result<Vec<Game>, String> {
     input.lines().map(|l| parse_game(l)).collect()
 }
@@ -61,53 +88,49 @@ fn parse_game(input: &str) -> Result<Game, String> {
     parser.literal("Game")?;
     let id = parser.isize()?;
     parser.literal(":")?;
-    let mut pulls: Vec<Pull> = vec!();
-    let mut pull = Pull::default();
+    let mut pulls: Vec<CubeSet> = vec!();
+    let mut pull = CubeSet::new();
 
     while !parser.is_exhausted() {
-        let amount = parser.isize()?;
-        match parser.one_of(vec!["red", "green", "blue"])? {
-            "red" => pull.red += amount,
-            "green" => pull.green += amount,
-            "blue" => pull.blue += amount,
-            other => return Err(format!("Wrong colour {}", other))
-        }
-
-        if parser.is_exhausted() {
+        let amount = parser.usize()?;
+        let colour = Colour::from_name(parser.word()?);
+        pull.add(colour, amount);
+
+        if parser.peek_literal(",") {
+            parser.literal(",")?;
+        } else if parser.peek_literal(";") {
+            parser.literal(";")?;
             pulls.push(pull);
-            break;
-        }
-
-        match parser.one_of(vec![",", ";"])? {
-            "," => {},
-            ";" => {
-                pulls.push(pull);
-                pull = Pull::default();
-            },
-            other => return Err(format!("Wrong separator {}", other))
+            pull = CubeSet::new();
+        } else {
+            parser.ensure_exhausted()?;
+            pulls.push(pull);
+            pull = CubeSet::new();
         }
     }
 
     Ok(Game { id, pulls })
 }
 
-fn filter_possible_games(games: Vec<Game>, bag: &Bag) -> Vec<Game> {
+fn filter_possible_games(games: Vec<Game>, bag: &CubeSet) -> Vec<Game> {
     games.into_iter()
-        .filter(|g| g.pulls.iter().all(|p| p.red <= bag.red && p.green <= bag.green && p.blue <= bag.blue))
+        .filter(|g| g.pulls.iter().all(|p| p.is_subset_of(bag)))
         .collect()
 }
 
-fn get_smallest_bag_for_game(game: &Game) -> Option<Bag> {
-    let red: isize = game.pulls.iter().map(|p| p.red).max()?;
-    let green: isize = game.pulls.iter().map(|p| p.green).max()?;
-    let blue: isize = game.pulls.iter().map(|p| p.blue).max()?;
-
-    Some(Bag { red, green, blue })
+fn get_smallest_bag_for_game(game: &Game) -> Option<CubeSet> {
+    game.pulls.iter().cloned().reduce(|bag, pull| bag.union(&pull))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day02::{Bag, filter_possible_games, Game, get_smallest_bag_for_game, parse_game, parse_input, Pull};
+    use crate::days::day02::{cubes, filter_possible_games, Game, get_smallest_bag_for_game, parse_game, parse_input, Colour};
+    use crate::days::day02::Colour::{Blue, Green, Red};
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("8"), puzzle2: Some("2286") },
+    ];
 
     const TEST_INPUT: &str = "\
 Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green\n\
@@ -122,19 +145,24 @@ Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green\
         assert_eq!(parse_game("Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green"), Ok(Game {
             id: 1,
             pulls: vec![
-                Pull { red: 4, green: 0, blue: 3 },
-                Pull { red: 1, green: 2, blue: 6 },
-                Pull { red: 0, green: 2, blue: 0 }
+                cubes(&[(Red, 4), (Blue, 3)]),
+                cubes(&[(Red, 1), (Green, 2), (Blue, 6)]),
+                cubes(&[(Green, 2)]),
             ]
         }));
         assert_eq!(parse_game("Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red"), Ok(Game {
             id: 3,
             pulls: vec![
-                Pull { red: 20, green: 8, blue: 6 },
-                Pull { red: 4, green: 13, blue: 5 },
-                Pull { red: 1, green: 5, blue: 0 }
+                cubes(&[(Red, 20), (Green, 8), (Blue, 6)]),
+                cubes(&[(Red, 4), (Green, 13), (Blue, 5)]),
+                cubes(&[(Red, 1), (Green, 5)]),
             ]
         }));
+        // Other colours are fine too
+        assert_eq!(parse_game("Game 7: 2 purple, 1 red, 3 purple"), Ok(Game { id: 7, pulls: vec![cubes(&[(Colour::Other("purple".to_string()), 5), (Red, 1)])] }));
+        assert!(parse_game("Game 7: 2 purple, 1").is_err());
+        // Only exact names are the puzzle's colours
+        assert_eq!(parse_game("Game 8: 2 reddish, 1 blue"), Ok(Game { id: 8, pulls: vec![cubes(&[(Colour::Other("reddish".to_string()), 2), (Blue, 1)])] }));
     }
 
     #[test]
@@ -148,9 +176,9 @@ Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green\
         assert_eq!(games[3], Game {
             id: 4,
             pulls: vec![
-                Pull { red: 3, green: 1, blue: 6 },
-                Pull { red: 6, green: 3, blue: 0 },
-                Pull { red: 14, green: 3, blue: 15 },
+                cubes(&[(Red, 3), (Green, 1), (Blue, 6)]),
+                cubes(&[(Red, 6), (Green, 3)]),
+                cubes(&[(Red, 14), (Green, 3), (Blue, 15)]),
             ]
         })
     }
@@ -158,7 +186,7 @@ Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green\
     #[test]
     fn test_filter_possible_games() {
         let games = parse_input(TEST_INPUT).unwrap();
-        let filtered_games = filter_possible_games(games, &Bag { red: 12, green: 13, blue: 14 });
+        let filtered_games = filter_possible_games(games, &cubes(&[(Red, 12), (Green, 13), (Blue, 14)]));
 
         assert_eq!(filtered_games.len(), 3);
         assert_eq!(filtered_games.iter().map(|g| g.id).collect::<Vec<_>>(), vec![1, 2, 5]);
@@ -168,21 +196,33 @@ Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green\
     fn test_get_smallest_bag_for_game() {
         let games = parse_input(TEST_INPUT).unwrap();
 
-        assert_eq!(get_smallest_bag_for_game(&games[0]), Some(Bag { red: 4, green: 2, blue: 6 }));
-        assert_eq!(get_smallest_bag_for_game(&games[1]), Some(Bag { red: 1, green: 3, blue: 4 }));
-        assert_eq!(get_smallest_bag_for_game(&games[2]), Some(Bag { red: 20, green: 13, blue: 6 }));
-        assert_eq!(get_smallest_bag_for_game(&games[3]), Some(Bag { red: 14, green: 3, blue: 15 }));
-        assert_eq!(get_smallest_bag_for_game(&games[4]), Some(Bag { red: 6, green: 3, blue: 2 }));
+        assert_eq!(get_smallest_bag_for_game(&games[0]), Some(cubes(&[(Red, 4), (Green, 2), (Blue, 6)])));
+        assert_eq!(get_smallest_bag_for_game(&games[1]), Some(cubes(&[(Red, 1), (Green, 3), (Blue, 4)])));
+        assert_eq!(get_smallest_bag_for_game(&games[2]), Some(cubes(&[(Red, 20), (Green, 13), (Blue, 6)])));
+        assert_eq!(get_smallest_bag_for_game(&games[3]), Some(cubes(&[(Red, 14), (Green, 3), (Blue, 15)])));
+        assert_eq!(get_smallest_bag_for_game(&games[4]), Some(cubes(&[(Red, 6), (Green, 3), (Blue, 2)])));
+        assert_eq!(get_smallest_bag_for_game(&Game { id: 6, pulls: vec![] }), None);
     }
 
     #[test]
-    fn test_bag_get_power() {
+    fn test_power() {
         // The power of the minimum set of cubes in game 1 is 48.
         // In games 2-5 it was 12, 1560, 630, and 36, respectively.
-        assert_eq!((Bag { red: 4, green: 2, blue: 6 }).get_power(), 48);
-        assert_eq!((Bag { red: 1, green: 3, blue: 4 }).get_power(), 12);
-        assert_eq!((Bag { red: 20, green: 13, blue: 6 }).get_power(), 1560);
-        assert_eq!((Bag { red: 14, green: 3, blue: 15 }).get_power(), 630);
-        assert_eq!((Bag { red: 6, green: 3, blue: 2 }).get_power(), 36);
+        assert_eq!(cubes(&[(Red, 4), (Green, 2), (Blue, 6)]).power(), 48);
+        assert_eq!(cubes(&[(Red, 1), (Green, 3), (Blue, 4)]).power(), 12);
+        assert_eq!(cubes(&[(Red, 20), (Green, 13), (Blue, 6)]).power(), 1560);
+        assert_eq!(cubes(&[(Red, 14), (Green, 3), (Blue, 15)]).power(), 630);
+        assert_eq!(cubes(&[(Red, 6), (Green, 3), (Blue, 2)]).power(), 36);
+        assert_eq!(cubes(&[(Red, 6), (Green, 3), (Blue, 2), (Colour::Other("purple".to_string()), 2)]).power(), 36);
+    }
+
+    #[test]
+    fn test_power_of_game_without_a_colour() {
+        // A game that never pulls a green cube doesn't need any in the bag, so its power is 0.
+        let game = parse_game("Game 6: 3 blue, 4 red; 1 red, 6 blue").unwrap();
+        let bag = get_smallest_bag_for_game(&game).unwrap();
+
+        assert_eq!(bag, cubes(&[(Red, 4), (Blue, 6)]));
+        assert_eq!(bag.power(), 0);
     }
-}
\ No newline at end of file
+}
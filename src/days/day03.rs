@@ -1,118 +1,98 @@
-use crate::days::Day;
-use crate::util::geometry::{Bounds, Point};
-use crate::util::number::parse_isize;
+use std::collections::HashMap;
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::geometry::{Grid, NumberSpan, Point};
 
 pub const DAY3: Day = Day {
-    puzzle1,
-    puzzle2,
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let schematic = parse_input(input).unwrap();
-    let part_numbers = get_part_numbers(&schematic);
+struct Solution;
 
-    let result: isize = part_numbers.iter().sum();
-    println!("Sum of part numbers: {}", result);
-}
+impl StructuredDay for Solution {
+    type Parsed = Schematic;
+
+    fn parse(input: &str) -> AocResult<Schematic> {
+        parse_input(input)
+    }
+
+    fn part1(schematic: &Schematic) -> AocResult<String> {
+        let part_numbers = get_part_numbers(schematic);
 
-fn puzzle2(input: &String) {
-    let schematic = parse_input(input).unwrap();
-    let gear_ratios = get_gear_ratios(&schematic);
+        let result: isize = part_numbers.iter().sum();
+        Ok(format!("Sum of part numbers: {}", result))
+    }
+
+    fn part2(schematic: &Schematic) -> AocResult<String> {
+        let gear_ratios = get_gear_ratios(schematic);
 
-    let result: isize = gear_ratios.iter().map(|(_, r)| r).sum();
-    println!("Sum of gear ratios: {}", result);
+        let result: isize = gear_ratios.iter().map(|(_, r)| r).sum();
+        Ok(format!("Sum of gear ratios: {}", result))
+    }
 }
 
 // Any number that touches a symbol (also diagonal) is a part number
-// Parse to a structures:
-// - Number + area for symbols
-// - Symbol + point
-
-#[derive(Eq, PartialEq, Debug, Copy, Clone)]
-struct Number {
-    number: isize,
-    bounds: Bounds,
-}
+// The schematic is just a grid of characters; numbers are extracted as spans, and symbols are anything that is
+// neither a digit nor a '.'.
 
-#[derive(Eq, PartialEq, Debug, Copy, Clone)]
-struct Symbol {
-    symbol: char,
-    point: Point,
-}
+type Schematic = Grid<char>;
 
-#[derive(Eq, PartialEq, Debug, Clone)]
-struct Schematic {
-    numbers: Vec<Number>,
-    symbols: Vec<Symbol>,
+fn is_symbol(c: &char) -> bool {
+    !c.is_ascii_digit() && '.'.ne(c)
 }
 
-fn parse_input(input: &str) -> Result<Schematic, String> {
-    let mut numbers: Vec<Number> = vec!();
-    let mut symbols: Vec<Symbol> = vec!();
-
-    let lines: Vec<_> = input.lines().collect();
-    for y in 0..lines.len() {
-        let chars: Vec<_> = lines[y].chars().collect();
-        let mut current_number: isize = 0;
-        let mut current_number_start: Option<usize> = None;
-
-        for x in 0..chars.len() {
-            match chars[x] {
-                digit if digit.is_numeric() => {
-                    if current_number_start.is_none() {
-                        current_number_start = Some(x)
-                    }
-
-                    let value: isize = parse_isize(digit.to_string().as_str())?;
-                    current_number *= 10;
-                    current_number += value;
-                }
-                symbol => {
-                    if let Some(start_x) = current_number_start {
-                        let mut bounds = Bounds::try_from_tlbr(y, start_x, y, x - 1)?;
-                        bounds.grow(1);
-                        numbers.push(Number { number: current_number, bounds });
-                        current_number = 0;
-                        current_number_start = None;
-                    }
-
-                    if symbol != '.' {
-                        symbols.push(Symbol { symbol, point: (x, y).try_into()? });
-                    }
-                }
-            }
-        }
-
-        if let Some(start_x) = current_number_start {
-            let mut bounds = Bounds::try_from_tlbr(y, start_x, y, chars.len() - 1)?;
-            bounds.grow(1);
-            numbers.push(Number { number: current_number, bounds });
-        }
-    }
+fn parse_input(input: &str) -> AocResult<Schematic> {
+    input.parse()
+}
 
-    Ok(Schematic { numbers, symbols })
+fn get_adjacent_symbols(schematic: &Schematic, number: &NumberSpan) -> Vec<(Point, char)> {
+    // Everything adjacent to the number is within its bounds grown by one (and the number itself isn't a symbol).
+    let mut around = number.bounds;
+    around.grow(1);
+
+    let mut symbols: Vec<_> = around.intersect(&schematic.bounds).iter()
+        .flat_map(|b| b.points())
+        .filter_map(|p| schematic.get(&p).filter(is_symbol).map(|c| (p, c)))
+        .collect();
+    symbols.sort_by_key(|(p, _)| *p);
+    symbols
 }
 
 fn get_part_numbers(schematic: &Schematic) -> Vec<isize> {
-    schematic.numbers.iter().filter(|n| schematic.symbols.iter().any(|s| n.bounds.contains(&s.point)))
-        .map(|n| n.number)
+    schematic.extract_number_spans().iter()
+        .filter(|n| !get_adjacent_symbols(schematic, n).is_empty())
+        .map(|n| n.value)
         .collect()
 }
 
-fn get_gear_ratios(schematic: &Schematic) -> Vec<(&Symbol, isize)> {
+fn get_gear_ratios(schematic: &Schematic) -> Vec<(Point, isize)> {
     // A gear is a '*' symbol with two numbers adjacent. The ratio is the multiplication of both
-    schematic.symbols.iter()
-        .filter(|s| s.symbol == '*')
-        .map(|s| (s, schematic.numbers.iter().filter(|n| n.bounds.contains(&s.point)).collect::<Vec<_>>()))
+    let mut gears: HashMap<Point, Vec<isize>> = HashMap::new();
+    for number in schematic.extract_number_spans() {
+        get_adjacent_symbols(schematic, &number).iter()
+            .filter(|(_, c)| '*'.eq(c))
+            .for_each(|(p, _)| gears.entry(*p).or_default().push(number.value));
+    }
+
+    let mut result: Vec<_> = gears.into_iter()
         .filter(|(_, l)| l.len() == 2)
-        .map(|(s, l)| (s, l.iter().map(|n| n.number).reduce(|l,r| l*r).unwrap()))
-        .collect()
+        .map(|(p, l)| (p, l.iter().product()))
+        .collect();
+    result.sort();
+    result
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day03::{get_gear_ratios, get_part_numbers, parse_input};
+    use crate::days::day03::{get_adjacent_symbols, get_gear_ratios, get_part_numbers, parse_input};
     use crate::util::geometry::Bounds;
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("4361"), puzzle2: Some("467835") },
+    ];
 
     const TEST_INPUT: &str = "\
         467..114..\n\
@@ -133,11 +113,12 @@ mod tests {
         assert!(result.is_ok(), "Expected OK, but got Err({})", result.err().unwrap());
 
         let schematic = result.unwrap();
-        assert_eq!(schematic.numbers.iter().map(|s| s.number).collect::<Vec<_>>(), vec![467, 114, 35, 633, 617, 58, 592, 755, 664, 598]);
-        assert_eq!(schematic.symbols.iter().map(|s| s.symbol).collect::<Vec<_>>(), vec!['*', '#', '*', '+', '$', '*']);
+        let numbers = schematic.extract_number_spans();
+        assert_eq!(numbers.iter().map(|s| s.value).collect::<Vec<_>>(), vec![467, 114, 35, 633, 617, 58, 592, 755, 664, 598]);
+        assert_eq!(numbers[0].bounds, Bounds::from_tlbr(0, 0, 0, 2));
 
-        assert_eq!(schematic.numbers[0].bounds, Bounds::from_tlbr(-1, -1, 1, 3));
-        assert_eq!(schematic.symbols[0].point, (3, 1).into());
+        assert_eq!(get_adjacent_symbols(&schematic, &numbers[0]), vec![((3, 1).into(), '*')]);
+        assert_eq!(get_adjacent_symbols(&schematic, &numbers[1]), vec![]);
     }
 
     #[test]
@@ -153,8 +134,6 @@ mod tests {
         let schematic = parse_input(TEST_INPUT).unwrap();
         let result = get_gear_ratios(&schematic);
 
-        assert_eq!(result.len(), 2);
-        assert_eq!(result[0], (&schematic.symbols[0], 16345));
-        assert_eq!(result[1], (&schematic.symbols[5], 451490));
+        assert_eq!(result, vec![((3, 1).into(), 16345), ((5, 8).into(), 451490)]);
     }
-}
\ No newline at end of file
+}
@@ -1,24 +1,34 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::str::FromStr;
-use crate::days::Day;
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
 use crate::util::parser::Parser;
 
 pub const DAY4: Day = Day {
-    puzzle1,
-    puzzle2
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let cards = input.lines().map(|l| l.parse::<ScratchCard>().unwrap());
-    let total_points = cards.map(|c| c.points()).sum::<u32>();
+struct Solution;
 
-    println!("Sum of card points: {}", total_points);
-}
-fn puzzle2(input: &String) {
-    let cards = input.lines().map(|l| l.parse::<ScratchCard>()).collect::<Result<Vec<_>, _>>().unwrap();
+impl StructuredDay for Solution {
+    type Parsed = Vec<ScratchCard>;
+
+    fn parse(input: &str) -> AocResult<Vec<ScratchCard>> {
+        input.lines().map(|l| l.parse::<ScratchCard>()).collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    fn part1(cards: &Vec<ScratchCard>) -> AocResult<String> {
+        let total_points = cards.iter().map(|c| c.points()).sum::<u32>();
 
-    let total_cards = get_total_cards(cards);
-    println!("Your cards resulted in a pile of {} cards.", total_cards);
+        Ok(format!("Sum of card points: {}", total_points))
+    }
+
+    fn part2(cards: &Vec<ScratchCard>) -> AocResult<String> {
+        let total_cards = get_total_cards(cards)?.iter().sum::<usize>();
+        Ok(format!("Your cards resulted in a pile of {} cards.", total_cards))
+    }
 }
 
 #[derive(Eq, PartialEq, Clone, Debug, Default)]
@@ -58,60 +68,61 @@ impl FromStr for ScratchCard {
         let mut winning_numbers: Vec<usize> = vec!();
         let mut card_numbers: Vec<usize> = vec!();
 
-        loop {
-            match parser.usize() {
-                Ok(number) => winning_numbers.push(number),
-                Err(..) => break
-            }
+        while parser.peek_usize().is_some() {
+            winning_numbers.push(parser.usize()?);
         }
         parser.literal("|")?;
-        loop {
-            match parser.usize() {
-                Ok(number) => card_numbers.push(number),
-                Err(..) => break
-            }
+        while parser.peek_usize().is_some() {
+            card_numbers.push(parser.usize()?);
         }
+        parser.ensure_exhausted()?;
 
         Ok(Self { id, winning_numbers, card_numbers })
     }
 }
 
-fn get_total_cards(initial_cards: Vec<ScratchCard>) -> usize {
+// The amount of winning numbers on each card, in the order of the pile.
+fn get_winners_per_card(cards: &[ScratchCard]) -> Vec<usize> {
+    cards.iter().map(|c| c.matching_numbers()).collect()
+}
+
+// Returns how many copies (including the original) we end up with of each card, in the order of the pile.
+// Fails for a pile that has the same card more than once, as we can't tell which one a copy would be of.
+fn get_total_cards(cards: &[ScratchCard]) -> Result<Vec<usize>, String> {
+    let mut ids = HashSet::new();
+    if let Some(card) = cards.iter().find(|c| !ids.insert(c.id)) {
+        return Err(format!("Card {} is in the pile more than once", card.id));
+    }
+
     // Cards actually win (copies!) of other cards.
     // If card 1 has 4 matching numbers, it yields an extra 2, 3, 4, and 5 card.
     // We need to 'repeat' this until we no longer get any winnings,
     // and get the total amount of cards (including the originals)
 
-    // We first make a mapping of each initial card and how much numbers match
-    // We then use that mapping to determine how much copies should be added to the pile.
-    // Since cards only win cards _after_ them, we can simplify the process a bit by handling them
-    // from last to first.
-    let cards: HashMap<usize, &ScratchCard> = HashMap::from_iter(initial_cards.iter().map(|c| (c.id, c)));
-
-    let mut winnings_map: HashMap<usize, usize> = HashMap::new();
-    let mut current_id = initial_cards.iter().max_by_key(|s| s.id).unwrap().id;
-
-    loop {
-        let card = cards.get(&current_id).unwrap();
-        let matches = card.matching_numbers();
-        let mut winnings = matches; // We win at least a single copy of each won card
-        for offset in 1..=matches {
-            // And whatever all our won cards would win us.
-            winnings += winnings_map.get(&(current_id + offset)).unwrap_or(&0);
+    // Since cards only win cards _after_ them, by the time we get to a card we know exactly how many copies of it we
+    // have, and every one of those wins a copy of each of the next `winners` cards. Cards never win past the end of
+    // the pile, and we go by position, so the card IDs don't matter.
+    let winners = get_winners_per_card(cards);
+    let mut copies = vec![1; cards.len()];
+
+    for (index, count) in winners.iter().enumerate() {
+        let end = (index + 1 + count).min(copies.len());
+        for won in index + 1..end {
+            copies[won] += copies[index];
         }
-        winnings_map.insert(current_id, winnings);
-        if current_id == 1 { break; }
-        current_id -= 1;
     }
 
-    // We now know how much each copy of a card wins, so all we need is to add all totals
-    // We start with a single copy of all cards, and we add all the winnings they accumulated
-    initial_cards.len() + winnings_map.values().sum::<usize>()
+    Ok(copies)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day04::{get_total_cards, ScratchCard};
+    use crate::days::day04::{get_total_cards, get_winners_per_card, ScratchCard};
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("13"), puzzle2: Some("30") },
+    ];
 
     const TEST_INPUT: &str = "\
         Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53\n\
@@ -151,6 +162,51 @@ mod tests {
     fn test_get_total_cards() {
         let cards = TEST_INPUT.lines().map(|l| l.parse::<ScratchCard>()).collect::<Result<Vec<_>, _>>().unwrap();
 
-        assert_eq!(get_total_cards(cards), 30);
+        assert_eq!(get_total_cards(&cards), Ok(vec![1, 2, 4, 8, 14, 1]));
+        assert_eq!(get_total_cards(&cards).unwrap().iter().sum::<usize>(), 30);
+    }
+
+    #[test]
+    fn test_get_winners_per_card() {
+        let cards = TEST_INPUT.lines().map(|l| l.parse::<ScratchCard>()).collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(get_winners_per_card(&cards), vec![4, 2, 2, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_get_total_cards_non_contiguous_ids() {
+        // IDs are only labels; winning past the end of the pile yields nothing.
+        let cards = "\
+            Card 7: 1 2 | 1 2\n\
+            Card 3: 1 | 1\n\
+            Card 12: 1 2 3 | 1 2 3\
+        ".lines().map(|l| l.parse::<ScratchCard>()).collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(get_total_cards(&cards), Ok(vec![1, 2, 4]));
+    }
+
+    fn parse_cards(input: &str) -> Vec<ScratchCard> {
+        input.lines().map(|l| l.parse::<ScratchCard>()).collect::<Result<Vec<_>, _>>().unwrap()
+    }
+
+    #[test]
+    fn test_get_total_cards_gaps_and_offsets() {
+        // The example with gaps in the IDs (and starting at 10) gives the same copies, as only the order matters
+        let renumbered: String = TEST_INPUT.lines().zip([10, 11, 14, 15, 20, 21])
+            .map(|(line, id)| format!("Card {}:{}\n", id, line.split_once(':').unwrap().1))
+            .collect();
+        assert_eq!(get_total_cards(&parse_cards(&renumbered)), Ok(vec![1, 2, 4, 8, 14, 1]));
+
+        // Same for a pile that's not in order of the IDs
+        let shuffled = "Card 3: 1 2 | 1 2\nCard 1: 1 | 1\nCard 2: 5 | 6";
+        assert_eq!(get_total_cards(&parse_cards(shuffled)), Ok(vec![1, 2, 4]));
+
+        assert_eq!(get_total_cards(&[]), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_get_total_cards_duplicate_ids() {
+        let cards = parse_cards("Card 1: 1 | 1\nCard 2: 1 | 1\nCard 1: 1 | 1");
+        assert_eq!(get_total_cards(&cards), Err("Card 1 is in the pile more than once".to_string()));
     }
 }
\ No newline at end of file
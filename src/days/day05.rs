@@ -1,24 +1,75 @@
 use std::ops::Range;
 use std::str::FromStr;
-use crate::days::Day;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::number::parse_num;
 use crate::util::parser::Parser;
+use crate::util::progress::{self, ProgressReporter};
+use crate::util::visualize::{self, Visualizer};
+use crate::util::{log, options, threads};
 
 pub const DAY5: Day = Day {
-    puzzle1,
-    puzzle2,
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let almanac = input.parse::<Almanac>().unwrap();
+struct Solution;
 
-    let lowest_location = almanac.initial_seeds.iter().map(|s| almanac.get_location(s)).min().unwrap();
-    println!("Lowest location of initial seeds: {}", lowest_location);
+impl StructuredDay for Solution {
+    type Parsed = Almanac;
+
+    fn parse(input: &str) -> AocResult<Almanac> {
+        input.parse::<Almanac>().map_err(Into::into)
+    }
+
+    fn part1(almanac: &Almanac) -> AocResult<String> {
+        let lowest_location = almanac.initial_seeds.iter().map(|s| almanac.get_location(s)).min().ok_or("No seeds in the almanac")?;
+        Ok(format!("Lowest location of initial seeds: {}", lowest_location))
+    }
+
+    fn part2(almanac: &Almanac) -> AocResult<String> {
+        if visualize::is_enabled() {
+            visualize::show("Seed ranges through the almanac", &almanac.get_range_flow(&almanac.get_seed_ranges()?));
+        }
+        let search = Search::new(options::get(METHOD_OPTION).as_deref(), options::get(MAX_RANGES_OPTION).as_deref())?;
+        let threads = threads::available();
+        Ok(format!("Lowest location of ranges: {}", almanac.find_lowest_location(search, threads, progress::reporter())?))
+    }
 }
 
-fn puzzle2(input: &String) {
-    let almanac = input.parse::<Almanac>().unwrap();
+// How to find the lowest location of the seed ranges: "ranges" (the default) combines the maps into a single one and
+// works with whole ranges, "brute-force" tries every single seed (which takes a while for the real input).
+const METHOD_OPTION: &str = "day5.method";
+// Combining the maps pays off as long as they don't split up into too many ranges. Beyond this many ranges (which an
+// adversarial input could cause) we brute force the seeds instead.
+const MAX_RANGES_OPTION: &str = "day5.max-ranges";
+const DEFAULT_MAX_RANGES: usize = 100_000;
+// The number of seeds a thread takes at a time when brute forcing.
+const BRUTE_FORCE_CHUNK_SIZE: usize = 1 << 20;
+
+// The search settings from the options above.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+struct Search {
+    use_ranges: bool,
+    max_ranges: usize,
+}
 
-    println!("Lowest location of ranges: {}", almanac.find_lowest_destination_seed());
+impl Search {
+    fn new(method: Option<&str>, max_ranges: Option<&str>) -> Result<Self, String> {
+        let use_ranges = match method {
+            None | Some("ranges") => true,
+            Some("brute-force") => false,
+            Some(method) => return Err(format!("Unknown method '{}' for {}, expected ranges or brute-force", method, METHOD_OPTION)),
+        };
+        let max_ranges = match max_ranges {
+            Some(max) => parse_num(max).map_err(|e| format!("Invalid {}: {}", MAX_RANGES_OPTION, e))?,
+            None => DEFAULT_MAX_RANGES,
+        };
+        Ok(Self { use_ranges, max_ranges })
+    }
 }
 
 #[derive(Eq, PartialEq, Debug, Clone, Default)]
@@ -44,6 +95,18 @@ impl Almanac {
         self.humidity_to_location.remap(&humidity)
     }
 
+    fn get_maps(&self) -> [(&str, &AlmanacMap); 7] {
+        [
+            ("seed-to-soil", &self.seed_to_soil),
+            ("soil-to-fertilizer", &self.soil_to_fertilizer),
+            ("fertilizer-to-water", &self.fertilizer_to_water),
+            ("water-to-light", &self.water_to_light),
+            ("light-to-temperature", &self.light_to_temperature),
+            ("temperature-to-humidity", &self.temperature_to_humidity),
+            ("humidity-to-location", &self.humidity_to_location),
+        ]
+    }
+
     fn get_seed_to_location_map(&self) -> AlmanacMap {
         let seed_to_fertilizer = self.soil_to_fertilizer.remap_map(&self.seed_to_soil);
         let seed_to_water = self.fertilizer_to_water.remap_map(&seed_to_fertilizer);
@@ -53,31 +116,112 @@ impl Almanac {
         self.humidity_to_location.remap_map(&seed_to_humidity)
     }
 
-    fn find_lowest_destination_seed(&self) -> usize {
-        // Seed inputs are considered pairs (start + length), given those ranges find the lowest position
+    fn get_seed_ranges(&self) -> Result<Vec<Range<usize>>, String> {
+        // Seed inputs are considered pairs (start + length)
+        if !self.initial_seeds.len().is_multiple_of(2) {
+            return Err(format!("Expected pairs of seed numbers, got {} numbers", self.initial_seeds.len()));
+        }
         let mut seed_ranges = vec![];
         for i in (0..self.initial_seeds.len()).step_by(2) {
             let start = self.initial_seeds[i];
             let length = self.initial_seeds[i+1];
             seed_ranges.push(start..(start+length));
         }
+        Ok(seed_ranges)
+    }
+
+    // Follows the seed ranges through every map, keeping track of how they get split up and shifted on the way.
+    fn get_range_flow(&self, seed_ranges: &[Range<usize>]) -> RangeFlow {
+        // We keep the pieces as a range from seeds to the current kind of number, so remapping them to the next kind
+        // splits them exactly like combining the maps does.
+        let mut pieces: Vec<_> = seed_ranges.iter()
+            .map(|r| AlmanacRange { source_start: r.start, destination_start: r.start, length: r.len() })
+            .collect();
+        let mut layers = vec![];
+
+        for (name, map) in self.get_maps() {
+            let mut steps = vec![];
+            let mut next_pieces = vec![];
+            for piece in &pieces {
+                for next in map.remap_range(piece) {
+                    let from = piece.destination_start + (next.source_start - piece.source_start);
+                    steps.push(FlowStep {
+                        seeds: next.source_range(),
+                        from: from..(from + next.length),
+                        to: next.destination_start..(next.destination_start + next.length),
+                    });
+                    next_pieces.push(next);
+                }
+            }
+            layers.push(FlowLayer { name: name.to_string(), steps });
+            pieces = next_pieces;
+        }
+
+        RangeFlow { layers }
+    }
 
-        let seed_to_location_map = self.get_seed_to_location_map();
+    fn find_lowest_location(&self, search: Search, threads: usize, progress: &dyn ProgressReporter) -> Result<usize, String> {
+        let seed_ranges = self.get_seed_ranges()?;
+
+        if search.use_ranges {
+            let seed_to_location_map = self.get_seed_to_location_map();
+            if seed_to_location_map.ranges.len() <= search.max_ranges {
+                return self.find_lowest_destination_seed(&seed_ranges, &seed_to_location_map);
+            }
+            log::info!("Combining the maps gave {} ranges (more than {}), trying every seed instead", seed_to_location_map.ranges.len(), search.max_ranges);
+        }
+
+        self.find_lowest_location_brute_force(&seed_ranges, threads, progress).ok_or("There are no seeds in the seed ranges".to_string())
+    }
+
+    // Tries every single seed, dividing them over the given number of threads in chunks. A single thread (all we get in
+    // wasm) goes through the chunks itself.
+    fn find_lowest_location_brute_force(&self, seed_ranges: &[Range<usize>], threads: usize, progress: &dyn ProgressReporter) -> Option<usize> {
+        let chunks: Vec<Range<usize>> = seed_ranges.iter()
+            .flat_map(|range| range.clone().step_by(BRUTE_FORCE_CHUNK_SIZE).map(|start| start..(start + BRUTE_FORCE_CHUNK_SIZE).min(range.end)))
+            .collect();
+        let next_chunk = AtomicUsize::new(0);
+        let lowest = AtomicUsize::new(usize::MAX);
+        let search_chunks = || {
+            while let Some(chunk) = chunks.get(next_chunk.fetch_add(1, Ordering::Relaxed)) {
+                if let Some(location) = chunk.clone().map(|seed| self.get_location(&seed)).min() {
+                    lowest.fetch_min(location, Ordering::Relaxed);
+                }
+                progress.progress("Seed chunks", next_chunk.load(Ordering::Relaxed).min(chunks.len()), Some(chunks.len()));
+            }
+        };
+
+        if threads <= 1 {
+            search_chunks();
+        } else {
+            thread::scope(|scope| {
+                for _ in 0..threads {
+                    scope.spawn(search_chunks);
+                }
+            });
+        }
+
+        Some(lowest.into_inner()).filter(|l| *l != usize::MAX)
+    }
+
+    fn find_lowest_destination_seed(&self, seed_ranges: &[Range<usize>], seed_to_location_map: &AlmanacMap) -> Result<usize, String> {
+        // Given the seed ranges, find the lowest position
         // To still not try all the ranges, we order the ranges inside the big map from lowest destination to highest.
         // Then, we'll find the first range that has a overlap with a range, and find the first hit from there, and hope we're right.
         let mut ranges = seed_to_location_map.ranges.clone();
         ranges.sort_by_key(|r| r.destination_start);
-        let interesting_range = ranges.iter().find(|r| seed_ranges.iter().any(|sr| r.overlaps(sr))).unwrap();
-        println!("Lowest seed should be in {}-{}", interesting_range.source_range().start, interesting_range.source_range().end);
+        let interesting_range = ranges.iter().find(|r| seed_ranges.iter().any(|sr| r.overlaps(sr)))
+            .ok_or("None of the seed ranges end up in a location range")?;
+        log::debug!("Lowest seed should be in {}-{}", interesting_range.source_range().start, interesting_range.source_range().end);
 
-        let seed_range = seed_ranges.iter().find(|sr| interesting_range.overlaps(sr)).unwrap();
-        println!("Seed should come from range {}-{}", seed_range.start, seed_range.end);
+        let seed_range = seed_ranges.iter().find(|sr| interesting_range.overlaps(sr)).ok_or("Lost the overlapping seed range")?;
+        log::debug!("Seed should come from range {}-{}", seed_range.start, seed_range.end);
 
         // Result should be the max of the interesting range and seed range's starts (either the first remapped, or the first in range)
         let seed = interesting_range.source_start.max(seed_range.start);
-        println!("The seed number should be {}", seed);
+        log::debug!("The seed number should be {}", seed);
 
-        interesting_range.remap(&seed).unwrap()
+        interesting_range.remap(&seed).ok_or(format!("Seed {} is not in the range it should be in", seed))
     }
 }
 
@@ -85,14 +229,14 @@ impl FromStr for Almanac {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut lines = s.lines().collect::<Vec<_>>();
+        let mut parser = Parser::new(s);
 
         // First line should be declaring the seeds:
         let mut initial_seeds = vec!();
-        let mut parser = Parser::new(lines[0]);
-        parser.literal("seeds:")?;
-        while !parser.is_exhausted() {
-            initial_seeds.push(parser.usize()?);
+        let mut seeds_parser = Parser::new(parser.line()?);
+        seeds_parser.literal("seeds:")?;
+        while !seeds_parser.is_exhausted() {
+            initial_seeds.push(seeds_parser.usize()?);
         }
 
         let mut almanac = Almanac {
@@ -100,37 +244,27 @@ impl FromStr for Almanac {
             ..Default::default()
         };
 
-        // From there, we should find blocks for the different maps followed by relevant lines and
-        // separated by a blank line.
-        let mut current_map_type = None;
-        let mut map_lines: Vec<&str> = vec![];
-        // Ensure there is (at least) a single blank line at the end so we don't need to duplicate the
-        // logic to add the values to the right map.
-        lines.push("");
-        for i in 2..lines.len() {
-            if current_map_type.is_none() {
-                // First line should be a map type:
-                current_map_type = Some(lines[i]);
-            } else if lines[i].trim().is_empty() {
-                // Empty line separates, so we create the map and add it to almanac
-                let ranges = map_lines.iter().map(|l| AlmanacRange::from_str(l)).collect::<Result<Vec<_>, _>>()?;
-                let map = AlmanacMap { ranges };
-                match current_map_type {
-                    None if map_lines.len() == 0 => continue, // skip accidental multiple newlines
-                    Some("seed-to-soil map:") => almanac.seed_to_soil = map,
-                    Some("soil-to-fertilizer map:") => almanac.soil_to_fertilizer = map,
-                    Some("fertilizer-to-water map:") => almanac.fertilizer_to_water = map,
-                    Some("water-to-light map:") => almanac.water_to_light = map,
-                    Some("light-to-temperature map:") => almanac.light_to_temperature = map,
-                    Some("temperature-to-humidity map:") => almanac.temperature_to_humidity = map,
-                    Some("humidity-to-location map:") => almanac.humidity_to_location = map,
-                    None => return Err(format!("Missing map name for ranges [{}]", map_lines.join(", "))),
-                    Some(unknown) => return Err(format!("Unknown map name: '{}'", unknown)),
-                }
-                current_map_type = None;
-                map_lines.clear();
-            } else {
-                map_lines.push(lines[i]);
+        // From there, we should find blocks for the different maps: a map type followed by relevant lines, separated
+        // by a blank line.
+        while !parser.is_exhausted() {
+            let mut block = Parser::new(parser.block()?);
+            let map_type = block.line()?;
+
+            let mut ranges = vec![];
+            while !block.is_exhausted() {
+                ranges.push(AlmanacRange::from_str(&block.line()?)?);
+            }
+            let map = AlmanacMap { ranges };
+
+            match map_type.as_str() {
+                "seed-to-soil map:" => almanac.seed_to_soil = map,
+                "soil-to-fertilizer map:" => almanac.soil_to_fertilizer = map,
+                "fertilizer-to-water map:" => almanac.fertilizer_to_water = map,
+                "water-to-light map:" => almanac.water_to_light = map,
+                "light-to-temperature map:" => almanac.light_to_temperature = map,
+                "temperature-to-humidity map:" => almanac.temperature_to_humidity = map,
+                "humidity-to-location map:" => almanac.humidity_to_location = map,
+                unknown => return Err(format!("Unknown map name: '{}'", unknown)),
             }
         }
 
@@ -138,6 +272,56 @@ impl FromStr for Almanac {
     }
 }
 
+// How the seed ranges get split up and shifted by every map, for --visualize. Developing the range remapping was mostly
+// writing these out by hand.
+#[derive(Eq, PartialEq, Debug, Clone)]
+struct RangeFlow {
+    layers: Vec<FlowLayer>,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+struct FlowLayer {
+    name: String,
+    steps: Vec<FlowStep>,
+}
+
+// A piece of a seed range going through a map, from one kind of number (e.g. soil) to the next (e.g. fertilizer).
+#[derive(Eq, PartialEq, Debug, Clone)]
+struct FlowStep {
+    seeds: Range<usize>,
+    from: Range<usize>,
+    to: Range<usize>,
+}
+
+impl FlowStep {
+    fn shift(&self) -> i64 {
+        self.to.start as i64 - self.from.start as i64
+    }
+}
+
+fn format_range(range: &Range<usize>) -> String {
+    format!("{}..{}", range.start, range.end)
+}
+
+impl Visualizer for RangeFlow {
+    fn render_frame(&self) -> String {
+        let mut output = String::new();
+        for layer in &self.layers {
+            let rows: Vec<_> = layer.steps.iter()
+                .map(|s| [format_range(&s.seeds), format_range(&s.from), format_range(&s.to), format!("{:+}", s.shift())])
+                .collect();
+            let width = |column: usize| rows.iter().map(|r| r[column].len()).max().unwrap_or_default();
+            let (seeds, from, to) = (width(0), width(1), width(2));
+
+            output += &format!("{} ({} ranges):\n", layer.name, rows.len());
+            for [s, f, t, shift] in rows {
+                output += &format!("  seeds {:<seeds$} | {:>from$} => {:<to$} ({})\n", s, f, t, shift);
+            }
+        }
+        output
+    }
+}
+
 #[derive(Eq, PartialEq, Debug, Clone, Default)]
 struct AlmanacMap {
     ranges: Vec<AlmanacRange>,
@@ -293,7 +477,14 @@ impl AlmanacRange {
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day05::{Almanac, AlmanacMap, AlmanacRange};
+    use crate::days::day05::{Almanac, AlmanacMap, AlmanacRange, FlowStep, Search, DEFAULT_MAX_RANGES};
+    use crate::util::progress::Silent;
+    use crate::util::visualize::Visualizer;
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("35"), puzzle2: Some("46") },
+    ];
 
     #[test]
     fn test_almanac_range_remap() {
@@ -436,8 +627,72 @@ mod tests {
     fn test_almanac_get_lowest_location_seed() {
         let almanac = TEST_INPUT.parse::<Almanac>().unwrap();
 
-        let result = almanac.find_lowest_destination_seed();
-        assert_eq!(result, 46);
+        let result = almanac.find_lowest_destination_seed(&almanac.get_seed_ranges().unwrap(), &almanac.get_seed_to_location_map());
+        assert_eq!(result, Ok(46));
+    }
+
+    #[test]
+    fn test_almanac_find_lowest_location_brute_force() {
+        let almanac = TEST_INPUT.parse::<Almanac>().unwrap();
+        let seed_ranges = almanac.get_seed_ranges().unwrap();
+
+        assert_eq!(almanac.find_lowest_location_brute_force(&seed_ranges, 1, &Silent), Some(46));
+        assert_eq!(almanac.find_lowest_location_brute_force(&seed_ranges, 3, &Silent), Some(46));
+        assert_eq!(almanac.find_lowest_location_brute_force(&[], 2, &Silent), None);
+    }
+
+    #[test]
+    fn test_search() {
+        assert_eq!(Search::new(None, None), Ok(Search { use_ranges: true, max_ranges: DEFAULT_MAX_RANGES }));
+        assert_eq!(Search::new(Some("brute-force"), Some("1")), Ok(Search { use_ranges: false, max_ranges: 1 }));
+        assert!(Search::new(Some("guess"), None).is_err());
+        assert!(Search::new(None, Some("many")).is_err());
+    }
+
+    #[test]
+    fn test_almanac_find_lowest_location() {
+        let almanac = TEST_INPUT.parse::<Almanac>().unwrap();
+        let ranges = Search { use_ranges: true, max_ranges: DEFAULT_MAX_RANGES };
+        let brute_force = Search { use_ranges: false, max_ranges: DEFAULT_MAX_RANGES };
+        let too_many_ranges = Search { use_ranges: true, max_ranges: 1 };
+
+        assert_eq!(almanac.find_lowest_location(ranges, 1, &Silent), Ok(46));
+        assert_eq!(almanac.find_lowest_location(brute_force, 2, &Silent), Ok(46));
+        assert_eq!(almanac.find_lowest_location(too_many_ranges, 1, &Silent), Ok(46));
+    }
+
+    #[test]
+    fn test_almanac_get_range_flow() {
+        let almanac = TEST_INPUT.parse::<Almanac>().unwrap();
+        let flow = almanac.get_range_flow(&almanac.get_seed_ranges().unwrap());
+
+        assert_eq!(flow.layers.len(), 7);
+        assert_eq!(flow.layers[0].name, "seed-to-soil");
+        assert_eq!(flow.layers[0].steps, vec![
+            FlowStep { seeds: 79..93, from: 79..93, to: 81..95 },
+            FlowStep { seeds: 55..68, from: 55..68, to: 57..70 },
+        ]);
+        // The fertilizer of seeds 55..68 is split by the fertilizer-to-water map, and both pieces move on
+        assert_eq!(flow.layers[2].steps, vec![
+            FlowStep { seeds: 79..93, from: 81..95, to: 81..95 },
+            FlowStep { seeds: 55..59, from: 57..61, to: 53..57 },
+            FlowStep { seeds: 59..68, from: 61..70, to: 61..70 },
+        ]);
+        assert_eq!(flow.layers[3].steps, vec![
+            FlowStep { seeds: 79..93, from: 81..95, to: 74..88 },
+            FlowStep { seeds: 55..59, from: 53..57, to: 46..50 },
+            FlowStep { seeds: 59..68, from: 61..70, to: 54..63 },
+        ]);
+        // The lowest location of all the pieces is the answer to part 2
+        assert_eq!(flow.layers[6].steps.iter().map(|s| s.to.start).min(), Some(46));
+
+        let frame = flow.render_frame();
+        assert!(frame.starts_with("\
+            seed-to-soil (2 ranges):\n  \
+              seeds 79..93 | 79..93 => 81..95 (+2)\n  \
+              seeds 55..68 | 55..68 => 57..70 (+2)\n\
+            soil-to-fertilizer (2 ranges):\n\
+        "), "Unexpected frame:\n{}", frame);
     }
 
     const TEST_INPUT: &str = "\
@@ -475,13 +730,4 @@ mod tests {
         60 56 37\n\
         56 93 4\n\
     ";
-
-    // ;; Brain dump, flattening maps?
-    // humidity => location ranges: (just the last map)
-    // 0..56 => 0..56 | 56..93 => 60..97 | 93..97 => 56..60 | 97.. => 97..
-    // temperature => location ranges (merging the last two maps)
-    // 0..69 => 1..70 :> [(0..55) 1..56 => 1..56, (55..69) 56..70 => 60..74]
-    // 69 => 0 :> 0
-    // 70.. => 70.. :> [70..93 => 74..97, 93..97 => 56..60, 97..]
-
-}
\ No newline at end of file
+}
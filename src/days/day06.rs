@@ -1,25 +1,34 @@
 use std::str::FromStr;
-use crate::days::Day;
-use crate::util::number::parse_usize;
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::number::parse_num;
 use crate::util::parser::Parser;
 
 pub const DAY6: Day = Day {
-    puzzle1,
-    puzzle2,
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let puzzle = input.parse::<Puzzle>().unwrap();
+struct Solution;
 
-    let result = puzzle.races.iter().map(|r| r.get_ways_to_win()).reduce(|l,r| l*r).unwrap();
+impl StructuredDay for Solution {
+    type Parsed = (Puzzle, Race);
 
-    println!("Puzzle 1 result: {}", result);
-}
+    fn parse(input: &str) -> AocResult<(Puzzle, Race)> {
+        // Part 2 reads the same input as a single race, ignoring the spaces between the numbers.
+        Ok((input.parse::<Puzzle>()?, input.parse::<Race>()?))
+    }
+
+    fn part1((puzzle, _): &(Puzzle, Race)) -> AocResult<String> {
+        let result = puzzle.races.iter().map(|r| r.get_ways_to_win()).reduce(|l,r| l*r).ok_or("No races in the input")?;
 
-fn puzzle2(input: &String) {
-    let race = input.parse::<Race>().unwrap();
+        Ok(format!("Puzzle 1 result: {}", result))
+    }
 
-    println!("Puzzle 2 result: {}", race.get_ways_to_win_abc());
+    fn part2((_, race): &(Puzzle, Race)) -> AocResult<String> {
+        Ok(format!("Puzzle 2 result: {}", race.get_ways_to_win_abc()))
+    }
 }
 
 #[derive(Eq, PartialEq, Debug, Default, Clone)]
@@ -114,8 +123,8 @@ impl FromStr for Race {
         if !lines[1].starts_with("Distance:") { return Err(format!("Line 2 does not start with Distance:")) }
 
         // Parse
-        let duration = parse_usize(&lines[0]["Time:".len()..].replace(" ", ""))?;
-        let record = parse_usize(&lines[1]["Distance:".len()..].replace(" ", ""))?;
+        let duration = parse_num(&lines[0]["Time:".len()..].replace(" ", ""))?;
+        let record = parse_num(&lines[1]["Distance:".len()..].replace(" ", ""))?;
 
         Ok(Race { duration, record })
     }
@@ -124,6 +133,11 @@ impl FromStr for Race {
 #[cfg(test)]
 mod tests {
     use crate::days::day06::{Puzzle, Race};
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("288"), puzzle2: Some("71503") },
+    ];
 
     #[test]
     fn test_puzzle_from_str() {
@@ -1,144 +1,252 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
-use std::str::FromStr;
-use crate::days::Day;
-use crate::util::number::parse_u8;
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
 use crate::util::parser::Parser;
+use crate::util::log;
 
 pub const DAY7: Day = Day {
-    puzzle1,
-    puzzle2,
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let hands = input.lines().map(|l| l.parse::<Hand>()).collect::<Result<Vec<_>, _>>().unwrap();
+struct Solution;
 
-    println!("Winnings in puzzle 1: {}", get_winnings(&hands));
-}
+impl StructuredDay for Solution {
+    type Parsed = Vec<Play>;
 
-fn puzzle2(input: &String) {
-    let hands = input.lines().map(|l| l.parse::<Hand2>()).collect::<Result<Vec<_>, _>>().unwrap();
+    fn parse(input: &str) -> AocResult<Vec<Play>> {
+        parse_plays(input).map_err(Into::into)
+    }
 
-    println!("Winnings in puzzle 2: {}", get_winnings2(&hands));
-}
+    fn part1(plays: &Vec<Play>) -> AocResult<String> {
+        let winnings = get_winnings(plays, RuleSet::standard())?;
+        winnings.describe_ties().iter().for_each(|tie| log::info!("{}", tie));
+
+        Ok(format!("Winnings in puzzle 1: {}", winnings.total))
+    }
 
-fn get_winnings(hands: &Vec<Hand>) -> usize {
-    let mut winnings = 0;
-    let mut sorted = hands.clone();
-    sorted.sort();
+    fn part2(plays: &Vec<Play>) -> AocResult<String> {
+        let winnings = get_winnings(plays, RuleSet::jokers())?;
+        winnings.describe_ties().iter().for_each(|tie| log::info!("{}", tie));
 
-    for i in 0..sorted.len() {
-        winnings += sorted[i].bid * (i + 1);
+        Ok(format!("Winnings in puzzle 2: {}", winnings.total))
     }
+}
 
-    winnings
+// Anything that can be ranked for winnings.
+trait HasBid {
+    fn bid(&self) -> usize;
 }
 
-fn get_winnings2(hands: &Vec<Hand2>) -> usize {
-    let mut winnings = 0;
-    let mut sorted = hands.clone();
-    sorted.sort();
+#[derive(Eq, PartialEq, Debug, Clone)]
+struct Winnings<T> {
+    total: usize,
+    ranked: Vec<T>, // Weakest first
+    // The ranks (indices in ranked) of every pair of equally strong neighbours. The puzzle doesn't say how to rank those,
+    // they keep their input order.
+    ties: Vec<(usize, usize)>,
+}
 
-    for i in 0..sorted.len() {
-        winnings += sorted[i].bid * (i + 1);
+impl<T: Display + HasBid> Winnings<T> {
+    fn describe_ties(&self) -> Vec<String> {
+        self.ties.iter().map(|&(a, b)| {
+            let (first, second) = (&self.ranked[a], &self.ranked[b]);
+            let effect = if first.bid() == second.bid() { "which doesn't matter for the same bid" } else { "which decides the winnings" };
+            format!("{} and {} are equally strong, ranked {} and {} by their input order ({})", first, second, a + 1, b + 1, effect)
+        }).collect()
     }
+}
+
+// Ranks the things from weakest to strongest, every thing winning its bid times its rank.
+fn rank<T: Ord + HasBid>(things: impl IntoIterator<Item = T>) -> Winnings<T> {
+    let mut ranked: Vec<_> = things.into_iter().collect();
+    // A stable sort, so ties at least keep their (input) order.
+    ranked.sort();
+
+    let total = ranked.iter().enumerate().map(|(i, thing)| thing.bid() * (i + 1)).sum();
+    let ties = (1..ranked.len()).filter(|i| ranked[i - 1].cmp(&ranked[*i]) == Ordering::Equal).map(|i| (i - 1, i)).collect();
 
-    winnings
+    Winnings { total, ranked, ties }
 }
 
+// A line of the input: the cards as they are written, and the bid. Which hand the cards make depends on the rules.
+type Play = (String, usize);
+
+fn parse_plays(input: &str) -> Result<Vec<Play>, String> {
+    input.lines().map(|line| {
+        let mut parser = Parser::new(line);
+        let cards = parser.str(5)?;
+        let bid = parser.usize()?;
+        parser.ensure_exhausted()?;
+        Ok((cards, bid))
+    }).collect()
+}
+
+fn get_winnings(plays: &[Play], rules: RuleSet) -> Result<Winnings<Hand>, String> {
+    let hands = plays.iter().map(|(cards, bid)| Hand::new(cards, *bid, rules)).collect::<Result<Vec<_>, _>>()?;
+    Ok(rank(hands))
+}
+
+// The strength of the cards, weakest first. Cards are stored by their index in this table, so parsing, ordering and
+// displaying hands all go through it.
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
-struct Hand {
-    cards: [u8; 5],
-    bid: usize,
+struct CardOrder {
+    cards: [char; 13],
 }
 
-impl Hand {
-    fn get_kind(&self) -> HandKind {
-        let mut map: HashMap<u8, usize> = HashMap::new();
-        for card in self.cards {
-            let current = map.get(&card).unwrap_or(&0);
-            map.insert(card, current + 1);
+impl CardOrder {
+    const STANDARD: &'static str = "23456789TJQKA";
+
+    fn parse(order: &str) -> Result<Self, String> {
+        let cards: [char; 13] = order.chars().collect::<Vec<_>>().try_into().map_err(|_| format!("Expected 13 cards in '{}'", order))?;
+        if let Some(card) = cards.iter().find(|c| cards.iter().filter(|o| c == o).count() > 1) {
+            return Err(format!("Card '{}' is in '{}' more than once", card, order));
         }
+        Ok(Self { cards })
+    }
+
+    // The same order, but with the given card moved to be the weakest one.
+    fn with_weakest(&self, card: char) -> Result<Self, String> {
+        let index = self.value_of(card).ok_or(format!("'{}' is not a card in '{}'", card, self))? as usize;
+        let mut cards = self.cards;
+        cards[..=index].rotate_right(1);
+        Ok(Self { cards })
+    }
+
+    fn value_of(&self, card: char) -> Option<u8> {
+        self.cards.iter().position(|c| card.eq(c)).map(|v| v as u8)
+    }
 
-        // Check number of entries on map
-        Self::get_kind_from_map(&mut map)
-    }
-
-    fn get_kind_from_map(map: &HashMap<u8, usize>) -> HandKind {
-        match map.len() {
-            1 => HandKind::FiveOfAKind, // Can only be five of the same
-            2 => {
-                if let [a, b] = map.values().collect::<Vec<_>>()[..2] {
-                    match (a, b) {
-                        (4, 1) | (1, 4) => HandKind::FourOfAKind,
-                        (2, 3) | (3, 2) => HandKind::FullHouse,
-                        _ => panic!("Invalid combo ({},{})", a, b)
-                    }
-                } else {
-                    panic!("Should not happen?!");
-                }
-            }
-            3 => {
-                if let [a, b, c] = map.values().collect::<Vec<_>>()[..3] {
-                    match (a, b, c) {
-                        (1, 1, 3) | (1, 3, 1) | (3, 1, 1) => HandKind::ThreeOfAKind,
-                        (1, 2, 2) | (2, 1, 2) | (2, 2, 1) => HandKind::TwoPair,
-                        _ => panic!("Invalid combo ({}, {}, {})", a, b, c)
-                    }
-                } else {
-                    panic!("Should not happen?!");
-                }
-            }
-            4 => HandKind::Pair, // Can only be a single pair
-            _ => HandKind::Garbage
+    fn card(&self, value: u8) -> char {
+        self.cards[value as usize]
+    }
+
+    // The cards with their values, for parsing them.
+    fn literals(&self) -> Vec<(String, u8)> {
+        self.cards.iter().enumerate().map(|(value, card)| (card.to_string(), value as u8)).collect()
+    }
+}
+
+impl Default for CardOrder {
+    fn default() -> Self {
+        Self::parse(Self::STANDARD).unwrap()
+    }
+}
+
+// The rules of the game: the order of the cards, and optionally which card acts as a wildcard.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+struct RuleSet {
+    card_order: CardOrder,
+    wildcard: Option<char>,
+}
+
+impl RuleSet {
+    fn standard() -> Self {
+        RuleSetBuilder::new().build().unwrap()
+    }
+
+    fn jokers() -> Self {
+        // Jokers can act like any other card, but are the weakest card on their own.
+        RuleSetBuilder::new().wildcard('J').build().unwrap()
+    }
+
+    fn get_card(&self, value: u8) -> char {
+        self.card_order.card(value)
+    }
+
+    fn is_wildcard(&self, value: u8) -> bool {
+        self.wildcard.is_some_and(|w| w == self.get_card(value))
+    }
+}
+
+struct RuleSetBuilder {
+    card_order: String,
+    wildcard: Option<char>,
+}
+
+#[allow(unused)]
+impl RuleSetBuilder {
+    fn new() -> Self {
+        Self { card_order: CardOrder::STANDARD.to_string(), wildcard: None }
+    }
+
+    fn card_order(mut self, card_order: &str) -> Self {
+        self.card_order = card_order.to_string();
+        self
+    }
+
+    // Makes the given card a wildcard, which also makes it the weakest card.
+    fn wildcard(mut self, card: char) -> Self {
+        self.wildcard = Some(card);
+        self
+    }
+
+    fn build(self) -> Result<RuleSet, String> {
+        let mut card_order = CardOrder::parse(&self.card_order)?;
+        if let Some(wildcard) = self.wildcard {
+            card_order = card_order.with_weakest(wildcard).map_err(|e| format!("Invalid wildcard: {}", e))?;
         }
+
+        Ok(RuleSet { card_order, wildcard: self.wildcard })
     }
 }
 
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
-struct Hand2 {
+struct Hand {
+    // Values of the cards, by their index in the card order of the rules
     cards: [u8; 5],
     bid: usize,
+    rules: RuleSet,
+    // Determined once when parsing, instead of on every comparison while sorting.
+    kind: HandKind,
 }
 
-impl Hand2 {
-    fn get_kind(&self) -> HandKind {
-        // Need a smarter way, as a joker (card value 1) can fit any slot.
-        // First know the amount of jokers (that opens or closes a lot of info)
-        // Partition other numbers into the map as before
-        let (jokers, cards): (Vec<_>, Vec<_>) = self.cards.into_iter().partition(|c| 1.eq(c));
+impl Hand {
+    fn new(cards_text: &str, bid: usize, rules: RuleSet) -> Result<Self, String> {
+        let mut parser = Parser::strict(cards_text);
+        let mut cards: [u8; 5] = [0; 5];
+        let options = rules.card_order.literals();
+
+        for card in cards.iter_mut() {
+            *card = parser.enum_of(&options)?;
+        }
+        parser.ensure_exhausted()?;
+
+        Ok(Hand {
+            cards,
+            bid,
+            rules,
+            kind: Hand::get_kind(&cards, rules),
+        })
+    }
+
+    fn get_kind(cards: &[u8; 5], rules: RuleSet) -> HandKind {
+        // Wildcards (jokers) can fit any slot, the best we can do with them is to add them to the largest group.
+        let (wildcards, cards): (Vec<_>, Vec<_>) = cards.iter().copied().partition(|c| rules.is_wildcard(*c));
         let mut map: HashMap<u8, usize> = HashMap::new();
         for card in cards {
             map.insert(card, map.get(&card).unwrap_or(&0) + 1);
         }
 
-        match jokers.len() {
-            5 | 4 => HandKind::FiveOfAKind, // 5 jokers, or 4 jokers + whatever
-            3 if map.len() == 1 => HandKind::FiveOfAKind, // 3 jokers + 2 of the same card
-            2 if map.len() == 1 => HandKind::FiveOfAKind, // 2 jokers + 3 same cards
-            1 if map.len() == 1 => HandKind::FiveOfAKind, // 1 joker + 4 same cards
-            3 => HandKind::FourOfAKind, // 3 jokers and 2 random cards, four of a kind is the highest score.
-            2 if map.len() == 2 => HandKind::FourOfAKind, // 2 jokers, 2 same cards, and a random card
-            2 if map.len() == 3 => HandKind::ThreeOfAKind, // 2 jokers and 3 random cards
-            1 if map.len() == 2 => {
-                // Need to check:
-                if let Some(value) = map.values().next() {
-                    match value {
-                        // - J + XX / YY => Full House
-                        2 => HandKind::FullHouse,
-                        // - J + XXX / Y => Four of a Kind
-                        1 | 3 => HandKind::FourOfAKind,
-                        _ => panic!("Wrong number of cards {}", value)
-                    }
-                } else {
-                    panic!("Should not happen?!");
-                }
-            }
-            1 if map.len() == 3 => HandKind::ThreeOfAKind, // 1 joker, with 2 cards, and 2 random cards (3 > 2+2)
-            1 if map.len() == 4 => HandKind::Pair, // 1 joker, and four random cards
-            0 => Hand::get_kind_from_map(&map), // 0 jokers, fall back to normal behaviour
-            wrong => panic!("Cannot have {} jokers?!", wrong)
+        let mut counts: Vec<_> = map.into_values().collect();
+        counts.sort_by(|a, b| b.cmp(a));
+        match counts.first_mut() {
+            Some(largest) => *largest += wildcards.len(),
+            None => counts.push(wildcards.len()), // Only wildcards
+        }
+
+        // A hand always has five cards, so the largest group and the number of groups tell the kind apart.
+        match (counts[0], counts.len()) {
+            (5, _) => HandKind::FiveOfAKind,
+            (4, _) => HandKind::FourOfAKind,
+            (3, 2) => HandKind::FullHouse,
+            (3, _) => HandKind::ThreeOfAKind,
+            (2, 3) => HandKind::TwoPair,
+            (2, _) => HandKind::Pair,
+            _ => HandKind::Garbage,
         }
     }
 }
@@ -156,60 +264,138 @@ enum HandKind {
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day07::{get_winnings, Hand, Hand2, HandKind};
+    use crate::days::day07::{get_winnings, parse_plays, CardOrder, Hand, HandKind, Play, RuleSet, RuleSetBuilder};
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("6440"), puzzle2: Some("5905") },
+    ];
+
+    fn parse_hands(input: &str, rules: RuleSet) -> Result<Vec<Hand>, String> {
+        parse_plays(input)?.iter().map(|(cards, bid)| Hand::new(cards, *bid, rules)).collect()
+    }
+
+    fn hand(cards: &str, rules: RuleSet) -> Hand {
+        Hand::new(cards, 0, rules).unwrap()
+    }
+
+    fn plays(lines: &[&str]) -> Vec<Play> {
+        parse_plays(&lines.join("\n")).unwrap()
+    }
 
     #[test]
-    fn test_hand_from_str() {
-        assert_eq!("32T3K 765".parse::<Hand>(), Ok(Hand {
-            cards: [3, 2, 10, 3, 13],
+    fn test_parse_plays() {
+        assert_eq!(parse_plays("32T3K 765\nKK677 28"), Ok(vec![("32T3K".to_string(), 765), ("KK677".to_string(), 28)]));
+        assert!(parse_plays("32T3K").is_err());
+        assert!(parse_plays("32T3K 765 1").is_err());
+    }
+
+    #[test]
+    fn test_hand_parse() {
+        let rules = RuleSet::standard();
+        assert_eq!(Hand::new("32T3K", 765, rules), Ok(Hand {
+            cards: [1, 0, 8, 1, 11],
+            bid: 765,
+            rules,
+            kind: HandKind::Pair,
+        }));
+
+        let rules = RuleSet::jokers();
+        assert_eq!(Hand::new("J2T3K", 765, rules), Ok(Hand {
+            cards: [0, 1, 9, 2, 11],
             bid: 765,
-        }))
+            rules,
+            kind: HandKind::Pair,
+        }));
+
+        assert!(Hand::new("32X3K", 765, RuleSet::standard()).is_err());
+        assert!(Hand::new("32T3", 765, RuleSet::standard()).is_err());
+        assert!(Hand::new("32T3KK", 765, RuleSet::standard()).is_err());
     }
 
     #[test]
     fn test_hand_display() {
-        assert_eq!(format!("{}", Hand { cards: [2, 4, 10, 13, 14], bid: 42 }), "24TKA 42".to_string())
+        assert_eq!(format!("{}", hand("24TKA", RuleSet::standard())), "24TKA 0".to_string());
+        assert_eq!(format!("{}", hand("J4TKA", RuleSet::jokers())), "J4TKA 0".to_string());
+    }
+
+    #[test]
+    fn test_rule_set_builder() {
+        assert_eq!(RuleSet::jokers().card_order.to_string(), "J23456789TQKA");
+        assert_eq!(RuleSetBuilder::new().wildcard('2').build().map(|r| r.card_order.to_string()), Ok("23456789TJQKA".to_string()));
+        assert!(RuleSetBuilder::new().wildcard('X').build().is_err());
+        assert!(RuleSetBuilder::new().card_order("AKQ").build().is_err());
+
+        // Wildcards other than J:
+        let rules = RuleSetBuilder::new().card_order("AKQJT98765432").wildcard('A').build().unwrap();
+        assert_eq!(hand("A2322", rules).kind, HandKind::FourOfAKind);
+        assert!(hand("A2322", rules) < hand("23222", rules));
+    }
+
+    #[test]
+    fn test_card_order() {
+        let order = CardOrder::default();
+        assert_eq!(order.value_of('2'), Some(0));
+        assert_eq!(order.value_of('A'), Some(12));
+        assert_eq!(order.value_of('X'), None);
+        assert_eq!(order.card(9), 'J');
+        assert_eq!(order.with_weakest('T').map(|o| o.to_string()), Ok("T23456789JQKA".to_string()));
+
+        assert!(CardOrder::parse("23456789TJQK").is_err());
+        assert!(CardOrder::parse("23456789TJQKK").is_err());
+        assert!(order.with_weakest('X').is_err());
+
+        // A custom order changes how hands are parsed, shown and ranked
+        let rules = RuleSetBuilder::new().card_order("T23456789JQKA").build().unwrap();
+        assert_eq!(hand("T2345", rules).cards, [0, 1, 2, 3, 4]);
+        assert_eq!(hand("T2345", rules).to_string(), "T2345 0");
+        assert!(hand("TT234", rules) < hand("22345", rules));
+        assert!(hand("TT234", RuleSet::standard()) > hand("22345", RuleSet::standard()));
     }
 
     #[test]
     fn test_hand_get_kind() {
-        assert_eq!(Hand { cards: [3, 3, 3, 3, 3], bid: 0 }.get_kind(), HandKind::FiveOfAKind);
-        assert_eq!(Hand { cards: [3, 3, 2, 3, 3], bid: 0 }.get_kind(), HandKind::FourOfAKind);
-        assert_eq!(Hand { cards: [3, 4, 3, 3, 4], bid: 0 }.get_kind(), HandKind::FullHouse);
-        assert_eq!(Hand { cards: [4, 4, 2, 3, 4], bid: 0 }.get_kind(), HandKind::ThreeOfAKind);
-        assert_eq!(Hand { cards: [4, 4, 2, 3, 2], bid: 0 }.get_kind(), HandKind::TwoPair);
-        assert_eq!(Hand { cards: [4, 4, 6, 3, 2], bid: 0 }.get_kind(), HandKind::Pair);
-        assert_eq!(Hand { cards: [4, 8, 6, 3, 2], bid: 0 }.get_kind(), HandKind::Garbage);
+        let rules = RuleSet::standard();
+        assert_eq!(hand("33333", rules).kind, HandKind::FiveOfAKind);
+        assert_eq!(hand("33233", rules).kind, HandKind::FourOfAKind);
+        assert_eq!(hand("34334", rules).kind, HandKind::FullHouse);
+        assert_eq!(hand("44234", rules).kind, HandKind::ThreeOfAKind);
+        assert_eq!(hand("44232", rules).kind, HandKind::TwoPair);
+        assert_eq!(hand("44632", rules).kind, HandKind::Pair);
+        assert_eq!(hand("48632", rules).kind, HandKind::Garbage);
+        assert_eq!(hand("JJJJJ", rules).kind, HandKind::FiveOfAKind);
+        assert_eq!(hand("J4632", rules).kind, HandKind::Garbage);
     }
 
     #[test]
-    fn test_hand2_get_kind() {
+    fn test_hand_get_kind_with_jokers() {
+        let rules = RuleSet::jokers();
         // Without any jokers:
-        assert_eq!(Hand2 { cards: [3, 3, 3, 3, 3], bid: 0 }.get_kind(), HandKind::FiveOfAKind);
-        assert_eq!(Hand2 { cards: [3, 3, 2, 3, 3], bid: 0 }.get_kind(), HandKind::FourOfAKind);
-        assert_eq!(Hand2 { cards: [3, 4, 3, 3, 4], bid: 0 }.get_kind(), HandKind::FullHouse);
-        assert_eq!(Hand2 { cards: [4, 4, 2, 3, 4], bid: 0 }.get_kind(), HandKind::ThreeOfAKind);
-        assert_eq!(Hand2 { cards: [4, 4, 2, 3, 2], bid: 0 }.get_kind(), HandKind::TwoPair);
-        assert_eq!(Hand2 { cards: [4, 4, 6, 3, 2], bid: 0 }.get_kind(), HandKind::Pair);
-        assert_eq!(Hand2 { cards: [4, 8, 6, 3, 2], bid: 0 }.get_kind(), HandKind::Garbage);
+        assert_eq!(hand("33333", rules).kind, HandKind::FiveOfAKind);
+        assert_eq!(hand("33233", rules).kind, HandKind::FourOfAKind);
+        assert_eq!(hand("34334", rules).kind, HandKind::FullHouse);
+        assert_eq!(hand("44234", rules).kind, HandKind::ThreeOfAKind);
+        assert_eq!(hand("44232", rules).kind, HandKind::TwoPair);
+        assert_eq!(hand("44632", rules).kind, HandKind::Pair);
+        assert_eq!(hand("48632", rules).kind, HandKind::Garbage);
 
         // With jokers:
-        assert_eq!(Hand2 { cards: [1, 1, 1, 1, 1], bid: 0 }.get_kind(), HandKind::FiveOfAKind);
-        assert_eq!(Hand2 { cards: [1, 1, 1, 1, 2], bid: 0 }.get_kind(), HandKind::FiveOfAKind);
-        assert_eq!(Hand2 { cards: [1, 1, 1, 2, 2], bid: 0 }.get_kind(), HandKind::FiveOfAKind);
-        assert_eq!(Hand2 { cards: [1, 1, 2, 2, 2], bid: 0 }.get_kind(), HandKind::FiveOfAKind);
-        assert_eq!(Hand2 { cards: [1, 2, 2, 2, 2], bid: 0 }.get_kind(), HandKind::FiveOfAKind);
-        assert_eq!(Hand2 { cards: [1, 1, 1, 2, 3], bid: 0 }.get_kind(), HandKind::FourOfAKind);
-        assert_eq!(Hand2 { cards: [1, 1, 2, 2, 3], bid: 0 }.get_kind(), HandKind::FourOfAKind);
-        assert_eq!(Hand2 { cards: [1, 1, 4, 2, 3], bid: 0 }.get_kind(), HandKind::ThreeOfAKind);
-        assert_eq!(Hand2 { cards: [1, 4, 4, 2, 3], bid: 0 }.get_kind(), HandKind::ThreeOfAKind);
-        assert_eq!(Hand2 { cards: [1, 4, 4, 3, 3], bid: 0 }.get_kind(), HandKind::FullHouse);
-        assert_eq!(Hand2 { cards: [1, 6, 4, 2, 3], bid: 0 }.get_kind(), HandKind::Pair);
+        assert_eq!(hand("JJJJJ", rules).kind, HandKind::FiveOfAKind);
+        assert_eq!(hand("JJJJ2", rules).kind, HandKind::FiveOfAKind);
+        assert_eq!(hand("JJJ22", rules).kind, HandKind::FiveOfAKind);
+        assert_eq!(hand("JJ222", rules).kind, HandKind::FiveOfAKind);
+        assert_eq!(hand("J2222", rules).kind, HandKind::FiveOfAKind);
+        assert_eq!(hand("JJJ23", rules).kind, HandKind::FourOfAKind);
+        assert_eq!(hand("JJ223", rules).kind, HandKind::FourOfAKind);
+        assert_eq!(hand("JJ423", rules).kind, HandKind::ThreeOfAKind);
+        assert_eq!(hand("J4423", rules).kind, HandKind::ThreeOfAKind);
+        assert_eq!(hand("J4433", rules).kind, HandKind::FullHouse);
+        assert_eq!(hand("J6423", rules).kind, HandKind::Pair);
     }
 
     #[test]
     fn test_sort_test_input() {
-        let hands = TEST_INPUT.lines().map(|l| l.parse::<Hand>()).collect::<Result<Vec<_>, _>>().unwrap();
+        let hands = parse_hands(TEST_INPUT, RuleSet::standard()).unwrap();
 
         let mut sorted = hands.clone();
         sorted.sort();
@@ -224,8 +410,8 @@ mod tests {
     }
 
     #[test]
-    fn test_sort_test_input2() {
-        let hands = TEST_INPUT.lines().map(|l| l.parse::<Hand2>()).collect::<Result<Vec<_>, _>>().unwrap();
+    fn test_sort_test_input_with_jokers() {
+        let hands = parse_hands(TEST_INPUT, RuleSet::jokers()).unwrap();
 
         let mut sorted = hands.clone();
         sorted.sort();
@@ -241,9 +427,28 @@ mod tests {
 
     #[test]
     fn test_get_winnings() {
-        let hands = TEST_INPUT.lines().map(|l| l.parse::<Hand>()).collect::<Result<Vec<_>, _>>().unwrap();
+        let test_plays = parse_plays(TEST_INPUT).unwrap();
+        assert_eq!(get_winnings(&test_plays, RuleSet::standard()).map(|w| w.total), Ok(6440));
+        assert_eq!(get_winnings(&test_plays, RuleSet::jokers()).map(|w| w.total), Ok(5905));
+        assert!(get_winnings(&plays(&["32X3K 765"]), RuleSet::standard()).is_err());
+
+        let hands = parse_hands(TEST_INPUT, RuleSet::jokers()).unwrap();
+        assert_eq!(get_winnings(&test_plays, RuleSet::jokers()).map(|w| w.ranked), Ok(vec![hands[0], hands[2], hands[1], hands[4], hands[3]]));
+        assert_eq!(get_winnings(&test_plays, RuleSet::jokers()).map(|w| w.ties), Ok(vec![]));
+    }
 
-        assert_eq!(get_winnings(&hands), 6440);
+    #[test]
+    fn test_get_winnings_ties() {
+        // The same hand twice, which the puzzle doesn't say how to rank
+        let winnings = get_winnings(&plays(&["KK677 28", "32T3K 765", "KK677 10"]), RuleSet::standard()).unwrap();
+
+        assert_eq!(winnings.ranked.iter().map(|h| h.bid).collect::<Vec<_>>(), vec![765, 28, 10]);
+        assert_eq!(winnings.total, 765 + 2 * 28 + 3 * 10);
+        assert_eq!(winnings.ties, vec![(1, 2)]);
+        assert_eq!(winnings.describe_ties(), vec!["KK677 28 and KK677 10 are equally strong, ranked 2 and 3 by their input order (which decides the winnings)"]);
+
+        let winnings = get_winnings(&plays(&["KK677 28", "KK677 28"]), RuleSet::standard()).unwrap();
+        assert_eq!(winnings.describe_ties(), vec!["KK677 28 and KK677 28 are equally strong, ranked 1 and 2 by their input order (which doesn't matter for the same bid)"]);
     }
 
     const TEST_INPUT: &str = "\
@@ -255,56 +460,21 @@ mod tests {
     ";
 }
 
-// # std trait implementations
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match self.get_kind().cmp(&other.get_kind()) {
-            Ordering::Greater => Ordering::Greater,
-            Ordering::Less => Ordering::Less,
-            Ordering::Equal => {
-                // Compare card values per position
-                for i in 0..5 {
-                    if self.cards[i] < other.cards[i] {
-                        return Ordering::Less;
-                    }
-                    if self.cards[i] > other.cards[i] {
-                        return Ordering::Greater;
-                    }
-                }
-                return Ordering::Equal;
-            }
-        }
-    }
-}
-
-impl PartialOrd for Hand {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl HasBid for Hand {
+    fn bid(&self) -> usize {
+        self.bid
     }
 }
 
-impl Ord for Hand2 {
+// # std trait implementations
+impl Ord for Hand {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.get_kind().cmp(&other.get_kind()) {
-            Ordering::Greater => Ordering::Greater,
-            Ordering::Less => Ordering::Less,
-            Ordering::Equal => {
-                // Compare card values per position
-                for i in 0..5 {
-                    if self.cards[i] < other.cards[i] {
-                        return Ordering::Less;
-                    }
-                    if self.cards[i] > other.cards[i] {
-                        return Ordering::Greater;
-                    }
-                }
-                return Ordering::Equal;
-            }
-        }
+        // By kind, and then by the card values per position
+        (self.kind, self.cards).cmp(&(other.kind, other.cards))
     }
 }
 
-impl PartialOrd for Hand2 {
+impl PartialOrd for Hand {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
@@ -337,102 +507,16 @@ impl PartialOrd for HandKind {
     }
 }
 
-impl FromStr for Hand {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parser = Parser::new(s);
-        let mut cards: [u8; 5] = [0; 5];
-
-        for i in 0..5 {
-            cards[i] = match parser.one_of(vec!["2", "3", "4", "5", "6", "7", "8", "9", "T", "J", "Q", "K", "A"])? {
-                val @ ("2" | "3" | "4" | "5" | "6" | "7" | "8" | "9") => parse_u8(val)?,
-                "T" => 10,
-                "J" => 11,
-                "Q" => 12,
-                "K" => 13,
-                "A" => 14,
-                inv => return Err(format!("Invalid char '{}'", inv))
-            }
-        }
-
-        let bid = parser.usize()?;
-        parser.ensure_exhausted()?;
-
-        Ok(Hand {
-            cards,
-            bid,
-        })
-    }
-}
-
-impl Display for Hand {
+impl Display for CardOrder {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        fn get_card_display(card: u8) -> char {
-            match card {
-                2..=9 => (('2' as u8) + (card - 2)) as char,
-                10 => 'T',
-                11 => 'J',
-                12 => 'Q',
-                13 => 'K',
-                14 => 'A',
-                _ => panic!("Invalid card value: {}", card)
-            }
-        }
-
-        for card in self.cards {
-            write!(f, "{}", get_card_display(card))?;
-        }
-
-        write!(f, " {}", self.bid)
-    }
-}
-
-impl FromStr for Hand2 {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parser = Parser::new(s);
-        let mut cards: [u8; 5] = [0; 5];
-
-        for i in 0..5 {
-            cards[i] = match parser.one_of(vec!["2", "3", "4", "5", "6", "7", "8", "9", "T", "J", "Q", "K", "A"])? {
-                "J" => 1,
-                val @ ("2" | "3" | "4" | "5" | "6" | "7" | "8" | "9") => parse_u8(val)?,
-                "T" => 10,
-                "Q" => 11,
-                "K" => 12,
-                "A" => 13,
-                inv => return Err(format!("Invalid char '{}'", inv))
-            }
-        }
-
-        let bid = parser.usize()?;
-        parser.ensure_exhausted()?;
-
-        Ok(Hand2 {
-            cards,
-            bid,
-        })
+        write!(f, "{}", self.cards.iter().collect::<String>())
     }
 }
 
-impl Display for Hand2 {
+impl Display for Hand {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        fn get_card_display(card: u8) -> char {
-            match card {
-                1 => 'J',
-                2..=9 => (('2' as u8) + (card - 2)) as char,
-                10 => 'T',
-                11 => 'Q',
-                12 => 'K',
-                13 => 'A',
-                _ => panic!("Invalid card value: {}", card)
-            }
-        }
-
         for card in self.cards {
-            write!(f, "{}", get_card_display(card))?;
+            write!(f, "{}", self.rules.get_card(card))?;
         }
 
         write!(f, " {}", self.bid)
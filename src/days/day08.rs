@@ -1,143 +1,200 @@
-use std::collections::{HashMap};
+use std::iter::successors;
 use std::str::FromStr;
-use crate::days::Day;
-use crate::util::number::lcm;
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::exact;
+use crate::util::cycles::{align_any, Cycle, find_cycle};
+use crate::util::interner::Interner;
 use crate::util::parser::Parser;
+use crate::util::log;
 
 pub const DAY8: Day = Day {
-    puzzle1,
-    puzzle2
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let map = input.parse::<Map>().unwrap();
+struct Solution;
 
-    println!("It takes {} steps to get to the end", map.steps_to_end().unwrap());
-}
+impl StructuredDay for Solution {
+    type Parsed = Map;
+
+    fn parse(input: &str) -> AocResult<Map> {
+        input.parse::<Map>().map_err(Into::into)
+    }
 
-fn puzzle2(input: &String) {
-    let map = input.parse::<Map>().unwrap();
+    fn part1(map: &Map) -> AocResult<String> {
+        Ok(format!("It takes {} steps to get to the end", map.steps_to_end()?))
+    }
 
-    println!("It takes {} ghost steps to get to the end", map.ghost_steps_to_end().unwrap());
+    fn part2(map: &Map) -> AocResult<String> {
+        Ok(format!("It takes {} ghost steps to get to the end", map.ghost_steps_to_end()?))
+    }
 }
 
+// Whether to follow a node's left or right element, rather than a direction on a grid (see geometry::Dir for those).
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
-enum Direction {
+enum Branch {
     Left,
     Right
 }
 
+// Nodes are referred to by their interned name, which is also their index in `nodes` and `passes`.
 #[derive(Eq, PartialEq, Debug, Clone)]
 struct Map {
-    directions: Vec<Direction>,
-    nodes: HashMap<String, (String, String)>
+    directions: Vec<Branch>,
+    names: Interner,
+    nodes: Vec<(u32, u32)>,
+    // For every node, where we end up after following all directions once.
+    passes: Vec<Pass>,
 }
 
-impl Map {
-    fn steps_to_end(&self) -> Result<usize, String> {
-        let mut steps = 0;
-        let mut node = &"AAA".to_string();
-
-        while node.ne("ZZZ") {
-            let (left, right) = match self.nodes.get(node) {
-                Some(val) => val,
-                None => return Err(format!("Missing node '{}' in map", node))
-            };
-
-            let direction = self.directions[steps % self.directions.len()];
-            steps += 1;
+// Following all directions once from a node: where we end up, and the end nodes (ending with 'Z') we are at on the way.
+// The ends are (step, node), where step 0 is the node we started at, and the target is not included (as that's step 0
+// of the next pass).
+#[derive(Eq, PartialEq, Debug, Clone)]
+struct Pass {
+    target: u32,
+    ends: Vec<(usize, u32)>,
+}
 
-            match direction {
-                Direction::Left => node = left,
-                Direction::Right => node = right
-            }
+impl Map {
+    fn next(&self, node: u32, direction: Branch) -> u32 {
+        let (left, right) = self.nodes[node as usize];
+        match direction {
+            Branch::Left => left,
+            Branch::Right => right
         }
-
-        Ok(steps)
     }
 
-    fn ghost_loop_info(&self, start: &String) -> Result<(usize, usize), String> {
-        // Loop through this route until we get back to a known state (based on direction index + node)
-        // Note: validated by running some debug on this, each start node only comes by a single end node
-        //  in their loops. Since that makes the solution simpler, we only care for that case.
+    fn pass(&self, node: u32) -> &Pass {
+        &self.passes[node as usize]
+    }
 
-        let mut seen: Vec<(usize, &String)> = vec![];
-        let mut direction_index = 0;
+    fn steps_to_end(&self) -> Result<usize, String> {
+        let start = self.names.get("AAA").ok_or("Missing node 'AAA' in map")?;
+        let end = self.names.get("ZZZ").ok_or("Missing node 'ZZZ' in map")?;
 
+        // Jump a full pass of directions at a time, until a pass gets us to the end. After visiting every node at the
+        // start of a pass, we're going around in circles.
         let mut node = start;
-        loop {
-            if let Some(index) = seen.iter().position(|(di, n)| direction_index.eq(di) && n.eq(&node)) {
-                // Found the loop, index is the offset, and everything after it is the loop.
-                // From the offset, find how much further the first end position is
-                // That end position will be the offset from start (the first end) and the loop size we know.
-                let loop_size = seen.len() - index;
-                let end_index = seen.iter().rposition(|(_, n)| n.ends_with("Z")).ok_or(format!("No end in loop for {}", start))?;
-                println!("Loop info for {}: at {} after {} steps, back there every {} next steps.", start, seen[end_index].1, end_index, loop_size);
-
-                return Ok((end_index, loop_size))
+        for pass in 0..self.nodes.len() {
+            if let Some((step, _)) = self.pass(node).ends.iter().find(|(_, n)| *n == end) {
+                return Ok(pass * self.directions.len() + step);
             }
+            node = self.pass(node).target;
+        }
 
-            seen.push((direction_index, node));
-
-            let (left, right) = match self.nodes.get(node) {
-                Some(val) => val,
-                None => return Err(format!("Missing node '{}' in map", node))
-            };
-
-            let direction = self.directions[direction_index];
-            direction_index = (direction_index + 1)%self.directions.len();
+        Err("There is no route from 'AAA' to 'ZZZ'".to_string())
+    }
 
-            match direction {
-                Direction::Right => node = right,
-                Direction::Left => node = left
+    fn ghost_loop_info(&self, start: u32) -> Result<GhostLoop, String> {
+        // Loop through this route until we get back to a known state (based on direction index + node)
+        // A route can pass end nodes before it gets into its loop (those it passes only once), and can pass multiple
+        // end nodes (or the same one multiple times) within its loop.
+        // Note: for the real input there is a single end node, reached after exactly the loop size, but we don't depend on that.
+
+        // At the start of a pass the direction index is always 0, so we can find the loop by jumping full passes.
+        let passes = self.directions.len();
+        let cycle = find_cycle(start, |node| self.pass(*node).target, |node| *node);
+        let loop_size = cycle.period * passes;
+
+        // The route might get into its loop halfway through the pass before. Walk it next to the same pass one loop
+        // later, the first step they are on the same node is where the loop starts.
+        let loop_start = match cycle.offset {
+            0 => 0,
+            offset => {
+                let mut node = successors(Some(start), |n| Some(self.pass(*n).target)).nth(offset - 1).unwrap();
+                let mut looped = successors(Some(node), |n| Some(self.pass(*n).target)).nth(cycle.period).unwrap();
+                let mut step = (offset - 1) * passes;
+                for &direction in &self.directions {
+                    if node == looped { break; }
+                    node = self.next(node, direction);
+                    looped = self.next(looped, direction);
+                    step += 1;
+                }
+                step
             }
+        };
+
+        // Go through the passes until the end of the first loop, to find out when we're at end nodes.
+        let end_indexes: Vec<_> = successors(Some(start), |n| Some(self.pass(*n).target))
+            .take(cycle.offset + cycle.period)
+            .enumerate()
+            .flat_map(|(pass, node)| self.pass(node).ends.iter().map(move |(step, _)| pass * passes + step))
+            .filter(|i| *i < loop_start + loop_size)
+            .collect();
+        let (early_ends, loop_ends): (Vec<_>, Vec<_>) = end_indexes.into_iter().partition(|i| *i < loop_start);
+
+        if loop_ends.is_empty() && early_ends.is_empty() {
+            return Err(format!("No end in loop for {}", self.names.name(start)));
         }
+        log::debug!("Loop info for {}: at an end after {:?} steps, back there every {} next steps.", self.names.name(start), loop_ends, loop_size);
+        if !early_ends.is_empty() {
+            log::debug!("  (and once after {:?} steps, before getting into the loop)", early_ends);
+        }
+
+        Ok(GhostLoop { early_ends, cycles: loop_ends.into_iter().map(|i| Cycle::new(i, loop_size)).collect() })
     }
 
     fn ghost_steps_to_end(&self) -> Result<usize, String> {
         // Take all nodes ending with 'A', and follow these paths simultaneously until they all are
         // on a node ending with 'Z'.
-        // Brute force was way too slow (of course) on the real set. We'll need to use some lcm magic.
-        // We'll handle each route one by one:
-        // - Find where they loop, and how long the loop is.
-        // - Using that information, take the first two routes; and (taking the start + loop size of the first)
-        //   find a point in time where it aligns with the second.
-        // - When found, use the alignment point as starting offset, and continue with the loop size being the lcm
-        //   of both loops.
-        // - Using that new information, find an alignment on the third route, and so on.
+        // Brute force was way too slow (of course) on the real set. We'll need to use some lcm magic:
+        // - Find where each route loops, how long the loop is, and when it gets to its end node(s).
+        // - Align all those cycles, the first moment they line up is the answer.
+        // - Unless all routes happen to be at an end before some of them got into their loop.
 
-        let start_nodes: Vec<_> = self.nodes.keys().filter(|k| k.ends_with("A")).collect();
+        let mut start_nodes: Vec<_> = self.names.iter().filter(|(_, n)| n.ends_with('A')).collect();
+        start_nodes.sort_by_key(|(_, n)| *n);
 
-        // We collect all the loop info's, giving us an initial offset and loop size.
-        let loop_info= start_nodes.iter().map(|n| self.ghost_loop_info(n)).collect::<Result<Vec<_>, _>>()?;
+        // We collect all the loop info's, giving us the end offsets and loop size.
+        let loops = start_nodes.iter().map(|(n, _)| self.ghost_loop_info(*n)).collect::<Result<Vec<_>, _>>()?;
 
-        let mut index = 1;
-        let (offset, mut cycle) = loop_info[0];
-        let mut t = offset;
+        let early = loops.iter().flat_map(|l| l.early_ends.iter())
+            .filter(|t| loops.iter().all(|l| l.is_at_end(**t)))
+            .min().copied();
 
-        loop {
-            let (next_offset, next_cycle) = loop_info[index];
+        let groups: Vec<_> = loops.iter().map(|l| l.cycles.clone()).collect();
+        let aligned = align_any(&groups)?;
 
-            if ((t + next_offset) % next_cycle) != 0 {
-                t += cycle;
-                continue;
-            } // Not yet lined up
+        if exact::is_enabled() {
+            if let Some(aligned) = aligned {
+                let cycles: Vec<_> = loops.iter().filter_map(|l| l.cycles.first()).map(|c| c.period).collect();
+                exact::check("day 8 ghost cycle lcm", aligned.period, exact::lcm(&cycles))?;
+            }
+        }
 
-            println!("Aligned route #{} ({})", index, start_nodes[index]);
-            index += 1;
+        [early, aligned.map(|c| c.offset)].into_iter().flatten().min()
+            .ok_or("Ghost routes never end up at an end node together".to_string())
+    }
+}
 
-            // If we just lined up the last route, we're done:
-            if index == loop_info.len() { return Ok(t) }
+// When a ghost route is at an end node: a few times before it gets into its loop, and then at a number of fixed points
+// in that loop.
+#[derive(Eq, PartialEq, Debug, Clone)]
+struct GhostLoop {
+    early_ends: Vec<usize>,
+    cycles: Vec<Cycle>,
+}
 
-            // Otherwise, make cycle the least common multiple of the current cycle and the next:
-            cycle = lcm(cycle, next_cycle);
-        }
+impl GhostLoop {
+    fn is_at_end(&self, steps: usize) -> bool {
+        self.early_ends.contains(&steps) || self.cycles.iter().any(|c| c.occurs_at(steps))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day08::{Direction, Map};
+    use crate::days::day08::{Branch, GhostLoop, Map, Pass};
+    use crate::util::cycles::Cycle;
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT_A, puzzle1: Some("2"), puzzle2: None },
+        Example { input: TEST_INPUT_B, puzzle1: Some("6"), puzzle2: None },
+        Example { input: GHOST_MAP, puzzle1: None, puzzle2: Some("6") },
+    ];
 
     #[test]
     fn test_parse_map() {
@@ -145,14 +202,22 @@ mod tests {
         assert!(result.is_ok(), "Expected Ok, but got Err({})", result.err().unwrap());
 
         let map = result.unwrap();
-        assert_eq!(map.directions, vec![Direction::Right,Direction::Left]);
-        assert_eq!(map.nodes.get(&"AAA".to_string()), Some(&("BBB".to_string(), "CCC".to_string())));
+        assert_eq!(map.directions, vec![Branch::Right,Branch::Left]);
+        let id = |name: &str| map.names.get(name).unwrap();
+        assert_eq!(map.nodes[id("AAA") as usize], (id("BBB"), id("CCC")));
+        // RL from AAA goes to CCC and then ZZZ, from CCC to GGG. ZZZ is an end node at every step.
+        assert_eq!(map.passes[id("AAA") as usize], Pass { target: id("ZZZ"), ends: vec![] });
+        assert_eq!(map.passes[id("CCC") as usize], Pass { target: id("GGG"), ends: vec![] });
+        assert_eq!(map.passes[id("ZZZ") as usize], Pass { target: id("ZZZ"), ends: vec![(0, id("ZZZ")), (1, id("ZZZ"))] });
 
         let result = TEST_INPUT_B.parse::<Map>();
         assert!(result.is_ok(), "Expected Ok, but got Err({})", result.err().unwrap());
 
         let map = result.unwrap();
-        assert_eq!(map.directions, vec![Direction::Left,Direction::Left,Direction::Right]);
+        assert_eq!(map.directions, vec![Branch::Left,Branch::Left,Branch::Right]);
+
+        assert_eq!("LR\n\nAAA = (BBB, ZZZ)\nZZZ = (ZZZ, ZZZ)".parse::<Map>(), Err("Missing node 'BBB' in map".to_string()));
+        assert_eq!("LR\n\nAAA = (AAA, AAA)\nAAA = (AAA, AAA)".parse::<Map>(), Err("Node 'AAA' is defined more than once".to_string()));
     }
 
     #[test]
@@ -162,6 +227,9 @@ mod tests {
 
         let map_b = TEST_INPUT_B.parse::<Map>().unwrap();
         assert_eq!(map_b.steps_to_end(), Ok(6));
+
+        let unreachable = "L\n\nAAA = (AAA, ZZZ)\nZZZ = (ZZZ, ZZZ)".parse::<Map>().unwrap();
+        assert_eq!(unreachable.steps_to_end(), Err("There is no route from 'AAA' to 'ZZZ'".to_string()));
     }
 
     #[test]
@@ -170,6 +238,44 @@ mod tests {
         assert_eq!(ghost_map.ghost_steps_to_end(), Ok(6));
     }
 
+    #[test]
+    fn test_ghost_steps_to_end_offset_loops() {
+        // 11A is first at 11Z after 3 steps, and then every 2 steps. 22A first after 2 steps, and then every 3 steps.
+        let ghost_map = OFFSET_GHOST_MAP.parse::<Map>().unwrap();
+        assert_eq!(ghost_map.ghost_loop_info(ghost_map.names.get("11A").unwrap()), Ok(GhostLoop { early_ends: vec![], cycles: vec![Cycle::new(3, 2)] }));
+        assert_eq!(ghost_map.ghost_loop_info(ghost_map.names.get("22A").unwrap()), Ok(GhostLoop { early_ends: vec![], cycles: vec![Cycle::new(2, 3)] }));
+        assert_eq!(ghost_map.ghost_steps_to_end(), Ok(5));
+
+        // With more than one direction, the loop can start halfway through a pass: 11A gets into the 11Z <-> 11B loop
+        // after 1 step, while the passes only repeat from the second one.
+        let ghost_map = "LL\n\n11A = (11Z, 11Z)\n11Z = (11B, 11B)\n11B = (11Z, 11Z)".parse::<Map>().unwrap();
+        assert_eq!(ghost_map.ghost_loop_info(ghost_map.names.get("11A").unwrap()), Ok(GhostLoop { early_ends: vec![], cycles: vec![Cycle::new(1, 2)] }));
+    }
+
+    #[test]
+    fn test_ghost_steps_to_end_multiple_ends() {
+        // 11A passes 12Z once before getting into its loop, in which it is at an end (11Z and 13Z) after 3 and 4 steps
+        // (and every 3 steps after). 22A is at 22Z after 2 steps, and then every 2 steps.
+        let ghost_map = MULTI_END_GHOST_MAP.parse::<Map>().unwrap();
+        assert_eq!(ghost_map.ghost_loop_info(ghost_map.names.get("11A").unwrap()), Ok(GhostLoop {
+            early_ends: vec![1],
+            cycles: vec![Cycle::new(3, 3), Cycle::new(4, 3)],
+        }));
+        assert_eq!(ghost_map.ghost_loop_info(ghost_map.names.get("22A").unwrap()), Ok(GhostLoop { early_ends: vec![], cycles: vec![Cycle::new(2, 2)] }));
+        assert_eq!(ghost_map.ghost_steps_to_end(), Ok(4));
+
+        // Both routes are at an end at the same time before one of them gets into its loop:
+        let ghost_map = EARLY_END_GHOST_MAP.parse::<Map>().unwrap();
+        assert_eq!(ghost_map.ghost_steps_to_end(), Ok(1));
+    }
+
+    #[test]
+    fn test_ghost_steps_to_end_never_aligned() {
+        // 11A is at an end on odd steps, 22A on even steps.
+        let ghost_map = NEVER_ALIGNED_GHOST_MAP.parse::<Map>().unwrap();
+        assert!(ghost_map.ghost_steps_to_end().is_err());
+    }
+
     const TEST_INPUT_A: &str = "\
         RL\n\
         \n\
@@ -202,46 +308,118 @@ mod tests {
         22Z = (22B, 22B)\n\
         XXX = (XXX, XXX)\
     ";
+
+    const OFFSET_GHOST_MAP: &str = "\
+        L\n\
+        \n\
+        11A = (11B, 11B)\n\
+        11B = (11C, 11C)\n\
+        11C = (11Z, 11Z)\n\
+        11Z = (11C, 11C)\n\
+        22A = (22B, 22B)\n\
+        22B = (22Z, 22Z)\n\
+        22Z = (22C, 22C)\n\
+        22C = (22D, 22D)\n\
+        22D = (22Z, 22Z)\
+    ";
+
+    const MULTI_END_GHOST_MAP: &str = "\
+        L\n\
+        \n\
+        11A = (12Z, 12Z)\n\
+        12Z = (11B, 11B)\n\
+        11B = (11Z, 11Z)\n\
+        11Z = (13Z, 13Z)\n\
+        13Z = (11B, 11B)\n\
+        22A = (22B, 22B)\n\
+        22B = (22Z, 22Z)\n\
+        22Z = (22B, 22B)\
+    ";
+
+    const EARLY_END_GHOST_MAP: &str = "\
+        L\n\
+        \n\
+        11A = (11Z, 11Z)\n\
+        11Z = (11B, 11B)\n\
+        11B = (11B, 11B)\n\
+        22A = (22Z, 22Z)\n\
+        22Z = (22Z, 22Z)\
+    ";
+
+    const NEVER_ALIGNED_GHOST_MAP: &str = "\
+        L\n\
+        \n\
+        11A = (11Z, 11Z)\n\
+        11Z = (11B, 11B)\n\
+        11B = (11Z, 11Z)\n\
+        22A = (22B, 22B)\n\
+        22B = (22Z, 22Z)\n\
+        22Z = (22B, 22B)\
+    ";
 }
 
 impl FromStr for Map {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let lines: Vec<_> = s.lines().collect();
+        let mut parser = Parser::new(s);
         // First line should be a L/R string to get the directions
         let mut directions = vec![];
-        for char in lines[0].chars() {
+        for char in parser.line()?.chars() {
             match char {
-                'R' => directions.push(Direction::Right),
-                'L' => directions.push(Direction::Left),
+                'R' => directions.push(Branch::Right),
+                'L' => directions.push(Branch::Left),
                 _ => return Err(format!("Invalid direction '{}'", char))
             }
         }
 
-        let mut nodes: HashMap<String, (String, String)> = HashMap::new();
-
-        for i in 1..lines.len() {
-            let line = lines[i];
-            if line.trim().is_empty() { continue } // ignore empty lines
-            // Each non-empty line should be a node => (left, right) mapping
-            // Each node should be 3 characters long.
-            let mut parser = Parser::new(line);
-            let src = parser.str(3)?;
-            parser.literal("=")?;
-            parser.literal("(")?;
-            let left = parser.str(3)?;
-            parser.literal(",")?;
-            let right = parser.str(3)?;
-            parser.literal(")")?;
-            parser.ensure_exhausted()?;
-
-            nodes.insert(src.clone(), (left, right));
+        let mut names = Interner::new();
+        let mut nodes: Vec<Option<(u32, u32)>> = vec![];
+
+        parser.skip_blank_lines();
+        while !parser.is_exhausted() {
+            // Each non-empty line should be a node => (left, right) mapping, like "AAA = (BBB, CCC)"
+            // Each node should be 3 characters long, so any other spacing means we'd read the wrong names.
+            let mut line = Parser::strict(parser.line()?);
+            let src = line.str(3)?;
+            line.expect_whitespace()?;
+            line.literal("=")?;
+            line.expect_whitespace()?;
+            line.literal("(")?;
+            let left = line.str(3)?;
+            line.literal(",")?;
+            line.expect_whitespace()?;
+            let right = line.str(3)?;
+            line.literal(")")?;
+            line.ensure_exhausted()?;
+
+            let src = names.intern(&src) as usize;
+            let next = (names.intern(&left), names.intern(&right));
+            nodes.resize(names.len(), None);
+            if nodes[src].replace(next).is_some() {
+                return Err(format!("Node '{}' is defined more than once", names.name(src as u32)));
+            }
         }
 
-        Ok(Map {
-            directions,
-            nodes
-        })
+        // Every node we can go to should be in the map.
+        let nodes = nodes.into_iter().enumerate()
+            .map(|(id, next)| next.ok_or(format!("Missing node '{}' in map", names.name(id as u32))))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let is_end: Vec<_> = names.iter().map(|(_, name)| name.ends_with('Z')).collect();
+        let mut map = Map { directions, names, nodes, passes: vec![] };
+        map.passes = (0..map.nodes.len() as u32).map(|start| {
+            let mut node = start;
+            let mut ends = vec![];
+            for (step, direction) in map.directions.iter().enumerate() {
+                if is_end[node as usize] {
+                    ends.push((step, node));
+                }
+                node = map.next(node, *direction);
+            }
+            Pass { target: node, ends }
+        }).collect();
+
+        Ok(map)
     }
 }
@@ -1,25 +1,42 @@
 use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
-use crate::days::Day;
-use crate::util::geometry::{Directions, Grid, Point};
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::geometry::{Dir, Grid, Point};
+use crate::util::visualize::{self, Visualizer};
 
 pub const DAY10: Day = Day {
-    puzzle1,
-    puzzle2
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let grid = input.parse::<PipeGrid>().unwrap();
+struct Solution;
 
-    let result = get_steps_to_furthest_point(&grid).unwrap();
-    println!("It takes {} steps to the furthest point in the loop.", result);
-}
-fn puzzle2(input: &String) {
-    let grid = input.parse::<PipeGrid>().unwrap();
+impl StructuredDay for Solution {
+    type Parsed = PipeGrid;
+
+    fn parse(input: &str) -> AocResult<PipeGrid> {
+        input.parse::<PipeGrid>()
+    }
+
+    fn part1(grid: &PipeGrid) -> AocResult<String> {
+        let result = get_steps_to_furthest_point(grid)?;
+        let mut output = format!("It takes {} steps to the furthest point in the loop.", result);
 
-    let result = get_tiles_enclosed_by_loop(&grid).unwrap();
-    println!("Grid contains {} tiles enclosed in the loop.", result);
+        for other in get_loops(grid)?.iter().skip(1) {
+            output += &format!("\n(Ignored loop not connected to the start of length {}, enclosing {} tiles)", other.length(), other.get_enclosed_tiles());
+        }
+        Ok(output)
+    }
+
+    fn part2(grid: &PipeGrid) -> AocResult<String> {
+        let enclosure = get_enclosure_map(grid)?;
+        visualize::show("Enclosed tiles", &EnclosedLoop { grid, enclosure: &enclosure });
+        let result = count_enclosed_tiles(&enclosure);
+        Ok(format!("Grid contains {} tiles enclosed in the loop.", result))
+    }
 }
 
 #[derive(Eq, PartialEq, Debug, Copy, Clone, Default)]
@@ -36,64 +53,143 @@ enum Pipe {
 }
 
 impl Pipe {
-    fn can_enter(&self, towards: Directions) -> bool {
+    fn can_enter(&self, towards: Dir) -> bool {
         match self {
             Self::None => false,
             Self::Start => true,
-            Self::TopBottom => towards == Directions::Bottom || towards == Directions::Top,
-            Self::LeftRight => towards == Directions::Left || towards == Directions::Right,
-            Self::LeftTop => towards == Directions::Right || towards == Directions::Bottom,
-            Self::LeftBottom => towards == Directions::Right || towards == Directions::Top,
-            Self::RightTop => towards == Directions::Left || towards == Directions::Bottom,
-            Self::RightBottom => towards == Directions::Left || towards == Directions::Top,
+            Self::TopBottom => towards == Dir::South || towards == Dir::North,
+            Self::LeftRight => towards == Dir::West || towards == Dir::East,
+            Self::LeftTop => towards == Dir::East || towards == Dir::South,
+            Self::LeftBottom => towards == Dir::East || towards == Dir::North,
+            Self::RightTop => towards == Dir::West || towards == Dir::South,
+            Self::RightBottom => towards == Dir::West || towards == Dir::North,
         }
     }
 
-    fn get_next_direction(&self, towards: Directions) -> Option<Directions> {
+    fn get_connections(&self) -> Vec<Dir> {
+        match self {
+            Self::None => vec![],
+            Self::Start => vec![Dir::North, Dir::East, Dir::South, Dir::West],
+            Self::TopBottom => vec![Dir::North, Dir::South],
+            Self::LeftRight => vec![Dir::West, Dir::East],
+            Self::LeftTop => vec![Dir::West, Dir::North],
+            Self::LeftBottom => vec![Dir::West, Dir::South],
+            Self::RightTop => vec![Dir::East, Dir::North],
+            Self::RightBottom => vec![Dir::East, Dir::South],
+        }
+    }
+
+    fn get_next_direction(&self, towards: Dir) -> Option<Dir> {
         if !self.can_enter(towards) { return None }
         match self {
             Self::None => None,
             Self::Start => None,
             Self::TopBottom => Some(towards),
             Self::LeftRight => Some(towards),
-            Self::LeftTop => if towards == Directions::Right { Some(Directions::Top) } else { Some(Directions::Left) }
-            Self::LeftBottom => if towards == Directions::Right { Some(Directions::Bottom) } else { Some(Directions::Left) }
-            Self::RightTop => if towards == Directions::Left { Some(Directions::Top) } else { Some(Directions::Right) }
-            Self::RightBottom => if towards == Directions::Left { Some(Directions::Bottom) } else { Some(Directions::Right) }
+            Self::LeftTop => if towards == Dir::East { Some(Dir::North) } else { Some(Dir::West) }
+            Self::LeftBottom => if towards == Dir::East { Some(Dir::South) } else { Some(Dir::West) }
+            Self::RightTop => if towards == Dir::West { Some(Dir::North) } else { Some(Dir::East) }
+            Self::RightBottom => if towards == Dir::West { Some(Dir::South) } else { Some(Dir::East) }
         }
     }
 }
 
 type PipeGrid = Grid<Pipe>;
 
+// Where a tile is relative to the loop through the start.
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Default)]
+enum Enclosure {
+    Loop,
+    Inside,
+    #[default]
+    Outside,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+struct PipeLoop {
+    pipes: Vec<(Point, Pipe)>,
+}
+
+impl PipeLoop {
+    fn length(&self) -> usize {
+        self.pipes.len()
+    }
+
+    fn get_enclosed_tiles(&self) -> usize {
+        // Shoelace formula gives us the area of the polygon through all pipe centers, Pick's theorem then gives us the
+        // number of tiles inside of it: area = inside + (boundary / 2) - 1
+        let points: Vec<_> = self.pipes.iter().map(|(p, _)| *p).collect();
+        let double_area = points.iter().zip(points.iter().cycle().skip(1))
+            .map(|(a, b)| a.x * b.y - b.x * a.y)
+            .sum::<isize>()
+            .unsigned_abs();
+
+        (double_area + 2 - self.length()) / 2
+    }
+}
+
+fn get_loops(grid: &PipeGrid) -> Result<Vec<PipeLoop>, String> {
+    // The loop through the start is the one we're after, but the grid can contain other (decoy) loops as well.
+    // We find those by trying to follow every pipe we haven't seen in a loop yet, and check if we get back to it.
+    let main_loop = PipeLoop { pipes: get_pipes_in_loop(grid)? };
+    let mut seen: HashSet<Point> = main_loop.pipes.iter().map(|(p, _)| *p).collect();
+    let mut result = vec![main_loop];
+
+    let mut entries = grid.entries();
+    entries.sort_by_key(|(p, _)| *p);
+
+    for (start, pipe) in entries {
+        if seen.contains(&start) || pipe == Pipe::None || pipe == Pipe::Start { continue; }
+
+        let mut pipes = vec![(start, pipe)];
+        let mut direction = pipe.get_connections()[0];
+        let mut current_point = start;
+
+        let closed = loop {
+            let next = match grid.get_adjacent_entry(&current_point, direction) {
+                Some((point, pipe)) if pipe.can_enter(direction) => (point, pipe),
+                _ => break false // Dead end (or leaving the grid)
+            };
+            if next.0 == start { break true }
+            if next.1 == Pipe::Start || seen.contains(&next.0) { break false }
+
+            direction = match next.1.get_next_direction(direction) {
+                Some(direction) => direction,
+                None => break false
+            };
+            current_point = next.0;
+            pipes.push(next);
+        };
+
+        // Either way, none of these pipes can start a new loop anymore.
+        seen.extend(pipes.iter().map(|(p, _)| *p));
+        if closed {
+            result.push(PipeLoop { pipes });
+        }
+    }
+
+    Ok(result)
+}
+
 fn get_pipes_in_loop(grid: &PipeGrid) -> Result<Vec<(Point, Pipe)>, String> {
-    let start = match grid.entries().iter().find(|(_, pipe)| Pipe::Start.eq(pipe)) {
-        Some((point, _)) => point.clone(),
+    let start = match grid.filter_points(|pipe| Pipe::Start.eq(pipe)).first() {
+        Some(point) => *point,
         None => return Err(format!("Could not find a start node in the grid"))
     };
 
     // start is the only node that can connect on four sides. As mentioned, only two can be followed
     // so we just take off in the first one that connects.
-    let mut next_direction =
-        if grid.get_adjacent(&start, Directions::Top).first().is_some_and(|p| p.can_enter(Directions::Top)) {
-            Directions::Top
-        } else if grid.get_adjacent(&start, Directions::Right).first().is_some_and(|p| p.can_enter(Directions::Right)) {
-            Directions::Right
-        } else if grid.get_adjacent(&start, Directions::Bottom).first().is_some_and(|p| p.can_enter(Directions::Bottom)) {
-            Directions::Bottom
-        } else if grid.get_adjacent(&start, Directions::Left).first().is_some_and(|p| p.can_enter(Directions::Left)) {
-            Directions::Left
-        } else {
-            return Err(format!("Could not move from start node?!"))
-        };
+    let mut next_direction = Dir::ALL.into_iter()
+        .find(|&d| grid.get_adjacent_entry(&start, d).is_some_and(|(_, p)| p.can_enter(d)))
+        .ok_or(format!("Could not move from start node?!"))?;
 
     let mut result = vec![(start, Pipe::Start)];
-    let (mut current_point, mut current_pipe) = grid.get_adjacent_entries(&start, next_direction).first().ok_or(format!("Couldn't traverse"))?;
+    let (mut current_point, mut current_pipe) = grid.get_adjacent_entry(&start, next_direction).ok_or(format!("Couldn't traverse"))?;
 
     while current_point.ne(&start) {
         result.push((current_point.clone(), current_pipe.clone()));
         next_direction = current_pipe.get_next_direction(next_direction).ok_or(format!("Could not traverse pipe"))?;
-        (current_point, current_pipe) = grid.get_adjacent_entries(&current_point, next_direction).first().cloned().ok_or(format!("Could not find next pipe"))?;
+        (current_point, current_pipe) = grid.get_adjacent_entry(&current_point, next_direction).ok_or(format!("Could not find next pipe"))?;
     }
 
     Ok(result)
@@ -103,15 +199,14 @@ fn get_steps_to_furthest_point(grid: &PipeGrid) -> Result<usize, String> {
     // In both the samples and my real input, there is only two valid ways from the start point.
     // From there on it's just following the trail until we're round to get the total length of the
     // pipe.
-    let pipes = get_pipes_in_loop(grid)?;
-    let length = pipes.len();
+    let length = get_loop_path(grid)?.len();
 
     // Make sure to take the middle in case length / 2 rounds down
     // E.g. 15 steps => 15/2 = 7, but we need the middle point which is 8
     Ok(length / 2 + length % 2)
 }
 
-fn get_tiles_enclosed_by_loop(grid: &PipeGrid) -> Result<usize, String> {
+fn get_enclosure_map(grid: &PipeGrid) -> Result<Grid<Enclosure>, String> {
     // We need to find tiles that are inside the loop. With a noteworthy note that two pipes next
     // to each other can be slipped by. As such, the tiles should be on the inside of the loop, and
     // not between outsides.
@@ -122,25 +217,23 @@ fn get_tiles_enclosed_by_loop(grid: &PipeGrid) -> Result<usize, String> {
     let pipes = get_pipes_in_loop(grid)?;
     let (start, _) = pipes.iter().min_by_key(|(point, _)| point).ok_or(format!("Could not find a pipe?!"))?;
 
-    let mut directional_map: Grid<HashSet<Directions>> = Grid::empty();
-    directional_map.set(start.clone(), HashSet::from([Directions::Right]));
-    let mut next_direction = Directions::Right; // Start by going right.
-    let (mut current_point, mut current_pipe) = grid.get_adjacent_entries(&start, next_direction).first().ok_or(format!("Couldn't traverse"))?;
+    let mut directional_map: Grid<HashSet<Dir>> = Grid::empty();
+    directional_map.set(start.clone(), HashSet::from([Dir::East]));
+    let mut next_direction = Dir::East; // Start by going right.
+    let (mut current_point, mut current_pipe) = grid.get_adjacent_entry(&start, next_direction).ok_or(format!("Couldn't traverse"))?;
 
-    fn get_next_direction(pipe: Pipe, point: Point, towards: Directions, grid: &PipeGrid) -> Option<Directions> {
+    fn get_next_direction(pipe: Pipe, point: Point, towards: Dir, grid: &PipeGrid) -> Option<Dir> {
         if pipe.eq(&Pipe::None) { None }
         else if pipe.ne(&Pipe::Start) { pipe.get_next_direction(towards) }
         else {
             // Find the other pipe connecting to start, and continue in that direction:
             let mut options = vec![];
             // If going towards top, we're coming from bottom, etc.
-            if towards.ne(&Directions::Top) { options.push(Directions::Bottom) }
-            if towards.ne(&Directions::Right) { options.push(Directions::Left) }
-            if towards.ne(&Directions::Bottom) { options.push(Directions::Top) }
-            if towards.ne(&Directions::Left) { options.push(Directions::Right) }
-            options.into_iter().filter_map(|d| grid.get_adjacent(&point, d.clone()).first().map(|p| (p.clone(), d)))
-                .filter(|(p, d)| p.can_enter(d.clone()))
-                .next().map(|(_, d)| d)
+            if towards.ne(&Dir::North) { options.push(Dir::South) }
+            if towards.ne(&Dir::East) { options.push(Dir::West) }
+            if towards.ne(&Dir::South) { options.push(Dir::North) }
+            if towards.ne(&Dir::West) { options.push(Dir::East) }
+            options.into_iter().find(|&d| grid.get_adjacent_entry(&point, d).is_some_and(|(_, p)| p.can_enter(d)))
         }
     }
 
@@ -148,28 +241,70 @@ fn get_tiles_enclosed_by_loop(grid: &PipeGrid) -> Result<usize, String> {
         let first_direction = next_direction;
         next_direction = get_next_direction(current_pipe, current_point, next_direction, grid).ok_or(format!("Could not traverse pipe {}", current_pipe))?;
         directional_map.set(current_point, HashSet::from([first_direction, next_direction]));
-        (current_point, current_pipe) = grid.get_adjacent_entries(&current_point, next_direction).first().cloned().ok_or(format!("Could not find next pipe"))?;
+        (current_point, current_pipe) = grid.get_adjacent_entry(&current_point, next_direction).ok_or(format!("Could not find next pipe"))?;
     }
 
-    fn is_enclosed(map: &Grid<HashSet<Directions>>, point: &Point) -> bool {
-        map.get_in_direction(point, Directions::Top).first().is_some_and(|d| d.contains(&Directions::Right)) &&
-            map.get_in_direction(point, Directions::Right).first().is_some_and(|d| d.contains(&Directions::Bottom)) &&
-            map.get_in_direction(point, Directions::Bottom).first().is_some_and(|d| d.contains(&Directions::Left)) &&
-            map.get_in_direction(point, Directions::Left).first().is_some_and(|d| d.contains(&Directions::Top))
+    fn is_enclosed(map: &Grid<HashSet<Dir>>, point: &Point) -> bool {
+        map.get_in_direction(point, Dir::North).first().is_some_and(|d| d.contains(&Dir::East)) &&
+            map.get_in_direction(point, Dir::East).first().is_some_and(|d| d.contains(&Dir::South)) &&
+            map.get_in_direction(point, Dir::South).first().is_some_and(|d| d.contains(&Dir::West)) &&
+            map.get_in_direction(point, Dir::West).first().is_some_and(|d| d.contains(&Dir::North))
     }
 
-    Ok(directional_map.points()
-        .iter()
-        // Take all points that are not part of the loop:
-        .filter(|p| pipes.iter().all(|(pp, _)| pp.ne(p)))
-        .filter(|p| is_enclosed(&directional_map, p))
-        .count())
+    let loop_points: HashSet<Point> = pipes.iter().map(|(p, _)| *p).collect();
+    let enclosure = grid.map(|point, _| if loop_points.contains(point) {
+        Enclosure::Loop
+    } else if is_enclosed(&directional_map, point) {
+        Enclosure::Inside
+    } else {
+        Enclosure::Outside
+    });
+
+    Ok(enclosure)
+}
+
+fn count_enclosed_tiles(enclosure: &Grid<Enclosure>) -> usize {
+    enclosure.filter_points(|e| Enclosure::Inside.eq(e)).len()
+}
+
+// The pipes of the loop in the order they are connected, starting at the start.
+fn get_loop_path(grid: &PipeGrid) -> Result<Vec<Point>, String> {
+    Ok(get_pipes_in_loop(grid)?.into_iter().map(|(p, _)| p).collect())
+}
+
+// Draws the loop with the box-drawing characters of the pipes, shading the tiles inside it and leaving out the rest.
+fn format_enclosure(grid: &PipeGrid, enclosure: &Grid<Enclosure>) -> String {
+    enclosure.render_with(|point, kind| match kind {
+        Enclosure::Loop => grid.get(point).unwrap_or_default().to_string(),
+        Enclosure::Inside => "░".to_string(),
+        Enclosure::Outside => " ".to_string(),
+    })
+}
+
+// The grid with the tiles enclosed by the loop shaded, for --visualize
+struct EnclosedLoop<'a> {
+    grid: &'a PipeGrid,
+    enclosure: &'a Grid<Enclosure>,
+}
+
+impl Visualizer for EnclosedLoop<'_> {
+    fn render_frame(&self) -> String {
+        format_enclosure(self.grid, self.enclosure)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day10::{get_steps_to_furthest_point, get_tiles_enclosed_by_loop, PipeGrid};
-    use crate::util::geometry::Bounds;
+    use crate::days::day10::{format_enclosure, get_enclosure_map, get_loop_path, get_loops, get_steps_to_furthest_point, count_enclosed_tiles, Enclosure, Pipe, PipeGrid};
+    use crate::util::geometry::{Bounds, Point};
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("8"), puzzle2: Some("1") },
+        Example { input: TEST_INPUT_NEST_1, puzzle1: None, puzzle2: Some("4") },
+        Example { input: TEST_INPUT_NEST_2, puzzle1: None, puzzle2: Some("8") },
+        Example { input: TEST_INPUT_NEST_3, puzzle1: None, puzzle2: Some("10") },
+    ];
 
     #[test]
     fn test_parse_and_fmt() {
@@ -195,15 +330,65 @@ mod tests {
     }
 
     #[test]
-    fn test_get_tiles_enclosed_by_loop() {
+    fn test_count_enclosed_tiles() {
         let grid = TEST_INPUT_NEST_1.parse::<PipeGrid>().unwrap();
-        assert_eq!(get_tiles_enclosed_by_loop(&grid), Ok(4));
+        assert_eq!(get_enclosure_map(&grid).map(|e| count_enclosed_tiles(&e)), Ok(4));
 
         let grid = TEST_INPUT_NEST_2.parse::<PipeGrid>().unwrap();
-        assert_eq!(get_tiles_enclosed_by_loop(&grid), Ok(8));
+        assert_eq!(get_enclosure_map(&grid).map(|e| count_enclosed_tiles(&e)), Ok(8));
 
         let grid = TEST_INPUT_NEST_3.parse::<PipeGrid>().unwrap();
-        assert_eq!(get_tiles_enclosed_by_loop(&grid), Ok(10));
+        assert_eq!(get_enclosure_map(&grid).map(|e| count_enclosed_tiles(&e)), Ok(10));
+    }
+
+    #[test]
+    fn test_get_loop_path() {
+        let grid = TEST_INPUT.parse::<PipeGrid>().unwrap();
+        let path = get_loop_path(&grid).unwrap();
+
+        assert_eq!(path.len(), 16);
+        assert_eq!(path[..4], [Point { x: 0, y: 2 }, Point { x: 1, y: 2 }, Point { x: 1, y: 1 }, Point { x: 2, y: 1 }]);
+        // Every pipe connects to the next, and the last one back to the start:
+        for (a, b) in path.iter().zip(path.iter().cycle().skip(1)) {
+            assert_eq!(a.manhattan_distance(b), 1);
+        }
+    }
+
+    #[test]
+    fn test_get_enclosure_map() {
+        let grid = TEST_INPUT_NEST_1.parse::<PipeGrid>().unwrap();
+        let enclosure = get_enclosure_map(&grid).unwrap();
+
+        assert_eq!(enclosure.bounds, grid.bounds);
+        assert_eq!(enclosure.values().iter().filter(|e| Enclosure::Loop.eq(e)).count(), get_loop_path(&grid).unwrap().len());
+        assert_eq!(format_enclosure(&grid, &enclosure), "\
+            \x20          \n\
+            \x20◎───────┐ \n\
+            \x20│┌─────┐│ \n\
+            \x20││     ││ \n\
+            \x20││     ││ \n\
+            \x20│└─┐ ┌─┘│ \n\
+            \x20│░░│ │░░│ \n\
+            \x20└──┘ └──┘ \n\
+            \x20          \
+        ");
+    }
+
+    #[test]
+    fn test_get_loops() {
+        let grid = TEST_INPUT_DECOY.parse::<PipeGrid>().unwrap();
+        let loops = get_loops(&grid).unwrap();
+
+        assert_eq!(loops.len(), 4);
+        assert_eq!(loops[0].pipes[0].1, Pipe::Start);
+        assert_eq!(loops.iter().map(|l| l.length()).collect::<Vec<_>>(), vec![8, 4, 10, 4]);
+        assert_eq!(loops.iter().map(|l| l.get_enclosed_tiles()).collect::<Vec<_>>(), vec![1, 0, 2, 0]);
+
+        // The start loop should match the other approach:
+        for (input, expected) in [(TEST_INPUT_NEST_1, 4), (TEST_INPUT_NEST_2, 8), (TEST_INPUT_NEST_3, 10)] {
+            let grid = input.parse::<PipeGrid>().unwrap();
+            assert_eq!(get_loops(&grid).unwrap()[0].get_enclosed_tiles(), expected);
+        }
     }
 
     const TEST_INPUT: &str = "\
@@ -214,6 +399,16 @@ mod tests {
         LJ.LJ\
     ";
 
+    const TEST_INPUT_DECOY: &str = "\
+        .....F7.\n\
+        .S-7.LJ.\n\
+        .|.|....\n\
+        .L-J....\n\
+        F--7.-|.\n\
+        |..|F7..\n\
+        L--JLJ..\
+    ";
+
     const TEST_INPUT_NEST_1: &str= "\
         ...........\n\
         .S-------7.\n\
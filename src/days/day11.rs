@@ -1,31 +1,34 @@
-use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
-use crate::days::Day;
-use crate::util::collection::CollectionExtension;
-use crate::util::geometry::{Grid, Point};
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::geometry::Grid;
 
 pub const DAY11: Day = Day {
-    puzzle1,
-    puzzle2,
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let map = input.parse::<GalaxyMap>().unwrap();
-    let galaxy_map = expand_galaxy(&map,2, false);
+struct Solution;
 
-    let distances = get_distance_between_galaxies(&galaxy_map);
-    let sum: isize = distances.iter().sum();
-    println!("Sum of distances between pairs of galaxies is: {}", sum);
-}
+impl StructuredDay for Solution {
+    type Parsed = GalaxyMap;
+
+    fn parse(input: &str) -> AocResult<GalaxyMap> {
+        // Only the galaxies matter, empty space before the first (or after the last) galaxy doesn't change any distance.
+        let mut map = input.parse::<GalaxyMap>()?;
+        map.normalize();
+        Ok(map)
+    }
 
-fn puzzle2(input: &String) {
-    let map = input.parse::<GalaxyMap>().unwrap();
-    let galaxy_map = expand_galaxy(&map,1_000_000, false);
+    fn part1(map: &GalaxyMap) -> AocResult<String> {
+        Ok(format!("Sum of distances between pairs of galaxies is: {}", sum_of_distances(map, 2)))
+    }
 
-    let distances = get_distance_between_galaxies(&galaxy_map);
-    let sum: isize = distances.iter().sum();
-    println!("Sum of distances between pairs of galaxies is: {}", sum);
+    fn part2(map: &GalaxyMap) -> AocResult<String> {
+        Ok(format!("Sum of distances between pairs of galaxies is: {}", sum_of_distances(map, 1_000_000)))
+    }
 }
 
 #[derive(Eq, PartialEq, Debug, Copy, Clone, Default)]
@@ -37,44 +40,35 @@ enum MapTile {
 
 type GalaxyMap = Grid<MapTile>;
 
-fn expand_galaxy(galaxy: &GalaxyMap, factor: usize, fill_empty: bool) -> GalaxyMap {
-    // The given galaxy will expand any empty row and column, for puzzle one (at least) they need
-    // to be doubled.
-
-    // To arrange this, we'll gather the empty rows and columns, and just offset any non-empty point
-    // depending on how many empty lines seen.
-    let empty_cols: Vec<_> = galaxy.bounds.x().filter(|col| galaxy.get_column(*col).iter().all(|tile| MapTile::Nothing.eq(tile))).collect();
-    let empty_rows: Vec<_> = galaxy.bounds.y().filter(|row| galaxy.get_row(*row).iter().all(|tile| MapTile::Nothing.eq(tile))).collect();
-
-    let mut new_galaxy = GalaxyMap::new(HashMap::from_iter(
-        galaxy.entries().into_iter().filter(|(_, tile)| MapTile::Galaxy.eq(tile))
-            .map(|(point, tile)| {
-                let empty_cols_before = empty_cols.iter().filter(|col| point.x > **col).count();
-                let empty_rows_before = empty_rows.iter().filter(|row| point.y > **row).count();
-                let x = point.x + ((empty_cols_before * factor) - empty_cols_before) as isize;
-                let y = point.y + ((empty_rows_before * factor) - empty_rows_before) as isize;
-                (Point::from((x, y)), tile)
-            })
-    ));
-    // To make testing / formatting a bit easier, fill the rest of the bounds explicitly with nothing.
-    if fill_empty {
-        for point in new_galaxy.points() {
-            if new_galaxy.get(&point).is_none() {
-                new_galaxy.set(point, MapTile::Nothing);
-            }
-        }
-    }
-    new_galaxy
-}
+fn sum_of_distances(galaxy: &GalaxyMap, factor: usize) -> usize {
+    // The given galaxy will expand any empty row and column by the given factor. Since the distance between two
+    // galaxies is a manhattan distance, we can handle both axes on their own, and never need the expanded map.
+    let galaxies_per_col = galaxy.bounds.x().map(|col| galaxy.iter_col(col).filter(|tile| MapTile::Galaxy.eq(tile)).count());
+    let galaxies_per_row = galaxy.bounds.y().map(|row| galaxy.iter_row(row).filter(|tile| MapTile::Galaxy.eq(tile)).count());
 
-fn get_distance_between_galaxies(galaxy: &GalaxyMap) -> Vec<isize> {
-    let galaxies: Vec<_> = galaxy.entries().into_iter().filter(|(_, tile)| MapTile::Galaxy.eq(tile)).map(|(p, _)| p).collect();
+    sum_of_axis_distances(galaxies_per_col, factor) + sum_of_axis_distances(galaxies_per_row, factor)
+}
 
-    let mut result = vec![];
+// Sums the distances along one axis between all pairs of galaxies, given the number of galaxies on each line.
+fn sum_of_axis_distances(galaxies_per_line: impl Iterator<Item=usize>, factor: usize) -> usize {
+    // Going over the lines in order, every galaxy on the current line is at the same distance from each galaxy seen
+    // before it; that adds up to (seen * position - sum of the positions seen) per galaxy.
+    let mut position = 0;
+    let mut seen = 0;
+    let mut sum_of_positions = 0;
+    let mut result = 0;
+
+    for count in galaxies_per_line {
+        if count == 0 {
+            // An empty line, these take up 'factor' lines after expanding.
+            position += factor;
+            continue;
+        }
 
-    for i in 0..galaxies.len() {
-        let current_point = galaxies[i];
-        result.push_all(&galaxies[(i+1)..].iter().map(|other| other.manhattan_distance(&current_point)).collect())
+        result += count * (seen * position - sum_of_positions);
+        seen += count;
+        sum_of_positions += count * position;
+        position += 1;
     }
 
     result
@@ -82,44 +76,28 @@ fn get_distance_between_galaxies(galaxy: &GalaxyMap) -> Vec<isize> {
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day11::{expand_galaxy, GalaxyMap, get_distance_between_galaxies};
+    use crate::days::day11::{GalaxyMap, sum_of_axis_distances, sum_of_distances};
+    use crate::days::Example;
 
-    #[test]
-    fn test_expand_galaxy() {
-        let galaxy = TEST_INPUT.parse::<GalaxyMap>().unwrap();
-        let expanded = expand_galaxy(&galaxy,2, true);
-
-        assert_eq!(format!("{}", expanded), "\
-            ....#........\n\
-            .........#...\n\
-            #............\n\
-            .............\n\
-            .............\n\
-            ........#....\n\
-            .#...........\n\
-            ............#\n\
-            .............\n\
-            .............\n\
-            .........#...\n\
-            #....#.......\
-        ");
-    }
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("374"), puzzle2: Some("82000210") },
+    ];
 
     #[test]
-    fn test_distance_between_galaxies() {
+    fn test_sum_of_distances() {
         let galaxy = TEST_INPUT.parse::<GalaxyMap>().unwrap();
-        let expanded = expand_galaxy(&galaxy, 2, false);
-
-        let distances = get_distance_between_galaxies(&expanded);
-        assert_eq!(distances.iter().sum::<isize>(), 374);
 
-        let expanded = expand_galaxy(&galaxy, 10, false);
-        let distances = get_distance_between_galaxies(&expanded);
-        assert_eq!(distances.iter().sum::<isize>(), 1030);
+        assert_eq!(sum_of_distances(&galaxy, 1), 292);
+        assert_eq!(sum_of_distances(&galaxy, 2), 374);
+        assert_eq!(sum_of_distances(&galaxy, 10), 1030);
+        assert_eq!(sum_of_distances(&galaxy, 100), 8410);
+    }
 
-        let expanded = expand_galaxy(&galaxy, 100, false);
-        let distances = get_distance_between_galaxies(&expanded);
-        assert_eq!(distances.iter().sum::<isize>(), 8410);
+    #[test]
+    fn test_sum_of_axis_distances() {
+        // Galaxies at 0, 0, 3 (with lines 1 and 2 empty, doubled): 0 + 5 + 5
+        assert_eq!(sum_of_axis_distances([2, 0, 0, 1].into_iter(), 2), 10);
+        assert_eq!(sum_of_axis_distances([0, 1, 0].into_iter(), 5), 0);
     }
 
     const TEST_INPUT: &str = "\
@@ -1,26 +1,58 @@
 use std::collections::{HashMap};
 use std::str::FromStr;
-use crate::days::Day;
+use std::thread;
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
 use crate::util::collection::CollectionExtension;
-use crate::util::number::parse_usize;
+use crate::util::number::parse_num;
+use crate::util::{profile, threads};
 
 pub const DAY12: Day = Day {
-    puzzle1,
-    puzzle2
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let lines = input.lines().map(|l| l.parse::<SpringLine>()).collect::<Result<Vec<_>, _>>().unwrap();
+struct Solution;
 
-    let result: usize = lines.iter().map(|l| l.get_valid_permutations()).sum();
-    println!("Sum of valid permutations: {}", result);
+impl StructuredDay for Solution {
+    type Parsed = Vec<SpringLine>;
+
+    fn parse(input: &str) -> AocResult<Vec<SpringLine>> {
+        input.lines().map(|l| l.parse::<SpringLine>()).collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    fn part1(lines: &Vec<SpringLine>) -> AocResult<String> {
+        let result: usize = lines.iter().map(|l| l.get_valid_permutations()).sum();
+        Ok(format!("Sum of valid permutations: {}", result))
+    }
+
+    fn part2(lines: &Vec<SpringLine>) -> AocResult<String> {
+        let unfolded: Vec<_> = profile::span!("unfold").run(|| lines.iter().map(|l| l.unfold()).collect());
+        let threads = threads::available();
+        let result = sum_valid_permutations(&unfolded, threads);
+        Ok(format!("Sum of valid unfolded permutations: {}", result))
+    }
 }
 
-fn puzzle2(input: &String) {
-    let lines = input.lines().map(|l| l.parse::<SpringLine>()).collect::<Result<Vec<_>, _>>().unwrap();
+// Every line is counted on its own, so we can spread them over a couple of threads. With a single thread we just go
+// through them in order.
+fn sum_valid_permutations(lines: &[SpringLine], threads: usize) -> usize {
+    if threads <= 1 {
+        return lines.iter().map(|l| l.get_valid_permutations()).sum();
+    }
 
-    let result: usize = lines.iter().map(|l| l.unfold().get_valid_permutations()).sum();
-    println!("Sum of valid unfolded permutations: {}", result);
+    let chunk_size = lines.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = lines.chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || {
+                let _span = profile::span!("{} lines", chunk.len());
+                chunk.iter().map(|l| l.get_valid_permutations()).sum::<usize>()
+            }))
+            .collect();
+
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    })
 }
 
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
@@ -92,6 +124,12 @@ impl SpringLine {
     }
 
     fn get_valid_permutations(&self) -> usize {
+        self.count_valid_permutations().0
+    }
+
+    // Returns the number of valid permutations, and the number of calls it took to get there (so tests can make sure
+    // we don't end up exploring an exponential number of branches again).
+    fn count_valid_permutations(&self) -> (usize, usize) {
         // Depth first, with cache.
         // Cache based on (index, group_index, current_group) storing the combinations found from that point.
         #[derive(Eq, PartialEq, Hash, Debug)]
@@ -99,8 +137,10 @@ impl SpringLine {
         type PermutationsCache = HashMap<PermutationsKey, usize>;
 
         let mut cache: PermutationsCache = PermutationsCache::new();
+        let mut calls = 0;
 
-        fn get_permutations(line: &SpringLine, current: Vec<Spring>, cache: &mut PermutationsCache) -> usize {
+        fn get_permutations(line: &SpringLine, current: Vec<Spring>, cache: &mut PermutationsCache, calls: &mut usize) -> usize {
+            *calls += 1;
             if let Some(index) = current.iter().position(|s| Spring::Unknown.eq(s)) {
                 let (current_group, group_index) = match line.get_group_state(&current[0..index]) {
                     Some(v) => v,
@@ -134,11 +174,11 @@ impl SpringLine {
 
                 if add_operational {
                     next_group[index] = Spring::Operational;
-                    operational = get_permutations(line, next_group.clone(), cache);
+                    operational = get_permutations(line, next_group.clone(), cache, calls);
                 }
                 if add_broken {
                     next_group[index] = Spring::Broken;
-                    broken = get_permutations(line, next_group.clone(), cache);
+                    broken = get_permutations(line, next_group.clone(), cache, calls);
                 }
 
                 cache.insert(key, operational + broken);
@@ -150,7 +190,8 @@ impl SpringLine {
             }
         }
 
-        get_permutations(self, self.springs.clone(), &mut cache)
+        let permutations = get_permutations(self, self.springs.clone(), &mut cache, &mut calls);
+        (permutations, calls)
     }
 
     fn unfold(&self) -> Self {
@@ -170,7 +211,12 @@ impl SpringLine {
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day12::SpringLine;
+    use crate::days::day12::{sum_valid_permutations, SpringLine};
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("21"), puzzle2: Some("525152") },
+    ];
 
     #[test]
     fn test_get_valid_permutations() {
@@ -196,6 +242,32 @@ mod tests {
         assert_eq!(lines[5].unfold().get_valid_permutations(), 506250);
     }
 
+    #[test]
+    fn test_sum_valid_permutations() {
+        let lines = TEST_INPUT.lines().map(|l| l.parse::<SpringLine>().map(|l| l.unfold())).collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(sum_valid_permutations(&lines, 1), 525152);
+        // Any number of threads should add up to the same, also when there are more threads than lines
+        assert_eq!(sum_valid_permutations(&lines, 4), 525152);
+        assert_eq!(sum_valid_permutations(&lines, 16), 525152);
+        assert_eq!(sum_valid_permutations(&[], 4), 0);
+    }
+
+    #[test]
+    fn test_valid_permutations_complexity() {
+        // All unknowns with a lot of small groups is the worst case for brute forcing (C(60, 15) permutations...)
+        let springs = "?".repeat(59);
+        let groups = vec!["1"; 15].join(",");
+        let line = format!("{} {}", springs, groups).parse::<SpringLine>().unwrap();
+
+        let (permutations, calls) = line.count_valid_permutations();
+        assert_eq!(permutations, 344_867_425_584); // C(45, 15), spreading the 30 spare operational springs over the 16 gaps
+
+        // Every (index, group_index, current_group) state is computed once, and tries at most two branches.
+        let states = (59 + 1) * (15 + 1) * (1 + 1);
+        assert!(calls <= 2 * states + 1, "Took {} calls, expected at most {}", calls, 2 * states + 1);
+    }
+
     const TEST_INPUT: &str = "\
         ???.### 1,1,3\n\
         .??..??...?##. 1,1,3\n\
@@ -227,7 +299,7 @@ impl FromStr for SpringLine {
         match parts.len() {
             2 => {
                 let springs = parts[0].chars().map(|c| Spring::try_from(c)).collect::<Result<Vec<_>, _>>()?;
-                let broken_groups = parts[1].split(",").map(|p| parse_usize(p)).collect::<Result<Vec<_>, _>>()?;
+                let broken_groups = parts[1].split(",").map(parse_num).collect::<Result<Vec<_>, _>>()?;
                 Ok(Self { springs, broken_groups })
             },
             _ => Err(format!("Expected a string with two parts, but got {}", parts.len()))
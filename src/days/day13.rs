@@ -1,32 +1,35 @@
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
-use crate::days::Day;
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
 use crate::days::day13::Mirror::{Horizontal, Vertical};
 use crate::util::geometry::Grid;
+use crate::util::parser::Parser;
 
 pub const DAY13: Day = Day {
-    puzzle1,
-    puzzle2,
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let maps = parse_input(input).unwrap();
+struct Solution;
 
-    let result: usize = maps.iter()
-        .map(|m| m.get_mirror().unwrap())
-        .map(|m| m.get_value())
-        .sum();
-    println!("Sum of summarized data: {}", result);
-}
+impl StructuredDay for Solution {
+    type Parsed = Vec<Map>;
+
+    fn parse(input: &str) -> AocResult<Vec<Map>> {
+        parse_input(input).map_err(Into::into)
+    }
 
-fn puzzle2(input: &String) {
-    let maps = parse_input(input).unwrap();
+    fn part1(maps: &Vec<Map>) -> AocResult<String> {
+        let result = summarize(maps, 0)?;
+        Ok(format!("Sum of summarized data: {}", result))
+    }
 
-    let result: usize = maps.iter()
-        .map(|m| m.get_mirror_v2().unwrap())
-        .map(|m| m.get_value())
-        .sum();
-    println!("Sum of fixed summarized data: {}", result);
+    fn part2(maps: &Vec<Map>) -> AocResult<String> {
+        let result = summarize(maps, 1)?;
+        Ok(format!("Sum of fixed summarized data: {}", result))
+    }
 }
 
 #[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
@@ -36,7 +39,25 @@ enum Tile {
     Rock,
 }
 
-type Map = Grid<Tile>;
+// Besides the grid, every row and column is kept as a bitmask (a bit per rock), so comparing two lines is just an XOR.
+#[derive(Eq, PartialEq, Clone, Debug)]
+struct Map {
+    grid: Grid<Tile>,
+    rows: Vec<u32>,
+    columns: Vec<u32>,
+}
+
+impl Map {
+    fn new(grid: Grid<Tile>) -> Result<Self, String> {
+        if grid.bounds.width > 32 || grid.bounds.height > 32 {
+            return Err(format!("Map of {}x{} does not fit in 32 bit lines", grid.bounds.width, grid.bounds.height));
+        }
+        let to_bits = |line: Vec<&Tile>| line.iter().fold(0u32, |bits, tile| bits << 1 | (Tile::Rock.eq(*tile) as u32));
+        let rows = grid.bounds.y().map(|y| to_bits(grid.iter_row(y).collect())).collect();
+        let columns = grid.bounds.x().map(|x| to_bits(grid.iter_col(x).collect())).collect();
+        Ok(Map { grid, rows, columns })
+    }
+}
 
 /**
  * Mirror line, usize param is the amount of columns/rows _before_ the mirror line.
@@ -48,80 +69,44 @@ enum Mirror {
 }
 
 impl Map {
-    fn get_mirror(&self) -> Option<Mirror> {
-        // Try finding the line after which the map mirrors (either horizontal or vertical)
-        // At least for part 1 (I'm already afraid for 2...) it should be easy enough to just loop over all columns/rows
-        // We can start at index 1, as we need at least _a_ line above/below the mirrors.
-
-        fn find_mirror_index(lines: Vec<Vec<Tile>>) -> Option<usize> {
-            for i in 1..lines.len() {
-                if lines[i-1] != lines[i] { continue };
-                let (first, second) = lines.split_at(i);
-                let len = first.len().min(second.len());
-                let first_lines: Vec<_> = first.iter().rev().take(len).collect();
-                let second_lines: Vec<_> = second.iter().take(len).collect();
-                if first_lines == second_lines {
-                    return Some(i);
-                }
-            }
-            None
-        }
-
-        let rows: Vec<_> = self.bounds.y().map(|y| self.get_row(y)).collect();
-        let cols: Vec<_> = self.bounds.x().map(|x| self.get_column(x)).collect();
-        if let Some(index) = find_mirror_index(rows) {
-            Some(Horizontal(index))
-        } else if let Some(index) = find_mirror_index(cols) {
-            Some(Vertical(index))
-        } else {
-            None
-        }
-    }
-
-    fn get_mirror_v2(&self) -> Option<Mirror> {
-        // Try finding the line after which the map mirrors when changing _exactly_ one tile to the other type (either horizontal or vertical)
-        // Idea: loop over all possible mirror lines, and compute the differences in this mirror.
-        // If the difference is exactly one, it is the new valid mirror line.
-        fn get_differences_in_line(left: &Vec<Tile>, right: &Vec<Tile>) -> usize {
-            let mut differences = 0;
-            for i in 0..left.len() {
-                if left[i] != right[i] { differences += 1 }
-            }
-            differences
-        }
-
-        fn get_differences_in_mirror(lines: &Vec<Vec<Tile>>, index: usize) -> usize {
+    // Finds all lines after which the map mirrors (either horizontal or vertical), when changing _exactly_ the given
+    // amount of tiles (smudges) to the other type.
+    // At least for part 1 (I'm already afraid for 2...) it should be easy enough to just loop over all columns/rows
+    // We can start at index 1, as we need at least _a_ line above/below the mirrors.
+    // For part 2 (one smudge), we compute the differences in the mirror for every possible mirror line instead of just
+    // checking whether the lines are equal. If the difference is exactly one, it is the new valid mirror line.
+    // The differences between two lines are the bits that are set in only one of them.
+    fn get_mirrors(&self, smudges: usize) -> Vec<Mirror> {
+        fn get_differences_in_mirror(lines: &[u32], index: usize) -> usize {
             let (first, second) = lines.split_at(index);
-            let len = first.len().min(second.len());
-            let first_lines: Vec<_> = first.iter().rev().take(len).collect();
-            let second_lines: Vec<_> = second.iter().take(len).collect();
-
-            let mut differences = 0;
-            for i in 0..len {
-                differences += get_differences_in_line(first_lines[i], second_lines[i])
-            }
-            differences
+            first.iter().rev().zip(second.iter())
+                .map(|(l, r)| (l ^ r).count_ones() as usize)
+                .sum()
         }
 
-        fn find_mirror_index(lines: Vec<Vec<Tile>>) -> Option<usize> {
-            for i in 1..lines.len() {
-                if get_differences_in_mirror(&lines, i) == 1 { return Some(i) }
-            }
-            None
+        fn find_mirror_indexes(lines: &[u32], smudges: usize) -> impl Iterator<Item=usize> + '_ {
+            (1..lines.len()).filter(move |i| get_differences_in_mirror(lines, *i) == smudges)
         }
 
-        let rows: Vec<_> = self.bounds.y().map(|y| self.get_row(y)).collect();
-        let cols: Vec<_> = self.bounds.x().map(|x| self.get_column(x)).collect();
-        if let Some(index) = find_mirror_index(rows) {
-            Some(Horizontal(index))
-        } else if let Some(index) = find_mirror_index(cols) {
-            Some(Vertical(index))
-        } else {
-            None
+        let horizontal = find_mirror_indexes(&self.rows, smudges).map(Horizontal);
+        let vertical = find_mirror_indexes(&self.columns, smudges).map(Vertical);
+        horizontal.chain(vertical).collect()
+    }
+
+    // The puzzle assumes every map has exactly one mirror line (for the given amount of smudges).
+    fn get_mirror(&self, smudges: usize) -> Result<Mirror, String> {
+        match self.get_mirrors(smudges).as_slice() {
+            [mirror] => Ok(*mirror),
+            [] => Err(format!("No mirror with {} smudge(s) in map:\n{}", smudges, self.grid)),
+            mirrors => Err(format!("Multiple mirrors with {} smudge(s) ({:?}) in map:\n{}", smudges, mirrors, self.grid))
         }
     }
 }
 
+fn summarize(maps: &Vec<Map>, smudges: usize) -> Result<usize, String> {
+    maps.iter().map(|m| m.get_mirror(smudges).map(|m| m.get_value())).sum()
+}
+
 impl Mirror {
     fn get_value(&self) -> usize {
         match self {
@@ -133,9 +118,14 @@ impl Mirror {
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day13::{Map, parse_input, Tile};
+    use crate::days::day13::{Map, parse_input, summarize, Tile};
     use crate::days::day13::Mirror::{Horizontal, Vertical};
     use crate::util::geometry::Bounds;
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("405"), puzzle2: Some("400") },
+    ];
 
     #[test]
     fn test_parse_input() {
@@ -144,29 +134,56 @@ mod tests {
 
         let maps = result.unwrap();
         assert_eq!(maps.len(), 2);
-        assert_eq!(maps[0].bounds, Bounds::from_size(9, 7));
-        assert_eq!(maps[1].bounds, Bounds::from_size(9, 7));
+        assert_eq!(maps[0].grid.bounds, Bounds::from_size(9, 7));
+        assert_eq!(maps[1].grid.bounds, Bounds::from_size(9, 7));
+
+        assert_eq!(maps[0].grid.get_row(0), vec![Tile::Rock, Tile::Ground, Tile::Rock, Tile::Rock, Tile::Ground, Tile::Ground, Tile::Rock, Tile::Rock, Tile::Ground]);
+        // Rows and columns as bits, left to right and top to bottom
+        assert_eq!(maps[0].rows[0], 0b101100110);
+        assert_eq!(maps[0].columns[0], 0b1011001);
+        assert_eq!(maps[0].rows.len(), 7);
+        assert_eq!(maps[0].columns.len(), 9);
 
-        assert_eq!(maps[0].get_row(0), vec![Tile::Rock, Tile::Ground, Tile::Rock, Tile::Rock, Tile::Ground, Tile::Ground, Tile::Rock, Tile::Rock, Tile::Ground])
+        assert!(".".repeat(33).parse::<Map>().is_err());
     }
 
     #[test]
-    fn test_map_get_mirror() {
+    fn test_map_get_mirrors() {
         let maps = parse_input(TEST_INPUT).unwrap();
 
-        assert_eq!(maps[0].get_mirror(), Some(Vertical(5)));
-        assert_eq!(maps[1].get_mirror(), Some(Horizontal(4)));
+        assert_eq!(maps[0].get_mirrors(0), vec![Vertical(5)]);
+        assert_eq!(maps[1].get_mirrors(0), vec![Horizontal(4)]);
 
         let map = FAILING_CASE.parse::<Map>().unwrap();
-        assert_eq!(map.get_mirror(), Some(Horizontal(10)))
+        assert_eq!(map.get_mirrors(0), vec![Horizontal(10)]);
+
+        // With a smudge:
+        assert_eq!(maps[0].get_mirrors(1), vec![Horizontal(3)]);
+        assert_eq!(maps[1].get_mirrors(1), vec![Horizontal(1)]);
+
+        // Both horizontal and vertical mirrors:
+        let map = "##\n##".parse::<Map>().unwrap();
+        assert_eq!(map.get_mirrors(0), vec![Horizontal(1), Vertical(1)]);
+        assert_eq!(map.get_mirrors(1), vec![]);
     }
 
     #[test]
-    fn test_map_get_mirror_v2() {
+    fn test_map_get_mirror() {
         let maps = parse_input(TEST_INPUT).unwrap();
 
-        assert_eq!(maps[0].get_mirror_v2(), Some(Horizontal(3)));
-        assert_eq!(maps[1].get_mirror_v2(), Some(Horizontal(1)));
+        assert_eq!(maps[0].get_mirror(0), Ok(Vertical(5)));
+        assert_eq!(maps[0].get_mirror(1), Ok(Horizontal(3)));
+
+        assert!("##\n##".parse::<Map>().unwrap().get_mirror(0).is_err());
+        assert!("#.\n.#".parse::<Map>().unwrap().get_mirror(0).is_err());
+    }
+
+    #[test]
+    fn test_summarize() {
+        let maps = parse_input(TEST_INPUT).unwrap();
+
+        assert_eq!(summarize(&maps, 0), Ok(405));
+        assert_eq!(summarize(&maps, 1), Ok(400));
     }
 
     #[test]
@@ -209,24 +226,25 @@ mod tests {
 }
 
 fn parse_input(input: &str) -> Result<Vec<Map>, String> {
+    let mut parser = Parser::new(input);
     let mut result = vec![];
-    let mut current_lines = vec![];
-
-    // Note: we add an empty line at the end to ensure we've pushed any last result
-    for line in input.lines().chain(vec![""]) {
-        if line.is_empty() {
-            if current_lines.len() > 0 {
-                result.push(current_lines.join("\n").parse()?);
-            }
-            current_lines.clear();
-        } else {
-            current_lines.push(line);
-        }
+
+    // Maps are separated by blank lines
+    while !parser.is_exhausted() {
+        result.push(parser.block()?.parse()?);
     }
 
     Ok(result)
 }
 
+impl FromStr for Map {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Map::new(s.parse()?)
+    }
+}
+
 impl FromStr for Tile {
     type Err = String;
 
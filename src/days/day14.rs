@@ -1,25 +1,37 @@
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
-use crate::days::Day;
-use crate::util::geometry::{Directions, Grid, Point};
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::geometry::{Dir, Grid, Point, RowCol};
+use crate::util::simulation::measure_after_steps;
+use crate::util::visualize::{self, Visualizer};
 
 pub const DAY14: Day = Day {
-    puzzle1,
-    puzzle2,
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let mut platform = input.parse::<Platform>().unwrap();
-    platform.tilt(Directions::Top);
+struct Solution;
 
-    println!("North beam load: {}", platform.get_north_beam_load());
-}
+impl StructuredDay for Solution {
+    type Parsed = Platform;
+
+    fn parse(input: &str) -> AocResult<Platform> {
+        input.parse::<Platform>().map_err(Into::into)
+    }
 
-fn puzzle2(input: &String) {
-    let mut platform = input.parse::<Platform>().unwrap();
+    fn part1(platform: &Platform) -> AocResult<String> {
+        let mut platform = platform.clone();
+        platform.tilt(Dir::North);
 
-    let load_result = platform.run_spin_cycle();
-    println!("North beam load after 1.000.000.000 spins: {}", load_result);
+        Ok(format!("North beam load: {}", platform.get_north_beam_load()))
+    }
+
+    fn part2(platform: &Platform) -> AocResult<String> {
+        let load_result = platform.run_spin_cycle();
+        Ok(format!("North beam load after 1.000.000.000 spins: {}", load_result))
+    }
 }
 
 #[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
@@ -30,10 +42,16 @@ enum Tile {
     Ground,
 }
 
-type Platform = Grid<Tile>;
+// The tiles are kept in a dense row-major buffer (instead of a Grid), so tilting can move the boulders around in place.
+#[derive(Eq, PartialEq, Clone, Debug)]
+struct Platform {
+    width: usize,
+    height: usize,
+    tiles: Vec<Tile>,
+}
 
 impl Platform {
-    fn tilt(&mut self, direction: Directions) {
+    fn tilt(&mut self, direction: Dir) {
         // 1. Take all boulders, sorted based on the direction
         //    - e.g. to top, sort based on lowest y value
         // 2. For each boulder, get everything in the direction
@@ -43,139 +61,113 @@ impl Platform {
         // 5. Probably refactor for puzzle 2 :silly:
 
         // 5 was unfortunately true. It is too slow to run cycles with the above.
-        // New plan: take the rows/columns, run over them (in the right direction) moving everything
-        // Clear out all boulders from the grid
-        // Insert them in the new positions based on the results.
-        let lines = match direction {
-            Directions::Top | Directions::Bottom => self.columns(),
-            Directions::Left | Directions::Right => self.rows(),
-            _ => return
+        // New plan: run over the rows/columns (starting at the side we're tilting to), keeping track of the first free
+        // spot. That is right after the last rock or boulder we passed, so every boulder we find just swaps places with
+        // the ground at that spot.
+        let (lines, length) = if direction.is_vertical() { (self.width, self.height) } else { (self.height, self.width) };
+
+        // Maps the i-th tile (in the tilt direction) of a line to its index in the buffer:
+        let (width, height) = (self.width, self.height);
+        let index = |line: usize, i: usize| match direction {
+            Dir::North => i * width + line,
+            Dir::South => (height - 1 - i) * width + line,
+            Dir::West => line * width + i,
+            Dir::East => line * width + (width - 1 - i),
         };
 
-        let mut result = vec![];
-        for line in lines {
-            let mut new_line = vec![Tile::Ground; line.len()]; // Pre-allocate with all ground tiles.
-
-            let is_reverse = direction == Directions::Bottom || direction == Directions::Right;
-            let range = 0..line.len();
-
-            let mut free_index = None;
-
-            let indexes = if is_reverse { range.rev().collect::<Vec<_>>() } else { range.collect::<Vec<_>>() };
-            for i in indexes {
-                match line[i] {
-                    Tile::Ground if free_index.is_none() => { free_index = Some(i) },
-                    Tile::Ground => {} // More free ground~
-                    Tile::Rock => free_index = None,
-                    Tile::Boulder if free_index.is_some() => {
-                        // Update index to be a boulder, move index (based on is_reverse)
-                        let index = free_index.unwrap();
-                        new_line[index] = Tile::Boulder;
-                        free_index = Some(if is_reverse { index - 1 } else { index + 1 })
-                    },
+        for line in 0..lines {
+            let mut free = 0;
+            for i in 0..length {
+                match self.tiles[index(line, i)] {
+                    Tile::Ground => {}
+                    Tile::Rock => free = i + 1,
                     Tile::Boulder => {
-                        // Just add the boulder at the same spot, cannot move:
-                        new_line[i] = Tile::Boulder
-                    },
+                        self.tiles.swap(index(line, free), index(line, i));
+                        free += 1;
+                    }
                 }
             }
-
-            result.push(new_line);
         }
 
-        // Remove boulders from the grid:
-        self.entries().iter().filter(|(_, t)| Tile::Boulder.eq(t)).for_each(|(p, _)| self.set(*p, Tile::Ground));
-
-        // Store result back into the grid
-        for i1 in 0..result.len() {
-            let line = &result[i1];
-            for i2 in 0..line.len() {
-                if line[i2] != Tile::Boulder { continue; }
-
-                // Determine which is x, which is y based on direction:
-                let (x, y) = match direction {
-                    // lines are columns (x), each tile is a row (y)
-                    Directions::Top | Directions::Bottom => (i1, i2),
-                    Directions::Left | Directions::Right => (i2, i1),
-                    _ => return
-                };
-
-                self.set(Point::try_from((x, y)).unwrap(), Tile::Boulder);
-            }
+        if visualize::is_enabled() {
+            visualize::show(&format!("Tilted {:?}", direction), self);
         }
     }
 
     fn get_north_beam_load(&self) -> usize {
         // Each boulder causes a load depending on the row from the bottom.
         // Basically, take the height of this grid, and subtract the y position.
-        self.entries().iter().filter(|(_, t)| Tile::Boulder.eq(t))
-            .map(|(p, _)| self.bounds.height - p.y as usize)
+        self.tiles.iter().enumerate().filter(|(_, t)| Tile::Boulder.eq(t))
+            .map(|(i, _)| self.height - i / self.width)
             .sum()
     }
 
-    fn run_spin_cycle(&mut self) -> usize {
+    fn run_spin_cycle(&self) -> usize {
         // We need to run 1.000.000.000 cycles. A cycle is a tilt top => left => bottom => right.
         // Obviously, running that real-time is _probably_ going to take too long.
         // However, knowing AoC, at some point this process will stabilize and start looping at some point.
         // As such, once we find the loop, we can just figure out where in the loop we'll end.
+        // The tiles after each cycle tell us whether we've already seen a state.
+        measure_after_steps(self.clone(), 1_000_000_000, Platform::spin, |p| p.tiles.clone(), |p| p.get_north_beam_load())
+    }
 
-        // To find the loop, we run the cycles storing the boulder locations after each cycle.
-        // Once we find a state we've already seen, we know the loop size and offset, and the rest will be simple.
-
-        #[derive(Eq, PartialEq, Hash)]
-        struct State { boulders: Vec<Point> }
-
-        let mut states: Vec<State> = vec![];
-
-        loop {
-            let boulders = self.entries().iter().filter(|(_, t)| Tile::Boulder.eq(t)).map(|(p, _)| *p).collect();
-            let state = State { boulders };
-
-            if let Some(offset) = states.iter().position(|s| state.eq(s)) {
-                let loop_len = states.len() - offset;
-                println!("Found loop from {} of len {}", offset, loop_len);
-                // Compute the 1.000.000.000 cycle state, and get the weight from that.
-
-                let target_index = offset + ((1_000_000_000 - offset) % loop_len);
-                return states[target_index].boulders.iter().map(|p| self.bounds.height - p.y as usize).sum()
-            }
-
-            states.push(state);
+    // A single spin cycle: tilt north, west, south, and east.
+    fn spin(&self) -> Platform {
+        let mut next = self.clone();
+        next.tilt(Dir::North);
+        next.tilt(Dir::West);
+        next.tilt(Dir::South);
+        next.tilt(Dir::East);
+        next
+    }
+}
 
-            // Do a cycle:
-            self.tilt(Directions::Top);    // North
-            self.tilt(Directions::Left);   // West
-            self.tilt(Directions::Bottom); // South
-            self.tilt(Directions::Right);  // East
-        }
+impl Visualizer for Platform {
+    fn render_frame(&self) -> String {
+        format!("{}", self)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day14::Platform;
-    use crate::util::geometry::Directions;
+    use crate::days::day14::{Platform, Tile};
+    use crate::util::geometry::Dir;
+    use crate::util::simulation::count_after_steps;
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("136"), puzzle2: Some("64") },
+    ];
 
     #[test]
     fn test_get_north_beam_load() {
         let mut grid = TEST_INPUT.parse::<Platform>().unwrap();
-        grid.tilt(Directions::Top);
+        grid.tilt(Dir::North);
 
         assert_eq!(grid.get_north_beam_load(), 136);
     }
 
     #[test]
     fn test_run_spin_cycle() {
-        let mut grid = TEST_INPUT.parse::<Platform>().unwrap();
+        let grid = TEST_INPUT.parse::<Platform>().unwrap();
 
         assert_eq!(grid.run_spin_cycle(), 64);
     }
 
+    #[test]
+    fn test_spin_keeps_boulders() {
+        // Spinning moves the boulders around, but never loses (or adds) any
+        let grid = TEST_INPUT.parse::<Platform>().unwrap();
+        let boulders = grid.tiles.iter().filter(|t| Tile::Boulder.eq(t)).count();
+
+        assert_eq!(count_after_steps(grid, 1_000_000_000, Platform::spin, |p| p.tiles.clone(), |t| Tile::Boulder.eq(t)), boulders);
+    }
+
     #[test]
     fn test_tilt() {
         let mut grid = TEST_INPUT.parse::<Platform>().unwrap();
 
-        grid.tilt(Directions::Top);
+        grid.tilt(Dir::North);
         assert_eq!(format!("{}", grid), "\
             OOOO.#.O..\n\
             OO..#....#\n\
@@ -189,7 +181,7 @@ mod tests {
             #....#....\
         ");
 
-        grid.tilt(Directions::Right);
+        grid.tilt(Dir::East);
         assert_eq!(format!("{}", grid), "\
             .OOOO#...O\n\
             ..OO#....#\n\
@@ -203,7 +195,7 @@ mod tests {
             #....#....\
         ");
 
-        grid.tilt(Directions::Right);
+        grid.tilt(Dir::East);
         assert_eq!(format!("{}", grid), "\
             .OOOO#...O\n\
             ..OO#....#\n\
@@ -217,7 +209,7 @@ mod tests {
             #....#....\
         ");
 
-        grid.tilt(Directions::Bottom);
+        grid.tilt(Dir::South);
         assert_eq!(format!("{}", grid), "\
             ...OO#...O\n\
             ..OO#....#\n\
@@ -231,7 +223,7 @@ mod tests {
             #O..O#..OO\
         ");
 
-        grid.tilt(Directions::Left);
+        grid.tilt(Dir::West);
         assert_eq!(format!("{}", grid), "\
             OO...#O...\n\
             OO..#....#\n\
@@ -245,7 +237,7 @@ mod tests {
             #OO..#OO..\
         ");
 
-        grid.tilt(Directions::Top);
+        grid.tilt(Dir::North);
         assert_eq!(format!("{}", grid), "\
             OO...#O...\n\
             OO..#....#\n\
@@ -295,4 +287,31 @@ impl Display for Tile {
             Tile::Ground => write!(f, ".")
         }
     }
-}
\ No newline at end of file
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let grid: Grid<Tile> = s.parse()?;
+        let (width, height) = (grid.bounds.width, grid.bounds.height);
+
+        let tiles = (0..height).flat_map(|row| (0..width).map(move |col| (row, col)))
+            .map(|(row, col)| Point::try_from(RowCol { row, col }).map(|p| grid.get(&p).unwrap_or_default()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { width, height, tiles })
+    }
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (index, row) in self.tiles.chunks(self.width.max(1)).enumerate() {
+            if index > 0 { writeln!(f)?; }
+            for tile in row {
+                write!(f, "{}", tile)?;
+            }
+        }
+        Ok(())
+    }
+}
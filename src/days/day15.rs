@@ -1,35 +1,44 @@
-use std::str::FromStr;
-use crate::days::Day;
-use crate::util::number::parse_usize;
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::hash::{hash_bytes, HashmapBoxes, HashParams};
+use crate::util::number::parse_num;
 
 pub const DAY15: Day = Day {
-    puzzle1,
-    puzzle2
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    println!("Initialization sequence check result: {}", check_initialization_sequence(input));
-}
+struct Solution;
 
-fn puzzle2(input: &String) {
-    println!("Initialization sequence check result: {}", run_initialization_sequence(input).unwrap());
-}
+impl StructuredDay for Solution {
+    // The steps are read from the input as they're needed, so all we keep is the (checked) input.
+    type Parsed = String;
 
-fn run_hash(input: &str) -> usize {
-    let mut hash = 0;
+    fn parse(input: &str) -> AocResult<String> {
+        for step in steps(input) {
+            Instruction::parse(step)?;
+        }
+        Ok(input.to_string())
+    }
 
-    for char in input.chars() {
-        if !char.is_ascii() { panic!("Can only hash ascii chars") }
-        hash += char as usize;
-        hash *= 17;
-        hash %= 256;
+    fn part1(input: &String) -> AocResult<String> {
+        Ok(format!("Initialization sequence check result: {}", check_initialization_sequence(input, HashParams::HASH)))
     }
 
-    hash
+    fn part2(input: &String) -> AocResult<String> {
+        Ok(format!("Initialization sequence check result: {}", run_initialization_sequence(input, HashParams::HASH)?))
+    }
 }
 
-fn check_initialization_sequence(input: &str) -> usize {
-    input.split(",").map(|l| run_hash(l.trim())).sum()
+// The steps of the sequence, borrowed from the input. Newlines are ignored by the puzzle, but would be hashed like any
+// other char when they're left in (the input ends with one), so every step is trimmed.
+fn steps(input: &str) -> impl Iterator<Item=&str> {
+    input.split(',').map(|step| step.trim())
+}
+
+fn check_initialization_sequence(input: &str, params: HashParams) -> usize {
+    steps(input).map(|step| hash_bytes(step.as_bytes(), params)).sum()
 }
 
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
@@ -39,100 +48,108 @@ enum Operation {
     Add(usize)
 }
 
-#[derive(Eq, PartialEq, Debug, Clone)]
-struct Instruction {
-    label: String,
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+struct Instruction<'a> {
+    label: &'a str,
     operation: Operation
 }
 
-#[derive(Eq, PartialEq, Debug, Clone)]
-struct Lens {
-    label: String,
-    focal_strength: usize
+impl<'a> Instruction<'a> {
+    // Instructions are written without any spaces, like "rn=1" and "cm-". The label is borrowed from the step.
+    fn parse(step: &'a str) -> Result<Self, String> {
+        // HASH is defined on ascii chars only, so that's what labels are made of.
+        let (label, rest) = step.split_at(step.bytes().take_while(|b| b.is_ascii_alphabetic()).count());
+        if label.is_empty() {
+            return Err(format!("Expected step '{}' to start with a label of ascii letters", step));
+        }
+        let operation = match rest.strip_prefix('=') {
+            Some(focal_strength) if !focal_strength.is_empty() && focal_strength.bytes().all(|b| b.is_ascii_digit()) => Operation::Add(parse_num(focal_strength)?),
+            None if rest == "-" => Operation::Remove,
+            _ => return Err(format!("Expected '-' or '=<focal strength>' after label '{}', got '{}'", label, rest)),
+        };
+
+        Ok(Self { label, operation })
+    }
 }
 
-fn run_initialization_sequence(input: &str) -> Result<usize, String> {
+fn run_initialization_sequence(input: &str, params: HashParams) -> Result<usize, String> {
     // Each entry is a label and operation, two variants:
     // LAB=4 => Lens labelled 'LAB' with focus strength 4, needs to be inserted in the hash bucket determined by hashing the label
     // LAB- => Remove lens labelled 'LAB' from its hash bucket (if it's there)
-    let mut hash_buckets: Vec<Vec<Lens>> = vec![];
-    // Make sure we have enough buckets:
-    for _ in 0..256 {
-        hash_buckets.push(vec![]);
-    }
-
-    let instructions = input.split(",").map(|p| p.trim()).map(|p| p.parse::<Instruction>()).collect::<Result<Vec<_>, _>>()?;
-    for instruction in instructions {
-        let bucket_index = run_hash(&instruction.label);
-        let vec = &mut hash_buckets[bucket_index];
-        let existing_index = vec.iter().position(|lens| instruction.label == lens.label);
+    let mut boxes = HashmapBoxes::new(params);
 
+    for step in steps(input) {
+        let instruction = Instruction::parse(step)?;
         match instruction.operation {
-            Operation::Add(focal_strength) => {
-                // Check if the lens already exists, if so, replace. Otherwise add to the end.
-                match existing_index {
-                    Some(index) => vec.get_mut(index).unwrap().focal_strength = focal_strength,
-                    None => vec.push(Lens { label: instruction.label, focal_strength })
-                }
-            },
-            Operation::Remove => {
-                match existing_index {
-                    Some(index) => { vec.remove(index); },
-                    None => {} // No-op
-                }
-            }
+            Operation::Add(focal_strength) => boxes.insert(instruction.label, focal_strength),
+            Operation::Remove => { boxes.remove(instruction.label); }
         }
     }
 
     // The result is calculated by taking every lens and calculating it's value.
     // The value is: (bucket_index + 1) * (lens_index + 1) * (focal_strength)
-    let mut result = 0;
-
-    for i in 0..256 {
-        let lenses = &hash_buckets[i];
-        for li in 0..lenses.len() {
-            result += (i+1) * (li+1) * lenses[li].focal_strength
-        }
-    }
-
-    Ok(result)
+    Ok(boxes.focusing_power(|focal_strength| *focal_strength))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day15::{run_hash, check_initialization_sequence, run_initialization_sequence};
+    use crate::days::day15::{check_initialization_sequence, run_initialization_sequence, steps, Instruction, Operation};
+    use std::num::NonZeroUsize;
+    use crate::solve;
+    use crate::util::hash::HashParams;
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("1320"), puzzle2: Some("145") },
+    ];
 
     #[test]
-    fn test_hash() {
-        assert_eq!(run_hash("HASH"), 52);
+    fn test_initialization_sequence() {
+        assert_eq!(check_initialization_sequence(TEST_INPUT, HashParams::HASH), 1320);
     }
 
     #[test]
-    fn test_initialization_sequence() {
-        assert_eq!(check_initialization_sequence("rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7"), 1320);
+    fn test_steps() {
+        assert_eq!(steps("rn=1,cm-\n").collect::<Vec<_>>(), vec!["rn=1", "cm-"]);
+        assert!(solve(15, 1, "rn=1,HASH").is_err());
+        assert!(solve(15, 2, "rn=1,cm-,").is_err());
     }
 
     #[test]
-    fn test_run_initialization_sequence() {
-        assert_eq!(run_initialization_sequence("rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7"), Ok(145));
+    fn test_trailing_newline() {
+        assert_eq!(check_initialization_sequence("cm-", HashParams::HASH), 253);
+        assert_eq!(check_initialization_sequence("cm-\n", HashParams::HASH), 253);
+        assert_eq!(check_initialization_sequence("cm- ", HashParams::HASH), 253);
+        assert_eq!(check_initialization_sequence(&format!("{}\r\n", TEST_INPUT), HashParams::HASH), 1320);
+        assert_eq!(run_initialization_sequence(&format!("{}\n", TEST_INPUT), HashParams::HASH), Ok(145));
     }
-}
 
-impl FromStr for Instruction {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let operation_index = s.chars().position(|c| c == '=' || c == '-').ok_or(format!("Could not find a '=' or '-' in input '{}'", s))?;
-        let label = s[0..operation_index].to_owned();
-        let operation = match s.chars().nth(operation_index) {
-            Some('=') => {
-                let focal_strength = parse_usize(&s[operation_index+1..])?;
-                Operation::Add(focal_strength)
-            },
-            Some('-') => Operation::Remove,
-            _ => return Err("Could no longer find the operation char?!".to_string())
-        };
+    #[test]
+    fn test_parse_instruction() {
+        assert_eq!(Instruction::parse("rn=1"), Ok(Instruction { label: "rn", operation: Operation::Add(1) }));
+        assert_eq!(Instruction::parse("cm-"), Ok(Instruction { label: "cm", operation: Operation::Remove }));
+        assert!(Instruction::parse("rn 1").is_err());
+        assert!(Instruction::parse("rn= 1").is_err());
+        assert!(Instruction::parse("rn=+1").is_err());
+        assert!(Instruction::parse("rn=").is_err());
+        assert!(Instruction::parse("cm--").is_err());
+        assert!(Instruction::parse("=1").is_err());
+        assert!(Instruction::parse("r n-").is_err());
+        assert_eq!(Instruction::parse("é=1"), Err("Expected step 'é=1' to start with a label of ascii letters".to_string()));
+        assert!(solve(15, 2, "é=1").is_err());
+    }
 
-        Ok(Self { label, operation })
+    #[test]
+    fn test_run_initialization_sequence() {
+        assert_eq!(run_initialization_sequence(TEST_INPUT, HashParams::HASH), Ok(145));
+    }
+
+    #[test]
+    fn test_run_initialization_sequence_single_box() {
+        // With a single box, all lenses end up in box 1: rn=1, cm=2, ot=7, ab=5, pc=6
+        let params = HashParams { multiplier: 17, modulus: NonZeroUsize::MIN };
+        assert_eq!(run_initialization_sequence(TEST_INPUT, params), Ok(1 + 2 * 2 + 3 * 7 + 4 * 5 + 5 * 6));
     }
-}
\ No newline at end of file
+
+    const TEST_INPUT: &str = "rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7";
+}
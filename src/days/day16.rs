@@ -1,22 +1,36 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
-use crate::days::Day;
-use crate::util::collection::CollectionExtension;
-use crate::util::geometry::{Directions, Grid, Point};
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::color::AnsiColor;
+use crate::util::bitset::PointSet;
+use crate::util::geometry::{Bounds, Dir, Directions, Grid, Point};
+use crate::util::graph::DiGraph;
+use crate::util::visualize::{self, Visualizer};
 
 pub const DAY16: Day = Day {
-    puzzle1,
-    puzzle2
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let contraption = input.parse::<Contraption>().unwrap();
-    println!("Number of energized tiles: {}", contraption.get_energized_tiles());
-}
+struct Solution;
+
+impl StructuredDay for Solution {
+    type Parsed = Contraption;
+
+    fn parse(input: &str) -> AocResult<Contraption> {
+        input.parse::<Contraption>()
+    }
+
+    fn part1(contraption: &Contraption) -> AocResult<String> {
+        Ok(format!("Number of energized tiles: {}", contraption.get_energized_tiles()))
+    }
 
-fn puzzle2(input: &String) {
-    let contraption = input.parse::<Contraption>().unwrap();
-    println!("Max number of energized tiles: {}", contraption.get_max_energized_tiles());
+    fn part2(contraption: &Contraption) -> AocResult<String> {
+        Ok(format!("Max number of energized tiles: {}", contraption.get_max_energized_tiles_cached()))
+    }
 }
 
 #[derive(Eq, PartialEq, Debug, Default, Copy, Clone)]
@@ -30,54 +44,104 @@ enum Tile {
 }
 
 impl Tile {
-    fn get_next_directions(&self, input: Directions) -> Vec<Directions> {
+    // Returns a static slice, as this is called for every step of every beam.
+    fn get_next_directions(&self, input: Dir) -> &'static [Dir] {
         match self {
-            Self::Empty => vec![input],
-            Self::HorSplit if Directions::Horizontal.has(input) => vec![input],
-            Self::HorSplit => vec![Directions::Left, Directions::Right],
-            Self::VerSplit if Directions::Vertical.has(input) => vec![input],
-            Self::VerSplit => vec![Directions::Top, Directions::Bottom],
+            Self::Empty => Self::straight(input),
+            Self::HorSplit if input.is_horizontal() => Self::straight(input),
+            Self::HorSplit => &[Dir::West, Dir::East],
+            Self::VerSplit if input.is_vertical() => Self::straight(input),
+            Self::VerSplit => &[Dir::North, Dir::South],
             // /
-            Self::MirrorRight if input == Directions::Top => vec![Directions::Right],
-            Self::MirrorRight if input == Directions::Right => vec![Directions::Top],
-            Self::MirrorRight if input == Directions::Bottom => vec![Directions::Left],
-            Self::MirrorRight if input == Directions::Left => vec![Directions::Bottom],
+            Self::MirrorRight if input == Dir::North => &[Dir::East],
+            Self::MirrorRight if input == Dir::East => &[Dir::North],
+            Self::MirrorRight if input == Dir::South => &[Dir::West],
+            Self::MirrorRight => &[Dir::South],
             // \
-            Self::MirrorLeft if input == Directions::Top => vec![Directions::Left],
-            Self::MirrorLeft if input == Directions::Right => vec![Directions::Bottom],
-            Self::MirrorLeft if input == Directions::Bottom => vec![Directions::Right],
-            Self::MirrorLeft if input == Directions::Left => vec![Directions::Top],
-            _ => vec![]
+            Self::MirrorLeft if input == Dir::North => &[Dir::West],
+            Self::MirrorLeft if input == Dir::East => &[Dir::South],
+            Self::MirrorLeft if input == Dir::South => &[Dir::East],
+            Self::MirrorLeft => &[Dir::North],
+        }
+    }
+
+    fn straight(input: Dir) -> &'static [Dir] {
+        match input {
+            Dir::North => &[Dir::North],
+            Dir::East => &[Dir::East],
+            Dir::South => &[Dir::South],
+            Dir::West => &[Dir::West],
         }
     }
 }
 
 type Contraption = Grid<Tile>;
 
+// A beam entering a point going into a direction
+type Beam = (Point, Dir);
+
+// The beams that passed through every tile of the contraption, as a mask of their directions (as single bit sets).
+// A lot cheaper to look up than hashing every beam, and the tiles any beam went through are the energized ones.
+struct BeamSet {
+    bounds: Bounds,
+    directions: Vec<u8>,
+}
+
+impl BeamSet {
+    fn new(bounds: Bounds) -> Self {
+        Self { bounds, directions: vec![0; bounds.width * bounds.height] }
+    }
+
+    fn index(&self, point: &Point) -> usize {
+        (point.y - self.bounds.top) as usize * self.bounds.width + (point.x - self.bounds.left) as usize
+    }
+
+    // Adds the beam, returning whether it wasn't in the set yet.
+    fn insert(&mut self, (point, direction): Beam) -> bool {
+        let index = self.index(&point);
+        let bit = Directions::from(direction) as u8;
+        let is_new = self.directions[index] & bit == 0;
+        self.directions[index] |= bit;
+        is_new
+    }
+
+    fn energized_count(&self) -> usize {
+        self.directions.iter().filter(|d| **d != 0).count()
+    }
+
+    fn energized(&self) -> impl Iterator<Item=Point> + '_ {
+        self.bounds.points().filter(|p| self.directions[self.index(p)] != 0)
+    }
+}
+
 impl Contraption {
     fn get_energized_tiles(&self) -> usize {
         // Start in top-left corner, going right
-        self.get_energized_tiles_from(Point::from((0, 0)), Directions::Right)
+        self.get_energized_tiles_from(Point::from((0, 0)), Dir::East)
     }
 
-    fn get_energized_tiles_from(&self, start: Point, direction: Directions) -> usize {
-        let mut energized_tiles: HashSet<(Point, Directions)> = HashSet::new();
-        let mut queue: VecDeque<(Point, Directions)> = VecDeque::from([(start, direction)]);
+    fn get_energized_tiles_from(&self, start: Point, direction: Dir) -> usize {
+        let mut energized_tiles = BeamSet::new(self.bounds);
+        let mut queue: VecDeque<(Point, Dir)> = VecDeque::from([(start, direction)]);
 
         loop {
             if let Some((current_point, direction)) = queue.pop_front() {
-                // Already visited going this direction?
-                if !energized_tiles.insert((current_point, direction)) { continue; }
-
                 // Get current tile:
                 let tile = match self.get(&current_point) {
                     Some(tile) => tile,
                     None => continue
                 };
 
+                // Already visited going this direction?
+                if !energized_tiles.insert((current_point, direction)) { continue; }
+
                 // Get next direction(s)
-                for direction in tile.get_next_directions(direction) {
-                    if let [point] = self.get_adjacent_points(&current_point, direction)[..] {
+                let next_directions = tile.get_next_directions(direction);
+                if next_directions.len() > 1 {
+                    visualize::show("Beam split", &EnergizedContraption { contraption: self, beams: &energized_tiles });
+                }
+                for &direction in next_directions {
+                    if let Some(point) = self.get_adjacent_point(&current_point, direction) {
                         queue.push_back((point, direction));
                     }
                 }
@@ -86,30 +150,133 @@ impl Contraption {
             }
         }
 
-        energized_tiles.iter().map(|(p, _)| p).collect::<Vec<_>>().deduplicate().len()
+        visualize::show("Energized", &EnergizedContraption { contraption: self, beams: &energized_tiles });
+        energized_tiles.energized_count()
     }
 
-    fn get_max_energized_tiles(&self) -> usize {
-        // 'Dumb' solution, just try for each side and each column (4x110 starts)
-        // Let's see how fast it is :joy:
-        let mut result = 0;
+    fn get_entry_beams(&self) -> Vec<Beam> {
+        let mut beams = vec![];
 
         for row in self.bounds.y() {
-            result = result.max(self.get_energized_tiles_from(Point::from((0, row)), Directions::Right));
-            result = result.max(self.get_energized_tiles_from(Point::from((0, row)), Directions::Left));
+            beams.push((Point::from((self.bounds.left, row)), Dir::East));
+            beams.push((Point::from((self.bounds.right(), row)), Dir::West));
         }
         for col in self.bounds.x() {
-            result = result.max(self.get_energized_tiles_from(Point::from((col, 0)), Directions::Bottom));
-            result = result.max(self.get_energized_tiles_from(Point::from((col, 0)), Directions::Top));
+            beams.push((Point::from((col, self.bounds.top)), Dir::South));
+            beams.push((Point::from((col, self.bounds.bottom())), Dir::North));
         }
 
-        result
+        beams
+    }
+
+    fn get_max_energized_tiles_cached(&self) -> usize {
+        // The 'dumb' solution (see the tests) follows the beams from every entry point, but the beams split by
+        // splitters are shared between a lot of entry points. So instead, we cut all beams into segments running up to
+        // the next splitter, and compute the tiles energized downstream of every segment only once. (That's quick
+        // enough to not need spreading the entry points over threads anymore.)
+        // Segments can feed back into each other (a splitter sending a beam back to where it came from), so we
+        // combine those loops first: all segments in a strongly connected component energize the same tiles.
+        let (segments, entry_nodes) = self.get_beam_segments();
+
+        let mut component_of = vec![0; segments.len()];
+        let mut energized: Vec<PointSet> = vec![];
+
+        // Components come in reverse topological order, so all downstream components are done before we need them.
+        for (index, component) in segments.strongly_connected_components().into_iter().enumerate() {
+            let mut tiles = PointSet::new(self.bounds);
+            for &node in &component {
+                component_of[node] = index;
+                tiles.union_with(segments.node(node));
+            }
+            for &node in &component {
+                for next in segments.successors(node) {
+                    if !component.contains(&next) {
+                        tiles.union_with(&energized[component_of[next]]);
+                    }
+                }
+            }
+            energized.push(tiles);
+        }
+
+        entry_nodes.iter().map(|node| energized[component_of[*node]].len()).max().unwrap_or(0)
+    }
+
+    // Builds the graph of beam segments, starting from all entry beams. Every node holds the tiles energized by the
+    // segment, and has an edge to the segments the splitter at its end sends the beam into.
+    fn get_beam_segments(&self) -> (DiGraph<PointSet, ()>, Vec<usize>) {
+        let mut segments = DiGraph::new();
+        let mut nodes: HashMap<Beam, usize> = HashMap::new();
+        let mut queue: VecDeque<Beam> = VecDeque::new();
+
+        let entry_nodes = self.get_entry_beams().into_iter().map(|beam| {
+            *nodes.entry(beam).or_insert_with(|| {
+                queue.push_back(beam);
+                segments.add_node(PointSet::new(self.bounds))
+            })
+        }).collect();
+
+        while let Some(start) = queue.pop_front() {
+            let node = nodes[&start];
+            let (mut current_point, mut direction) = start;
+
+            loop {
+                let tile = match self.get(&current_point) {
+                    Some(tile) => tile,
+                    None => break
+                };
+                segments.node_mut(node).insert(current_point);
+
+                let next_directions = tile.get_next_directions(direction);
+                if next_directions.len() > 1 {
+                    for &direction in next_directions {
+                        if let Some(point) = self.get_adjacent_point(&current_point, direction) {
+                            let next = *nodes.entry((point, direction)).or_insert_with(|| {
+                                queue.push_back((point, direction));
+                                segments.add_node(PointSet::new(self.bounds))
+                            });
+                            segments.add_edge(node, next, ());
+                        }
+                    }
+                    break;
+                }
+
+                match next_directions.first().and_then(|&d| self.get_adjacent_point(&current_point, d).map(|p| (p, d))) {
+                    // Without splitting, the beam can only loop by coming back to where the segment started. (Beams
+                    // can be followed back, so there's no other way to run into a loop.)
+                    Some(next) if next == start => break,
+                    Some(next) => (current_point, direction) = next,
+                    None => break
+                }
+            }
+        }
+
+        (segments, entry_nodes)
+    }
+}
+
+// The contraption with the tiles energized by the given beams highlighted, for --visualize
+struct EnergizedContraption<'a> {
+    contraption: &'a Contraption,
+    beams: &'a BeamSet,
+}
+
+impl Visualizer for EnergizedContraption<'_> {
+    fn render_frame(&self) -> String {
+        let energized: HashSet<Point> = self.beams.energized().collect();
+        self.contraption.render_highlighted(&energized, AnsiColor::Yellow)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day16::Contraption;
+    use crate::util::allocations::count_allocations;
+    use crate::days::day16::{BeamSet, Contraption};
+    use crate::util::geometry::{Bounds, Dir, Point};
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("46"), puzzle2: Some("51") },
+    ];
 
     #[test]
     fn test_get_energized_tiles() {
@@ -117,10 +284,78 @@ mod tests {
         assert_eq!(contraption.get_energized_tiles(), 46);
     }
 
+    #[test]
+    fn test_get_energized_tiles_allocations() {
+        let contraption = TEST_INPUT.parse::<Contraption>().unwrap();
+        let (result, allocations) = count_allocations(|| contraption.get_energized_tiles());
+        assert_eq!(result, 46);
+        // Only the queue growth should allocate, not every step of the beam loop
+        assert!(allocations <= 32, "Expected at most 32 allocations, got {}", allocations);
+    }
+
+    #[test]
+    fn test_beam_set() {
+        let mut beams = BeamSet::new(Bounds::from_tlbr(2, 3, 4, 5));
+        assert!(beams.insert((Point { x: 3, y: 2 }, Dir::East)));
+        assert!(beams.insert((Point { x: 3, y: 2 }, Dir::North)));
+        assert!(!beams.insert((Point { x: 3, y: 2 }, Dir::East)));
+        assert!(beams.insert((Point { x: 5, y: 4 }, Dir::West)));
+
+        assert_eq!(beams.energized_count(), 2);
+        assert_eq!(beams.energized().collect::<Vec<_>>(), vec![Point { x: 3, y: 2 }, Point { x: 5, y: 4 }]);
+    }
+
+    // 'Dumb' solution, just try for each side and each column (4x110 starts)
+    // Let's see how fast it is :joy:
+    fn get_max_energized_tiles(contraption: &Contraption) -> usize {
+        contraption.get_entry_beams().into_iter()
+            .map(|(start, direction)| contraption.get_energized_tiles_from(start, direction))
+            .max()
+            .unwrap_or(0)
+    }
+
     #[test]
     fn test_get_max_energized_tiles() {
         let contraption = TEST_INPUT.parse::<Contraption>().unwrap();
-        assert_eq!(contraption.get_max_energized_tiles(), 51);
+        assert_eq!(get_max_energized_tiles(&contraption), 51);
+    }
+
+    #[test]
+    fn test_get_max_energized_tiles_cached() {
+        let contraption = TEST_INPUT.parse::<Contraption>().unwrap();
+        assert_eq!(contraption.get_max_energized_tiles_cached(), 51);
+    }
+
+    #[test]
+    fn test_get_beam_segments() {
+        let contraption = TEST_INPUT.parse::<Contraption>().unwrap();
+        let (segments, entry_nodes) = contraption.get_beam_segments();
+
+        assert_eq!(entry_nodes.len(), 40);
+        // Every segment but those leaving the contraption end in a splitter sending the beam two ways
+        assert!((0..segments.len()).all(|node| [0, 1, 2].contains(&segments.successors(node).count())));
+        // The first entry beam (top-left, going right) runs into the splitter right next to it
+        assert_eq!(segments.node(entry_nodes[0]).len(), 2);
+    }
+
+    #[test]
+    fn test_cached_matches_following_beams() {
+        // Besides the example, a couple of contraptions where mirrors send split beams back into their splitter, to be
+        // sure loops between segments are handled.
+        let contraptions = [
+            TEST_INPUT,
+            "\\.../\n.\\-/.\n.|.|.\n./-\\.\n/...\\",
+            "/.-.\\\n.....\n|.../\n.....\n\\.-./",
+            "./|\\.\n.\\-/.\n./-\\.\n.\\|/.",
+        ];
+
+        for input in contraptions {
+            let contraption = input.parse::<Contraption>().unwrap();
+            let (segments, entry_nodes) = contraption.get_beam_segments();
+            assert_eq!(contraption.get_max_energized_tiles_cached(), get_max_energized_tiles(&contraption), "For:\n{}", input);
+            assert_eq!(entry_nodes.len(), 2 * (contraption.bounds.width + contraption.bounds.height));
+            assert!(!segments.is_empty());
+        }
     }
 
     const TEST_INPUT: &str = "\
@@ -150,4 +385,16 @@ impl FromStr for Tile {
             _ => Err(format!("Invalid tile '{}'", s))
         }
     }
-}
\ No newline at end of file
+}
+
+impl Display for Tile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tile::Empty => write!(f, "."),
+            Tile::VerSplit => write!(f, "|"),
+            Tile::HorSplit => write!(f, "-"),
+            Tile::MirrorRight => write!(f, "/"),
+            Tile::MirrorLeft => write!(f, "\\"),
+        }
+    }
+}
@@ -1,59 +1,107 @@
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap};
-use crate::days::Day;
-use crate::days::day17::TrafficMode::{Normal, Ultra};
-use crate::util::geometry::{Directions, Grid, Point};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::color::AnsiColor;
+use crate::util::geometry::{CellFormat, Dir, Grid, Point};
+use crate::util::visualize::{self, Visualizer};
 
 pub const DAY17: Day = Day {
-    puzzle1,
-    puzzle2
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let map = TrafficMap::parse(input).unwrap();
-    println!("Least heat loss: {}", map.get_best_path(Normal));
+struct Solution;
+
+impl StructuredDay for Solution {
+    type Parsed = TrafficMap;
+
+    fn parse(input: &str) -> AocResult<TrafficMap> {
+        TrafficMap::parse(input)
+    }
+
+    fn part1(map: &TrafficMap) -> AocResult<String> {
+        Ok(format!("Least heat loss: {}", map.get_best_path(NORMAL_CRUCIBLE)))
+    }
+
+    fn part2(map: &TrafficMap) -> AocResult<String> {
+        Ok(format!("Least heat loss ultra crucibles™: {}", map.get_best_path(ULTRA_CRUCIBLE)))
+    }
 }
 
-fn puzzle2(input: &String) {
-    let map = TrafficMap::parse(input).unwrap();
-    println!("Least heat loss ultra crucibles™: {}", map.get_best_path(Ultra));
+// How a crucible is allowed to move: it needs to move at least min_straight blocks in a direction before it can turn
+// (or stop), and can move at most max_straight blocks in a single direction.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+struct CrucibleRules {
+    min_straight: usize,
+    max_straight: usize,
 }
 
-type TrafficMap = Grid<usize>;
+// Normal crucibles can move at most three blocks in a single direction
+const NORMAL_CRUCIBLE: CrucibleRules = CrucibleRules { min_straight: 1, max_straight: 3 };
+// Ultra crucibles need to move at least four blocks before turning (or stopping), and at most ten
+const ULTRA_CRUCIBLE: CrucibleRules = CrucibleRules { min_straight: 4, max_straight: 10 };
 
-#[derive(Eq, PartialEq, Debug, Copy, Clone)]
-enum TrafficMode {
-    Normal,
-    Ultra
+impl CrucibleRules {
+    fn can_stop(&self, amount: usize) -> bool {
+        amount >= self.min_straight
+    }
+
+    // The moves (direction, and the amount of blocks moved in that direction after the move) a crucible can make after
+    // moving amount blocks in the given direction. We cannot turn around, and an amount of 0 (the start) can turn right away.
+    fn next_moves(&self, direction: Dir, amount: usize) -> impl Iterator<Item = (Dir, usize)> {
+        let straight = if amount < self.max_straight { Some((direction, amount + 1)) } else { None };
+        let turns = (amount == 0 || amount >= self.min_straight).then(|| direction.turns());
+        straight.into_iter().chain(turns.into_iter().flatten().map(|d| (d, 1)))
+    }
 }
 
+type TrafficMap = Grid<usize>;
+
 impl TrafficMap {
-    fn parse(input: &str) -> Result<TrafficMap, String> {
-        input.parse()
+    fn parse(input: &str) -> AocResult<TrafficMap> {
+        // The heat loss of every block is a single digit in the puzzle, but a map with larger heat losses can separate the
+        // blocks by commas or whitespace.
+        Grid::parse_with(input, CellFormat::detect(input))
     }
 
-    fn get_best_path(&self, mode: TrafficMode) -> usize {
+    fn get_best_path(&self, rules: CrucibleRules) -> usize {
         // We need to find the best path from top-left (0,0) to bottom-right.
-        // We can go at most three steps in the same direction (sadly, making this not a simple dijkstra...)
+        // We can go at most max_straight steps in the same direction (sadly, making this not a simple dijkstra...)
         // However, I hope we can fit this into something close to it by:
         // - Keeping a distance map keyed by (point, direction, steps) (yes, this could somewhat explode, but I hope it'll still work)
         // - Using a BinaryHeap to push next steps / getting the lowest current value from
         // - Ending when we find one that ends up at the destination
-        // (Except the 'weird' key, and possible 12 times as large distance map as normal... it should be close to dijkstra)
+        // Every block costs at least 1 heat loss, so the manhattan distance to the destination never overestimates the
+        // remaining heat loss. Using that as A* heuristic makes us explore towards the destination first.
         let mut distances: HashMap<TrafficDistanceKey, usize> = HashMap::new();
         let mut queue: BinaryHeap<TrafficDistanceEntry> = BinaryHeap::new();
 
         let destination: Point = (self.bounds.right(), self.bounds.bottom()).into();
+        let estimate = |point: &Point| point.manhattan_distance(&destination) as usize;
 
-        // Initial entry we start with an amount of 0, so that we can still travel three moves even in the same direction.
-        queue.push(TrafficDistanceEntry { point: (0, 0).into(), heat_loss: 0, direction: Directions::Right, amount: 0 });
+        // Initial entries start with an amount of 0, so that we can still travel max_straight moves even in the same direction.
+        for direction in [Dir::East, Dir::South] {
+            let point: Point = (0, 0).into();
+            queue.push(TrafficDistanceEntry { point, heat_loss: 0, estimated_heat_loss: estimate(&point), direction, amount: 0 });
+        }
 
+        let mut processed = 0;
         while let Some(entry) = queue.pop() {
-            // Are we done?
-            if entry.point == destination {
+            // Are we done? (We can only stop once we moved enough blocks in a straight line)
+            if entry.point == destination && rules.can_stop(entry.amount) {
+                if visualize::is_enabled() {
+                    visualize::show(&format!("Found destination with heat loss {}", entry.heat_loss), &SearchState { map: self, distances: &distances });
+                }
                 return entry.heat_loss
             }
 
+            processed += 1;
+            if visualize::is_enabled() && processed % 1000 == 0 {
+                visualize::show(&format!("Searching, current heat loss {}", entry.heat_loss), &SearchState { map: self, distances: &distances });
+            }
+
             // Get key to distance map:
             let key = TrafficDistanceKey { point: entry.point, amount: entry.amount, direction: entry.direction };
             if let Some(distance) = distances.get(&key) {
@@ -63,41 +111,16 @@ impl TrafficMap {
             // Update distance map:
             distances.insert(key, entry.heat_loss);
 
-            // If our direction is still allowed, we add it with an additional amount. We add all other directions with amount 1.
-            // Note: we cannot turn around
-            // Two different options here:
-            // - in normal mode, we can turn whenever.
-            // - in ultra mode, we can turn only after going in one direction for 4 blocks.
-            let options = match entry.direction {
-                Directions::Top if mode == Normal && entry.amount < 3 => vec![Directions::Top, Directions::Left, Directions::Right],
-                Directions::Top if mode == Normal => vec![Directions::Left, Directions::Right],
-                Directions::Top if mode == Ultra && entry.amount < 4 => vec![Directions::Top],
-                Directions::Top if mode == Ultra && entry.amount < 10 => vec![Directions::Top, Directions::Left, Directions::Right],
-                Directions::Top if mode == Ultra => vec![Directions::Left, Directions::Right],
-
-                Directions::Right if mode == Normal && entry.amount < 3 => vec![Directions::Right, Directions::Top, Directions::Bottom],
-                Directions::Right if mode == Normal => vec![Directions::Top, Directions::Bottom],
-                Directions::Right if mode == Ultra && entry.amount < 4 => vec![Directions::Right],
-                Directions::Right if mode == Ultra && entry.amount < 10 => vec![Directions::Right, Directions::Top, Directions::Bottom],
-                Directions::Right if mode == Ultra => vec![Directions::Top, Directions::Bottom],
-
-                Directions::Bottom if mode == Normal && entry.amount < 3 => vec![Directions::Bottom, Directions::Left, Directions::Right],
-                Directions::Bottom if mode == Normal => vec![Directions::Left, Directions::Right],
-                Directions::Bottom if mode == Ultra && entry.amount < 4 => vec![Directions::Bottom],
-                Directions::Bottom if mode == Ultra && entry.amount < 10 => vec![Directions::Bottom, Directions::Left, Directions::Right],
-                Directions::Bottom if mode == Ultra => vec![Directions::Left, Directions::Right],
-
-                Directions::Left if mode == Normal && entry.amount < 3 => vec![Directions::Left, Directions::Top, Directions::Bottom],
-                Directions::Left if mode == Normal => vec![Directions::Top, Directions::Bottom],
-                Directions::Left if mode == Ultra && entry.amount < 4 => vec![Directions::Left],
-                Directions::Left if mode == Ultra && entry.amount < 10 => vec![Directions::Left, Directions::Top, Directions::Bottom],
-                Directions::Left if mode == Ultra => vec![Directions::Top, Directions::Bottom],
-                _ => continue
-            };
-
-            for direction in options {
-                if let [(next_point, heat_loss)] = self.get_adjacent_entries(&entry.point, direction)[..] {
-                    queue.push(TrafficDistanceEntry { point: next_point, heat_loss: entry.heat_loss + heat_loss, direction, amount: if entry.direction == direction { entry.amount + 1 } else { 1 } })
+            for (direction, amount) in rules.next_moves(entry.direction, entry.amount) {
+                if let Some((next_point, heat_loss)) = self.get_adjacent_entry(&entry.point, direction) {
+                    let next_heat_loss = entry.heat_loss + heat_loss;
+                    queue.push(TrafficDistanceEntry {
+                        point: next_point,
+                        heat_loss: next_heat_loss,
+                        estimated_heat_loss: next_heat_loss + estimate(&next_point),
+                        direction,
+                        amount,
+                    })
                 }
             }
         }
@@ -107,27 +130,42 @@ impl TrafficMap {
     }
 }
 
-// Key for the distance map to implement Dijkstra.
+// The map with the blocks explored by the search highlighted, for --visualize
+struct SearchState<'a> {
+    map: &'a TrafficMap,
+    distances: &'a HashMap<TrafficDistanceKey, usize>,
+}
+
+impl Visualizer for SearchState<'_> {
+    fn render_frame(&self) -> String {
+        let explored: HashSet<Point> = self.distances.keys().map(|k| k.point).collect();
+        self.map.render_highlighted(&explored, AnsiColor::Red)
+    }
+}
+
+// Key for the distance map to implement A*.
 #[derive(Eq, PartialEq, Debug, Hash, Copy, Clone)]
 struct TrafficDistanceKey {
     point: Point,
-    direction: Directions,
+    direction: Dir,
     amount: usize,
 }
 
-// Entry for the Dijkstra BinaryHeap representing the current location data and heat loss (travel distance)
+// Entry for the A* BinaryHeap representing the current location data, heat loss (travel distance), and the heat loss
+// including the (lower bound) estimate to the destination.
 #[derive(Eq, PartialEq, Debug, Hash, Copy, Clone)]
 struct TrafficDistanceEntry {
     point: Point,
-    direction: Directions,
+    direction: Dir,
     amount: usize,
     heat_loss: usize,
+    estimated_heat_loss: usize,
 }
 
 impl Ord for TrafficDistanceEntry {
     fn cmp(&self, other: &Self) -> Ordering {
         // Note: we invert the Ord here such that BinaryHeap.pop returns the _smallest_ value
-        other.heat_loss.cmp(&self.heat_loss)
+        other.estimated_heat_loss.cmp(&self.estimated_heat_loss)
     }
 }
 
@@ -139,16 +177,23 @@ impl PartialOrd<Self> for TrafficDistanceEntry {
 
 #[cfg(test)]
 mod tests {
+    use crate::util::allocations::count_allocations;
     use std::collections::BinaryHeap;
-    use crate::days::day17::{TrafficDistanceEntry, TrafficMap, TrafficMode};
-    use crate::util::geometry::{Directions};
+    use crate::days::day17::{CrucibleRules, NORMAL_CRUCIBLE, TrafficDistanceEntry, TrafficMap, ULTRA_CRUCIBLE};
+    use crate::util::geometry::Dir;
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("102"), puzzle2: Some("94") },
+        Example { input: ULTRA_TEST_INPUT, puzzle1: None, puzzle2: Some("71") },
+    ];
 
     #[test]
     fn test_traffic_distance_key_ordering() {
         let mut heap = BinaryHeap::new();
-        let entry_1 = TrafficDistanceEntry { point: (0, 0).into(), direction: Directions::Left, amount: 4, heat_loss: 100 };
-        let entry_2 = TrafficDistanceEntry { point: (10, 3).into(), direction: Directions::Left, amount: 1, heat_loss: 95 };
-        let entry_3 = TrafficDistanceEntry { point: (12, 4).into(), direction: Directions::Bottom, amount: 3, heat_loss: 105 };
+        let entry_1 = TrafficDistanceEntry { point: (0, 0).into(), direction: Dir::West, amount: 4, heat_loss: 90, estimated_heat_loss: 100 };
+        let entry_2 = TrafficDistanceEntry { point: (10, 3).into(), direction: Dir::West, amount: 1, heat_loss: 95, estimated_heat_loss: 95 };
+        let entry_3 = TrafficDistanceEntry { point: (12, 4).into(), direction: Dir::South, amount: 3, heat_loss: 80, estimated_heat_loss: 105 };
 
         heap.push(entry_1.clone());
         heap.push(entry_2.clone());
@@ -163,8 +208,50 @@ mod tests {
     fn test_get_best_path() {
         let map = TrafficMap::parse(TEST_INPUT).unwrap();
 
-        assert_eq!(map.get_best_path(TrafficMode::Normal), 102);
-        assert_eq!(map.get_best_path(TrafficMode::Ultra), 94);
+        assert_eq!(map.get_best_path(NORMAL_CRUCIBLE), 102);
+        assert_eq!(map.get_best_path(ULTRA_CRUCIBLE), 94);
+
+        // Without any constraints, this is just the shortest path
+        assert_eq!(map.get_best_path(CrucibleRules { min_straight: 1, max_straight: usize::MAX }), 78);
+    }
+
+    #[test]
+    fn test_parse_delimited() {
+        let map = TrafficMap::parse("1, 12, 1\n1, 1, 1\n20, 30, 1").unwrap();
+        assert_eq!(map.get(&(1, 0).into()), Some(12));
+
+        // Going around the 12 is cheaper than going through it
+        assert_eq!(map.get_best_path(NORMAL_CRUCIBLE), 4);
+        assert_eq!(TrafficMap::parse("1 12 1\n1 1 1\n20 30 1"), Ok(map));
+    }
+
+    #[test]
+    fn test_next_moves() {
+        let moves = |rules: CrucibleRules, direction, amount| rules.next_moves(direction, amount).collect::<Vec<_>>();
+
+        assert_eq!(moves(NORMAL_CRUCIBLE, Dir::East, 0), vec![(Dir::East, 1), (Dir::North, 1), (Dir::South, 1)]);
+        assert_eq!(moves(NORMAL_CRUCIBLE, Dir::North, 2), vec![(Dir::North, 3), (Dir::West, 1), (Dir::East, 1)]);
+        assert_eq!(moves(NORMAL_CRUCIBLE, Dir::North, 3), vec![(Dir::West, 1), (Dir::East, 1)]);
+
+        assert_eq!(moves(ULTRA_CRUCIBLE, Dir::West, 3), vec![(Dir::West, 4)]);
+        assert_eq!(moves(ULTRA_CRUCIBLE, Dir::West, 4), vec![(Dir::West, 5), (Dir::North, 1), (Dir::South, 1)]);
+        assert_eq!(moves(ULTRA_CRUCIBLE, Dir::West, 10), vec![(Dir::North, 1), (Dir::South, 1)]);
+    }
+
+    #[test]
+    fn test_get_best_path_allocations() {
+        let map = TrafficMap::parse(TEST_INPUT).unwrap();
+        let (result, allocations) = count_allocations(|| map.get_best_path(ULTRA_CRUCIBLE));
+        assert_eq!(result, 94);
+        // Only the queue/map growth should allocate, not every step of the heap loop
+        assert!(allocations <= 32, "Expected at most 32 allocations, got {}", allocations);
+    }
+
+    #[test]
+    fn test_get_best_path_minimum_before_stopping() {
+        let map = TrafficMap::parse(ULTRA_TEST_INPUT).unwrap();
+
+        assert_eq!(map.get_best_path(ULTRA_CRUCIBLE), 71);
     }
 
     const TEST_INPUT: &str = "\
@@ -182,4 +269,12 @@ mod tests {
         2546548887735\n\
         4322674655533\
     ";
+
+    const ULTRA_TEST_INPUT: &str = "\
+        111111111111\n\
+        999999999991\n\
+        999999999991\n\
+        999999999991\n\
+        999999999991\
+    ";
 }
\ No newline at end of file
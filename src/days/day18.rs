@@ -1,26 +1,56 @@
-use std::ops::RangeInclusive;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::write;
 use std::str::FromStr;
-use crate::days::Day;
-use crate::util::geometry::{Directions};
-use crate::util::number::{parse_usize_radix};
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::geometry::{Dir, Point};
+use crate::util::number::parse_num_radix;
 use crate::util::parser::Parser;
+use crate::util::polygon::enclosed_points;
+use crate::util::visualize;
 
 pub const DAY18: Day = Day {
-    puzzle1,
-    puzzle2,
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let operations = Operation::parse_input(input).unwrap();
-    println!("Lagoon size after digging: {}", fill(&operations, false));
+struct Solution;
+
+impl StructuredDay for Solution {
+    type Parsed = Vec<Operation>;
+
+    fn parse(input: &str) -> AocResult<Vec<Operation>> {
+        Operation::parse_input(input).map_err(Into::into)
+    }
+
+    fn part1(operations: &Vec<Operation>) -> AocResult<String> {
+        if visualize::is_enabled() {
+            // The lagoon is too big to draw in the terminal, so we export it instead.
+            Operation::to_svg(operations, PlanSource::Plain, true, "day18-part1.svg")?;
+        }
+        Ok(format!("Lagoon size after digging: {}", fill(operations, PlanSource::Plain)?))
+    }
+
+    fn part2(operations: &Vec<Operation>) -> AocResult<String> {
+        if visualize::is_enabled() {
+            Operation::to_svg(operations, PlanSource::Encoded, true, "day18-part2.svg")?;
+        }
+        Ok(format!("Lagoon size after digging: {}", fill(operations, PlanSource::Encoded)?))
+    }
 }
 
-fn puzzle2(input: &String) {
-    let operations = Operation::parse_input(input).unwrap();
-    println!("Lagoon size after digging: {}", fill(&operations, true));
+fn fill(operations: &Vec<Operation>, source: PlanSource) -> Result<isize, String> {
+    // The trench goes through the middle of the dug cells, so the lagoon consists of all grid points on or inside the
+    // polygon through the trench corners. (Shoelace for the area, and Pick's theorem to get to the points.)
+    Ok(enclosed_points(&Operation::get_corners(operations, source)?) as isize)
 }
 
-fn fill(operations: &Vec<Operation>, use_encoded_data: bool) -> isize {
+// The original implementation, matching up left/right ranges row by row. Kept as a cross-check for fill.
+#[cfg(test)]
+fn fill_by_ranges(operations: &Vec<Operation>, source: PlanSource) -> isize {
+    use std::ops::RangeInclusive;
+
     // The naive implementation to actually draw the walls might be too slow given puzzle two uses the 6-char hex
     // values as amounts instead.
     // The amount of tiles 'to fill' is also going to be be too high to traverse one-by-one, even for the test input.
@@ -35,30 +65,31 @@ fn fill(operations: &Vec<Operation>, use_encoded_data: bool) -> isize {
         Left(RangeInclusive<isize>, isize),
     }
 
+    let instructions = Operation::get_instructions(operations, source).unwrap();
     let mut processed = vec![];
 
     let mut current_row: isize = 0;
     let mut current_col: isize = 0;
 
-    for oi in 0..operations.len() {
-        let operation = operations[oi];
-        let prev_operation = if oi > 0 { operations[oi - 1] } else { operations[operations.len() - 1] };
-        let next_operation = if oi < operations.len() - 1 { operations[oi + 1] } else { operations[0] };
-        let amount = operation.amount(use_encoded_data) as isize;
+    for oi in 0..instructions.len() {
+        let instruction = instructions[oi];
+        let prev_instruction = if oi > 0 { instructions[oi - 1] } else { instructions[instructions.len() - 1] };
+        let next_instruction = if oi < instructions.len() - 1 { instructions[oi + 1] } else { instructions[0] };
+        let amount = instruction.amount as isize;
         // Note: we only store left/right, as that contains all information we need.
-        match operation.direction(use_encoded_data) {
-            Directions::Top => { current_row -= amount; }
-            Directions::Bottom => { current_row += amount; }
-            Directions::Right => {
+        match instruction.direction {
+            Dir::North => { current_row -= amount; }
+            Dir::South => { current_row += amount; }
+            Dir::East => {
                 let mut start = current_col;
                 let mut length = amount;
                 // If the previous operation went down, our first cell is empty
-                if prev_operation.direction(use_encoded_data) == Directions::Bottom {
+                if prev_instruction.direction == Dir::South {
                     start += 1;
                     length -= 1;
                 }
                 // If the next operation goes up, our last cell is empty
-                if next_operation.direction(use_encoded_data) == Directions::Top {
+                if next_instruction.direction == Dir::North {
                     length -= 1;
                 }
 
@@ -66,15 +97,15 @@ fn fill(operations: &Vec<Operation>, use_encoded_data: bool) -> isize {
                 processed.push(ProcessedOperation::Right(range, current_row));
                 current_col += amount;
             }
-            Directions::Left => {
+            Dir::West => {
                 let mut start = current_col - amount;
                 let mut length = amount;
                 // If the previous operation went up, our last cell is empty
-                if prev_operation.direction(use_encoded_data) == Directions::Top {
+                if prev_instruction.direction == Dir::North {
                     length -= 1;
                 }
                 // If the next operation goes down, our first cell is empty
-                if next_operation.direction(use_encoded_data) == Directions::Bottom {
+                if next_instruction.direction == Dir::South {
                     start += 1;
                     length -= 1;
                 }
@@ -83,7 +114,6 @@ fn fill(operations: &Vec<Operation>, use_encoded_data: bool) -> isize {
                 processed.push(ProcessedOperation::Left(range, current_row));
                 current_col -= amount;
             }
-            _ => {} // Ignore wrong direction, should not happen from input
         }
     }
 
@@ -141,13 +171,34 @@ fn fill(operations: &Vec<Operation>, use_encoded_data: bool) -> isize {
     lagoon_size
 }
 
+// Which of the instructions in the dig plan to follow.
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
-struct Operation {
-    raw_direction: Directions,
-    raw_amount: usize,
+enum PlanSource {
+    Plain,   // The direction and amount, e.g. "R 6" (puzzle 1)
+    Encoded, // The hex 'color', e.g. "(#70c710)" (puzzle 2)
+}
 
-    encoded_direction: Directions,
-    encoded_amount: usize,
+impl PlanSource {
+    // What the puzzle using this source needs, to explain a dig plan that doesn't have it.
+    fn requirement(&self) -> &'static str {
+        match self {
+            PlanSource::Plain => "Puzzle 1 needs a plain instruction (like \"R 6\")",
+            PlanSource::Encoded => "Puzzle 2 needs an encoded instruction (like \"(#70c710)\")",
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+struct Instruction {
+    direction: Dir,
+    amount: usize,
+}
+
+// A line of the dig plan, which can have the plain instruction, the encoded one, or both.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+struct Operation {
+    plain: Option<Instruction>,
+    encoded: Option<Instruction>,
 }
 
 impl Operation {
@@ -155,32 +206,209 @@ impl Operation {
         input.lines().map(|l| l.parse::<Operation>()).collect()
     }
 
-    fn direction(&self, use_encoded_data: bool) -> Directions {
-        if use_encoded_data { self.encoded_direction } else { self.raw_direction }
+    fn instruction(&self, source: PlanSource) -> Option<Instruction> {
+        match source {
+            PlanSource::Plain => self.plain,
+            PlanSource::Encoded => self.encoded,
+        }
+    }
+
+    fn get_instructions(operations: &Vec<Operation>, source: PlanSource) -> Result<Vec<Instruction>, String> {
+        operations.iter().enumerate()
+            .map(|(i, operation)| operation.instruction(source).ok_or(format!("{} on every line of the dig plan, but line {} has none", source.requirement(), i + 1)))
+            .collect()
+    }
+
+    // The corners of the trench, starting (and ending) at (0, 0).
+    fn get_corners(operations: &Vec<Operation>, source: PlanSource) -> Result<Vec<Point>, String> {
+        let mut current = Point { x: 0, y: 0 };
+        let mut corners = vec![current];
+        for instruction in Self::get_instructions(operations, source)? {
+            current = current.step(instruction.direction, instruction.amount);
+            corners.push(current);
+        }
+
+        // The lagoon is only enclosed when the trench gets back to where it started.
+        if corners.len() < 3 {
+            return Err(format!("The dig plan needs at least 2 instructions to enclose a lagoon, got {}", corners.len() - 1));
+        }
+        if current != corners[0] {
+            return Err(format!("The trench ends at {} instead of getting back to {}", current, corners[0]));
+        }
+        Ok(corners)
+    }
+
+    // Draws the trench (through the middle of the dug cells) as an SVG polygon, optionally filling the interior.
+    #[cfg(any(test, not(target_arch = "wasm32")))]
+    fn to_svg_string(operations: &Vec<Operation>, source: PlanSource, fill_interior: bool) -> Result<String, String> {
+        let corners = Self::get_corners(operations, source)?;
+        let left = corners.iter().map(|p| p.x).min().unwrap_or(0);
+        let top = corners.iter().map(|p| p.y).min().unwrap_or(0);
+        let width = corners.iter().map(|p| p.x).max().unwrap_or(0) - left;
+        let height = corners.iter().map(|p| p.y).max().unwrap_or(0) - top;
+        // The trench is a single cell wide, which would be invisible for the encoded (huge) instructions.
+        let stroke_width = ((width.max(height) as f64) / 500f64).max(1f64);
+
+        let points = corners.iter().map(|p| format!("{},{}", p.x, p.y)).collect::<Vec<_>>().join(" ");
+        Ok(format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n  <polygon points=\"{}\" fill=\"{}\" stroke=\"black\" stroke-width=\"{}\" />\n</svg>\n",
+            left as f64 - stroke_width, top as f64 - stroke_width, width as f64 + 2f64 * stroke_width, height as f64 + 2f64 * stroke_width,
+            points, if fill_interior { "lightblue" } else { "none" }, stroke_width
+        ))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn to_svg(operations: &Vec<Operation>, source: PlanSource, fill_interior: bool, path: &str) -> Result<(), String> {
+        write(path, Self::to_svg_string(operations, source, fill_interior)?)
+            .map_err(|e| format!("Could not write svg to '{}': {}", path, e))
     }
-    fn amount(&self, use_encoded_data: bool) -> usize {
-        if use_encoded_data { self.encoded_amount } else { self.raw_amount }
+
+    #[cfg(target_arch = "wasm32")]
+    fn to_svg(_operations: &Vec<Operation>, _source: PlanSource, _fill_interior: bool, path: &str) -> Result<(), String> {
+        Err(format!("Cannot write svg to '{}', there is no filesystem in wasm", path))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day18::{Operation, fill};
-    use crate::util::geometry::Directions;
+    use crate::days::day18::{fill, fill_by_ranges, Instruction, Operation, PlanSource};
+    use crate::solve;
+    use crate::util::error::AocError;
+    use crate::util::geometry::{Bounds, Dir, Grid, Point};
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("62"), puzzle2: Some("952408144115") },
+    ];
 
     #[test]
     fn test_parse_operation() {
-        assert_eq!("R 6 (#70c710)".parse::<Operation>(), Ok(Operation { raw_direction: Directions::Right, raw_amount: 6, encoded_direction: Directions::Right, encoded_amount: 0x70c71 }));
-        assert_eq!("L 2 (#5713f0)".parse::<Operation>(), Ok(Operation { raw_direction: Directions::Left, raw_amount: 2, encoded_direction: Directions::Right, encoded_amount: 0x5713f }));
-        assert_eq!("U 3 (#a77fa3)".parse::<Operation>(), Ok(Operation { raw_direction: Directions::Top, raw_amount: 3, encoded_direction: Directions::Top, encoded_amount: 0xa77fa }));
-        assert_eq!("D 2 (#411b91)".parse::<Operation>(), Ok(Operation { raw_direction: Directions::Bottom, raw_amount: 2, encoded_direction: Directions::Bottom, encoded_amount: 0x411b9 }));
+        fn instruction(direction: Dir, amount: usize) -> Option<Instruction> {
+            Some(Instruction { direction, amount })
+        }
+
+        assert_eq!("R 6 (#70c710)".parse::<Operation>(), Ok(Operation { plain: instruction(Dir::East, 6), encoded: instruction(Dir::East, 0x70c71) }));
+        assert_eq!("L 2 (#5713f0)".parse::<Operation>(), Ok(Operation { plain: instruction(Dir::West, 2), encoded: instruction(Dir::East, 0x5713f) }));
+        assert_eq!("U 3 (#a77fa3)".parse::<Operation>(), Ok(Operation { plain: instruction(Dir::North, 3), encoded: instruction(Dir::North, 0xa77fa) }));
+        assert_eq!("D 2 (#411b91)".parse::<Operation>(), Ok(Operation { plain: instruction(Dir::South, 2), encoded: instruction(Dir::South, 0x411b9) }));
+
+        // Other encodings of the plan
+        assert_eq!("R 6".parse::<Operation>(), Ok(Operation { plain: instruction(Dir::East, 6), encoded: None }));
+        assert_eq!("(#70c710)".parse::<Operation>(), Ok(Operation { plain: None, encoded: instruction(Dir::East, 0x70c71) }));
+        assert_eq!("#411b91".parse::<Operation>(), Ok(Operation { plain: None, encoded: instruction(Dir::South, 0x411b9) }));
+        assert!("".parse::<Operation>().is_err());
+        assert!("R 6 (#70c710".parse::<Operation>().is_err());
+        assert!("R 6 (#70c714)".parse::<Operation>().is_err());
+    }
+
+    #[test]
+    fn test_fill_other_encodings() {
+        let plain = TEST_INPUT.lines().map(|l| &l[..l.find(" (").unwrap()]).collect::<Vec<_>>().join("\n");
+        let hex = TEST_INPUT.lines().map(|l| &l[l.find('#').unwrap()..(l.len() - 1)]).collect::<Vec<_>>().join("\n");
+
+        let operations = Operation::parse_input(&plain).unwrap();
+        assert_eq!(fill(&operations, PlanSource::Plain), Ok(62));
+        assert_eq!(fill(&operations, PlanSource::Encoded), Err("Puzzle 2 needs an encoded instruction (like \"(#70c710)\") on every line of the dig plan, but line 1 has none".to_string()));
+
+        let operations = Operation::parse_input(&hex).unwrap();
+        assert_eq!(fill(&operations, PlanSource::Encoded), Ok(952408144115));
+        assert_eq!(fill(&operations, PlanSource::Plain), Err("Puzzle 1 needs a plain instruction (like \"R 6\") on every line of the dig plan, but line 1 has none".to_string()));
+    }
+
+    #[test]
+    fn test_solve_missing_encoding() {
+        // A plan with only some of the encoded instructions still solves puzzle 1, but not puzzle 2
+        let plan = "R 2 (#000020)\nD 1\nL 2 (#000022)\nU 1 (#000013)";
+
+        assert_eq!(solve(18, 1, plan), Ok("Lagoon size after digging: 6".to_string()));
+        assert_eq!(solve(18, 2, plan), Err(AocError::Logic("Puzzle 2 needs an encoded instruction (like \"(#70c710)\") on every line of the dig plan, but line 2 has none".to_string())));
+    }
+
+    #[test]
+    fn test_to_svg_string() {
+        let operations = Operation::parse_input("R 2 (#000020)\nD 1 (#000011)\nL 2 (#000022)\nU 1 (#000013)").unwrap();
+
+        assert_eq!(Operation::get_corners(&operations, PlanSource::Plain), Ok(vec![(0, 0).into(), (2, 0).into(), (2, 1).into(), (0, 1).into(), (0, 0).into()]));
+        assert_eq!(Operation::to_svg_string(&operations, PlanSource::Plain, false), Ok("\
+            <svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-1 -1 4 3\">\n  \
+            <polygon points=\"0,0 2,0 2,1 0,1 0,0\" fill=\"none\" stroke=\"black\" stroke-width=\"1\" />\n\
+            </svg>\n".to_string()));
+        assert!(Operation::to_svg_string(&operations, PlanSource::Encoded, true).unwrap().contains("fill=\"lightblue\""));
+    }
+
+    // Digs out the trench cell by cell, with its top-left at (0, 0). Only feasible for the plain instructions, to check the
+    // corners against the puzzle's drawing.
+    fn dig_trench(operations: &Vec<Operation>, source: PlanSource) -> Result<Grid<bool>, String> {
+        let mut trench = Grid::default();
+        let mut current = Point { x: 0, y: 0 };
+        trench.set(current, true);
+        for instruction in Operation::get_instructions(operations, source)? {
+            for _ in 0..instruction.amount {
+                current = current.step(instruction.direction, 1);
+                trench.set(current, true);
+            }
+        }
+        trench.normalize();
+        Ok(trench)
+    }
+
+    #[test]
+    fn test_dig_trench() {
+        // The trench as drawn in the puzzle:
+        let trench = dig_trench(&Operation::parse_input(TEST_INPUT).unwrap(), PlanSource::Plain).unwrap();
+        assert_eq!(trench.render_with(|_, _| "#".to_string()).replace(' ', "."), "\
+            #######\n\
+            #.....#\n\
+            ###...#\n\
+            ..#...#\n\
+            ..#...#\n\
+            ###.###\n\
+            #...#..\n\
+            ##..###\n\
+            .#....#\n\
+            .######");
+
+        // Going up and left first puts the trench's corner at (0, 0) as well.
+        let trench = dig_trench(&Operation::parse_input("L 2 (#000022)\nU 1 (#000013)\nR 2 (#000020)\nD 1 (#000011)").unwrap(), PlanSource::Plain).unwrap();
+        assert_eq!(trench.bounds, Bounds { top: 0, left: 0, width: 3, height: 2 });
     }
 
     #[test]
     fn test_fill() {
         let operations = Operation::parse_input(TEST_INPUT).unwrap();
-        assert_eq!(fill(&operations, false), 62);
-        assert_eq!(fill(&operations, true), 952408144115);
+        assert_eq!(fill(&operations, PlanSource::Plain), Ok(62));
+        assert_eq!(fill(&operations, PlanSource::Encoded), Ok(952408144115));
+
+        // Trenches that don't get back to the start don't enclose anything
+        assert_eq!(
+            fill(&Operation::parse_input("R 2 (#000020)\nD 1 (#000011)").unwrap(), PlanSource::Plain),
+            Err("The trench ends at (2,1) instead of getting back to (0,0)".to_string())
+        );
+        assert_eq!(fill(&vec![], PlanSource::Plain), Err("The dig plan needs at least 2 instructions to enclose a lagoon, got 0".to_string()));
+        assert!(solve(18, 1, "").is_err());
+        assert!(solve(18, 1, "R 2 (#000020)\nD 1 (#000011)").is_err());
+    }
+
+    #[test]
+    fn test_fill_by_ranges() {
+        let operations = Operation::parse_input(TEST_INPUT).unwrap();
+        assert_eq!(fill_by_ranges(&operations, PlanSource::Plain), 62);
+        assert_eq!(fill_by_ranges(&operations, PlanSource::Encoded), 952408144115);
+
+        // Both implementations should agree, also for concave shapes. (Note: the range version only works for trenches
+        // dug clockwise, like the example and real input. The polygon one doesn't care.)
+        let shapes = [
+            "R 2 (#000020)\nD 2 (#000021)\nL 2 (#000022)\nU 2 (#000023)",
+            "R 2 (#000020)\nD 2 (#000021)\nR 2 (#000020)\nD 2 (#000021)\nL 4 (#000042)\nU 4 (#000043)",
+            "R 4 (#000040)\nD 4 (#000041)\nL 1 (#000012)\nU 2 (#000023)\nL 2 (#000022)\nD 2 (#000021)\nL 1 (#000012)\nU 4 (#000043)",
+        ];
+        for shape in shapes {
+            let operations = Operation::parse_input(shape).unwrap();
+            assert_eq!(fill(&operations, PlanSource::Plain), Ok(fill_by_ranges(&operations, PlanSource::Plain)), "Mismatch for {}", shape);
+        }
+
+        let counter_clockwise = Operation::parse_input("D 4 (#000041)\nR 4 (#000040)\nU 2 (#000023)\nL 2 (#000022)\nU 2 (#000023)\nL 2 (#000022)").unwrap();
+        assert_eq!(fill(&counter_clockwise, PlanSource::Plain), Ok(21));
     }
 
     const TEST_INPUT: &str = "\
@@ -205,28 +433,32 @@ impl FromStr for Operation {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Lines come as "R 6 (#70c710)", but we also take just the plain "R 6", or just the hex "(#70c710)"/"#70c710".
         let mut parser = Parser::new(s);
 
-        let raw_direction = match parser.one_of(vec!["U", "R", "D", "L"])? {
-            "U" => Directions::Top,
-            "R" => Directions::Right,
-            "D" => Directions::Bottom,
-            "L" => Directions::Left,
-            s => return Err(format!("Invalid direction {}", s))
+        let plain = match parser.peek_literal("(") || parser.peek_literal("#") {
+            true => None,
+            false => {
+                let direction = parser.enum_of(&[("U", Dir::North), ("R", Dir::East), ("D", Dir::South), ("L", Dir::West)])?;
+                let amount = parser.usize()?;
+                Some(Instruction { direction, amount })
+            }
         };
-        let raw_amount = parser.usize()?;
-        parser.literal("(#")?;
-        let encoded_amount = parse_usize_radix(&parser.str(5)?, 16)?;
-        let encoded_direction = match &*(parser.str(1)?) {
-            "0" => Directions::Right,
-            "1" => Directions::Bottom,
-            "2" => Directions::Left,
-            "3" => Directions::Top,
-            s => return Err(format!("Invalid encoded direction {}", s))
+
+        let encoded = match parser.is_exhausted() {
+            true => None,
+            false => {
+                let parenthesized = parser.peek_literal("(");
+                if parenthesized { parser.literal("(")?; }
+                parser.literal("#")?;
+                let amount = parse_num_radix(&parser.str(5)?, 16)?;
+                let direction = parser.enum_of(&[("0", Dir::East), ("1", Dir::South), ("2", Dir::West), ("3", Dir::North)])?;
+                if parenthesized { parser.literal(")")?; }
+                Some(Instruction { direction, amount })
+            }
         };
-        parser.literal(")")?;
         parser.ensure_exhausted()?;
 
-        Ok(Self { raw_direction, raw_amount, encoded_direction, encoded_amount })
+        Ok(Self { plain, encoded })
     }
 }
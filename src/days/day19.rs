@@ -1,24 +1,41 @@
+use std::collections::HashMap;
 use std::ops::RangeInclusive;
 use std::str::FromStr;
-use crate::days::Day;
-use crate::util::number::parse_usize;
-use crate::util::parser::Parser;
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::exact;
+use crate::util::graph::DiGraph;
+use crate::util::number::parse_num;
+use crate::util::parser::{Parser, str_get};
+use crate::util::log;
 
 pub const DAY19: Day = Day {
-    puzzle1,
-    puzzle2,
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let system = WorkflowSystem::parse(input).unwrap();
+struct Solution;
 
-    println!("Rating of accepted gears: {}", system.get_accepted_rating())
-}
+impl StructuredDay for Solution {
+    type Parsed = WorkflowSystem;
+
+    fn parse(input: &str) -> AocResult<WorkflowSystem> {
+        WorkflowSystem::parse(input).map_err(Into::into)
+    }
+
+    fn part1(system: &WorkflowSystem) -> AocResult<String> {
+        Ok(format!("Rating of accepted gears: {}", system.get_accepted_rating()?))
+    }
+
+    fn part2(system: &WorkflowSystem) -> AocResult<String> {
+        let mut system = system.clone();
 
-fn puzzle2(input: &String) {
-    let system = WorkflowSystem::parse(input).unwrap();
+        let stats = system.simplify();
+        log::debug!("Simplified {} workflows ({} rules) to {} workflows ({} rules)", stats.workflows_before, stats.rules_before, stats.workflows_after, stats.rules_after);
 
-    println!("Distinct combinations of accepted gears: {}", system.get_accepted_combinations())
+        Ok(format!("Distinct combinations of accepted gears: {}", system.get_accepted_combinations()?))
+    }
 }
 
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
@@ -70,26 +87,111 @@ struct Rule {
     action: Action,
 }
 
-impl Rule {
-    fn apply(&self, gear: &Gear) -> Option<Action> {
-        if self.condition.matches(gear) {
-            Some(self.action.clone())
-        } else {
-            None
-        }
-    }
-}
-
 #[derive(Eq, PartialEq, Debug, Clone)]
 struct Workflow {
     name: String,
     rules: Vec<Rule>,
 }
 
-impl Workflow {
-    fn get_result(&self, gear: &Gear) -> Action {
-        // A workflow should always have a catch-all rule, so we should be able to unwrap
-        self.rules.iter().find_map(|r| r.apply(gear)).unwrap()
+// Where a decision leads to: another decision (by index), or the end of the line for the gear.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+enum Target {
+    Decision(usize),
+    Accept,
+    Reject,
+}
+
+// A single conditional rule, with where to go when it matches and where to go when it doesn't.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+struct Decision {
+    condition: Condition,
+    matched: Target,
+    unmatched: Target,
+}
+
+// The workflows compiled into a (binary) decision tree. Jumps between workflows, catch-all rules and workflows that
+// only consist of a catch-all are all resolved while compiling, so running a gear through it is just following
+// indices until it is accepted or rejected.
+#[derive(Eq, PartialEq, Debug, Clone)]
+struct DecisionTree {
+    decisions: Vec<Decision>,
+    root: Target,
+}
+
+impl DecisionTree {
+    fn compile(workflows: &[Workflow]) -> Result<DecisionTree, String> {
+        let index_of: HashMap<&str, usize> = workflows.iter().enumerate().map(|(i, w)| (w.name.as_str(), i)).collect();
+        if index_of.len() != workflows.len() {
+            return Err("Workflow names are not unique".to_string());
+        }
+
+        // Every conditional rule becomes a decision, the (last) catch-all rules are folded into the one before them.
+        let mut first_decision = vec![];
+        let mut decision_count = 0;
+        for workflow in workflows {
+            match workflow.rules.split_last() {
+                Some((last, conditional)) if last.condition == Condition::None && conditional.iter().all(|r| r.condition != Condition::None) => {
+                    first_decision.push(decision_count);
+                    decision_count += conditional.len();
+                }
+                _ => return Err(format!("Workflow '{}' should end with (only) a catch-all rule", workflow.name))
+            }
+        }
+
+        fn name_of(action: &Action) -> &str {
+            match action {
+                Action::Jump(name) => name,
+                Action::Accept => "A",
+                Action::Reject => "R",
+            }
+        }
+
+        // Where the given workflow starts, following workflows that only have a catch-all rule.
+        let resolve = |action: &Action| -> Result<Target, String> {
+            let start = name_of(action);
+            let mut action = action;
+            // Without any decisions in between, we can only pass every workflow once before looping.
+            for _ in 0..=workflows.len() {
+                let name = match action {
+                    Action::Accept => return Ok(Target::Accept),
+                    Action::Reject => return Ok(Target::Reject),
+                    Action::Jump(name) => name,
+                };
+                let index = *index_of.get(name.as_str()).ok_or(format!("Unknown workflow '{}'", name))?;
+                match &workflows[index].rules[..] {
+                    [catch_all] => action = &catch_all.action,
+                    _ => return Ok(Target::Decision(first_decision[index])),
+                }
+            }
+            Err(format!("Workflows loop without any condition from '{}'", start))
+        };
+
+        let mut decisions = Vec::with_capacity(decision_count);
+        for workflow in workflows {
+            let (catch_all, conditional) = workflow.rules.split_last().unwrap();
+            let otherwise = resolve(&catch_all.action)?;
+            for (index, rule) in conditional.iter().enumerate() {
+                let unmatched = if index + 1 < conditional.len() { Target::Decision(decisions.len() + 1) } else { otherwise };
+                decisions.push(Decision { condition: rule.condition, matched: resolve(&rule.action)?, unmatched });
+            }
+        }
+
+        let root = resolve(&Action::Jump("in".to_string()))?;
+        Ok(DecisionTree { decisions, root })
+    }
+
+    fn accepts(&self, gear: &Gear) -> bool {
+        let mut current = self.root;
+        loop {
+            match current {
+                Target::Decision(index) => {
+                    let decision = &self.decisions[index];
+                    current = if decision.condition.matches(gear) { decision.matched } else { decision.unmatched };
+                }
+                Target::Accept => return true,
+                Target::Reject => return false,
+            }
+        }
     }
 }
 
@@ -101,139 +203,284 @@ struct Gear {
     s: usize,
 }
 
+// A (hyper-rectangular) region of gears, by the range of values for each category.
+#[derive(Eq, PartialEq, Debug, Clone)]
+struct XmasRegion {
+    x: RangeInclusive<usize>,
+    m: RangeInclusive<usize>,
+    a: RangeInclusive<usize>,
+    s: RangeInclusive<usize>,
+}
+
+impl XmasRegion {
+    fn full() -> Self {
+        XmasRegion { x: 1..=4000, m: 1..=4000, a: 1..=4000, s: 1..=4000 }
+    }
+
+    fn empty() -> Self {
+        XmasRegion { x: 1..=0, m: 1..=0, a: 1..=0, s: 1..=0 }
+    }
+
+    fn count(&self) -> usize {
+        [&self.x, &self.m, &self.a, &self.s].into_iter().map(|r| r.clone().count()).product()
+    }
+
+    fn intersect(&self, other: &Self) -> Option<Self> {
+        fn intersect_range(a: &RangeInclusive<usize>, b: &RangeInclusive<usize>) -> RangeInclusive<usize> {
+            *a.start().max(b.start())..=*a.end().min(b.end())
+        }
+
+        let region = XmasRegion {
+            x: intersect_range(&self.x, &other.x),
+            m: intersect_range(&self.m, &other.m),
+            a: intersect_range(&self.a, &other.a),
+            s: intersect_range(&self.s, &other.s),
+        };
+
+        if region.count() == 0 { None } else { Some(region) }
+    }
+
+    fn are_disjoint(regions: &[Self]) -> bool {
+        regions.iter().enumerate().all(|(i, a)| regions[i + 1..].iter().all(|b| a.intersect(b).is_none()))
+    }
+
+    fn count_union(regions: &[Self]) -> usize {
+        // Inclusion-exclusion, one region at a time:
+        // |A ∪ rest| = |A| - |(A ∩ r1) ∪ (A ∩ r2) ∪ ...| + |rest|
+        match regions {
+            [] => 0,
+            [first, rest @ ..] => {
+                let overlaps: Vec<_> = rest.iter().filter_map(|r| first.intersect(r)).collect();
+                first.count() - Self::count_union(&overlaps) + Self::count_union(rest)
+            }
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+struct SimplifyStats {
+    workflows_before: usize,
+    workflows_after: usize,
+    rules_before: usize,
+    rules_after: usize,
+}
+
 #[derive(Eq, PartialEq, Debug, Clone)]
 struct WorkflowSystem {
     workflows: Vec<Workflow>,
     gears: Vec<Gear>,
 }
 
+// A gear going around in a loop of workflows would never be accepted or rejected, so we don't take any loops.
+fn check_for_loops(workflows: &[Workflow]) -> Result<(), String> {
+    let mut graph = DiGraph::new();
+    for workflow in workflows {
+        graph.add_node(workflow.name.as_str());
+    }
+    for (index, workflow) in workflows.iter().enumerate() {
+        for rule in &workflow.rules {
+            if let Action::Jump(target) = &rule.action {
+                // Unknown workflows are reported when following the rules
+                if let Some(target) = workflows.iter().position(|w| w.name.eq(target)) {
+                    graph.add_edge(index, target, ());
+                }
+            }
+        }
+    }
+
+    match graph.cyclic_components().first() {
+        Some(component) => Err(format!("Workflows loop through '{}'", component.iter().map(|i| *graph.node(*i)).collect::<Vec<_>>().join("', '"))),
+        None => Ok(()),
+    }
+}
+
 impl WorkflowSystem {
     fn parse(input: &str) -> Result<WorkflowSystem, String> {
-        if let [workflows_input, gears_input] = input.split("\n\n").collect::<Vec<_>>()[..] {
-            let workflows = workflows_input.lines().map(|l| l.parse::<Workflow>()).collect::<Result<Vec<_>, _>>()?;
-            let gears = gears_input.lines().map(|l| l.parse::<Gear>()).collect::<Result<Vec<_>, _>>()?;
-            Ok(WorkflowSystem { workflows, gears })
-        } else {
-            Err("Could not split input on a blank line correctly.".to_string())
-        }
+        // Workflows and gears are separated by a blank line
+        let mut parser = Parser::new(input);
+        let workflows = parser.block()?.lines().map(|l| l.parse::<Workflow>()).collect::<Result<Vec<_>, _>>()?;
+        let gears = parser.block()?.lines().map(|l| l.parse::<Gear>()).collect::<Result<Vec<_>, _>>()?;
+        parser.ensure_exhausted()?;
+
+        check_for_loops(&workflows)?;
+        Ok(WorkflowSystem { workflows, gears })
     }
 
-    fn get_workflow(&self, name: &str) -> Workflow {
-        self.workflows.iter().find(|w| w.name.eq(name)).unwrap().clone()
+    fn count_rules(&self) -> usize {
+        self.workflows.iter().map(|w| w.rules.len()).sum()
     }
 
-    fn accepts(&self, gear: &Gear) -> bool {
-        // We assume the 'in' workflow exists
-        let mut current = self.get_workflow("in");
+    fn simplify(&mut self) -> SimplifyStats {
+        // Makes the workflow graph smaller without changing which gears are accepted:
+        // - Rules right before the catch-all rule with the same action are useless (either way, we end up there)
+        // - Workflows with only a catch-all rule can be inlined into the rules jumping to them
+        // - Workflows that cannot be reached from 'in' can be removed
+        // Inlining can make new rules useless, so we repeat this until nothing changes anymore.
+        let workflows_before = self.workflows.len();
+        let rules_before = self.count_rules();
 
         loop {
-            match current.get_result(gear) {
-                Action::Jump(target) => {
-                    current = self.get_workflow(&target);
+            let mut changed = false;
+
+            for workflow in &mut self.workflows {
+                while let [.., second_last, last] = &workflow.rules[..] {
+                    if second_last.action != last.action { break }
+                    workflow.rules.remove(workflow.rules.len() - 2);
+                    changed = true;
                 }
-                Action::Accept => return true,
-                Action::Reject => return false
             }
+
+            // We keep 'in' around, as that is where we start.
+            let trivial = self.workflows.iter()
+                .find(|w| w.name != "in" && matches!(&w.rules[..], [Rule { condition: Condition::None, action }] if *action != Action::Jump(w.name.clone())))
+                .cloned();
+            if let Some(trivial) = trivial {
+                let action = trivial.rules[0].action.clone();
+                for rule in self.workflows.iter_mut().flat_map(|w| w.rules.iter_mut()) {
+                    if rule.action == Action::Jump(trivial.name.clone()) {
+                        rule.action = action.clone();
+                    }
+                }
+                changed = true;
+            }
+
+            let mut reachable = vec!["in".to_string()];
+            let mut index = 0;
+            while index < reachable.len() {
+                if let Some(workflow) = self.workflows.iter().find(|w| w.name == reachable[index]) {
+                    for rule in &workflow.rules {
+                        if let Action::Jump(target) = &rule.action {
+                            if !reachable.contains(target) { reachable.push(target.clone()) }
+                        }
+                    }
+                }
+                index += 1;
+            }
+            let workflow_count = self.workflows.len();
+            self.workflows.retain(|w| reachable.contains(&w.name));
+            changed |= workflow_count != self.workflows.len();
+
+            if !changed { break }
         }
+
+        SimplifyStats { workflows_before, workflows_after: self.workflows.len(), rules_before, rules_after: self.count_rules() }
+    }
+
+    fn get_workflow(&self, name: &str) -> Result<&Workflow, String> {
+        self.workflows.iter().find(|w| w.name.eq(name)).ok_or(format!("Unknown workflow '{}'", name))
     }
 
-    fn get_accepted_rating(&self) -> usize {
-        self.gears.iter().filter(|g| self.accepts(g)).map(|g| g.x + g.m + g.a + g.s).sum()
+    fn get_accepted_rating(&self) -> Result<usize, String> {
+        // Compile once, and run all gears through that
+        let tree = DecisionTree::compile(&self.workflows)?;
+        Ok(self.gears.iter().filter(|g| tree.accepts(g)).map(|g| g.x + g.m + g.a + g.s).sum())
     }
 
-    fn get_accepted_combinations(&self) -> usize {
+    fn get_accepted_combinations(&self) -> Result<usize, String> {
         // Ehh...
         // This mean we need to find paths from in => 'A' states, and determine which ranges of x, m, a, and s lead there.
         // Starting at 'in', we can follow all rules, keeping track of the xmas ranges (which are initially 1..=4000)
         // Since every condition is either '>' or '<', we should be able to trim the ranges until we arrive at an 'A'
         // Then we "just" need to merge the accepted ranges and multiply the results.
 
-        #[derive(Debug, Clone)]
-        struct Ranges {
-            x: RangeInclusive<usize>,
-            m: RangeInclusive<usize>,
-            a: RangeInclusive<usize>,
-            s: RangeInclusive<usize>
-        }
-
-        let initial = Ranges { x: 1..=4000, m: 1..=4000, a: 1..=4000, s: 1..=4000 };
-        let mut accepted_ranges: Vec<Ranges> = vec![];
+        let initial = XmasRegion::full();
+        let mut accepted_ranges: Vec<XmasRegion> = vec![];
 
-        fn make_unmatching(rule: &Rule, ranges: &Ranges) -> Ranges {
+        fn make_unmatching(rule: &Rule, ranges: &XmasRegion) -> XmasRegion {
             match &rule.condition {
-                Condition::None => Ranges { x: 1..=0, m: 1..=0, a: 1..=0, s: 1..=0 },
+                Condition::None => XmasRegion::empty(),
                 Condition::LT(cat, value) => match cat {
-                    Category::X => Ranges { x: *value..=*ranges.x.end(), ..ranges.clone() },
-                    Category::M => Ranges { m: *value..=*ranges.m.end(), ..ranges.clone() },
-                    Category::A => Ranges { a: *value..=*ranges.a.end(), ..ranges.clone() },
-                    Category::S => Ranges { s: *value..=*ranges.s.end(), ..ranges.clone() },
+                    Category::X => XmasRegion { x: *value..=*ranges.x.end(), ..ranges.clone() },
+                    Category::M => XmasRegion { m: *value..=*ranges.m.end(), ..ranges.clone() },
+                    Category::A => XmasRegion { a: *value..=*ranges.a.end(), ..ranges.clone() },
+                    Category::S => XmasRegion { s: *value..=*ranges.s.end(), ..ranges.clone() },
                 },
                 Condition::GT(cat, value) => match cat {
-                    Category::X => Ranges { x: *ranges.x.start()..=*value, ..ranges.clone() },
-                    Category::M => Ranges { m: *ranges.m.start()..=*value, ..ranges.clone() },
-                    Category::A => Ranges { a: *ranges.a.start()..=*value, ..ranges.clone() },
-                    Category::S => Ranges { s: *ranges.s.start()..=*value, ..ranges.clone() },
+                    Category::X => XmasRegion { x: *ranges.x.start()..=*value, ..ranges.clone() },
+                    Category::M => XmasRegion { m: *ranges.m.start()..=*value, ..ranges.clone() },
+                    Category::A => XmasRegion { a: *ranges.a.start()..=*value, ..ranges.clone() },
+                    Category::S => XmasRegion { s: *ranges.s.start()..=*value, ..ranges.clone() },
                 }
             }
         }
 
-        fn follow_rule(system: &WorkflowSystem, rule: &Rule, ranges: &Ranges, accepted: &mut Vec<Ranges>) {
-            let ranges = match &rule.condition {
+        fn make_matching(rule: &Rule, ranges: &XmasRegion) -> XmasRegion {
+            match &rule.condition {
                 Condition::None => ranges.clone(),
+                // Nothing is below 0 (or above the largest value)
+                Condition::LT(_, 0) | Condition::GT(_, usize::MAX) => XmasRegion::empty(),
                 Condition::GT(cat, value) => match cat {
-                    Category::X => Ranges { x: *value+1..=*ranges.x.end(), ..ranges.clone() },
-                    Category::M => Ranges { m: *value+1..=*ranges.m.end(), ..ranges.clone() },
-                    Category::A => Ranges { a: *value+1..=*ranges.a.end(), ..ranges.clone() },
-                    Category::S => Ranges { s: *value+1..=*ranges.s.end(), ..ranges.clone() },
+                    Category::X => XmasRegion { x: *value+1..=*ranges.x.end(), ..ranges.clone() },
+                    Category::M => XmasRegion { m: *value+1..=*ranges.m.end(), ..ranges.clone() },
+                    Category::A => XmasRegion { a: *value+1..=*ranges.a.end(), ..ranges.clone() },
+                    Category::S => XmasRegion { s: *value+1..=*ranges.s.end(), ..ranges.clone() },
                 },
                 Condition::LT(cat, value) => match cat {
-                    Category::X => Ranges { x: *ranges.x.start()..=*value-1, ..ranges.clone() },
-                    Category::M => Ranges { m: *ranges.m.start()..=*value-1, ..ranges.clone() },
-                    Category::A => Ranges { a: *ranges.a.start()..=*value-1, ..ranges.clone() },
-                    Category::S => Ranges { s: *ranges.s.start()..=*value-1, ..ranges.clone() },
+                    Category::X => XmasRegion { x: *ranges.x.start()..=*value-1, ..ranges.clone() },
+                    Category::M => XmasRegion { m: *ranges.m.start()..=*value-1, ..ranges.clone() },
+                    Category::A => XmasRegion { a: *ranges.a.start()..=*value-1, ..ranges.clone() },
+                    Category::S => XmasRegion { s: *ranges.s.start()..=*value-1, ..ranges.clone() },
                 }
-            };
-
-            match &rule.action {
-                Action::Jump(workflow) => {
-                    // Follow this workflow with the new ranges
-                    follow_workflow(system, workflow, &ranges, accepted);
-                }
-                Action::Accept => { accepted.push(ranges); }
-                Action::Reject => {} // do nothing
             }
         }
 
-        fn follow_workflow(system: &WorkflowSystem, workflow: &str, ranges: &Ranges, accepted: &mut Vec<Ranges>) {
-            let workflow = system.get_workflow(workflow);
+        // The workflows still to follow with the ranges that get there. Kept as a list rather than recursing, as a long
+        // chain of jumps could otherwise run out of stack.
+        let mut pending: Vec<(&str, XmasRegion)> = vec![("in", initial)];
+        while let Some((workflow, mut ranges)) = pending.pop() {
             // Note: we cannot just follow every rule; not following the first rule will also mutate the ranges to ensure it _doesn't_ match.
-            let mut ranges = ranges.clone();
-            for rule in workflow.rules {
-                follow_rule(system, &rule, &ranges, accepted);
-                ranges = make_unmatching(&rule, &ranges);
+            for rule in &self.get_workflow(workflow)?.rules {
+                let matching = make_matching(rule, &ranges);
+                match &rule.action {
+                    Action::Jump(next) => pending.push((next, matching)),
+                    Action::Accept => accepted_ranges.push(matching),
+                    Action::Reject => {} // do nothing
+                }
+                ranges = make_unmatching(rule, &ranges);
             }
         }
 
-        follow_workflow(self, "in", &initial, &mut accepted_ranges);
-
         // println!("Accepted ranges:\n{}", accepted_ranges.iter().cloned().map(|r| format!("{:?} => {}", r.clone(), r.x.count() * r.m.count() * r.a.count() * r.s.count())).collect::<Vec<_>>().join("\n"));
 
         // And now... how to make a number of accepted combinations from this result...?!
         // For the test data, this results in separate ranges... I'm a bit worries about the real data, though.
+        // So, make sure: if any of the regions overlap, we need to correct for counting the overlap multiple times.
+        let disjoint = XmasRegion::are_disjoint(&accepted_ranges);
+        let combinations = if disjoint {
+            accepted_ranges.iter().map(|r| r.count()).sum()
+        } else {
+            log::debug!("Accepted regions overlap, using inclusion-exclusion to count them");
+            XmasRegion::count_union(&accepted_ranges)
+        };
+
+        if exact::is_enabled() && disjoint {
+            // A single range can hold up to 4000^4 combinations, redo the products and sum in u128 to be sure nothing overflowed.
+            let exact_combinations: u128 = accepted_ranges.iter()
+                .map(|r| [&r.x, &r.m, &r.a, &r.s].into_iter().map(|c| c.clone().count() as u128).product::<u128>())
+                .sum();
+            exact::check("day 19 accepted combinations", combinations, exact_combinations)?;
+        }
 
-        accepted_ranges.iter().cloned().map(|r| r.x.count() * r.m.count() * r.a.count() * r.s.count()).sum()
+        Ok(combinations)
     }
 }
 
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day19::{Action, Category, Condition, Gear, Rule, Workflow, WorkflowSystem};
+    use crate::days::day19::{Action, Category, Condition, Decision, DecisionTree, Gear, Rule, SimplifyStats, Target, Workflow, WorkflowSystem, XmasRegion};
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("19114"), puzzle2: Some("167409079868000") },
+    ];
 
     #[test]
     fn test_parse_rule() {
         assert_eq!("a<2006:qkq".parse::<Rule>(), Ok(Rule { condition: Condition::LT(Category::A, 2006), action: Action::Jump("qkq".to_string()) }));
         assert_eq!("m>2090:A".parse::<Rule>(), Ok(Rule { condition: Condition::GT(Category::M, 2090), action: Action::Accept }));
+        assert!("a:A".parse::<Rule>().is_err());
+        assert!(":A".parse::<Rule>().is_err());
         assert_eq!("rfg".parse::<Rule>(), Ok(Rule { condition: Condition::None, action: Action::Jump("rfg".to_string()) }));
         assert_eq!("A".parse::<Rule>(), Ok(Rule { condition: Condition::None, action: Action::Accept }));
         assert_eq!("R".parse::<Rule>(), Ok(Rule { condition: Condition::None, action: Action::Reject }));
@@ -256,25 +503,93 @@ mod tests {
     }
 
     #[test]
-    fn test_workflow_system_accepts() {
+    fn test_decision_tree_accepts() {
         let system = WorkflowSystem::parse(TEST_INPUT).unwrap();
-        assert_eq!(system.accepts(&system.gears[0]), true);
-        assert_eq!(system.accepts(&system.gears[1]), false);
-        assert_eq!(system.accepts(&system.gears[2]), true);
-        assert_eq!(system.accepts(&system.gears[3]), false);
-        assert_eq!(system.accepts(&system.gears[4]), true);
+        let tree = DecisionTree::compile(&system.workflows).unwrap();
+        assert_eq!(tree.accepts(&system.gears[0]), true);
+        assert_eq!(tree.accepts(&system.gears[1]), false);
+        assert_eq!(tree.accepts(&system.gears[2]), true);
+        assert_eq!(tree.accepts(&system.gears[3]), false);
+        assert_eq!(tree.accepts(&system.gears[4]), true);
+    }
+
+    #[test]
+    fn test_decision_tree_compile() {
+        let workflows: Vec<Workflow> = ["in{x<10:skip,R}", "skip{next}", "next{m>5:A,A}"].iter().map(|w| w.parse().unwrap()).collect();
+        assert_eq!(DecisionTree::compile(&workflows), Ok(DecisionTree {
+            decisions: vec![
+                Decision { condition: Condition::LT(Category::X, 10), matched: Target::Decision(1), unmatched: Target::Reject },
+                Decision { condition: Condition::GT(Category::M, 5), matched: Target::Accept, unmatched: Target::Accept },
+            ],
+            root: Target::Decision(0),
+        }));
+
+        let compile = |workflows: &[&str]| DecisionTree::compile(&workflows.iter().map(|w| w.parse().unwrap()).collect::<Vec<Workflow>>());
+        assert_eq!(compile(&["in{x<10:nope,R}"]), Err("Unknown workflow 'nope'".to_string()));
+        assert_eq!(compile(&["in{x<10:A}"]), Err("Workflow 'in' should end with (only) a catch-all rule".to_string()));
+        assert_eq!(compile(&["in{x<10:A,b}", "a{b}", "b{a}"]), Err("Workflows loop without any condition from 'b'".to_string()));
+        assert_eq!(compile(&["start{A}"]), Err("Unknown workflow 'in'".to_string()));
     }
 
     #[test]
     fn test_workflow_system_accepted_rating() {
         let system = WorkflowSystem::parse(TEST_INPUT).unwrap();
-        assert_eq!(system.get_accepted_rating(), 19114);
+        assert_eq!(system.get_accepted_rating(), Ok(19114));
     }
 
     #[test]
     fn test_workflow_system_get_accepted_combinations() {
         let system = WorkflowSystem::parse(TEST_INPUT).unwrap();
-        assert_eq!(system.get_accepted_combinations(), 167409079868000);
+        assert_eq!(system.get_accepted_combinations(), Ok(167409079868000));
+    }
+
+    #[test]
+    fn test_workflow_system_bad_workflows() {
+        let gear = "\n\n{x=1,m=1,a=1,s=1}";
+
+        let system = WorkflowSystem::parse(&format!("in{{x<10:nope,R}}{}", gear)).unwrap();
+        assert_eq!(system.get_accepted_combinations(), Err("Unknown workflow 'nope'".to_string()));
+
+        // Nothing is below 0, so nothing gets accepted:
+        let system = WorkflowSystem::parse(&format!("in{{x<0:A,R}}{}", gear)).unwrap();
+        assert_eq!(system.get_accepted_combinations(), Ok(0));
+
+        assert_eq!(WorkflowSystem::parse(&format!("in{{x<5:b,R}}\nb{{x<5:b,R}}{}", gear)).err(), Some("Workflows loop through 'b'".to_string()));
+        assert!(WorkflowSystem::parse(&format!("in{{x<5:b,R}}\nb{{m<5:in,A}}{}", gear)).is_err());
+    }
+
+    #[test]
+    fn test_xmas_region() {
+        let a = XmasRegion { x: 1..=10, ..XmasRegion::full() };
+        let b = XmasRegion { x: 6..=20, m: 1..=2, ..XmasRegion::full() };
+        let c = XmasRegion { x: 11..=20, ..XmasRegion::full() };
+
+        assert_eq!(a.count(), 10 * 4000 * 4000 * 4000);
+        assert_eq!(XmasRegion::empty().count(), 0);
+        assert_eq!(a.intersect(&b), Some(XmasRegion { x: 6..=10, m: 1..=2, ..XmasRegion::full() }));
+        assert_eq!(a.intersect(&c), None);
+
+        assert_eq!(XmasRegion::are_disjoint(&[a.clone(), c.clone()]), true);
+        assert_eq!(XmasRegion::are_disjoint(&[a.clone(), b.clone(), c.clone()]), false);
+
+        assert_eq!(XmasRegion::count_union(&[a.clone(), c.clone()]), a.count() + c.count());
+        // b is fully within a ∪ c
+        assert_eq!(XmasRegion::count_union(&[a.clone(), b.clone(), c.clone()]), a.count() + c.count());
+        assert_eq!(XmasRegion::count_union(&[a.clone(), a.clone(), a.clone()]), a.count());
+    }
+
+    #[test]
+    fn test_workflow_system_simplify() {
+        let mut system = WorkflowSystem::parse(TEST_INPUT).unwrap();
+        let stats = system.simplify();
+
+        // lnx and gd always end up in the same place, after inlining lnx, qs does too.
+        assert_eq!(stats, SimplifyStats { workflows_before: 11, workflows_after: 8, rules_before: 25, rules_after: 19 });
+        assert_eq!(system.get_workflow("qqz").unwrap().rules[0], "s>2770:A".parse::<Rule>().unwrap());
+        assert_eq!(system.workflows.iter().any(|w| w.name == "lnx" || w.name == "gd" || w.name == "qs"), false);
+
+        assert_eq!(system.get_accepted_rating(), Ok(19114));
+        assert_eq!(system.get_accepted_combinations(), Ok(167409079868000));
     }
 
     const TEST_INPUT: &str = "\
@@ -330,9 +645,9 @@ impl FromStr for Condition {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let category: Category = s[0..=0].parse()?;
-        let op = &s[1..=1];
-        let value = parse_usize(&s[2..])?;
+        let category: Category = str_get(s, 0..=0)?.parse()?;
+        let op = str_get(s, 1..=1)?;
+        let value = parse_num(str_get(s, 2..)?)?;
         match op {
             "<" => Ok(Self::LT(category, value)),
             ">" => Ok(Self::GT(category, value)),
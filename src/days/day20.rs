@@ -1,21 +1,39 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
-use crate::days::Day;
-use crate::util::number::lcm;
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::exact;
+use crate::util::cycles::{align_all, Cycle, find_cycle_within};
+use crate::util::graph::DiGraph;
+use crate::util::interner::Interner;
+use crate::util::parser::str_get;
+use crate::util::log;
 
 pub const DAY20: Day = Day {
-    puzzle1,
-    puzzle2,
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let mut system: SignalSystem = input.parse().unwrap();
-    println!("Pulses after 1000 cycles: {}", system.compute_pulses());
-}
+struct Solution;
+
+impl StructuredDay for Solution {
+    type Parsed = SignalSystem;
 
-fn puzzle2(input: &String) {
-    let mut system: SignalSystem = input.parse().unwrap();
-    println!("Presses before low rx output: {}", system.button_presses_before_low_output());
+    fn parse(input: &str) -> AocResult<SignalSystem> {
+        input.parse::<SignalSystem>().map_err(Into::into)
+    }
+
+    fn part1(system: &SignalSystem) -> AocResult<String> {
+        let mut system = system.clone();
+        Ok(format!("Pulses after 1000 cycles: {}", system.compute_pulses()))
+    }
+
+    fn part2(system: &SignalSystem) -> AocResult<String> {
+        let mut system = system.clone();
+        Ok(format!("Presses before low rx output: {}", system.button_presses_before_low_output()?))
+    }
 }
 
 // We have a button (our input) which always sends a low signal (x1000 for puzzle 1) to the broadcaster
@@ -31,8 +49,22 @@ fn puzzle2(input: &String) {
 // We need to build something to keep the proper state of the machine, so we can find a loop and count the number of
 // low and high signals processed. We know we've looped once the whole system is back in an earlier seen state.
 
+// Modules are referred to by their interned name, which is also their index in the system's modules. The button and
+// broadcaster are always interned first, so we know their ids up front.
+const BUTTON: u32 = 0;
+const BROADCASTER: u32 = 1;
+
+fn module_names() -> Interner {
+    let mut names = Interner::new();
+    names.intern("button");
+    names.intern("broadcaster");
+    names
+}
+
 #[derive(Eq, PartialEq, Debug, Clone)]
 struct SignalSystem {
+    names: Interner,
+    // Indexed by (interned) name, names without a module of their own (the button, rx, ...) are an Output module.
     modules: Vec<Module>,
     history: SignalHistory,
     signals: VecDeque<Signal>
@@ -40,107 +72,110 @@ struct SignalSystem {
 
 impl SignalSystem {
     fn press_button(&mut self) {
-        self.queue_signal(Signal { source: "button".to_string(), destination: "broadcaster".to_string(), state: SignalState::Low });
+        self.queue_signal(Signal::button());
         self.process_queue(|_| {});
     }
 
-    fn press_button_with_callback(&mut self, on_signal: impl FnMut(&Signal)) {
-        self.queue_signal(Signal { source: "button".to_string(), destination: "broadcaster".to_string(), state: SignalState::Low });
-        self.process_queue(on_signal);
-    }
-
     fn queue_signal(&mut self, signal: Signal) {
         self.signals.push_back(signal)
     }
 
+    // Starts recording the pulses of every module, from the next signal on.
+    fn enable_stats(&mut self) {
+        self.history.stats = Some(SignalStats::new(self.modules.len()));
+    }
+
+    fn stats(&self) -> Option<&SignalStats> {
+        self.history.stats.as_ref()
+    }
+
     #[cfg(test)]
     fn get_module(&self, module: &str) -> Option<&Module> {
-        self.modules.iter().find(|m| m.get_name() == module)
+        self.modules.get(self.names.get(module)? as usize)
     }
 
-    fn get_module_mut(&mut self, module: &str) -> Option<&mut Module> {
-        self.modules.iter_mut().find(|m| m.get_name() == module)
+    // Formatted like the puzzle does, e.g. "button -low-> broadcaster"
+    fn describe(&self, signal: &Signal) -> String {
+        format!("{} -{}-> {}", self.names.name(signal.source), signal.state, self.names.name(signal.destination))
     }
 
     fn process_queue(&mut self, mut on_signal: impl FnMut(&Signal)) {
         // Main loop, while there is a signal in the queue, we process it (resulting in possibly more signals in the queue)
-        loop {
-            if let Some(signal) = self.signals.pop_front() {
-                // Update history
-                match signal.state {
-                    SignalState::Low => self.history.low += 1,
-                    SignalState::High => self.history.high += 1,
-                }
+        while let Some(signal) = self.process_next_signal() {
+            on_signal(&signal);
+        }
+    }
+
+    // Processes the first signal in the queue (if any), returning it.
+    fn process_next_signal(&mut self) -> Option<Signal> {
+        let signal = self.signals.pop_front()?;
 
-                on_signal(&signal);
+        self.history.record(&signal);
 
-                // Find target module (an unknown module is considered output)
-                if let Some(target) = self.get_module_mut(&signal.destination) {
-                    for signal in target.process(&signal) {
-                        self.queue_signal(signal);
+        self.modules[signal.destination as usize].process(&signal, &mut self.signals);
+
+        Some(signal)
+    }
+
+    fn snapshot(&self) -> StateSnapshot {
+        let mut entries = vec![];
+        for module in &self.modules {
+            match module {
+                Module::Broadcaster(_) | Module::Output(_) => {}
+                Module::FlipFlop(f) => entries.push((self.names.name(f.name).to_string(), if f.state == SignalState::High { "on" } else { "off" }.to_string())),
+                Module::Conjunction(c) => {
+                    for (input, state) in c.inputs.iter().zip(&c.state) {
+                        entries.push((format!("{}.{}", self.names.name(c.name), self.names.name(*input)), state.to_string()));
                     }
                 }
-            } else {
-                return;
             }
         }
+        entries.sort();
+        StateSnapshot(entries)
     }
 
     fn compute_pulses(&mut self) -> usize {
         // System should loop at some point, after which we know an offset + loop size, and can compute pulses after 1000 presses
         // State to find loop: SignalState of FlipFlops, input states for Conjunctions
-        // Info to keep per state: number of signals sent (to compute the total/loop and the remainder)
-
-        let mut seen_states = vec![];
-        self.press_button();
-
-        loop {
-            let current_state = self.get_state();
-            if let Some(offset) = seen_states.iter().position(|s| current_state.eq(s)) {
-                let loop_size = seen_states.len() - offset;
-                println!("Found loop: offset = {}, length = {}", offset, loop_size);
-
-                // Get number of signals before the loop
-                let before_loop = seen_states[..offset].iter().map(|s| s.1).fold(SignalHistory::default(), |acc, c| SignalHistory { low: acc.low + c.low, high: acc.high + c.high });
-                // Compute amount of full loops
-                let full_loops = 1000 / loop_size;
-                // Get number of signals per loop multiplied by the amount of full loops
-                let during_loop = seen_states[offset..].iter().map(|s| s.1).fold(SignalHistory::default(), |acc, c| SignalHistory { low: acc.low + (c.low * full_loops), high: acc.high + (c.high * full_loops) });
-                // Compute remainder of signals
-                let remainder = 1000 % loop_size;
-                let after_loop = seen_states[offset..(offset + remainder)].iter().map(|s| s.1).fold(SignalHistory::default(), |acc, c| SignalHistory { low: acc.low + c.low, high: acc.high + c.high });
-
-                let total = SignalHistory { low: before_loop.low + during_loop.low + after_loop.low, high: before_loop.high + during_loop.high + after_loop.high };
-
-                println!("Signals:\nBefore loop: {:?}\nDuring loop: {:?}\nAfter loop: {:?}\nTotal: {:?}", before_loop, during_loop, after_loop, total);
-
-                return total.low * total.high;
+        // The pulses sent by a press only depend on that state, so we only need to count them up to the end of the first loop.
+        const PRESSES: usize = 1000;
+
+        let press = |system: &SignalSystem| {
+            let mut next = system.clone();
+            next.press_button();
+            next
+        };
+        let key = |system: &SignalSystem| system.modules.iter().flat_map(|m| m.get_state()).copied().collect::<Vec<_>>();
+
+        let cycle = match find_cycle_within(self.clone(), PRESSES, press, key) {
+            Some(cycle) => {
+                log::debug!("Found loop: offset = {}, length = {}", cycle.offset, cycle.period);
+                cycle
             }
+            // Funny, our puzzle doesn't even have a loop... all the effort above... :joy:
+            None => Cycle::new(PRESSES, 1)
+        };
 
-            seen_states.push(current_state);
-
-            if seen_states.len() == 1000 {
-                // Funny, our puzzle doesn't even have a loop... all the effort above... :joy:
-                let total = seen_states.iter().map(|s| s.1).fold(SignalHistory::default(), |acc, c| SignalHistory { low: acc.low + c.low, high: acc.high + c.high });
-                return total.low * total.high;
-            }
-
-            // Push the button~
+        let mut pulses = vec![];
+        for _ in 0..(cycle.offset + cycle.period).min(PRESSES) {
             self.press_button();
+            pulses.push(self.get_and_clear_pulses());
         }
-    }
 
-    fn get_state(&mut self) -> (Vec<(String, Vec<(String, SignalState)>)>, SignalHistory) {
-        (self.modules.iter().map(|m| m.get_state()).collect(), self.get_and_clear_history())
+        let total = (0..PRESSES).map(|press| pulses[cycle.equivalent_step(press)])
+            .fold(PulseCount::default(), |acc, c| PulseCount { low: acc.low + c.low, high: acc.high + c.high });
+
+        total.low * total.high
     }
 
-    fn get_and_clear_history(&mut self) -> SignalHistory {
-        let history = self.history;
-        self.history = SignalHistory::default();
-        history
+    fn get_and_clear_pulses(&mut self) -> PulseCount {
+        let pulses = PulseCount { low: self.history.low, high: self.history.high };
+        self.history.low = 0;
+        self.history.high = 0;
+        pulses
     }
 
-    fn button_presses_before_low_output(&mut self) -> usize {
+    fn button_presses_before_low_output(&mut self) -> Result<usize, String> {
         // Brute force (obviously) doesn't work. Can we reverse engineer what is needed to get a low signal?
         // No idea where to start, honestly.
         // Chances exist we have mini-loops with the flip-flops, which would then mean we could count a LCM of all loops?
@@ -154,49 +189,227 @@ impl SignalSystem {
         // To get a _low_ rx signal, the ss, fz, mf, and fh signals need to be high
         // As such, all loops would need to output a low at the same time (all flip-flops on)
 
-        // There is probably a way to determine this bit programmatically, fwiw. But knowing that, we can figure out
-        // the four loops (how many presses 'till the end conjunction sends a low signal), and then we just need to LCM those numbers.
-        // Note: unless wrong, I'm assuming the whole loop resets once the conjunction triggers.
-
-        let mut presses = 0;
-        let mut ss_loop = None;
-        let mut fz_loop = None;
-        let mut mf_loop = None;
-        let mut fh_loop = None;
-
-        while ss_loop.and(fz_loop).and(mf_loop).and(fh_loop).is_none() {
-            presses += 1;
-            self.press_button_with_callback(|s| {
-                match (s.source.as_str(), s.state) {
-                    ("ss", SignalState::High) => {
-                        ss_loop = Some(presses);
-                        println!("Found high-output from ss after {} presses", presses);
-                    },
-                    ("fz", SignalState::High) => {
-                        fz_loop = Some(presses);
-                        println!("Found high-output from fz after {} presses", presses);
-                    },
-                    ("mf", SignalState::High) => {
-                        mf_loop = Some(presses);
-                        println!("Found high-output from mf after {} presses", presses);
-                    },
-                    ("fh", SignalState::High) => {
-                        fh_loop = Some(presses);
-                        println!("Found high-output from fh after {} presses", presses);
-                    },
-                    _ => {} // not interesting
-                }
-            });
+        // Knowing that, we can figure out the four loops (how many presses 'till the end conjunction sends a low signal),
+        // and then we just need to align those. Which conjunctions feed the rx conjunction is read from the module graph.
+        // Assumption: the whole loop resets once the conjunction triggers, so a loop first found after N presses
+        //  triggers again every N presses. (That makes the alignment a plain LCM.)
+        let counters = self.find_counters("rx")?;
+        self.enable_stats();
+
+        let loops = loop {
+            // Stats are enabled above
+            let stats = self.stats().unwrap();
+            if let Some(loops) = counters.iter().map(|c| stats.first_high(*c)).collect::<Option<Vec<_>>>() {
+                break loops;
+            }
+            self.press_button();
+        };
+        for line in self.describe_stats() {
+            log::debug!("{}", line);
         }
 
-        lcm(lcm(ss_loop.unwrap(), fz_loop.unwrap()), lcm(mf_loop.unwrap(), fh_loop.unwrap()))
+        let cycles: Vec<_> = loops.iter().map(|l| Cycle::from_start(*l)).collect();
+        let presses = align_all(&cycles)?.ok_or("Counter loops never align")?.offset;
+
+        if exact::is_enabled() {
+            exact::check("day 20 button press lcm", presses, exact::lcm(&loops))?;
+        }
+
+        Ok(presses)
+    }
+
+    // The pulses sent by every conjunction since the stats were enabled, e.g. "&ql: 12 low, 4 high, first high in press 3".
+    fn describe_stats(&self) -> Vec<String> {
+        let Some(stats) = self.stats() else { return vec![] };
+
+        self.modules.iter().filter(|m| matches!(m, Module::Conjunction(_))).map(|m| {
+            let sent = stats.sent(m.get_name());
+            let first_high = match stats.first_high(m.get_name()) {
+                Some(press) => format!("first high in press {}", press),
+                None => "no high yet".to_string(),
+            };
+            format!("&{}: {} low, {} high, {} (of {})", self.names.name(m.get_name()), sent.low, sent.high, first_high, stats.presses())
+        }).collect()
+    }
+
+    // The modules as a graph, with an edge for every output. Nodes have the same index as the modules.
+    fn graph(&self) -> DiGraph<u32, ()> {
+        let mut graph = DiGraph::new();
+        for module in &self.modules {
+            graph.add_node(module.get_name());
+        }
+        for (index, module) in self.modules.iter().enumerate() {
+            for output in module.get_outputs() {
+                graph.add_edge(index, output.module as usize, ());
+            }
+        }
+        graph
+    }
+
+    // The output is driven by a single conjunction, so it gets a low signal once all of that conjunction's inputs sent
+    // a high signal during the same press. Each of those inputs should be the end of a counter: a loop of flip-flops
+    // (a cycle in the graph) that fires once every N presses.
+    fn find_counters(&self, output: &str) -> Result<Vec<u32>, String> {
+        let graph = self.graph();
+        let name = |node: usize| self.names.name(*graph.node(node));
+        let output = self.names.get(output).ok_or(format!("No module outputs to {}", output))? as usize;
+
+        let conjunction = match graph.predecessors(output).collect::<Vec<_>>()[..] {
+            [conjunction] => conjunction,
+            _ => return Err(format!("Expected a single module sending to {}", name(output)))
+        };
+        if !matches!(self.modules[conjunction], Module::Conjunction(_)) {
+            return Err(format!("Expected {} to be a conjunction", name(conjunction)));
+        }
+
+        let loops = graph.cyclic_components();
+        let counters: Vec<_> = graph.predecessors(conjunction).collect();
+        if let Some(counter) = counters.iter().find(|c| !loops.iter().any(|l| graph.can_reach(l[0], **c))) {
+            return Err(format!("{} is not fed by a loop of modules", name(*counter)));
+        }
+
+        Ok(counters.into_iter().map(|c| *graph.node(c)).collect())
     }
 }
 
-#[derive(Eq, PartialEq, Default, Debug, Copy, Clone)]
+// Steps through the system one button press or signal at a time, for the runner's debug mode (`cargo run debug 20`).
+pub struct Debugger {
+    system: SignalSystem,
+    presses: usize,
+}
+
+impl Debugger {
+    pub fn new(input: &str) -> Result<Debugger, String> {
+        Ok(Debugger { system: input.parse()?, presses: 0 })
+    }
+
+    pub fn presses(&self) -> usize {
+        self.presses
+    }
+
+    // Presses the button and processes all resulting signals, returning them. When in the middle of a button press
+    // (after stepping), this finishes that press instead.
+    pub fn press(&mut self) -> Vec<String> {
+        if self.system.signals.is_empty() {
+            self.queue_button_press();
+        }
+        let mut signals = vec![];
+        self.system.process_queue(|s| signals.push(*s));
+        signals.iter().map(|s| self.system.describe(s)).collect()
+    }
+
+    // Processes a single signal, pressing the button first if nothing is going on.
+    pub fn step(&mut self) -> String {
+        if self.system.signals.is_empty() {
+            self.queue_button_press();
+        }
+        // There is at least the button signal
+        let signal = self.system.process_next_signal().unwrap();
+        self.system.describe(&signal)
+    }
+
+    pub fn queue(&self) -> Vec<String> {
+        self.system.signals.iter().map(|s| self.system.describe(s)).collect()
+    }
+
+    pub fn snapshot(&self) -> StateSnapshot {
+        self.system.snapshot()
+    }
+
+    fn queue_button_press(&mut self) {
+        self.presses += 1;
+        self.system.queue_signal(Signal::button());
+    }
+}
+
+// The state of all flip-flops (on/off) and the remembered inputs of all conjunctions (low/high), sorted by name.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct StateSnapshot(Vec<(String, String)>);
+
+impl StateSnapshot {
+    // Describes every state that is different in the other snapshot.
+    pub fn diff(&self, other: &StateSnapshot) -> Vec<String> {
+        self.0.iter().zip(other.0.iter())
+            .filter(|((_, before), (_, after))| before != after)
+            .map(|((name, before), (_, after))| format!("{}: {} -> {}", name, before, after))
+            .collect()
+    }
+}
+
+impl Display for StateSnapshot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let lines: Vec<_> = self.0.iter().map(|(name, state)| format!("{}: {}", name, state)).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[derive(Eq, PartialEq, Default, Debug, Clone)]
 struct SignalHistory {
     low: usize,
     high: usize,
+    // Only kept once enabled, as it's a bit more work for every signal.
+    stats: Option<SignalStats>,
+}
+
+impl SignalHistory {
+    fn record(&mut self, signal: &Signal) {
+        match signal.state {
+            SignalState::Low => self.low += 1,
+            SignalState::High => self.high += 1,
+        }
+        if let Some(stats) = &mut self.stats {
+            stats.record(signal);
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Default, Debug, Copy, Clone)]
+struct PulseCount {
+    low: usize,
+    high: usize,
+}
+
+// What every module (by id) did since the stats were enabled.
+#[derive(Eq, PartialEq, Debug, Clone)]
+struct SignalStats {
+    presses: usize,
+    // The pulses sent by every module.
+    sent: Vec<PulseCount>,
+    // The press during which every module sent its first high pulse. For the conjunctions ending a counter, that is
+    // the length of their loop.
+    first_high: Vec<Option<usize>>,
+}
+
+impl SignalStats {
+    fn new(modules: usize) -> Self {
+        Self { presses: 0, sent: vec![PulseCount::default(); modules], first_high: vec![None; modules] }
+    }
+
+    fn record(&mut self, signal: &Signal) {
+        if signal.source == BUTTON {
+            self.presses += 1;
+        }
+        let source = signal.source as usize;
+        match signal.state {
+            SignalState::Low => self.sent[source].low += 1,
+            SignalState::High => {
+                self.sent[source].high += 1;
+                self.first_high[source].get_or_insert(self.presses);
+            }
+        }
+    }
+
+    fn presses(&self) -> usize {
+        self.presses
+    }
+
+    fn sent(&self, module: u32) -> PulseCount {
+        self.sent[module as usize]
+    }
+
+    fn first_high(&self, module: u32) -> Option<usize> {
+        self.first_high[module as usize]
+    }
 }
 
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
@@ -205,170 +418,240 @@ enum SignalState {
     High,
 }
 
-#[derive(Eq, PartialEq, Debug, Clone)]
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
 struct Signal {
-    source: String,
-    destination: String,
+    source: u32,
+    destination: u32,
+    // Which of the destination's inputs the signal arrives at (only used by conjunctions).
+    input: usize,
     state: SignalState,
 }
 
+impl Signal {
+    fn button() -> Self {
+        Signal { source: BUTTON, destination: BROADCASTER, input: 0, state: SignalState::Low }
+    }
+}
+
+// Where a module sends its signals to: the destination module, and which of its inputs that is.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+struct Output {
+    module: u32,
+    input: usize,
+}
+
+impl Output {
+    fn send(&self, source: u32, state: SignalState, queue: &mut VecDeque<Signal>) {
+        queue.push_back(Signal { source, destination: self.module, input: self.input, state })
+    }
+}
+
+impl Display for SignalState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignalState::Low => write!(f, "low"),
+            SignalState::High => write!(f, "high"),
+        }
+    }
+}
+
 #[derive(Eq, PartialEq, Debug, Clone)]
 enum Module {
     Broadcaster(Broadcaster),
     FlipFlop(FlipFlop),
     Conjunction(Conjunction),
+    // Anything else that is sent signals (or sends them, like the button), which just consumes them.
+    Output(u32),
 }
 
 impl Module {
-    fn get_name(&self) -> &str {
+    fn get_name(&self) -> u32 {
+        match self {
+            Module::Broadcaster(_) => BROADCASTER,
+            Module::FlipFlop(module) => module.name,
+            Module::Conjunction(module) => module.name,
+            Module::Output(name) => *name,
+        }
+    }
+
+    fn get_outputs(&self) -> &[Output] {
         match self {
-            Module::Broadcaster(_) => "broadcaster",
-            Module::FlipFlop(module) => &module.name,
-            Module::Conjunction(module) => &module.name,
+            Module::Broadcaster(module) => &module.outputs,
+            Module::FlipFlop(module) => &module.outputs,
+            Module::Conjunction(module) => &module.outputs,
+            Module::Output(_) => &[],
         }
     }
 
-    fn get_outputs(&self) -> Vec<String> {
+    fn get_outputs_mut(&mut self) -> &mut [Output] {
         match self {
-            Module::Broadcaster(module) => module.outputs.clone(),
-            Module::FlipFlop(module) => module.outputs.clone(),
-            Module::Conjunction(module) => module.outputs.clone(),
+            Module::Broadcaster(module) => &mut module.outputs,
+            Module::FlipFlop(module) => &mut module.outputs,
+            Module::Conjunction(module) => &mut module.outputs,
+            Module::Output(_) => &mut [],
         }
     }
 
-    fn process(&mut self, signal: &Signal) -> Vec<Signal> {
+    // Processes the signal, adding the signals it results in to the queue.
+    fn process(&mut self, signal: &Signal, queue: &mut VecDeque<Signal>) {
         match self {
-            Module::Broadcaster(module) => module.process(signal),
-            Module::FlipFlop(module) => module.process(signal),
-            Module::Conjunction(module) => module.process(signal),
+            Module::Broadcaster(module) => module.process(signal, queue),
+            Module::FlipFlop(module) => module.process(signal, queue),
+            Module::Conjunction(module) => module.process(signal, queue),
+            Module::Output(_) => {}
         }
     }
 
-    fn register_input(&mut self, input: &str) {
+    // Registers the input, returning which of the module's inputs it is.
+    fn register_input(&mut self, input: u32) -> usize {
         // Only conjunction is interested in knowing its inputs
-        if let Module::Conjunction(module) = self {
-            module.register_input(input)
+        match self {
+            Module::Conjunction(module) => module.register_input(input),
+            _ => 0
         }
     }
 
-    fn get_state(&self) -> (String, Vec<(String, SignalState)>) {
+    fn get_state(&self) -> &[SignalState] {
         match self {
-            Module::Broadcaster(_) => ("broadcaster".to_string(), vec![]),
-            Module::FlipFlop(f) => f.get_state(),
-            Module::Conjunction(c) => c.get_state(),
+            Module::Broadcaster(_) | Module::Output(_) => &[],
+            Module::FlipFlop(f) => std::slice::from_ref(&f.state),
+            Module::Conjunction(c) => &c.state,
         }
     }
 }
 
+fn to_outputs(modules: Vec<u32>) -> Vec<Output> {
+    modules.into_iter().map(|module| Output { module, input: 0 }).collect()
+}
+
 #[derive(Eq, PartialEq, Debug, Clone)]
 struct Broadcaster {
-    outputs: Vec<String>,
+    outputs: Vec<Output>,
 }
 
 impl Broadcaster {
-    fn new(outputs: Vec<String>) -> Self {
-        Self { outputs }
+    fn new(outputs: Vec<u32>) -> Self {
+        Self { outputs: to_outputs(outputs) }
     }
 
-    fn process(&self, signal: &Signal) -> Vec<Signal> {
-        self.outputs.iter().map(|o| Signal { source: "broadcaster".to_string(), destination: o.clone(), state: signal.state }).collect()
+    fn process(&self, signal: &Signal, queue: &mut VecDeque<Signal>) {
+        self.outputs.iter().for_each(|o| o.send(BROADCASTER, signal.state, queue))
     }
 }
 
 #[derive(Eq, PartialEq, Debug, Clone)]
 struct FlipFlop {
-    name: String,
+    name: u32,
     state: SignalState,
-    outputs: Vec<String>,
+    outputs: Vec<Output>,
 }
 
 impl FlipFlop {
-    fn new(name: String, outputs: Vec<String>) -> Self {
-        Self { name, outputs, state: SignalState::Low } // off by default
+    fn new(name: u32, outputs: Vec<u32>) -> Self {
+        Self { name, outputs: to_outputs(outputs), state: SignalState::Low } // off by default
     }
 
-    fn process(&mut self, signal: &Signal) -> Vec<Signal> {
+    fn process(&mut self, signal: &Signal, queue: &mut VecDeque<Signal>) {
         match signal.state {
-            SignalState::High => vec![], // High signals are ignored
+            SignalState::High => {}, // High signals are ignored
             SignalState::Low => {
                 self.state = match self.state {
                     SignalState::Low => SignalState::High,
                     SignalState::High => SignalState::Low,
                 };
-                self.outputs.iter().map(|o| Signal { source: self.name.clone(), destination: o.clone(), state: self.state }).collect()
+                self.outputs.iter().for_each(|o| o.send(self.name, self.state, queue))
             }
         }
     }
-
-    fn get_state(&self) -> (String, Vec<(String, SignalState)>) {
-        (self.name.clone(), vec![("state".to_string(), self.state)])
-    }
 }
 
 #[derive(Eq, PartialEq, Debug, Clone)]
 struct Conjunction {
-    name: String,
-    state: HashMap<String, SignalState>,
-    outputs: Vec<String>,
+    name: u32,
+    // The last signal of every input, by the index the input was registered with.
+    inputs: Vec<u32>,
+    state: Vec<SignalState>,
+    // How many of the inputs last sent a high signal, so we don't need to check all of them on every signal.
+    high_inputs: usize,
+    outputs: Vec<Output>,
 }
 
 impl Conjunction {
-    fn new(name: String, outputs: Vec<String>) -> Self {
-        Self { name, outputs, state: HashMap::new() }
+    fn new(name: u32, outputs: Vec<u32>) -> Self {
+        Self { name, inputs: vec![], state: vec![], high_inputs: 0, outputs: to_outputs(outputs) }
     }
 
-    fn process(&mut self, signal: &Signal) -> Vec<Signal> {
-        self.state.insert(signal.source.clone(), signal.state);
-        let state = if self.state.values().all(|v| *v == SignalState::High) { SignalState::Low } else { SignalState::High };
-        self.outputs.iter().map(|o| Signal { source: self.name.clone(), destination: o.clone(), state }).collect()
-    }
-
-    fn register_input(&mut self, input: &str) {
-        self.state.insert(input.to_string(), SignalState::Low);
+    fn process(&mut self, signal: &Signal, queue: &mut VecDeque<Signal>) {
+        let previous = std::mem::replace(&mut self.state[signal.input], signal.state);
+        match (previous, signal.state) {
+            (SignalState::Low, SignalState::High) => self.high_inputs += 1,
+            (SignalState::High, SignalState::Low) => self.high_inputs -= 1,
+            _ => {}
+        }
+        let state = if self.high_inputs == self.inputs.len() { SignalState::Low } else { SignalState::High };
+        self.outputs.iter().for_each(|o| o.send(self.name, state, queue))
     }
 
-    fn get_state(&self) -> (String, Vec<(String, SignalState)>) {
-        (self.name.clone(), self.state.iter().map(|(i, s)| (i.clone(), s.clone())).collect())
+    fn register_input(&mut self, input: u32) -> usize {
+        self.inputs.push(input);
+        self.state.push(SignalState::Low);
+        self.inputs.len() - 1
     }
 }
 
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-    use crate::days::day20::{Broadcaster, Conjunction, FlipFlop, Module, SignalState, SignalSystem};
-    use crate::util::collection::VecToString;
+    use crate::days::day20::{module_names, Broadcaster, Conjunction, Debugger, FlipFlop, Module, Output, PulseCount, SignalState, SignalSystem, BUTTON};
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_SYSTEM_1, puzzle1: Some("32000000"), puzzle2: None },
+        Example { input: TEST_SYSTEM_2, puzzle1: Some("11687500"), puzzle2: None },
+    ];
 
     #[test]
     fn test_parse_module() {
-        assert_eq!("broadcaster -> a, b, c".parse(), Ok(Module::Broadcaster(Broadcaster::new(vec!["a", "b", "c"].to_string()))));
-        assert_eq!("%a -> inv, con".parse(), Ok(Module::FlipFlop(FlipFlop::new("a".to_string(), vec!["inv", "con"].to_string()))));
-        assert_eq!("&con -> output".parse(), Ok(Module::Conjunction(Conjunction::new("con".to_string(), vec!["output"].to_string()))));
+        // 0 and 1 are the button and broadcaster, the rest is numbered in order of appearance
+        let mut names = module_names();
+        assert_eq!(Module::parse("broadcaster -> a, b, c", &mut names), Ok(Module::Broadcaster(Broadcaster::new(vec![2, 3, 4]))));
+        assert_eq!(Module::parse("%a -> inv, con", &mut names), Ok(Module::FlipFlop(FlipFlop::new(2, vec![5, 6]))));
+        assert_eq!(Module::parse("&con -> output", &mut names), Ok(Module::Conjunction(Conjunction::new(6, vec![7]))));
+        assert!(Module::parse(" -> output", &mut names).is_err());
+        assert_eq!(names.name(7), "output");
     }
 
     #[test]
     fn test_parse_system() {
-        assert_eq!(TEST_SYSTEM_1.parse(), Ok(SignalSystem {
-            modules: vec![
-                Module::Broadcaster(Broadcaster { outputs: vec!["a", "b", "c"].to_string() }),
-                Module::FlipFlop(FlipFlop::new("a".to_string(), vec!["b"].to_string())),
-                Module::FlipFlop(FlipFlop::new("b".to_string(), vec!["c"].to_string())),
-                Module::FlipFlop(FlipFlop::new("c".to_string(), vec!["inv"].to_string())),
-                Module::Conjunction(Conjunction { name: "inv".to_string(), state: HashMap::from([("c".to_string(), SignalState::Low)]), outputs: vec!["a"].to_string() }),
-            ],
-            ..SignalSystem::default()
-        }));
-
-        assert_eq!(TEST_SYSTEM_2.parse(), Ok(SignalSystem {
-            modules: vec![
-                Module::Broadcaster(Broadcaster { outputs: vec!["a"].to_string() }),
-                Module::FlipFlop(FlipFlop::new("a".to_string(), vec!["inv", "con"].to_string())),
-                Module::Conjunction(Conjunction { name: "inv".to_string(), state: HashMap::from([("a".to_string(), SignalState::Low)]), outputs: vec!["b"].to_string() }),
-                Module::FlipFlop(FlipFlop::new("b".to_string(), vec!["con"].to_string())),
-                Module::Conjunction(Conjunction { name: "con".to_string(), state: HashMap::from([("a".to_string(), SignalState::Low), ("b".to_string(), SignalState::Low)]), outputs: vec!["output"].to_string() }),
-            ],
-            ..SignalSystem::default()
-        }));
+        let system: SignalSystem = TEST_SYSTEM_1.parse().unwrap();
+        let id = |name: &str| system.names.get(name).unwrap();
+        let output = |name: &str, input: usize| Output { module: id(name), input };
+        assert_eq!(system.modules, vec![
+            Module::Output(id("button")),
+            Module::Broadcaster(Broadcaster::new(vec![id("a"), id("b"), id("c")])),
+            Module::FlipFlop(FlipFlop::new(id("a"), vec![id("b")])),
+            Module::FlipFlop(FlipFlop::new(id("b"), vec![id("c")])),
+            Module::FlipFlop(FlipFlop::new(id("c"), vec![id("inv")])),
+            Module::Conjunction(Conjunction { name: id("inv"), inputs: vec![id("c")], state: vec![SignalState::Low], high_inputs: 0, outputs: vec![output("a", 0)] }),
+        ]);
+
+        // Every module is at the index of its name, and knows which input of a conjunction it sends to.
+        let system: SignalSystem = TEST_SYSTEM_2.parse().unwrap();
+        let id = |name: &str| system.names.get(name).unwrap();
+        let output = |name: &str, input: usize| Output { module: id(name), input };
+        assert_eq!(system.modules.len(), system.names.len());
+        assert_eq!(system.get_module("a"), Some(&Module::FlipFlop(FlipFlop { name: id("a"), state: SignalState::Low, outputs: vec![output("inv", 0), output("con", 0)] })));
+        assert_eq!(system.get_module("b"), Some(&Module::FlipFlop(FlipFlop { name: id("b"), state: SignalState::Low, outputs: vec![output("con", 1)] })));
+        assert_eq!(system.get_module("con"), Some(&Module::Conjunction(Conjunction {
+            name: id("con"),
+            inputs: vec![id("a"), id("b")],
+            state: vec![SignalState::Low, SignalState::Low],
+            high_inputs: 0,
+            outputs: vec![output("output", 0)],
+        })));
+        assert_eq!(system.get_module("output"), Some(&Module::Output(id("output"))));
+
+        assert_eq!("%a -> b\n%a -> c".parse::<SignalSystem>(), Err("Module 'a' is defined more than once".to_string()));
     }
 
     #[test]
@@ -421,6 +704,100 @@ mod tests {
         assert_eq!(flop.state, SignalState::Low);
     }
 
+    #[test]
+    fn test_stats() {
+        let mut system: SignalSystem = TEST_SYSTEM_2.parse().unwrap();
+        assert_eq!(system.stats(), None);
+
+        system.enable_stats();
+        for _ in 0..4 {
+            system.press_button();
+        }
+
+        let id = |name: &str| system.names.get(name).unwrap();
+        let stats = system.stats().unwrap();
+        assert_eq!(stats.presses(), 4);
+        // a flips every press, sending to both inv and con
+        assert_eq!(stats.sent(id("a")), PulseCount { low: 4, high: 4 });
+        assert_eq!(stats.sent(BUTTON), PulseCount { low: 4, high: 0 });
+        assert_eq!(stats.first_high(id("con")), Some(1));
+        // inv only sends a high once a turns off again
+        assert_eq!(stats.first_high(id("inv")), Some(2));
+        assert_eq!(stats.first_high(id("output")), None);
+
+        // Every pulse is sent by some module
+        let sent: Vec<_> = system.modules.iter().map(|m| stats.sent(m.get_name())).collect();
+        assert_eq!(sent.iter().map(|p| p.low).sum::<usize>(), system.history.low);
+        assert_eq!(sent.iter().map(|p| p.high).sum::<usize>(), system.history.high);
+
+        assert_eq!(system.describe_stats(), vec![
+            "&inv: 2 low, 2 high, first high in press 2 (of 4)",
+            "&con: 2 low, 4 high, first high in press 1 (of 4)",
+        ]);
+    }
+
+    #[test]
+    fn test_debugger() {
+        let mut debugger = Debugger::new(TEST_SYSTEM_2).unwrap();
+        let before = debugger.snapshot();
+
+        assert_eq!(debugger.step(), "button -low-> broadcaster");
+        assert_eq!(debugger.queue(), vec!["broadcaster -low-> a"]);
+        assert_eq!(debugger.presses(), 1);
+
+        assert_eq!(debugger.press(), vec![
+            "broadcaster -low-> a",
+            "a -high-> inv",
+            "a -high-> con",
+            "inv -low-> b",
+            "con -high-> output",
+            "b -high-> con",
+            "con -low-> output",
+        ]);
+        assert_eq!(debugger.presses(), 1);
+        assert!(debugger.queue().is_empty());
+        assert_eq!(before.diff(&debugger.snapshot()), vec!["a: off -> on", "b: off -> on", "con.a: low -> high", "con.b: low -> high", "inv.a: low -> high"]);
+
+        let before = debugger.snapshot();
+        assert_eq!(debugger.press(), vec![
+            "button -low-> broadcaster",
+            "broadcaster -low-> a",
+            "a -low-> inv",
+            "a -low-> con",
+            "inv -high-> b",
+            "con -high-> output",
+        ]);
+        assert_eq!(debugger.presses(), 2);
+        assert_eq!(before.diff(&debugger.snapshot()), vec!["a: on -> off", "con.a: high -> low", "inv.a: high -> low"]);
+        assert_eq!(debugger.snapshot().to_string(), "a: off\nb: on\ncon.a: low\ncon.b: high\ninv.a: low");
+    }
+
+    #[test]
+    fn test_find_counters() {
+        let system: SignalSystem = "\
+            broadcaster -> a, c\n\
+            %a -> b\n\
+            %b -> a, x\n\
+            &x -> out\n\
+            %c -> d\n\
+            %d -> c, y\n\
+            &y -> out\n\
+            &out -> rx\
+        ".parse().unwrap();
+
+        let graph = system.graph();
+        // Including the button and rx
+        assert_eq!(graph.len(), 10);
+        assert_eq!(graph.cyclic_components().len(), 2);
+
+        assert_eq!(system.find_counters("rx"), Ok(vec![system.names.get("x").unwrap(), system.names.get("y").unwrap()]));
+        assert!(system.find_counters("nope").is_err());
+        // out is fed by x and y, not by a single conjunction
+        assert!(system.find_counters("out").is_err());
+        // Without loops, there are no counters
+        assert!(TEST_SYSTEM_2.parse::<SignalSystem>().unwrap().find_counters("output").is_err());
+    }
+
     #[test]
     fn test_compute_pulses() {
         let mut system: SignalSystem = TEST_SYSTEM_1.parse().unwrap();
@@ -451,49 +828,66 @@ impl FromStr for SignalSystem {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut modules: Vec<Module> = vec![];
+        let mut names = module_names();
+        let mut parsed: Vec<Module> = vec![];
 
         // We need to parse each line into an input (+ type) and outputs.
         for line in s.lines() {
-            modules.push(line.parse()?);
+            parsed.push(Module::parse(line, &mut names)?);
         }
 
-        // At the end, we'll loop over all modules to register inputs.
-        for module in modules.clone() { // Note: need to clone to allow mutating the source
-            for output in module.get_outputs() {
-                if let Some(target) = modules.iter_mut().find(|m| m.get_name() == output) {
-                    target.register_input(module.get_name());
-                }
+        // Put every module at the index of its name, anything that has no module of its own just takes the signals.
+        let mut modules: Vec<_> = names.iter().map(|(id, _)| Module::Output(id)).collect();
+        for module in parsed {
+            let index = module.get_name() as usize;
+            if !matches!(modules[index], Module::Output(_)) {
+                return Err(format!("Module '{}' is defined more than once", names.name(module.get_name())));
             }
+            modules[index] = module;
         }
 
-        Ok(Self { modules, ..Self::default() })
+        // At the end, we'll loop over all modules to register inputs, and tell the outputs which input they are.
+        for index in 0..modules.len() {
+            let name = modules[index].get_name();
+            for output in 0..modules[index].get_outputs().len() {
+                let target = modules[index].get_outputs()[output].module as usize;
+                let input = modules[target].register_input(name);
+                modules[index].get_outputs_mut()[output].input = input;
+            }
+        }
+
+        Ok(Self { names, modules, ..Self::default() })
     }
 }
 
-impl FromStr for Module {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+impl Module {
+    // Parses a module, interning its name and outputs.
+    fn parse(s: &str, names: &mut Interner) -> Result<Self, String> {
         let [label_str, outputs_str] = match s.split("->").collect::<Vec<_>>()[..] {
             [label, outputs] => Ok([label.trim(), outputs.trim()]),
             _ => Err(format!("Invalid module line '{}'", s))
         }?;
 
-        let outputs = outputs_str.split(',').map(|p| p.trim().to_string()).collect::<Vec<_>>();
-
-        match &label_str[0..1] {
-            "%" => Ok(Module::FlipFlop(FlipFlop::new(label_str[1..].to_string(), outputs))),
-            "&" => Ok(Module::Conjunction(Conjunction::new(label_str[1..].to_string(), outputs))),
-            _ if label_str == "broadcaster" => Ok(Module::Broadcaster(Broadcaster::new(outputs))),
-            _ => Err(format!("Invalid module: '{}'", label_str))
-        }
+        // Intern the label before the outputs, so names are numbered in the order they are defined.
+        let kind = str_get(label_str, 0..1)?;
+        let name = match kind {
+            "%" | "&" => names.intern(str_get(label_str, 1..)?),
+            _ if label_str == "broadcaster" => BROADCASTER,
+            _ => return Err(format!("Invalid module: '{}'", label_str))
+        };
+
+        let outputs = outputs_str.split(',').map(|p| names.intern(p.trim())).collect::<Vec<_>>();
+        Ok(match kind {
+            "%" => Module::FlipFlop(FlipFlop::new(name, outputs)),
+            "&" => Module::Conjunction(Conjunction::new(name, outputs)),
+            _ => Module::Broadcaster(Broadcaster::new(outputs)),
+        })
     }
 }
 
 impl Default for SignalSystem {
     fn default() -> Self {
-        Self { modules: vec![], history: SignalHistory::default(), signals: VecDeque::new() }
+        Self { names: module_names(), modules: vec![], history: SignalHistory::default(), signals: VecDeque::new() }
     }
 }
 
@@ -1,20 +1,43 @@
-use std::collections::{HashMap, VecDeque};
 use std::str::FromStr;
-use crate::days::Day;
-use crate::util::geometry::{Directions, Grid, Point};
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::days::day21::ExpansionMethod::{Geometric, Quadratic};
+use crate::util::geometry::{Bounds, Grid, Point, WrappingGrid};
+use crate::util::log;
 
 pub const DAY21: Day = Day {
-    puzzle1,
-    puzzle2
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let garden: Garden = input.parse().unwrap();
-    println!("There are {} tiles reachable with 64 steps", garden.get_tiles_within(64));
+struct Solution;
+
+impl StructuredDay for Solution {
+    type Parsed = Garden;
+
+    fn parse(input: &str) -> AocResult<Garden> {
+        input.parse::<Garden>()
+    }
+
+    fn part1(garden: &Garden) -> AocResult<String> {
+        Ok(format!("There are {} tiles reachable with 64 steps", garden.get_tiles_within(64, Geometric)?))
+    }
+
+    fn part2(garden: &Garden) -> AocResult<String> {
+        let geometric = garden.get_tiles_within(26501365, Geometric)?;
+        let quadratic = garden.get_tiles_within(26501365, Quadratic)?;
+        if geometric != quadratic {
+            log::info!("Warning: geometric ({}) and quadratic ({}) methods disagree!", geometric, quadratic);
+        }
+        Ok(format!("There are {} tiles reachable with 26501365 steps", geometric))
+    }
 }
-fn puzzle2(input: &String) {
-    let garden: Garden = input.parse().unwrap();
-    println!("There are {} tiles reachable with 26501365 steps", garden.get_tiles_within(26501365));
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+enum ExpansionMethod {
+    Geometric, // Decompose the reachable area into full, edge, and corner maps
+    Quadratic  // Sample the infinite garden at three points and fit a quadratic through them
 }
 
 #[derive(Eq, PartialEq, Debug, Copy, Clone, Default)]
@@ -27,47 +50,145 @@ enum Tile {
 
 type Garden = Grid<Tile>;
 
+// Walking the infinite garden tile by tile is fine up to this many steps, beyond that we need the map's shape to
+// extrapolate.
+const MAX_WALKING_STEPS: usize = 1000;
+
+// The offsets (x, y) to the tiles we can step to.
+const SIDES: [(isize, isize); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+
 impl Garden {
     fn get_tiles_from(&self, start: Point, num_steps: usize, odd_tiles: bool, overflow: bool) -> usize {
         // Note: we don't wrap, if num_steps is big enough, will just count all tiles from the start point.
-        let mut queue: VecDeque<(Point, usize)> = VecDeque::from([(start, 0)]);
-        let mut distances: HashMap<Point, usize> = HashMap::new();
+        let wrapping = WrappingGrid::new(self);
+
+        // Breadth-first, level by level, so the first time we reach a tile is the fastest way to get there. Every step
+        // flips the even-ness of (x + y), so the neighbours of the tiles we just reached are either tiles of the previous
+        // level or new ones. That means we only need to keep the last two levels around instead of every tile we've seen.
+        // The levels are kept sorted (and free of duplicates) to look tiles up in them.
+        let key = |p: &Point| (p.y, p.x);
+        let mut previous: Vec<Point> = vec![];
+        let mut frontier: Vec<Point> = vec![start];
+        let mut next: Vec<Point> = vec![];
+        // The number of tiles reached in an even and an odd number of steps.
+        let mut counts = [0, 0];
+
+        for steps in 0..=num_steps {
+            if frontier.is_empty() { break; } // Reached every tile we can
+            counts[steps % 2] += frontier.len();
+            if steps == num_steps { break; } // No more steps to take
+
+            // The tiles above, left of, right of and below a sorted level are sorted as well, merging those gives the
+            // next level in order (skipping the tiles of the previous level as we pass them).
+            next.clear();
+            let mut heads = [0; 4];
+            let mut previous_index = 0;
+            loop {
+                let mut lowest: Option<(Point, usize)> = None;
+                for (side, offset) in SIDES.iter().enumerate() {
+                    let Some(point) = frontier.get(heads[side]).map(|p| *p + *offset) else { continue };
+                    if lowest.is_none_or(|(l, _)| key(&point) < key(&l)) { lowest = Some((point, side)); }
+                }
+                let Some((next_point, side)) = lowest else { break };
+                heads[side] += 1;
+                if next.last() == Some(&next_point) { continue; }
+                while previous.get(previous_index).is_some_and(|p| key(p) < key(&next_point)) { previous_index += 1; }
+                if previous.get(previous_index) == Some(&next_point) { continue; }
+
+                // Part 2 mentions that this garden actually infinitely loops; so if we get a point outside our bounds, we need to wrap it.
+                let next_tile = if overflow { wrapping.get(&next_point) } else { self.get(&next_point) };
+                if next_tile.is_some_and(|tile| tile != Tile::Rock) {
+                    next.push(next_point);
+                }
+            }
 
-        while queue.len() > 0 {
-            let (point, current_steps) = queue.pop_front().unwrap();
+            // Rotate the buffers, so they're reused instead of allocating new ones every level
+            std::mem::swap(&mut previous, &mut frontier);
+            std::mem::swap(&mut frontier, &mut next);
+        }
 
-            if let Some(distance) = distances.get(&point) {
-                if *distance <= current_steps { continue } // Already been here earlier
-            }
+        // We want to count all tiles reached in a number of steps matching the even-ness of the target
+        counts[odd_tiles as usize]
+    }
+
+    fn find_start(&self) -> Result<Point, String> {
+        let starts = self.filter_points(|t| Tile::Start.eq(t));
+        match starts[..] {
+            [start] => Ok(start),
+            [] => Err("There is no start tile in the garden".to_string()),
+            _ => Err(format!("Expected a single start tile in the garden, but found {}", starts.len()))
+        }
+    }
 
-            distances.insert(point, current_steps);
+    // Both expansion methods only work on maps shaped like the real input, this checks the map actually is.
+    fn validate_assumptions(&self) -> Result<(), String> {
+        let start = self.find_start()?;
+        let Bounds { top, left, width, height } = self.bounds;
+        let (bottom, right) = (top + height as isize - 1, left + width as isize - 1);
 
-            if current_steps == num_steps { continue; } // No more steps to take
+        if width != height {
+            return Err(format!("The garden is not square ({}x{})", width, height));
+        }
+        if width % 2 == 0 || start.x != left + width as isize / 2 || start.y != top + height as isize / 2 {
+            return Err(format!("The start {} is not in the middle of the garden", start));
+        }
 
-            // Get surrounding tiles, part 2 mentions that this garden actually infinitely loops; so if we get a point outside our bounds, we need to wrap it.
-            for next_point in point.get_points_around(Directions::NonDiagonal) {
-                let width = self.bounds.width as isize;
-                let height = self.bounds.height as isize;
+        let is_free = |p: &Point| self.get(p).is_some_and(|t| t != Tile::Rock);
+        if let Some(rock) = self.bounds.points().find(|p| (p.y == top || p.y == bottom || p.x == left || p.x == right) && !is_free(p)) {
+            return Err(format!("The border of the garden is not empty, there is a rock at {}", rock));
+        }
+        if let Some(rock) = self.bounds.points().find(|p| (p.x == start.x || p.y == start.y) && !is_free(p)) {
+            return Err(format!("There is no free lane from the start to the edges, there is a rock at {}", rock));
+        }
 
-                // Remap point to be inside map domain
-                let remapped_point = Point {
-                    x: if overflow { ((next_point.x % width) + width) % width } else { next_point.x },
-                    y: if overflow { ((next_point.y % height) + height) % height } else { next_point.y },
-                };
+        Ok(())
+    }
 
-                if let Some(tile) = self.get(&remapped_point) {
-                    if tile != Tile::Rock {
-                        queue.push_back((next_point, current_steps + 1))
-                    }
-                }
+    fn get_tiles_within(&self, num_steps: usize, method: ExpansionMethod) -> Result<usize, String> {
+        let start = self.find_start()?;
+        if let Err(assumption) = self.validate_assumptions() {
+            // On any other map, we can still just walk, as long as the number of steps is reasonable.
+            if num_steps > MAX_WALKING_STEPS {
+                return Err(format!("Cannot extrapolate {} steps: {}", num_steps, assumption));
             }
+            return Ok(self.get_tiles_from(start, num_steps, !num_steps.is_multiple_of(2), true));
+        }
+
+        Ok(match method {
+            Geometric => self.get_tiles_within_geometric(start, num_steps)?,
+            Quadratic => self.get_tiles_within_quadratic(start, num_steps)
+        })
+    }
+
+    fn get_tiles_within_quadratic(&self, start: Point, num_steps: usize) -> usize {
+        // Every time we walk another map length, the reachable area grows by a ring of maps. The number of maps in
+        // that ring grows linearly, so the tile count at (offset + n * map_length) steps is quadratic in n.
+        // Sampling n = 0, 1, 2 gives us enough to fit that quadratic and extrapolate.
+        // Assumptions: start in the middle, and the start row/column being free (the real input lines up 65 + n*131)
+        // (checked by validate_assumptions)
+        let map_length = self.bounds.width;
+        let offset = num_steps % map_length;
+        let n = num_steps / map_length;
+
+        if n < 3 {
+            // Nothing to extrapolate, just walk it
+            return self.get_tiles_from(start, num_steps, !num_steps.is_multiple_of(2), true);
         }
 
-        // We want to determine all distances that match the even-ness of the target
-        distances.values().filter(|l| ((*l % 2) == 0) != odd_tiles).count()
+        let [a, b, c] = [0, 1, 2].map(|i| {
+            let steps = offset + i * map_length;
+            self.get_tiles_from(start, steps, !steps.is_multiple_of(2), true) as isize
+        });
+
+        // Newton's forward differences: f(n) = a + n * (b - a) + n * (n - 1) / 2 * (c - 2b + a)
+        let n = n as isize;
+        let first_difference = b - a;
+        let second_difference = c - 2 * b + a;
+
+        (a + n * first_difference + (n * (n - 1) / 2) * second_difference) as usize
     }
 
-    fn get_tiles_within(&self, num_steps: usize) -> usize {
+    fn get_tiles_within_geometric(&self, start: Point, num_steps: usize) -> Result<usize, String> {
         // Tiles probably differ odd/even, so we need to get a full odd and full even tile count,
         // then (if num_steps > map size) we need to compute the top, left, right, bottom, and corner tile counts
         // (a manhattan distance circle is a square, so all NE corners will be the same, etc)
@@ -90,14 +211,21 @@ impl Garden {
         // 26501365 steps = (202300 * 131) + 65 (exactly goes to the top of a map even...)
         // This (also) means one kind of corner map per side.
 
-        let start = self.entries().iter().find(|(_, t)| Tile::Start.eq(t)).unwrap().0;
         let corner_distance = start.manhattan_distance(&(0, 0).into()) as usize; // Assumptions: square map and start in middle
         let map_length = self.bounds.width;
-        let steps_odd = (num_steps % 2) != 0;
+        let steps_odd = !num_steps.is_multiple_of(2);
 
         if num_steps < corner_distance {
             // Puzzle 1
-            return self.get_tiles_from(start, num_steps, steps_odd, true)
+            return Ok(self.get_tiles_from(start, num_steps, steps_odd, true))
+        }
+
+        // The maps below only line up when the steps end exactly on the edge of a map, at least two maps out.
+        if num_steps % map_length != map_length / 2 || num_steps / map_length < 2 {
+            if num_steps > MAX_WALKING_STEPS {
+                return Err(format!("Cannot extrapolate {} steps: they don't end on the edge of a map (at least 2 maps out)", num_steps));
+            }
+            return Ok(self.get_tiles_from(start, num_steps, steps_odd, true));
         }
 
         // Puzzle 2
@@ -107,7 +235,7 @@ impl Garden {
         // Number of full maps (left/right/top/bottom)
         let full_maps = (num_steps - corner_distance) / map_length;
 
-        println!("We can fit {} full maps in any direction, even maps have {} tiles, odd maps {}", full_maps, odd_count, even_count);
+        log::debug!("We can fit {} full maps in any direction, even maps have {} tiles, odd maps {}", full_maps, odd_count, even_count);
 
         // The initial map should be the same odd/even as the number of steps, the second the other, etc.
         // Full maps form a square:
@@ -139,8 +267,8 @@ impl Garden {
         // of the inner edge.
         let end_step = full_maps + 1;
         let steps_left = num_steps - (((end_step - 1) * map_length) + start.x as usize);
-        println!("There are {} steps left for end-points", steps_left);
-        let end_odd = (steps_odd && (end_step % 2) == 0) || (!steps_odd && (end_step % 2) == 1);
+        log::debug!("There are {} steps left for end-points", steps_left);
+        let end_odd = steps_odd == end_step.is_multiple_of(2);
 
         total_tiles += self.get_tiles_from((start.x, map_length as isize - 1).into(), steps_left, !end_odd, false); // Top
         total_tiles += self.get_tiles_from((map_length as isize - 1, start.y).into(), steps_left, !end_odd, false); // Right
@@ -155,7 +283,7 @@ impl Garden {
         let tiles_left_large = num_steps - (corner_distance + ((end_step - 2) * map_length)) - 2;
         let tiles_left_small = num_steps - (corner_distance + ((end_step - 1) * map_length)) - 2;
 
-        println!("There are {}/{} steps left for corners, and {}/{} corners", tiles_left_large, tiles_left_small, corner_count, corner_count + 1);
+        log::debug!("There are {}/{} steps left for corners, and {}/{} corners", tiles_left_large, tiles_left_small, corner_count, corner_count + 1);
 
         total_tiles += corner_count * self.get_tiles_from((0, map_length as isize - 1).into(), tiles_left_large, end_odd, false); // NE
         total_tiles += (corner_count + 1) * self.get_tiles_from((0, map_length as isize - 1).into(), tiles_left_small, !end_odd, false); // NE
@@ -166,19 +294,141 @@ impl Garden {
         total_tiles += corner_count * self.get_tiles_from((map_length as isize - 1, map_length as isize - 1).into(), tiles_left_large, end_odd, false); // NW
         total_tiles += (corner_count + 1) * self.get_tiles_from((map_length as isize - 1, map_length as isize - 1).into(), tiles_left_small, !end_odd, false); // NW
 
-        total_tiles
+        Ok(total_tiles)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day21::Garden;
+    use crate::util::allocations::count_allocations;
+    use crate::days::day21::ExpansionMethod::{Geometric, Quadratic};
+    use crate::days::day21::{Garden, Tile};
+    use crate::util::geometry::{Bounds, Directions};
+    use crate::util::simulation::count_after_steps;
+    use crate::days::Example;
+
+    // The puzzles use the step counts for the real input (and part 2 relies on its free lanes), so the examples don't apply.
+    pub(super) const EXAMPLES: &[Example] = &[];
 
     #[test]
     fn test_get_tiles_within() {
         let garden: Garden = TEST_INPUT.parse().unwrap();
 
-        assert_eq!(garden.get_tiles_within(6), 16);
+        assert_eq!(garden.get_tiles_within(6, Geometric), Ok(16));
+        assert_eq!(garden.get_tiles_within(6, Quadratic), Ok(16));
+        // The example doesn't have free lanes, so these can't be extrapolated but are still walked:
+        assert_eq!(garden.get_tiles_within(10, Geometric), Ok(50));
+        assert_eq!(garden.get_tiles_within(100, Quadratic), Ok(6536));
+        assert_eq!(
+            garden.get_tiles_within(5000, Geometric),
+            Err("Cannot extrapolate 5000 steps: There is no free lane from the start to the edges, there is a rock at (5,1)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_assumptions() {
+        assert_eq!(FREE_LANES_INPUT.parse::<Garden>().unwrap().validate_assumptions(), Ok(()));
+        assert_eq!(
+            TEST_INPUT.parse::<Garden>().unwrap().validate_assumptions(),
+            Err("There is no free lane from the start to the edges, there is a rock at (5,1)".to_string())
+        );
+        assert_eq!(
+            "...\n.S.\n...\n...".parse::<Garden>().unwrap().validate_assumptions(),
+            Err("The garden is not square (3x4)".to_string())
+        );
+        assert_eq!(
+            "...\n..S\n...".parse::<Garden>().unwrap().validate_assumptions(),
+            Err("The start (2,1) is not in the middle of the garden".to_string())
+        );
+        assert_eq!(
+            "..#\n.S.\n...".parse::<Garden>().unwrap().validate_assumptions(),
+            Err("The border of the garden is not empty, there is a rock at (2,0)".to_string())
+        );
+        assert_eq!(
+            "...\n...\n...".parse::<Garden>().unwrap().validate_assumptions(),
+            Err("There is no start tile in the garden".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_tiles_from_allocations() {
+        let garden: Garden = TEST_INPUT.parse().unwrap();
+        let start = garden.entries().iter().find(|(_, t)| Tile::Start.eq(t)).unwrap().0;
+        let (result, allocations) = count_allocations(|| garden.get_tiles_from(start, 50, false, true));
+        assert_eq!(result, 1594);
+        // Only growing the level buffers should allocate, not every step of the BFS loop
+        assert!(allocations <= 32, "Expected at most 32 allocations, got {}", allocations);
+    }
+
+    #[test]
+    fn test_get_tiles_from_tiled() {
+        // Overflowing the garden is the same as walking through a bunch of copies of it:
+        let garden: Garden = TEST_INPUT.parse().unwrap();
+        let start = garden.entries().iter().find(|(_, t)| Tile::Start.eq(t)).unwrap().0;
+        let (width, height) = (garden.bounds.width as isize, garden.bounds.height as isize);
+
+        let mut tiled = Garden::empty();
+        for y in 0..3 {
+            for x in 0..3 {
+                tiled.paste((x * width, y * height).into(), &garden);
+            }
+        }
+        assert!(tiled.view(&Bounds { top: height, left: width, ..garden.bounds }) == garden);
+
+        for steps in [6, 10, 16] {
+            assert_eq!(
+                tiled.get_tiles_from(start + (width, height), steps, !steps.is_multiple_of(2), false),
+                garden.get_tiles_from(start, steps, !steps.is_multiple_of(2), true)
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_tiles_from_simulated() {
+        // Stepping from all reached tiles at once (marking them as start tiles), the garden ends up flipping between the
+        // odd and the even tiles, so counting them after any number of steps should match walking it.
+        let garden: Garden = TEST_INPUT.parse().unwrap();
+        let start = garden.entries().iter().find(|(_, t)| Tile::Start.eq(t)).unwrap().0;
+        let walk = |garden: &Garden| garden.map(|p, t| match t {
+            Tile::Rock => Tile::Rock,
+            _ if garden.get_adjacent_entries(p, Directions::NonDiagonal).iter().any(|(_, t)| Tile::Start.eq(t)) => Tile::Start,
+            _ => Tile::Garden,
+        });
+        let cells = |garden: &Garden| garden.bounds.points().map(|p| garden.get(&p)).collect::<Vec<_>>();
+
+        for steps in [6, 10, 101, 1_000_000_000] {
+            assert_eq!(
+                count_after_steps(garden.clone(), steps, walk, cells, |t| *t == Some(Tile::Start)),
+                garden.get_tiles_from(start, steps, !steps.is_multiple_of(2), false)
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_tiles_within_quadratic() {
+        // The test input doesn't have free lanes from the start, so use a map that matches the real input's shape.
+        let garden: Garden = FREE_LANES_INPUT.parse().unwrap();
+        let start = garden.entries().iter().find(|(_, t)| Tile::Start.eq(t)).unwrap().0;
+
+        // Brute-force walking the infinite garden should match the extrapolation
+        let steps = 5 + 11 * 6;
+        assert_eq!(garden.get_tiles_within(steps, Quadratic), Ok(garden.get_tiles_from(start, steps, true, true)));
+        assert_eq!(garden.get_tiles_within(steps, Quadratic), garden.get_tiles_within(steps, Geometric));
+    }
+
+    #[test]
+    fn test_get_tiles_within_geometric_unaligned() {
+        // Steps that don't end on the edge of a map (at least 2 maps out) can't be decomposed, so they're walked instead.
+        let garden: Garden = FREE_LANES_INPUT.parse().unwrap();
+        let start = garden.entries().iter().find(|(_, t)| Tile::Start.eq(t)).unwrap().0;
+
+        for steps in [10, 12, 16, 20, 21, 22, 27, 32, 38] {
+            assert_eq!(garden.get_tiles_within(steps, Geometric), Ok(garden.get_tiles_from(start, steps, !steps.is_multiple_of(2), true)), "{} steps", steps);
+        }
+        assert_eq!(
+            garden.get_tiles_within(5000, Geometric),
+            Err("Cannot extrapolate 5000 steps: they don't end on the edge of a map (at least 2 maps out)".to_string())
+        );
     }
 
     const TEST_INPUT: &str = "\
@@ -194,6 +444,20 @@ mod tests {
         .##..##.##.\n\
         ...........\
     ";
+
+    const FREE_LANES_INPUT: &str = "\
+        ...........\n\
+        .##.#...#..\n\
+        ..#....##..\n\
+        .#..#....#.\n\
+        ...#...#...\n\
+        .....S.....\n\
+        ..#.....#..\n\
+        .##.#...##.\n\
+        ....#.#....\n\
+        .#..#....#.\n\
+        ...........\
+    ";
 }
 
 impl FromStr for Tile {
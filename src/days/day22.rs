@@ -1,26 +1,37 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
-use crate::days::Day;
-use crate::util::collection::CollectionExtension;
-use crate::util::geometry::{Point3D};
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::geometry::{Bounds3D, Point3D};
+use crate::util::visualize::{self, Visualizer};
 
 pub const DAY22: Day = Day {
-    puzzle1,
-    puzzle2,
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let mut stack: Stack = input.parse().unwrap();
-    stack.settle();
+struct Solution;
 
-    println!("There are {} blocks that can be disintegrated.", stack.count_removable_blocks());
-}
+impl StructuredDay for Solution {
+    type Parsed = Stack;
+
+    fn parse(input: &str) -> AocResult<Stack> {
+        // Both puzzles are about the stack after all blocks have fallen down.
+        let mut stack = input.parse::<Stack>()?;
+        visualize::show("Falling blocks", &stack);
+        stack.settle();
+        visualize::show("Settled blocks", &stack);
+        Ok(stack)
+    }
 
-fn puzzle2(input: &String) {
-    let mut stack: Stack = input.parse().unwrap();
-    stack.settle();
+    fn part1(stack: &Stack) -> AocResult<String> {
+        Ok(format!("There are {} blocks that can be disintegrated.", stack.count_removable_blocks()))
+    }
 
-    println!("Chain reaction size: {}", stack.sum_of_chain_reactions());
+    fn part2(stack: &Stack) -> AocResult<String> {
+        Ok(format!("Chain reaction size: {}", stack.sum_of_chain_reactions()))
+    }
 }
 
 // For parsing:
@@ -33,158 +44,158 @@ fn puzzle2(input: &String) {
 
 #[derive(Eq, PartialEq, Debug, Copy, Clone, Hash)]
 struct Block {
-    from: Point3D,
-    to: Point3D,
+    bounds: Bounds3D,
 }
 
 impl Block {
-    fn points(&self) -> Vec<Point3D> {
-        let mut points = vec![];
-        for x in self.from.x..=self.to.x {
-            for y in self.from.y..=self.to.y {
-                for z in self.from.z..=self.to.z {
-                    points.push(Point3D { x, y, z })
-                }
-            }
-        }
-        points
+    fn new(from: Point3D, to: Point3D) -> Block {
+        Block { bounds: Bounds3D::from_corners(from, to) }
     }
 
     fn bottom(&self) -> isize {
-        self.from.z.min(self.to.z)
+        self.bounds.min.z
     }
 
     fn top(&self) -> isize {
-        self.from.z.max(self.to.z)
+        self.bounds.max.z
     }
 
     fn bottom_points(&self) -> Vec<Point3D> {
-        let mut points = vec![];
         let z = self.bottom();
-        for x in self.from.x..=self.to.x {
-            for y in self.from.y..=self.to.y {
-                points.push(Point3D { x, y, z })
-            }
-        }
-        points
+        self.bounds.footprint().points().map(|p| Point3D { x: p.x, y: p.y, z }).collect()
     }
 
     fn drop(&mut self, by: isize) {
-        self.from.z -= by;
-        self.to.z -= by;
-    }
-
-    fn points_below(&self) -> Vec<Point3D> {
-        let z = self.bottom() - 1;
-        let mut points = vec![];
-        for x in self.from.x..=self.to.x {
-            for y in self.from.y..=self.to.y {
-                points.push(Point3D { x, y, z })
-            }
-        }
-        points
+        self.bounds = self.bounds.translate(&(0, 0, -by).into());
     }
 
+    #[cfg(test)]
     fn supported_by(&self, block: &Block) -> bool {
-        !self.points_below().union(&block.points()).is_empty()
+        // The block needs to be in the layer right below this one, overlapping it when looking from above.
+        let below = Bounds3D { min: Point3D { z: self.bottom() - 1, ..self.bounds.min }, max: Point3D { z: self.bottom() - 1, ..self.bounds.max } };
+        below.intersects(&block.bounds)
     }
 }
 
 #[derive(Eq, PartialEq, Debug, Clone)]
 struct Stack {
     blocks: Vec<Block>,
+    // For every block (by index), the blocks it rests on. Filled by settling the stack.
+    supporters: Vec<Vec<usize>>,
 }
 
 impl Stack {
-    fn settle(&mut self) {
+    // Returns the number of column heights looked up, so tests can make sure every block only looks at the columns
+    // below it once (no matter how far it falls).
+    fn settle(&mut self) -> usize {
         // Sort own blocks from lowest to highest
         self.blocks.sort_by_key(|b| b.bottom());
+        self.supporters = vec![];
 
-        let mut seen_points: Vec<Point3D> = vec![];
+        // Highest settled z for each (x, y) column, and the block that's there
+        let mut heights: HashMap<(isize, isize), (isize, usize)> = HashMap::new();
+        let mut touched = 0;
 
         // For each block, find the lowest point (either ground or a previous block) for each of the bottom points
-        for block in &mut self.blocks {
-            // Get the Z value that would support this block
-            let z_support = block.bottom_points().iter()
-                .map(|p| seen_points.iter().filter(|sp| p.x == sp.x && p.y == sp.y).max_by_key(|sp| sp.z).map(|p| p.z).unwrap_or(0))
-                .max().unwrap_or(0);
+        for (id, block) in self.blocks.iter_mut().enumerate() {
+            let bottom_points = block.bottom_points();
+
+            // Get the Z value that would support this block, and the blocks at that height (if not the ground)
+            let below: Vec<_> = bottom_points.iter().filter_map(|p| heights.get(&(p.x, p.y)).cloned()).collect();
+            touched += bottom_points.len();
+            let z_support = below.iter().map(|(z, _)| *z).max().unwrap_or(0);
+            let mut supporters: Vec<_> = below.iter().filter(|(z, _)| *z == z_support).map(|(_, id)| *id).collect();
+            supporters.sort();
+            supporters.dedup();
+            self.supporters.push(supporters);
+
             // Drop the block to rest on that value:
             let drop_by = block.bottom() - (z_support + 1);
             block.drop(drop_by);
-            seen_points.push_all(&block.points());
-        }
-    }
 
-    fn count_removable_blocks(&self) -> usize {
-        // Iterating multiple times is a bit slow
-        let mut blocks_by_z: HashMap<isize, Vec<Block>> = HashMap::new();
-        for block in &self.blocks {
-            for z in block.bottom()..=block.top() {
-                let mut vec = blocks_by_z.get(&z).cloned().unwrap_or(vec![]);
-                vec.push(block.clone());
-                blocks_by_z.insert(z, vec);
+            // Only the top of the block matters for the blocks after this one
+            let top = block.top();
+            for p in &bottom_points {
+                heights.insert((p.x, p.y), (top, id));
             }
         }
 
-        let mut removable = 0;
-        for block in &self.blocks {
-            let above: Vec<_> = match blocks_by_z.get(&(block.top() + 1)) {
-                Some(blocks) => blocks.iter().filter(|b| b.supported_by(&block)).collect(),
-                None => vec![]
-            };
-            let siblings = match blocks_by_z.get(&block.top()) {
-                Some(blocks) => blocks.except(block),
-                None => vec![]
-            };
-
-            let can_remove = above.is_empty() || (!siblings.is_empty() && above.iter().all(|a| siblings.iter().any(|s| a.supported_by(s))));
-            if can_remove {
-                removable += 1
-            }
-        }
+        touched
+    }
 
-        removable
+    fn count_removable_blocks(&self) -> usize {
+        // A block can be removed, unless it is the only support of another block
+        let sole_supporters: HashSet<_> = self.supporters.iter().filter(|s| s.len() == 1).map(|s| s[0]).collect();
+        self.blocks.len() - sole_supporters.len()
     }
 
     fn sum_of_chain_reactions(&self) -> usize {
-        // Iterating multiple times is a bit slow
-        let mut blocks_by_z: HashMap<isize, Vec<Block>> = HashMap::new();
-        for block in &self.blocks {
-            for z in block.bottom()..=block.top() {
-                let mut vec = blocks_by_z.get(&z).cloned().unwrap_or(vec![]);
-                vec.push(block.clone());
-                blocks_by_z.insert(z, vec);
+        // For each block, start collecting blocks that will no longer be supported. Blocks only rest on blocks that
+        // settled before them, so a single pass over the blocks after it finds everything that falls.
+        let mut chain_reaction = 0;
+
+        for removed in 0..self.blocks.len() {
+            let mut falling = vec![false; self.blocks.len()];
+            falling[removed] = true;
+
+            for id in removed + 1..self.blocks.len() {
+                let supporters = &self.supporters[id];
+                if !supporters.is_empty() && supporters.iter().all(|s| falling[*s]) {
+                    falling[id] = true;
+                    chain_reaction += 1; // ignore the removed block itself.
+                }
             }
         }
 
-        // Build a map for each block which blocks support it?
-        // Then, for each block, start collecting blocks that will no longer be supported
-        let mut supported_by: HashMap<Block, Vec<Block>> = HashMap::new();
-        for block in &self.blocks {
-            let supports = match blocks_by_z.get(&(block.bottom() - 1)) {
-                Some(blocks) => blocks.iter().filter(|b| block.supported_by(b)).cloned().collect(),
-                None => vec![]
-            };
-            supported_by.insert(block.clone(), supports);
-        }
+        chain_reaction
+    }
 
-        fn count_falling_blocks(falling: Vec<Block>, supported_by: &HashMap<Block, Vec<Block>>) -> usize {
-            let new_falling: Vec<_> = supported_by.iter().filter(|(b, s)| !falling.contains(b) && !s.is_empty() && s.iter().all(|sb| falling.contains(sb))).map(|(b, _)| b).cloned().collect();
-            if new_falling.len() == 0 {
-                falling.len()
-            } else {
-                count_falling_blocks(falling.into_iter().chain(new_falling.into_iter()).collect(), supported_by)
+    // A side view of the stack like the diagrams in the puzzle, with the horizontal axis given by name and coordinate
+    // (the other one is looked along). Blocks are labelled A-Z by their index (wrapping around after Z), a '?' marks
+    // a cell where more than one block is behind each other.
+    fn render_side(&self, axis: char, coordinate: fn(&Point3D) -> isize) -> Vec<String> {
+        let left = self.blocks.iter().map(|b| coordinate(&b.bounds.min)).min().unwrap_or(0);
+        let right = self.blocks.iter().map(|b| coordinate(&b.bounds.max)).max().unwrap_or(0);
+        let top = self.blocks.iter().map(|b| b.top()).max().unwrap_or(0).max(1);
+
+        let mut cells: HashMap<(isize, isize), Option<usize>> = HashMap::new();
+        for (id, block) in self.blocks.iter().enumerate() {
+            for h in coordinate(&block.bounds.min)..=coordinate(&block.bounds.max) {
+                for z in block.bottom()..=block.top() {
+                    cells.entry((h, z)).and_modify(|cell| if *cell != Some(id) { *cell = None }).or_insert(Some(id));
+                }
             }
         }
 
-        let mut chain_reaction = 0;
-
-        for block in &self.blocks {
-            let falling = count_falling_blocks(vec![block.clone()], &supported_by) - 1;
-            chain_reaction += falling; // ignore self.
+        let width = (right - left + 1) as usize;
+        let mut lines = vec![
+            format!("{:^width$}", axis),
+            (left..=right).map(|h| char::from(b'0' + h.rem_euclid(10) as u8)).collect(),
+        ];
+        for z in (1..=top).rev() {
+            let row: String = (left..=right).map(|h| match cells.get(&(h, z)) {
+                None => '.',
+                Some(None) => '?',
+                Some(Some(id)) => char::from(b'A' + (id % 26) as u8),
+            }).collect();
+            lines.push(format!("{} {}", row, z));
         }
+        lines.push(format!("{} 0", "-".repeat(width)));
+        lines
+    }
+}
 
-        chain_reaction
+// The stack seen from the front (x and z) and the side (y and z), next to each other.
+impl Visualizer for Stack {
+    fn render_frame(&self) -> String {
+        let front = self.render_side('x', |p| p.x);
+        let side = self.render_side('y', |p| p.y);
+        let width = front.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+
+        front.iter().zip(&side)
+            .map(|(f, s)| format!("{:width$}   {}", f, s).trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
     }
 }
 
@@ -192,10 +203,16 @@ impl Stack {
 mod tests {
     use crate::days::day22::{Block, Stack};
     use crate::util::geometry::Point3D;
+    use crate::util::visualize::Visualizer;
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("5"), puzzle2: Some("7") },
+    ];
 
     #[test]
     fn test_parse_block() {
-        assert_eq!("0,0,2~2,0,2".parse(), Ok(Block { from: Point3D { x: 0, y: 0, z: 2 }, to: Point3D { x: 2, y: 0, z: 2 } }));
+        assert_eq!("0,0,2~2,0,2".parse(), Ok(Block::new(Point3D { x: 0, y: 0, z: 2 }, Point3D { x: 2, y: 0, z: 2 })));
     }
 
     #[test]
@@ -204,16 +221,34 @@ mod tests {
         stack.settle();
 
         assert_eq!(stack.blocks, vec![
-            Block { from: (1, 0, 1).into(), to: (1, 2, 1).into() }, // A
-            Block { from: (0, 0, 2).into(), to: (2, 0, 2).into() }, // B
-            Block { from: (0, 2, 2).into(), to: (2, 2, 2).into() }, // C
-            Block { from: (0, 0, 3).into(), to: (0, 2, 3).into() }, // D
-            Block { from: (2, 0, 3).into(), to: (2, 2, 3).into() }, // E
-            Block { from: (0, 1, 4).into(), to: (2, 1, 4).into() }, // F
-            Block { from: (1, 1, 5).into(), to: (1, 1, 6).into() }, // G
+            Block::new((1, 0, 1).into(), (1, 2, 1).into()), // A
+            Block::new((0, 0, 2).into(), (2, 0, 2).into()), // B
+            Block::new((0, 2, 2).into(), (2, 2, 2).into()), // C
+            Block::new((0, 0, 3).into(), (0, 2, 3).into()), // D
+            Block::new((2, 0, 3).into(), (2, 2, 3).into()), // E
+            Block::new((0, 1, 4).into(), (2, 1, 4).into()), // F
+            Block::new((1, 1, 5).into(), (1, 1, 6).into()), // G
         ]);
     }
 
+    #[test]
+    fn test_settle_complexity() {
+        // A tall tower of flat and upright blocks, where every block needs to fall all the way down
+        let input: String = (0..200).map(|i| match i % 2 {
+            0 => format!("0,0,{}~2,0,{}\n", 10 * i + 5, 10 * i + 5),
+            _ => format!("1,0,{}~1,0,{}\n", 10 * i + 5, 10 * i + 7),
+        }).collect();
+        let mut stack: Stack = input.parse().unwrap();
+
+        let footprints: usize = stack.blocks.iter().map(|b| b.bottom_points().len()).sum();
+        let touched = stack.settle();
+
+        // Every block looks up the columns below it exactly once, however tall it is and however far it falls
+        assert_eq!(touched, footprints);
+        assert_eq!(footprints, 100 * 3 + 100);
+        assert_eq!(stack.blocks.last().unwrap().top(), 100 + 100 * 3);
+    }
+
     #[test]
     fn test_removable_block_count() {
         let mut stack: Stack = TEST_INPUT.parse().unwrap();
@@ -229,6 +264,30 @@ mod tests {
 
         assert_eq!(stack.blocks[1].supported_by(&stack.blocks[0]), true);
         assert_eq!(stack.blocks[2].supported_by(&stack.blocks[0]), true);
+
+        assert_eq!(stack.supporters, vec![vec![], vec![0], vec![0], vec![1, 2], vec![1, 2], vec![3, 4], vec![5]]);
+        for (id, block) in stack.blocks.iter().enumerate() {
+            let expected: Vec<_> = (0..stack.blocks.len()).filter(|s| block.supported_by(&stack.blocks[*s])).collect();
+            assert_eq!(stack.supporters[id], expected);
+        }
+    }
+
+    #[test]
+    fn test_render_frame() {
+        let mut stack: Stack = TEST_INPUT.parse().unwrap();
+        stack.settle();
+
+        // The settled stack from the puzzle description
+        assert_eq!(stack.render_frame(), " x       y\n\
+            012     012\n\
+            .G. 6   .G. 6\n\
+            .G. 5   .G. 5\n\
+            FFF 4   .F. 4\n\
+            D.E 3   ??? 3\n\
+            ??? 2   B.C 2\n\
+            .A. 1   AAA 1\n\
+            --- 0   --- 0\
+        ");
     }
 
     #[test]
@@ -259,7 +318,7 @@ impl FromStr for Block {
             _ => Err(format!("Invalid block: '{}'", s))
         }?;
 
-        Ok(Block { from, to })
+        Ok(Block::new(from, to))
     }
 }
 
@@ -268,6 +327,6 @@ impl FromStr for Stack {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let blocks = s.lines().map(|s| s.parse()).collect::<Result<Vec<_>, _>>()?;
-        Ok(Self { blocks })
+        Ok(Self { blocks, supporters: vec![] })
     }
 }
@@ -1,24 +1,40 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{HashMap, VecDeque};
 use std::str::FromStr;
-use crate::days::Day;
-use crate::util::collection::CollectionExtension;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::bitset::PointSet;
 use crate::util::geometry::{Directions, Grid, Point};
+use crate::util::{log, profile, threads};
+use crate::util::progress::{self, ProgressReporter};
 
 pub const DAY23: Day = Day {
-    puzzle1,
-    puzzle2,
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let map: Map = input.parse().unwrap();
+struct Solution;
 
-    println!("Longest hike path: {} steps", map.longest_hike_path(true).unwrap());
-}
+impl StructuredDay for Solution {
+    type Parsed = Map;
+
+    fn parse(input: &str) -> AocResult<Map> {
+        input.parse::<Map>()
+    }
 
-fn puzzle2(input: &String) {
-    let map: Map = input.parse().unwrap();
+    fn part1(map: &Map) -> AocResult<String> {
+        let threads = threads::available();
+
+        Ok(format!("Longest hike path: {} steps", map.longest_hike_path(true, threads, progress::reporter())?))
+    }
 
-    println!("Longest non-slippery hike path: {} steps", map.longest_hike_path(false).unwrap());
+    fn part2(map: &Map) -> AocResult<String> {
+        let threads = threads::available();
+
+        Ok(format!("Longest non-slippery hike path: {} steps", map.longest_hike_path(false, threads, progress::reporter())?))
+    }
 }
 
 #[derive(Eq, PartialEq, Debug, Default, Copy, Clone)]
@@ -35,17 +51,15 @@ enum Tile {
 type Map = Grid<Tile>;
 
 impl Map {
-    fn start(&self) -> Point {
-        self.get_row(0).iter().position(|t| Tile::Path.eq(t)).map(|x| Point { x: x as isize, y: 0 })
-            .ok_or("Could not find a start point?!")
-            .unwrap()
+    fn start(&self) -> Result<Point, String> {
+        self.iter_row(0).position(|t| Tile::Path.eq(t)).map(|x| Point { x: x as isize, y: 0 })
+            .ok_or("Could not find a start point?!".to_string())
     }
 
-    fn end(&self) -> Point {
+    fn end(&self) -> Result<Point, String> {
         let y = self.bounds.bottom();
-        self.get_row(y).iter().position(|t| Tile::Path.eq(t)).map(|x| Point { x: x as isize, y })
-            .ok_or("Could not find an end point?!")
-            .unwrap()
+        self.iter_row(y).position(|t| Tile::Path.eq(t)).map(|x| Point { x: x as isize, y })
+            .ok_or("Could not find an end point?!".to_string())
     }
 
     // Obviously forcing it works only for the test input. However, the map itself isn't very complex. We should be able
@@ -53,9 +67,9 @@ impl Map {
     // two connected ones. That should give us a weighted graph where we can just brute force through without too many
     // things to iterate over. (Since we cannot visit a tile more than once, we can also only visit a node once.)
 
-    fn longest_hike_path(&self, slippery: bool) -> Option<usize> {
-        let graph = Graph::new(self, slippery);
-        graph.longest_path()
+    fn longest_hike_path(&self, slippery: bool, threads: usize, progress: &dyn ProgressReporter) -> Result<usize, String> {
+        let graph = profile::span!("build graph").run(|| Graph::new(self, slippery))?;
+        graph.longest_path(threads, progress).ok_or("There is no path to the end".to_string())
     }
 }
 
@@ -78,11 +92,11 @@ struct Graph {
 }
 
 impl Graph {
-    fn new(map: &Map, slippery: bool) -> Self {
+    fn new(map: &Map, slippery: bool) -> Result<Self, String> {
         // A point is a node if there are more than two paths adjacent to it
-        fn is_node(map: &Map, p: &Point) -> bool {
-            map.start().eq(p) || map.end().eq(p) ||
-                map.get_adjacent(p, Directions::NonDiagonal).iter().filter(|t| Tile::Forest.ne(t)).count() > 2
+        fn is_node(map: &Map, graph: &Graph, p: &Point) -> bool {
+            graph.start.eq(p) || graph.end.eq(p) ||
+                map.neighbors(p, Directions::NonDiagonal).filter(|(_, t)| Tile::Forest.ne(t)).count() > 2
         }
 
         fn can_enter(map: &Map, from: &Point, to: &Point, slippery: bool) -> bool {
@@ -101,24 +115,24 @@ impl Graph {
             }
         }
 
-        fn follow_path(map: &Map, graph: &mut Graph, node: &Point, first: &Point, visited: &mut HashSet<Point>, slippery: bool) -> Option<Point> {
+        fn follow_path(map: &Map, graph: &mut Graph, node: &Point, first: &Point, visited: &mut PointSet, slippery: bool) -> Result<Option<Point>, String> {
             let mut path = vec![*node]; // Keep points out of visited until we reach a next node, in case we cannot follow this.
 
-            if visited.contains(first) { return None; } // Already visited this path before
-            if !can_enter(map, node, first, slippery) { return None; } // Cannot enter first tile
+            if visited.contains(first) { return Ok(None); } // Already visited this path before
+            if !can_enter(map, node, first, slippery) { return Ok(None); } // Cannot enter first tile
 
             let mut current = *first;
             let mut one_direction = false;
 
-            while !is_node(map, &current) {
+            while !is_node(map, graph, &current) {
                 path.push(current);
 
-                let next = match current.get_points_around(Directions::NonDiagonal).iter()
-                    .filter(|p| !path.contains(p) && can_enter(map, &current, p, slippery))
-                    .collect::<Vec<_>>()[..] {
-                    [next] => *next,
-                    [] => return None,
-                    _ => panic!("Entered a junction?!")
+                let mut options = current.points_around(Directions::NonDiagonal)
+                    .filter(|p| !path.contains(p) && can_enter(map, &current, p, slippery));
+                let next = match (options.next(), options.next()) {
+                    (Some(next), None) => next,
+                    (None, _) => return Ok(None),
+                    _ => return Err(format!("Entered a junction at {}?!", current))
                 };
 
                 if slippery {
@@ -136,28 +150,30 @@ impl Graph {
                 graph.add_path(&current, *node, path.len());
             }
 
-            Some(current)
+            Ok(Some(current))
         }
 
-        fn visit_map(map: &Map, graph: &mut Graph, slippery: bool) {
-            let mut visited: HashSet<Point> = HashSet::new();
+        fn visit_map(map: &Map, graph: &mut Graph, slippery: bool) -> Result<(), String> {
+            let mut visited = PointSet::new(map.bounds);
             let mut queue: VecDeque<Point> = VecDeque::new();
-            queue.push_back(map.start());
+            queue.push_back(graph.start);
 
-            while !queue.is_empty() {
-                let node = queue.pop_front().unwrap(); // Guarded by while check
+            while let Some(node) = queue.pop_front() {
                 graph.add_node(node);
 
-                node.get_points_around(Directions::NonDiagonal).iter()
-                    .filter_map(|p| follow_path(map, graph, &node, p, &mut visited, slippery))
-                    .for_each(|next_node| queue.push_back(next_node));
+                for p in node.points_around(Directions::NonDiagonal) {
+                    if let Some(next_node) = follow_path(map, graph, &node, &p, &mut visited, slippery)? {
+                        queue.push_back(next_node);
+                    }
+                }
             }
+            Ok(())
         }
-        let mut result = Self { start: map.start(), end: map.end(), nodes: HashMap::new() };
+        let mut result = Self { start: map.start()?, end: map.end()?, nodes: HashMap::new() };
 
-        visit_map(map, &mut result, slippery);
+        visit_map(map, &mut result, slippery)?;
 
-        result
+        Ok(result)
     }
 
     fn add_node(&mut self, node: Point) {
@@ -172,55 +188,302 @@ impl Graph {
         }
     }
 
-    fn longest_path(&self) -> Option<usize> {
+    fn longest_path(&self, threads: usize, progress: &dyn ProgressReporter) -> Option<usize> {
         // This is an NP-Hard problem, so I don't feel bad doing this brute-forced...
         // (We convert to this graph first so that we don't need to run over the whole path multiple times, saving
         //  us some processing time.)
+        // To speed it up a bit more, we split the search into a bunch of partial hikes that are divided over a couple
+        // of threads. The longest hike found so far is shared between them, so that every thread can skip hikes that
+        // can't possibly beat it anymore.
+        // With a single thread (which is all we get in wasm) we explore the hikes on this one.
+        let search = profile::span!("prepare search").run(|| HikeSearch::new(self))?;
+        let hikes = search.split(threads.max(1) * 8);
+        let next_hike = AtomicUsize::new(0);
+        let explore_hikes = || {
+            while let Some(hike) = hikes.get(next_hike.fetch_add(1, Ordering::Relaxed)) {
+                let _span = profile::span!("explore hike from node {}", hike.current);
+                search.explore(&mut hike.clone(), progress);
+            }
+        };
+
+        if threads <= 1 {
+            explore_hikes();
+        } else {
+            thread::scope(|scope| {
+                for _ in 0..threads {
+                    scope.spawn(explore_hikes);
+                }
+            });
+        }
+
+        match search.paths_found.load(Ordering::Relaxed) {
+            0 => None,
+            _ => Some(search.longest.load(Ordering::Relaxed))
+        }
+    }
+}
 
-        fn get_longest_path(graph: &Graph, nodes: Vec<Point>, current_length: usize) -> Option<usize> {
-            let current = nodes.last().unwrap(); // Nodes should not be empty.
+// Removes the paths that cannot be part of any hike from the start to the end, so the search doesn't have to find that
+// out the hard way (over and over again). Returns how many paths were removed.
+// - Paths back into the start, and out of the end.
+// - Paths from nodes we cannot get to, or to nodes we cannot get to the end from.
+// - Every hike passes the separators (nodes that split the start's part of the map from the end's part) exactly once,
+//   after which there's no going back: paths from a separator to the start's side, paths into it from the end's side,
+//   and paths into dead ends hanging off it are all useless. On the real input, this means that the junctions right
+//   after the start and before the end can only be passed in one direction.
+// We look at the map as if paths go both ways to find the separators, as any hike is a hike on that map as well.
+fn prune_paths(paths: &mut [Vec<(usize, usize)>], start: usize, end: usize) -> usize {
+    let before = paths.iter().map(|p| p.len()).sum::<usize>();
+
+    paths[end].clear();
+    paths.iter_mut().for_each(|p| p.retain(|(destination, _)| *destination != start));
+
+    let forward: Vec<Vec<_>> = paths.iter().map(|p| p.iter().map(|(destination, _)| *destination).collect()).collect();
+    let mut backward = vec![vec![]; paths.len()];
+    let mut both = forward.clone();
+    for (source, destinations) in forward.iter().enumerate() {
+        for &destination in destinations {
+            backward[destination].push(source);
+            both[destination].push(source);
+        }
+    }
 
-            if graph.end.eq(current) { return Some(current_length); }
+    let from_start = reachable(&forward, start, None);
+    let to_end = reachable(&backward, end, None);
+    retain_paths(paths, |source, destination| from_start[source] && to_end[destination]);
 
-            // For each connected – unvisited – node, try getting the longest path to end.
-            let node = graph.nodes.get(current)?;
-            let mut result = None;
+    for separator in 0..paths.len() {
+        if separator == start || separator == end { continue; }
 
-            for path in &node.paths {
-                if nodes.contains(&path.destination) { continue; }
+        let start_side = reachable(&both, start, Some(separator));
+        if start_side[end] { continue; } // Not a separator
+        let end_side = reachable(&both, end, Some(separator));
+        let is_useful = |node: usize| node == separator || start_side[node] || end_side[node];
 
-                if let Some(distance) = get_longest_path(graph, nodes.append_item(&path.destination), current_length + path.length) {
-                    result = match result {
-                        None => Some(distance),
-                        Some(current) if current < distance => Some(distance),
-                        result => result
-                    };
+        retain_paths(paths, |source, destination| {
+            is_useful(source) && is_useful(destination)
+                && !(source == separator && start_side[destination])
+                && !(destination == separator && end_side[source])
+        });
+    }
+
+    before - paths.iter().map(|p| p.len()).sum::<usize>()
+}
+
+// The nodes that can be reached from the given one, without passing the blocked node.
+fn reachable(neighbours: &[Vec<usize>], from: usize, blocked: Option<usize>) -> Vec<bool> {
+    let mut seen = vec![false; neighbours.len()];
+    let mut queue = VecDeque::from([from]);
+    seen[from] = true;
+    while let Some(node) = queue.pop_front() {
+        for &next in &neighbours[node] {
+            if !seen[next] && Some(next) != blocked {
+                seen[next] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+    seen
+}
+
+fn retain_paths(paths: &mut [Vec<(usize, usize)>], keep: impl Fn(usize, usize) -> bool) {
+    for (source, destinations) in paths.iter_mut().enumerate() {
+        destinations.retain(|(destination, _)| keep(source, *destination));
+    }
+}
+
+// The graph with nodes by index instead of point, plus the state shared by all threads searching it.
+struct HikeSearch {
+    paths: Vec<Vec<(usize, usize)>>,
+    start: usize,
+    end: usize,
+    // The longest path entering each node. Every node we still visit adds at most that to the length of a hike.
+    max_incoming: Vec<usize>,
+    longest: AtomicUsize,
+    paths_found: AtomicUsize,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+struct Hike {
+    current: usize,
+    length: usize,
+    visited: Vec<bool>,
+    // The most the hike could still get longer, see max_incoming.
+    remaining: usize,
+}
+
+impl HikeSearch {
+    fn new(graph: &Graph) -> Option<HikeSearch> {
+        let points: Vec<_> = graph.nodes.keys().copied().collect();
+        let index_of = |point: &Point| points.iter().position(|p| p == point);
+
+        let mut paths: Vec<Vec<_>> = points.iter()
+            .map(|p| graph.nodes[p].paths.iter().filter_map(|path| Some((index_of(&path.destination)?, path.length))).collect())
+            .collect();
+        let start = index_of(&graph.start)?;
+        let end = index_of(&graph.end)?;
+
+        let total = paths.iter().map(|p| p.len()).sum::<usize>();
+        let pruned = prune_paths(&mut paths, start, end);
+        log::debug!("Pruned {} of {} paths that cannot be part of a hike", pruned, total);
+
+        let mut max_incoming = vec![0; points.len()];
+        for &(destination, length) in paths.iter().flatten() {
+            max_incoming[destination] = max_incoming[destination].max(length);
+        }
+
+        Some(HikeSearch {
+            paths,
+            start,
+            end,
+            max_incoming,
+            longest: AtomicUsize::new(0),
+            paths_found: AtomicUsize::new(0),
+        })
+    }
+
+    fn start(&self) -> Hike {
+        let mut visited = vec![false; self.paths.len()];
+        visited[self.start] = true;
+        let remaining = self.max_incoming.iter().sum::<usize>() - self.max_incoming[self.start];
+        Hike { current: self.start, length: 0, visited, remaining }
+    }
+
+    fn step(&self, hike: &Hike, destination: usize, length: usize) -> Hike {
+        let mut visited = hike.visited.clone();
+        visited[destination] = true;
+        Hike { current: destination, length: hike.length + length, visited, remaining: hike.remaining - self.max_incoming[destination] }
+    }
+
+    // Splits the search into (at least the given amount of) partial hikes, by taking every possible step from the
+    // shortest hikes until there are enough of them. Hikes that reached the end are kept, so they are counted as well.
+    fn split(&self, count: usize) -> Vec<Hike> {
+        let mut hikes = VecDeque::from([self.start()]);
+
+        while hikes.len() < count {
+            match hikes.iter().position(|h| h.current != self.end) {
+                Some(index) => {
+                    let hike = hikes.remove(index).unwrap();
+                    for &(destination, length) in &self.paths[hike.current] {
+                        if !hike.visited[destination] {
+                            hikes.push_back(self.step(&hike, destination, length));
+                        }
+                    }
                 }
+                None => break
             }
+        }
+
+        hikes.into()
+    }
 
-            result
+    // Depth-first search of all hikes continuing the given one, recording the longest one reaching the end.
+    fn explore(&self, hike: &mut Hike, progress: &dyn ProgressReporter) {
+        if hike.current == self.end {
+            self.longest.fetch_max(hike.length, Ordering::Relaxed);
+            let paths_found = self.paths_found.fetch_add(1, Ordering::Relaxed) + 1;
+            progress.progress("Hike paths found", paths_found, None);
+            return;
         }
 
-        get_longest_path(self, vec![self.start], 0)
+        // Even visiting every node left over the longest paths wouldn't beat the best one found.
+        if self.paths_found.load(Ordering::Relaxed) > 0 && hike.length + hike.remaining <= self.longest.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let current = hike.current;
+        for &(destination, length) in &self.paths[current] {
+            if hike.visited[destination] { continue; }
+
+            hike.visited[destination] = true;
+            hike.current = destination;
+            hike.length += length;
+            hike.remaining -= self.max_incoming[destination];
+
+            self.explore(hike, progress);
+
+            hike.visited[destination] = false;
+            hike.current = current;
+            hike.length -= length;
+            hike.remaining += self.max_incoming[destination];
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day23::{Graph, Map, Node, Path};
+    use crate::days::day23::{prune_paths, Graph, HikeSearch, Map, Node, Path};
+    use crate::util::progress::Silent;
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("94"), puzzle2: Some("154") },
+    ];
 
     #[test]
     fn test_longest_hike_path() {
         let map: Map = TEST_INPUT.parse().unwrap();
 
-        assert_eq!(map.longest_hike_path(true), Some(94));
-        assert_eq!(map.longest_hike_path(false), Some(154));
+        assert_eq!(map.longest_hike_path(true, 1, &Silent), Ok(94));
+        assert_eq!(map.longest_hike_path(false, 1, &Silent), Ok(154));
+        assert_eq!(map.longest_hike_path(true, 4, &Silent), Ok(94));
+        assert_eq!(map.longest_hike_path(false, 4, &Silent), Ok(154));
+    }
+
+    #[test]
+    fn test_hike_search_split() {
+        let map: Map = TEST_INPUT.parse().unwrap();
+        let search = HikeSearch::new(&Graph::new(&map, false).unwrap()).unwrap();
+
+        // The start only has a single path, to (3,5), which has two more.
+        let hikes = search.split(2);
+        assert_eq!(hikes.len(), 2);
+        assert!(hikes.iter().all(|h| h.visited.iter().filter(|v| **v).count() == 3));
+        assert!(hikes.iter().all(|h| h.length == 37));
+
+        // Splitting further than possible only leaves finished hikes
+        assert!(search.split(1000).iter().all(|h| h.current == search.end));
+    }
+
+    #[test]
+    fn test_prune_paths() {
+        // 0 is the start, 4 the end. 1 and 3 are separators, with 2 in between them and 5 a dead end hanging off 3.
+        //   0 - 1 = 2 = 3 - 4
+        //               |
+        //               5
+        let mut paths = vec![
+            vec![(1, 1)],
+            vec![(0, 1), (2, 1), (2, 2)],
+            vec![(1, 1), (1, 2), (3, 1), (3, 2)],
+            vec![(2, 1), (2, 2), (4, 1), (5, 1)],
+            vec![(3, 1)],
+            vec![(3, 1)],
+        ];
+
+        assert_eq!(prune_paths(&mut paths, 0, 4), 8);
+        assert_eq!(paths, vec![
+            vec![(1, 1)],
+            vec![(2, 1), (2, 2)],
+            vec![(3, 1), (3, 2)],
+            vec![(4, 1)],
+            vec![],
+            vec![],
+        ]);
+
+        // Nothing to prune when there is a way around every node
+        let mut paths = vec![
+            vec![(1, 1), (2, 1)],
+            vec![(2, 1), (3, 1)],
+            vec![(1, 1), (3, 1)],
+            vec![],
+        ];
+        assert_eq!(prune_paths(&mut paths, 0, 3), 0);
     }
 
     #[test]
     fn test_convert_to_graph() {
         let map: Map = TEST_INPUT.parse().unwrap();
-        let slippery_graph = Graph::new(&map, true);
+        let slippery_graph = Graph::new(&map, true).unwrap();
 
         assert_eq!(slippery_graph.nodes.contains_key(&slippery_graph.start), true);
         assert_eq!(slippery_graph.nodes.contains_key(&slippery_graph.end), true);
@@ -241,7 +504,7 @@ mod tests {
             ]
         }));
 
-        let full_graph = Graph::new(&map, false);
+        let full_graph = Graph::new(&map, false).unwrap();
 
         assert_eq!(full_graph.nodes.contains_key(&(1, 0).into()), true);
         assert_eq!(full_graph.nodes.contains_key(&(3, 5).into()), true);
@@ -1,25 +1,68 @@
 use std::ops::RangeInclusive;
 use std::str::FromStr;
-use crate::days::Day;
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
+use crate::util::exact;
 use crate::util::geometry::Point3D;
+use crate::util::options;
+use crate::util::progress::{self, ProgressReporter};
 
 pub const DAY24: Day = Day {
-    puzzle1,
-    puzzle2,
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let stones = parse_input(input).unwrap();
-    let area = 200_000_000_000_000f64..=400_000_000_000_000f64;
+struct Solution;
 
-    println!("Number of intersections in target area: {}", Hailstone::count_2d_intersections(&stones, &area));
+impl StructuredDay for Solution {
+    type Parsed = Vec<Hailstone>;
+
+    fn parse(input: &str) -> AocResult<Vec<Hailstone>> {
+        parse_input(input).map_err(Into::into)
+    }
+
+    fn part1(stones: &Vec<Hailstone>) -> AocResult<String> {
+        let area = get_test_area()?;
+
+        Ok(format!("Number of intersections in target area: {}", Hailstone::count_2d_intersections(stones, &area)))
+    }
+
+    fn part2(stones: &Vec<Hailstone>) -> AocResult<String> {
+        let stone = Hailstone::find_stone_hitting_all(stones, progress::reporter())?;
+        let result = stone.position.x + stone.position.y + stone.position.z;
+        if exact::is_enabled() {
+            exact::check("day 24 coordinate sum", result, stone.position.x as i128 + stone.position.y as i128 + stone.position.z as i128)?;
+        }
+        Ok(format!("Stone hitting all hailstones: {:?}, result: {}", stone, result))
+    }
 }
 
-fn puzzle2(input: &String) {
-    let stones = parse_input(input).unwrap();
+// The area to count the intersections in. The examples use a much smaller one, which can be set with
+// --option day24.area=7..27
+const TEST_AREA_OPTION: &str = "day24.area";
+const DEFAULT_TEST_AREA: RangeInclusive<f64> = 200_000_000_000_000f64..=400_000_000_000_000f64;
 
-    let stone = Hailstone::find_stone_hitting_all(&stones).unwrap();
-    println!("Stone hitting all hailstones: {:?}, result: {}", stone, stone.position.x + stone.position.y + stone.position.z);
+fn get_test_area() -> Result<RangeInclusive<f64>, String> {
+    match options::get(TEST_AREA_OPTION) {
+        Some(area) => parse_area(&area),
+        None => Ok(DEFAULT_TEST_AREA),
+    }
+}
+
+// An area as "<min>..<max>", both inclusive.
+fn parse_area(s: &str) -> Result<RangeInclusive<f64>, String> {
+    let parse = |n: &str| n.trim().parse::<f64>().map_err(|e| format!("Invalid area bound '{}': {}", n, e));
+    match s.split_once("..") {
+        Some((min, max)) => {
+            let (min, max) = (parse(min)?, parse(max)?);
+            if min > max {
+                return Err(format!("Invalid area '{}', the minimum is larger than the maximum", s));
+            }
+            Ok(min..=max)
+        }
+        None => Err(format!("Invalid area '{}', expected <min>..<max>", s))
+    }
 }
 
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
@@ -105,6 +148,33 @@ impl Hailstone {
         }
     }
 
+    fn hits_exactly(&self, other: &Self) -> bool {
+        // Integer-only check (no f64 rounding) that there is a single moment t >= 0 where both stones are in the same spot.
+        let axes = [
+            (self.position.x, self.velocity.x, other.position.x, other.velocity.x),
+            (self.position.y, self.velocity.y, other.position.y, other.velocity.y),
+            (self.position.z, self.velocity.z, other.position.z, other.velocity.z),
+        ];
+
+        let mut time: Option<i128> = None;
+        for (p1, v1, p2, v2) in axes {
+            let dp = p2 as i128 - p1 as i128;
+            let dv = v1 as i128 - v2 as i128;
+            if dv == 0 {
+                // Same speed on this axis, so they need to be on the same coordinate all along
+                if dp != 0 { return false; }
+                continue;
+            }
+            if dp % dv != 0 { return false; }
+
+            let t = dp / dv;
+            if t < 0 || time.is_some_and(|current| current != t) { return false; }
+            time = Some(t);
+        }
+
+        true
+    }
+
     fn count_2d_intersections(stones: &Vec<Self>, area: &RangeInclusive<f64>) -> usize {
         let mut count = 0;
         for a in 0..stones.len() {
@@ -115,7 +185,7 @@ impl Hailstone {
         count
     }
 
-    fn find_stone_hitting_all(stones: &Vec<Self>) -> Option<Hailstone> {
+    fn find_stone_hitting_all(stones: &Vec<Self>, progress: &dyn ProgressReporter) -> Result<Hailstone, String> {
         // To find the stone offset and velocity, we simply brute force all velocities.
         // To eliminate the time factor, we map all (or a subset, at least) hailstones to subtract the test velocity (x,y).
         // If we can find a point where the mapped stones hit each other, we have a candidate. From there, we do the
@@ -124,11 +194,11 @@ impl Hailstone {
 
         fn find_intersection(stones: &Vec<Hailstone>, intersect: impl (Fn(&Hailstone, &Hailstone) -> Option<(f64, f64)>)) -> Option<(f64, f64)> {
             // We need an integer position, and as such, an integer intersection.
-            let main = stones[0];
+            let (main, others) = stones.split_first()?;
             let mut current_point: Option<(f64, f64)> = None;
 
-            for stone in &stones[1..] {
-                let (a, b) = intersect(&main, stone)?;
+            for stone in others {
+                let (a, b) = intersect(main, stone)?;
 
                 if let Some((cur_a, cur_b)) = current_point {
                     if (cur_a - a).abs() > 1f64 || (cur_b - b).abs() > 1f64 {
@@ -146,9 +216,15 @@ impl Hailstone {
         const USE_STONES: usize = 10; // Number of stones to validate
         const MAX_Z: isize = 10000; // Bind the maximum Z value to search, in case we have a x/y match with no Z match
         // Note: based on velocities in the input, which seem < 1000, so I don't expect a very large Z necessary.
+        const MAX_VELOCITY: isize = 1000; // Same for x/y, so we give up (instead of searching forever) when nothing hits
 
-        for i in 0..isize::MAX {
-            if i % 100 == 0 { println!("{}...", i); }
+        // Any velocity will hit one or two stones, we need at least three to pin the rock down.
+        if stones.len() < 3 {
+            return Err(format!("Need at least 3 hailstones to find the rock, got {}", stones.len()));
+        }
+
+        for i in 0..=MAX_VELOCITY {
+            progress.progress("Searching rock x/y velocities", i as usize, None);
 
             for j in 0..=i {
                 for [x, y] in [[i, j], [j, i]] {
@@ -156,8 +232,6 @@ impl Hailstone {
                         let rock_dx = sx * x;
                         let rock_dy = sy * y;
 
-                        // println!("Testing dx/dy {}/{}", rock_dx, rock_dy);
-
                         // Remap set of hailstones to subtract the test velocity
                         let xy_stones = stones.iter()
                             .take(USE_STONES)
@@ -171,7 +245,7 @@ impl Hailstone {
                             None => continue, // No match, continue
                         };
 
-                        println!("Found hit for {},{}", x, y);
+                        progress.message(&format!("Found hit for {},{}", x, y));
 
                         for z in 0..MAX_Z {
                             for sz in [-1, 1] {
@@ -190,30 +264,47 @@ impl Hailstone {
                                     None => continue, // No match, continue
                                 };
 
-                                println!("Found z {}", z);
+                                progress.message(&format!("Found z {}", z));
 
                                 // We got all data:
-                                return Some(Hailstone {
+                                let rock = Hailstone {
                                     position: Point3D { x: x as isize, y: y as isize, z: z as isize },
                                     velocity: Point3D { x: rock_dx, y: rock_dy, z: rock_dz },
-                                });
+                                };
+
+                                // The position is rounded from f64 intersections, make sure it actually hits everything.
+                                if exact::is_enabled() {
+                                    if let Some(missed) = stones.iter().find(|s| !rock.hits_exactly(s)) {
+                                        return Err(format!("Exact check failed for day 24 rock {:?}: misses hailstone {:?}", rock, missed));
+                                    }
+                                }
+
+                                return Ok(rock);
                             }
                         }
 
-                        println!("No hit for z <= 5000?");
+                        progress.message(&format!("No hit for z < {}?", MAX_Z));
                     }
                 }
             }
         }
 
-        None
+        Err(format!("No rock with x/y velocities up to {} hits all hailstones", MAX_VELOCITY))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day24::{Hailstone, parse_input};
+    use crate::days::day24::{Hailstone, parse_area, parse_input, TEST_AREA_OPTION};
+    use crate::solve;
+    use crate::util::options;
+    use crate::util::progress::Silent;
     use crate::util::geometry::Point3D;
+    use crate::days::Example;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: None, puzzle2: Some("47") },
+    ];
 
     #[test]
     fn test_intersects_2d() {
@@ -233,14 +324,46 @@ mod tests {
         assert_eq!(Hailstone::count_2d_intersections(&stones, &test_range), 2);
     }
 
+    #[test]
+    fn test_parse_area() {
+        assert_eq!(parse_area("7..27"), Ok(7f64..=27f64));
+        assert_eq!(parse_area("200000000000000..400000000000000"), Ok(200_000_000_000_000f64..=400_000_000_000_000f64));
+        assert!(parse_area("7-27").is_err());
+        assert!(parse_area("27..7").is_err());
+        assert!(parse_area("7..x").is_err());
+    }
+
+    #[test]
+    fn test_example_area_option() {
+        // The example through the same code as the real puzzle, just with the example's area
+        let result = options::with(&[(TEST_AREA_OPTION, "7..27")], || solve(24, 1, TEST_INPUT));
+
+        assert_eq!(result, Ok("Number of intersections in target area: 2".to_string()));
+    }
+
+    #[test]
+    fn test_hits_exactly() {
+        let stones = parse_input(TEST_INPUT).unwrap();
+        let rock = Hailstone { position: Point3D { x: 24, y: 13, z: 10 }, velocity: Point3D { x: -3, y: 1, z: 2 } };
+
+        assert!(stones.iter().all(|s| rock.hits_exactly(s)));
+
+        let off_by_one = Hailstone { position: Point3D { x: 24, y: 13, z: 11 }, ..rock };
+        assert_eq!(off_by_one.hits_exactly(&stones[0]), false);
+    }
+
     #[test]
     fn test_find_stone_hitting_all() {
         let stones = parse_input(TEST_INPUT).unwrap();
 
-        assert_eq!(Hailstone::find_stone_hitting_all(&stones), Some(Hailstone {
+        assert_eq!(Hailstone::find_stone_hitting_all(&stones, &Silent), Ok(Hailstone {
             position: Point3D { x: 24, y: 13, z: 10 },
             velocity: Point3D { x: -3, y: 1, z: 2 },
-        }))
+        }));
+
+        assert!(Hailstone::find_stone_hitting_all(&vec![], &Silent).is_err());
+        assert!(Hailstone::find_stone_hitting_all(&stones[0..1].to_vec(), &Silent).is_err());
+        assert!(solve(24, 2, "").is_err());
     }
 
     const TEST_INPUT: &str = "\
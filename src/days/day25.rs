@@ -1,21 +1,45 @@
 use std::collections::HashMap;
 use std::str::FromStr;
-use crate::days::Day;
+use crate::days::{parse, Day, StructuredDay};
+use crate::util::error::AocResult;
 use crate::util::collection::CollectionExtension;
+use crate::util::dot;
+use crate::util::interner::Interner;
+use crate::util::parser::str_get;
+use crate::util::random::{self, Random};
+use crate::util::{log, options};
 
 pub const DAY25: Day = Day {
-    puzzle1,
-    puzzle2
+    parse: parse::<Solution>,
+    #[cfg(test)]
+    examples: tests::EXAMPLES,
 };
 
-fn puzzle1(input: &String) {
-    let mess: Mess = input.parse().unwrap();
+struct Solution;
 
-    println!("Result of groups: {}", mess.split_components().unwrap());
-}
-fn puzzle2(_input: &String) {
-    // Part 2 is a 'freebie', assuming you got all stars. :see_no_evil:
-    println!("Happy part 2 solvings~");
+impl StructuredDay for Solution {
+    type Parsed = Mess;
+
+    fn parse(input: &str) -> AocResult<Mess> {
+        input.parse::<Mess>().map_err(Into::into)
+    }
+
+    fn part1(mess: &Mess) -> AocResult<String> {
+        let group = mess.find_cut(options::get(METHOD_OPTION).as_deref(), &mut Random::seeded())?;
+
+        if dot::path().is_some() {
+            let cut = group.as_ref().map(|g| mess.get_cut_wires(g)).unwrap_or_default();
+            dot::write(&mess.to_dot(&cut))?;
+        }
+
+        let group = group.ok_or("Could not split the components by cutting three wires")?;
+        Ok(format!("Result of groups: {}", mess.split_components(&group)))
+    }
+
+    fn part2(_mess: &Mess) -> AocResult<String> {
+        // Part 2 is a 'freebie', assuming you got all stars. :see_no_evil:
+        Ok("Happy part 2 solvings~".to_string())
+    }
 }
 
 // We need to find 3 wires that, when cut, separate the big mess of components into two separate groups (of whatever sizes (>1 ofc)).
@@ -26,71 +50,183 @@ fn puzzle2(_input: &String) {
 // Need a representation that easily allows counting group sizes and counting the number of connections between the groups.
 // Might work with recursive DFS?
 
+// How to find the wires to cut: "search" (the default) grows a group until it's connected by three wires, "karger" uses
+// Karger's randomized min-cut (with the seed of the run, see --seed).
+const METHOD_OPTION: &str = "day25.method";
+// Every attempt has a small chance of finding the cut; for a real input a few hundred attempts is usually plenty.
+const KARGER_ATTEMPTS: usize = 10_000;
+
+// Components are referred to by their interned name.
 #[derive(Eq, PartialEq, Debug, Clone)]
 struct Mess {
-    components: Vec<String>,
+    names: Interner,
+    components: Vec<u32>,
     wires: Vec<Wire>
 }
 
 impl Mess {
-    fn get_outgoing_connections(&self, group: &Vec<String>) -> Vec<String> {
+    fn get_outgoing_connections(&self, group: &Vec<u32>) -> Vec<u32> {
         self.wires.iter().filter_map(|w| match (group.contains(&w.left), group.contains(&w.right)) {
             (true, true) | (false, false) => None, // Either both or neither are in the group
-            (true, false) => Some(w.right.clone()),
-            (false, true) => Some(w.left.clone())
+            (true, false) => Some(w.right),
+            (false, true) => Some(w.left)
         }).collect()
     }
 
-    fn split_components(&self) -> Option<usize> {
-        fn duplicates(list: Vec<String>) -> Vec<(String, isize)> {
-            let mut map: HashMap<String, isize> = HashMap::new();
+    // The wires with one end in the group, and one outside of it.
+    fn get_cut_wires(&self, group: &Vec<u32>) -> Vec<&Wire> {
+        self.wires.iter().filter(|w| group.contains(&w.left) != group.contains(&w.right)).collect()
+    }
+
+    // The product of the sizes of the group and the rest of the components.
+    fn split_components(&self, group: &Vec<u32>) -> usize {
+        group.len() * (self.components.len() - group.len())
+    }
+
+    // The wiring as an (undirected) DOT graph, with the given wires highlighted.
+    fn to_dot(&self, highlighted: &[&Wire]) -> String {
+        let mut result = "graph day25 {\n".to_string();
+        for wire in &self.wires {
+            let attributes = if highlighted.contains(&wire) { " [color=red, penwidth=3]" } else { "" };
+            result += &format!("  {} -- {}{};\n", dot::quote(self.names.name(wire.left)), dot::quote(self.names.name(wire.right)), attributes);
+        }
+        result += "}\n";
+        result
+    }
+
+    // Finds a group of components that can be separated from the others by cutting exactly three wires.
+    fn find_group(&self) -> Option<Vec<u32>> {
+        fn duplicates(list: Vec<u32>) -> Vec<(u32, isize)> {
+            let mut map: HashMap<u32, isize> = HashMap::new();
             for item in list {
-                let count = map.get(&item).unwrap_or(&0) + 1;
-                map.insert(item.clone(), count);
+                *map.entry(item).or_default() += 1;
             }
 
             let mut vec: Vec<_> = map.into_iter().collect();
-            vec.sort_by_key(|(_, c)| -c);
+            // Ties are broken by id (the order the components appear in the input), so the search is deterministic.
+            vec.sort_by_key(|(id, c)| (-c, *id));
             vec
         }
 
-        // Add the first component, and from there use DFS? to add new components (probably prioritize ones with multiple
-        // connections?) until we find a division where there is exactly three outgoing connections.
-        fn find_group(mess: &Mess, left: Vec<String>, right: &Vec<String>) -> Option<Vec<String>> {
+        // A group being grown by the search: the components to try adding to it (the one with most connections to the
+        // group first, and the others second), and the ones that were already tried (and thus are left out from then on).
+        struct Frame {
+            left: Vec<u32>,
+            right: Vec<u32>,
+            candidates: Vec<u32>,
+            next: usize,
+            next_right: Vec<u32>,
+        }
+
+        // Starts growing the group, unless we're done: a group (>= 2 items) that can be cut by exactly three connections.
+        fn enter(mess: &Mess, left: Vec<u32>, right: Vec<u32>) -> Result<Vec<u32>, Frame> {
             let connections = mess.get_outgoing_connections(&left);
-            // End condition: we have a group (>= 2 items) that can be cut by exactly three connections.
-            if left.len() >= 2 && connections.len() == 3 { return Some(left) }
-
-            // Otherwise, we include a new component based on the one that has most connections to our group first, and the others second.
-            let mut next_right = right.clone();
-            for (item, _) in duplicates(connections) {
-                if right.contains(&item) { continue; } // already ignored before
-                if let Some(group) = find_group(mess, left.append_item(&item), &next_right) {
-                    return Some(group);
-                }
-                next_right.push(item);
+            if left.len() >= 2 && connections.len() == 3 { return Ok(left) }
+
+            let candidates = duplicates(connections).into_iter().map(|(item, _)| item).collect();
+            Err(Frame { left, next_right: right.clone(), right, candidates, next: 0 })
+        }
+
+        // Add the first component, and from there use DFS to add new components until we find a division where there
+        // are exactly three outgoing connections. The groups can get large, so the DFS keeps its own stack of frames
+        // instead of recursing.
+        let mut stack = match enter(self, vec![*self.components.first()?], vec![]) {
+            Ok(group) => return Some(group),
+            Err(frame) => vec![frame],
+        };
+
+        while let Some(frame) = stack.last_mut() {
+            let Some(offset) = frame.candidates[frame.next..].iter().position(|c| !frame.right.contains(c)) else {
+                // Tried every component, backtrack
+                stack.pop();
+                continue;
+            };
+            let item = frame.candidates[frame.next + offset];
+            frame.next += offset + 1;
+
+            let (left, right) = (frame.left.append_item(&item), frame.next_right.clone());
+            // Should the search with this component fail, it's left out of the rest of this group's searches.
+            frame.next_right.push(item);
+
+            match enter(self, left, right) {
+                Ok(group) => return Some(group),
+                Err(frame) => stack.push(frame),
             }
+        }
+
+        None
+    }
 
-            None
+    // The group on one side of the three wires to cut, found with the given method (see METHOD_OPTION).
+    fn find_cut(&self, method: Option<&str>, rng: &mut Random) -> Result<Option<Vec<u32>>, String> {
+        match method {
+            None | Some("search") => Ok(self.find_group()),
+            Some("karger") => Ok(self.find_group_karger(rng, KARGER_ATTEMPTS)),
+            Some(method) => Err(format!("Unknown method '{}' for {}, expected search or karger", method, METHOD_OPTION)),
+        }
+    }
+
+    // Karger's min-cut: contract randomly picked wires until only two groups of components are left, and see if they
+    // are connected by three wires. Contracting the wires in a random order (skipping the ones within a group) is the
+    // same as picking a random wire every time, and can be done with a union-find.
+    fn find_group_karger(&self, rng: &mut Random, attempts: usize) -> Option<Vec<u32>> {
+        fn root(parents: &mut [u32], mut id: u32) -> u32 {
+            while parents[id as usize] != id {
+                parents[id as usize] = parents[parents[id as usize] as usize];
+                id = parents[id as usize];
+            }
+            id
         }
 
-        let left = find_group(self, vec![self.components[0].clone()], &vec![])?.len();
-        let right = self.components.len() - left;
+        // Component ids are handed out in order, so they can be used as indices.
+        let count = self.components.len();
+        if count < 2 { return None; }
+        let mut order: Vec<&Wire> = self.wires.iter().collect();
 
-        Some(left * right)
+        for attempt in 1..=attempts {
+            rng.shuffle(&mut order);
+            let mut parents: Vec<u32> = (0..count as u32).collect();
+            let mut groups = count;
+            for wire in &order {
+                if groups == 2 { break; }
+                let (left, right) = (root(&mut parents, wire.left), root(&mut parents, wire.right));
+                if left != right {
+                    parents[left as usize] = right;
+                    groups -= 1;
+                }
+            }
+
+            let cut = self.wires.iter().filter(|w| root(&mut parents, w.left) != root(&mut parents, w.right)).count();
+            if cut == 3 {
+                log::debug!("Found the cut after {} attempt(s) (seed {})", attempt, random::seed());
+                let group = root(&mut parents, self.components[0]);
+                return Some(self.components.iter().copied().filter(|id| root(&mut parents, *id) == group).collect());
+            }
+        }
+
+        None
     }
 }
 
 #[derive(Eq, PartialEq, Debug, Clone)]
 struct Wire {
-    left: String,
-    right: String
+    left: u32,
+    right: u32
 }
 
 #[cfg(test)]
 mod tests {
     use crate::days::day25::Mess;
-    use crate::util::collection::VecToString;
+    use crate::days::Example;
+    use crate::util::random::Random;
+
+    pub(super) const EXAMPLES: &[Example] = &[
+        Example { input: TEST_INPUT, puzzle1: Some("54"), puzzle2: None },
+    ];
+
+    fn ids(mess: &Mess, names: &[&str]) -> Vec<u32> {
+        names.iter().map(|n| mess.names.get(n).unwrap()).collect()
+    }
 
     #[test]
     fn test_get_outgoing_connections() {
@@ -98,26 +234,67 @@ mod tests {
 
         // Note: we include double connection to properly count the amount in our search.
         assert_eq!(mess.get_outgoing_connections(
-            &vec!["jqt", "rhn", "xhk", "bvb"].to_string()
-        ), vec!["nvd", "hfx", "cmg", "hfx", "hfx", "ntq", "ntq", "ntq"].to_string());
+            &ids(&mess, &["jqt", "rhn", "xhk", "bvb"])
+        ), ids(&mess, &["nvd", "hfx", "cmg", "hfx", "hfx", "ntq", "ntq", "ntq"]));
         // jqt -> nvd, hfx -> xhk, cmg -> bvb, rhn -> hfx, bvb -> hfx, ntq -> jqt, ntq -> bvb, ntq -> xhk
 
         assert_eq!(mess.get_outgoing_connections(
-            &vec!["jqt", "rhn", "xhk", "bvb", "hfx"].to_string()
-        ), vec!["nvd", "cmg", "pzl", "ntq", "ntq", "ntq", "ntq"].to_string());
+            &ids(&mess, &["jqt", "rhn", "xhk", "bvb", "hfx"])
+        ), ids(&mess, &["nvd", "cmg", "pzl", "ntq", "ntq", "ntq", "ntq"]));
         // jqt -> nvd, cmg -> bvb, pzl -> hfx, ntq -> jqt, ntq -> hfx, ntq -> bvb, ntq -> xhk
 
         assert_eq!(mess.get_outgoing_connections(
-            &vec!["jqt", "rhn", "xhk", "bvb", "hfx", "ntq"].to_string()
-        ), vec!["nvd", "cmg", "pzl"].to_string());
+            &ids(&mess, &["jqt", "rhn", "xhk", "bvb", "hfx", "ntq"])
+        ), ids(&mess, &["nvd", "cmg", "pzl"]));
         // jqt -> nvd, cmg -> bvb, pzl -> hfx
     }
 
     #[test]
     fn test_split_components() {
         let mess: Mess = TEST_INPUT.parse().unwrap();
+        let group = mess.find_group().unwrap();
+
+        assert_eq!(mess.split_components(&group), 54);
+        let mut cut: Vec<_> = mess.get_cut_wires(&group).iter().map(|w| format!("{}/{}", mess.names.name(w.left), mess.names.name(w.right))).collect();
+        cut.sort();
+        assert_eq!(cut, vec!["cmg/bvb", "jqt/nvd", "pzl/hfx"]);
+    }
+
+    #[test]
+    fn test_find_group_karger() {
+        let mess: Mess = TEST_INPUT.parse().unwrap();
+
+        for seed in 0..10 {
+            let group = mess.find_group_karger(&mut Random::new(seed), 1000).unwrap();
+            assert_eq!(mess.split_components(&group), 54);
+            assert_eq!(mess.get_cut_wires(&group).len(), 3);
+        }
+        // When every component has four wires, no three of them can split it
+        let mess: Mess = "aaa: bbb ccc ddd eee\nbbb: ccc ddd eee\nccc: ddd eee\nddd: eee".parse().unwrap();
+        assert_eq!(mess.find_group_karger(&mut Random::new(1), 100), None);
+    }
+
+    #[test]
+    fn test_find_cut() {
+        let mess: Mess = TEST_INPUT.parse().unwrap();
+
+        for method in [None, Some("search"), Some("karger")] {
+            let group = mess.find_cut(method, &mut Random::new(7)).unwrap().unwrap();
+            assert_eq!(mess.split_components(&group), 54);
+        }
+        assert!(mess.find_cut(Some("guess"), &mut Random::new(7)).is_err());
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let mess: Mess = "jqt: rhn xhk\nrhn: xhk".parse().unwrap();
 
-        assert_eq!(mess.split_components(), Some(54));
+        assert_eq!(mess.to_dot(&[&mess.wires[1]]), "\
+            graph day25 {\n  \
+              \"jqt\" -- \"rhn\";\n  \
+              \"jqt\" -- \"xhk\" [color=red, penwidth=3];\n  \
+              \"rhn\" -- \"xhk\";\n\
+            }\n");
     }
 
     const TEST_INPUT: &str = "\
@@ -141,20 +318,19 @@ impl FromStr for Mess {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut components: Vec<String> = vec![];
+        let mut names = Interner::new();
         let mut wires = vec![];
 
         for line in s.lines() {
-            let component = line[0..3].to_string();
-            if !components.contains(&component) { components.push(component.clone()) }
+            let component = names.intern(str_get(line, 0..3)?);
 
-            for connection in line[4..].trim().split(' ').collect::<Vec<_>>() {
-                let other = connection.to_string();
-                if !components.contains(&other) { components.push(other.clone()) }
-                wires.push(Wire { left: component.clone(), right: other.clone() });
+            for connection in str_get(line, 4..)?.trim().split(' ') {
+                wires.push(Wire { left: component, right: names.intern(connection) });
             }
         }
 
-        Ok(Self { components, wires })
+        // Ids are handed out in order, so every component is in there once, in the order they appear in the input.
+        let components = names.iter().map(|(id, _)| id).collect();
+        Ok(Self { names, components, wires })
     }
 }
\ No newline at end of file
@@ -0,0 +1,60 @@
+// The puzzle solvers as a library, so that other tools (benchmark harnesses, a web frontend, ...) can call them directly,
+// e.g. `aoc2023::solve(17, 1, &input)`. The binary in main.rs is just the command line runner around it.
+// The library (not the runner) also builds for wasm32-unknown-unknown: `cargo build --lib --target wasm32-unknown-unknown`.
+
+pub mod days;
+pub mod util;
+
+pub use days::*;
+use util::error::{AocError, AocResult};
+
+// Runs a single puzzle on the given input. This does not touch the filesystem, and does not report progress, so it can
+// be used from a browser playground.
+pub fn solve(day: u8, part: u8, input: &str) -> AocResult<String> {
+    if part != 1 && part != 2 {
+        return Err(AocError::Unsupported(format!("Invalid part {}, expected 1 or 2", part)));
+    }
+    parse_day(day, input)?.part(part)
+}
+
+// Runs both puzzles on the given input, parsing it only once. Fails when the input cannot be parsed, otherwise gives
+// the result of every puzzle (as one can fail while the other works).
+pub fn solve_both(day: u8, input: &str) -> AocResult<(AocResult<String>, AocResult<String>)> {
+    let parsed = parse_day(day, input)?;
+    Ok((parsed.part1(), parsed.part2()))
+}
+
+fn parse_day(day: u8, input: &str) -> AocResult<Box<dyn ParsedInput>> {
+    let implementation = get_day(day as i32)?;
+
+    // The console reporter needs a clock and a terminal, neither of which we can count on here. Same goes for logging.
+    util::progress::set_quiet(true);
+    util::log::set_level(util::log::Level::Quiet);
+    (implementation.parse)(&input.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{solve, solve_both};
+    use crate::util::error::AocError;
+
+    #[test]
+    fn test_solve() {
+        assert_eq!(solve(1, 1, "1abc2\npqr3stu8vwx"), Ok("Puzzle 1: 50".to_string()));
+        assert_eq!(solve(9, 2, "10 13 16 21 30 45"), Ok("Puzzle 2: 5".to_string()));
+
+        assert!(matches!(solve(0, 1, ""), Err(AocError::Unsupported(_))));
+        assert!(matches!(solve(26, 1, ""), Err(AocError::Unsupported(_))));
+        assert!(matches!(solve(1, 3, ""), Err(AocError::Unsupported(_))));
+        assert!(matches!(solve(2, 1, "Game one: 3 blue"), Err(AocError::Parse(_))));
+        assert!(matches!(solve(9, 1, "1 2 x"), Err(AocError::Parse(_))));
+    }
+
+    #[test]
+    fn test_solve_both() {
+        assert_eq!(solve_both(9, "10 13 16 21 30 45"), Ok((Ok("Puzzle 1: 68".to_string()), Ok("Puzzle 2: 5".to_string()))));
+
+        assert!(matches!(solve_both(26, ""), Err(AocError::Unsupported(_))));
+        assert!(matches!(solve_both(9, "1 2 x"), Err(AocError::Parse(_))));
+    }
+}
@@ -1,26 +1,243 @@
 extern crate core;
 
-mod days;
-mod util;
-
 use std::env::args;
-use days::{get_day, Day};
-use util::input::{read_input};
-use util::number::{parse_i32};
+use std::panic::{self, AssertUnwindSafe};
+use std::fs::{read_to_string, write};
+use std::io::{stdin, stdout, BufRead, Write};
+use std::time::{Duration, Instant};
+use serde_json::{json, Map, Value};
+use aoc2023::{days, util};
+use days::{get_day, implemented_days, Day};
+use util::input::{list_inputs, read_input_from, InputSource};
+use util::number::parse_num;
+use util::diff::pretty_diff;
+use util::stats::{format_bytes, DurationStats};
+use util::date::Date;
+use util::answers::{self, AnswerCache, ANSWERS_FILE};
+use util::timings::{self, TimingRecord};
+use util::submit::{self, Outcome};
+use util::error::{AocError, AocResult};
+use util::log::Level;
+use util::profile::Span;
 
 fn print_usage()
 {
     eprintln!("
-Usage: cargo run <command> [<command_arg>, ...]
+Usage: cargo run <command> [<command_arg>, ...] [--<option>, ...]
 
 Commands:
     day <day number> - run the puzzles for the given day.
-    add <day number> - add base files and wiring for a new day.
+    day today (or just: today) - run the puzzles for today's day during the event (using the CET date).
+    add <day number> (or: new-day <day number>) - add base files and wiring for a new day, from resources/day.rs.hbs.
+    debug <day number> - step through the puzzle interactively (only day 20: button presses and signals).
+
+Options:
+    --exact - recompute overflow-prone results with arbitrary precision and compare them to the fast path.
+    --bench[=<runs>] - run the puzzles <runs> times (default 10), report min/median/mean durations and store them in benchmarks.json.
+                       When built with `--features memory`, the peak memory usage of each puzzle is reported as well.
+                       The median durations are appended to target/aoc-timings.jsonl, and compared to the previous run there.
+    --check - compare the answers against the ones recorded in answers.json (recording them when missing).
+    --submit <part> - submit the answer to the given part (1 or 2) to adventofcode.com, after asking for confirmation. Needs the
+                      session cookie of the site in AOC_SESSION or .aoc-session, the outcome is recorded in answers.json.
+    --input <path> - read the puzzle input from the given file instead of resources/dayXX.txt.
+    --stdin - read the puzzle input from stdin instead of resources/dayXX.txt.
+    --input-dir <dir> - run the puzzles for every input in the given directory named dayXX*.txt (e.g. day05-friend.txt).
+    --compare <path a> <path b> - run the puzzles for both inputs, showing their answers and durations side by side.
+    --visualize[=<ms>] - animate intermediate states in the terminal (days 5, 10, 14, 16, 17, 18 and 22), with a frame delay of <ms> (default 50).
+                         Day 5 shows how its seed ranges get split up and shifted by every map.
+                         Day 10 shows the tiles enclosed by its loop.
+                         Day 18 exports its lagoon to day18-partN.svg instead.
+    --dot <path> - write the puzzle's graph to the given file in the DOT format (graphviz), day 25 highlights the wires to cut.
+    --option <name>=<value> - set a puzzle parameter that is not in the input, e.g. day24.area=7..27 to use the example's test area.
+    --profile <path> - write a trace of how long every day, parse and part (and phases of the heavier days) took to the given
+                       file, in the Chrome tracing format (load it in chrome://tracing or https://ui.perfetto.dev).
+    --seed <n> - seed the randomized algorithms with <n> instead of the default, e.g. day 25 with --option day25.method=karger.
+    --quiet - don't show progress of long-running puzzles, or any other output than the answers (implied by --bench and --check).
+    --verbose - show debug output of the puzzles as well, like the loops they found (written to stderr).
+
+Answers are cached in resources/answers after every run, answers that changed since the previous run are pointed out.
 ");
 }
 
 fn main() {
-    let a: Vec<String> = args().collect();
+    let all_args: Vec<String> = args().collect();
+    let mut a: Vec<String> = vec![];
+    let mut bench_runs: Option<usize> = None;
+    let mut check = false;
+    let mut source = InputSource::Default;
+    let mut input_dir: Option<String> = None;
+    let mut compare: Option<(String, String)> = None;
+    let mut submit_part: Option<u8> = None;
+
+    let mut arg_iter = all_args.into_iter();
+    while let Some(arg) = arg_iter.next() {
+        if !arg.starts_with("--") {
+            a.push(arg);
+            continue;
+        }
+
+        match arg.as_str() {
+            "--exact" => util::exact::enable(),
+            "--check" => check = true,
+            "--quiet" => {
+                util::progress::set_quiet(true);
+                util::log::set_level(Level::Quiet);
+            }
+            "--verbose" => util::log::set_level(Level::Debug),
+            "--option" => {
+                let result = match arg_iter.next() {
+                    Some(option) => util::options::set_from_arg(&option),
+                    None => Err(format!("Missing <name>=<value> for {}", arg)),
+                };
+                if let Err(err) = result {
+                    eprintln!("{}", err);
+                    print_usage();
+                    return;
+                }
+            }
+            "--visualize" => util::visualize::enable(Duration::from_millis(util::visualize::DEFAULT_FRAME_DELAY_MS)),
+            f if f.starts_with("--visualize=") => {
+                match parse_num::<u64>(&f["--visualize=".len()..]) {
+                    Ok(delay) => util::visualize::enable(Duration::from_millis(delay)),
+                    Err(_) => {
+                        eprintln!("Invalid frame delay: {}", arg);
+                        print_usage();
+                        return;
+                    }
+                }
+            }
+            "--dot" => {
+                match arg_iter.next() {
+                    Some(path) => util::dot::enable(&path),
+                    None => {
+                        eprintln!("Missing path for {}", arg);
+                        print_usage();
+                        return;
+                    }
+                }
+            }
+            f if f.starts_with("--dot=") => util::dot::enable(&f["--dot=".len()..]),
+            "--profile" => {
+                match arg_iter.next() {
+                    Some(path) => util::profile::enable(&path),
+                    None => {
+                        eprintln!("Missing path for {}", arg);
+                        print_usage();
+                        return;
+                    }
+                }
+            }
+            f if f.starts_with("--profile=") => util::profile::enable(&f["--profile=".len()..]),
+            "--seed" => {
+                match arg_iter.next().map(|seed| parse_num::<u64>(&seed)) {
+                    Some(Ok(seed)) => util::random::set_seed(seed),
+                    _ => {
+                        eprintln!("Missing or invalid seed for {}", arg);
+                        print_usage();
+                        return;
+                    }
+                }
+            }
+            f if f.starts_with("--seed=") => {
+                match parse_num::<u64>(&f["--seed=".len()..]) {
+                    Ok(seed) => util::random::set_seed(seed),
+                    Err(_) => {
+                        eprintln!("Invalid seed: {}", arg);
+                        print_usage();
+                        return;
+                    }
+                }
+            }
+            "--stdin" => source = InputSource::Stdin,
+            "--input" => {
+                match arg_iter.next() {
+                    Some(path) => source = InputSource::File(path),
+                    None => {
+                        eprintln!("Missing path for {}", arg);
+                        print_usage();
+                        return;
+                    }
+                }
+            }
+            f if f.starts_with("--input=") => source = InputSource::File(f["--input=".len()..].to_string()),
+            "--input-dir" => {
+                match arg_iter.next() {
+                    Some(path) => input_dir = Some(path),
+                    None => {
+                        eprintln!("Missing path for {}", arg);
+                        print_usage();
+                        return;
+                    }
+                }
+            }
+            f if f.starts_with("--input-dir=") => input_dir = Some(f["--input-dir=".len()..].to_string()),
+            "--compare" => {
+                match (arg_iter.next(), arg_iter.next()) {
+                    (Some(a), Some(b)) => compare = Some((a, b)),
+                    _ => {
+                        eprintln!("Missing paths for {}, it needs two inputs", arg);
+                        print_usage();
+                        return;
+                    }
+                }
+            }
+            "--submit" => {
+                match arg_iter.next().map(|part| parse_num::<u8>(&part)) {
+                    Some(Ok(part)) if part == 1 || part == 2 => submit_part = Some(part),
+                    _ => {
+                        eprintln!("Missing or invalid part for {}, it should be 1 or 2", arg);
+                        print_usage();
+                        return;
+                    }
+                }
+            }
+            f if f.starts_with("--submit=") => {
+                match parse_num::<u8>(&f["--submit=".len()..]) {
+                    Ok(part) if part == 1 || part == 2 => submit_part = Some(part),
+                    _ => {
+                        eprintln!("Invalid part: {}, it should be 1 or 2", arg);
+                        print_usage();
+                        return;
+                    }
+                }
+            }
+            "--bench" => bench_runs = Some(DEFAULT_BENCH_RUNS),
+            f if f.starts_with("--bench=") => {
+                match parse_num::<usize>(&f["--bench=".len()..]) {
+                    Ok(runs) if runs > 0 => bench_runs = Some(runs),
+                    _ => {
+                        eprintln!("Invalid number of benchmark runs: {}", arg);
+                        print_usage();
+                        return;
+                    }
+                }
+            }
+            _ => {
+                eprintln!("Unknown option: {}", arg);
+                print_usage();
+                return;
+            }
+        }
+    }
+
+    if check || bench_runs.is_some() {
+        // Progress and log output would only get in the way of the timings/results
+        util::progress::set_quiet(true);
+        util::log::set_level(Level::Quiet);
+    }
+
+    if a.get(1).is_some_and(|c| c == "today") {
+        a.insert(1, "day".to_string());
+    }
+    if a.get(1).is_some_and(|c| c == "day") && a.get(2).is_some_and(|d| d == "today") {
+        match todays_day() {
+            Ok(day) => a[2] = day.to_string(),
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        }
+    }
 
     if a.len() < 3 {
         print_usage();
@@ -28,37 +245,572 @@ fn main() {
     }
 
     match a[1].as_str() {
+        "day" if submit_part.is_some() => {
+            if input_dir.is_some() || compare.is_some() {
+                eprintln!("Can only submit the answer for a single input");
+            } else if let Some(part) = submit_part {
+                submit_day(&a[2], &source, part)
+            }
+        }
+        "day" if compare.is_some() => {
+            if let Some((a_path, b_path)) = &compare {
+                compare_day(&a[2], a_path, b_path)
+            }
+        }
         "day" => {
-            run_day(&a[2])
+            let sources = match &input_dir {
+                Some(dir) => match parse_num::<i32>(&a[2]).and_then(|d| list_inputs(dir, d)) {
+                    Ok(paths) => paths.into_iter().map(InputSource::File).collect(),
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        return;
+                    }
+                },
+                None => vec![source]
+            };
+
+            for source in &sources {
+                if let InputSource::File(path) = source {
+                    if input_dir.is_some() { println!("== {} ==", path); }
+                }
+
+                // A panic for one input (e.g. an assumption not holding for it) should not stop us from running the others
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    match bench_runs {
+                        _ if check => check_day(&a[2], source),
+                        Some(runs) => bench_day(&a[2], source, runs),
+                        None => run_day(&a[2], source)
+                    }
+                }));
+                if result.is_err() {
+                    eprintln!("Failed to run day {} for {:?}", a[2], source);
+                }
+            }
         }
-        "add" => {
+        "add" | "new-day" => {
             add_day(&a[2])
         }
+        "debug" => {
+            debug_day(&a[2], &source)
+        }
         _ => {
             print_usage();
         }
     }
+
+    if let Err(err) = util::profile::write() {
+        eprintln!("{}", err);
+    }
+}
+
+const DEFAULT_BENCH_RUNS: usize = 10;
+const BENCH_FILE: &str = "benchmarks.json";
+
+fn todays_day() -> Result<i32, String> {
+    let today = Date::today_cet()?;
+    let implemented = implemented_days().iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ");
+
+    match today.advent_day() {
+        Some(day) if get_day(day).is_ok() => Ok(day),
+        Some(day) => Err(format!("Day {} has no implementation yet. Implemented days: {}", day, implemented)),
+        None => Err(format!("Today ({}) is not during the event (December 1st to 25th). Implemented days: {}", today, implemented)),
+    }
+}
+
+fn load_day(day_num: &str, source: &InputSource) -> AocResult<(i32, String, Day)> {
+    let d = parse_num::<i32>(day_num)?;
+    let day = get_day(d)?;
+    let input = read_input_from(d, source)?;
+    Ok((d, input, day))
 }
 
-fn run_day(day_num: &str)
+fn run_day(day_num: &str, source: &InputSource)
 {
-    let result: Result<(String, Day), String> = parse_i32(day_num)
-        .and_then(|d| get_day(d).and_then(|day| read_input(d).and_then(|input| Ok((input, day)))));
+    match load_day(day_num, source) {
+        Ok((day_number, input, day)) => {
+            let _span = Span::begin(|| format!("day {:02}", day_number));
+            // Both puzzles are solved from the same parsed input
+            let parsed = match Span::begin(|| "parse".to_string()).run(|| (day.parse)(&input)) {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    eprintln!("Parsing the input failed: {}", err);
+                    return;
+                }
+            };
+
+            let mut answers = vec![];
+            for part in [1, 2] {
+                let answer = Span::begin(|| format!("part {}", part)).run(|| parsed.part(part));
+                util::progress::reporter().finish();
+                match answer {
+                    Ok(answer) => {
+                        println!("{}", answer);
+                        answers.push(answer);
+                    }
+                    Err(err) => eprintln!("Puzzle {} failed: {}", part, err),
+                }
+            }
+            // Answers are cached by part, so only when we have all of them
+            if answers.len() == 2 {
+                update_answer_cache(day_number, source, &answers);
+            }
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+        }
+    }
+}
+
+// Runs (and times) the given step of solving a day, turning a panic into an error as well.
+fn run_timed<T>(step: impl FnOnce() -> AocResult<T>) -> (Result<T, String>, Duration) {
+    let start = Instant::now();
+    let result = panic::catch_unwind(AssertUnwindSafe(step));
+    let elapsed = start.elapsed();
+    util::progress::reporter().finish();
+
     match result {
-        Ok((input, day)) => {
-            (day.puzzle1)(&input);
-            (day.puzzle2)(&input);
+        Ok(Ok(value)) => (Ok(value), elapsed),
+        Ok(Err(err)) => (Err(err.to_string()), elapsed),
+        Err(_) => (Err("panicked".to_string()), elapsed),
+    }
+}
+
+fn compare_day(day_num: &str, a_path: &str, b_path: &str)
+{
+    let day = match parse_num::<i32>(day_num).and_then(get_day) {
+        Ok(day) => day,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+    let mut inputs = vec![];
+    for path in [a_path, b_path] {
+        match parse_num::<i32>(day_num).and_then(|d| read_input_from(d, &InputSource::File(path.to_string()))) {
+            Ok(input) => inputs.push((path, input)),
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        }
+    }
+
+    // A failure (or panic) for one input should still show us what the other does
+    println!("== parse ==");
+    let mut parsed = vec![];
+    let mut durations = vec![];
+    for (path, input) in &inputs {
+        let (result, elapsed) = run_timed(|| (day.parse)(input));
+        match &result {
+            Ok(_) => println!("{} ({:?}): ok", path, elapsed),
+            Err(err) => println!("{} ({:?}): FAILED: {}", path, elapsed, err),
+        }
+        parsed.push(result);
+        durations.push(elapsed);
+    }
+    println!("{}", compare_durations(b_path, durations[1], durations[0]));
+
+    for part in [1, 2] {
+        println!("== puzzle{} ==", part);
+
+        let mut durations = vec![];
+        for ((path, _), parsed) in inputs.iter().zip(&parsed) {
+            let (result, elapsed) = match parsed {
+                Ok(parsed) => run_timed(|| parsed.part(part)),
+                Err(_) => (Err("could not parse the input".to_string()), Duration::ZERO),
+            };
+
+            let answer = result.unwrap_or_else(|err| format!("FAILED: {}", err));
+            println!("{} ({:?}):\n  {}", path, elapsed, answer.replace('\n', "\n  "));
+            durations.push(elapsed);
+        }
+
+        println!("{}", compare_durations(b_path, durations[1], durations[0]));
+    }
+}
+
+fn compare_durations(name: &str, duration: Duration, other: Duration) -> String {
+    match (as_millis(duration), as_millis(other)) {
+        (_, 0.0) => format!("{} took {:?}", name, duration),
+        (ms, other_ms) if ms >= other_ms => format!("{} took {:.2}x as long", name, ms / other_ms),
+        (ms, other_ms) => format!("{} was {:.2}x as fast", name, other_ms / ms.max(f64::MIN_POSITIVE)),
+    }
+}
+
+fn bench_day(day_num: &str, source: &InputSource, runs: usize)
+{
+    let (day_number, input, day) = match load_day(day_num, source) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    let mut results = Map::new();
+
+    // Parsing is timed on its own, the puzzles are then all solved from the same parsed input.
+    let (parsed, samples, peak_bytes) = bench_step(runs, || (day.parse)(&input));
+    let parsed = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("Parsing the input failed: {}", err);
+            return;
+        }
+    };
+    println!("Parsed the input");
+    results.insert("parse".to_string(), bench_result(&samples, peak_bytes));
+
+    for part in [1, 2] {
+        let (answer, samples, peak_bytes) = bench_step(runs, || parsed.part(part));
+        let answer = match answer {
+            Ok(answer) => answer,
+            Err(err) => {
+                // Timings of a failing puzzle don't mean much
+                eprintln!("puzzle{} failed: {}", part, err);
+                continue;
+            }
+        };
+
+        println!("{}", answer);
+        results.insert(format!("puzzle{}", part), bench_result(&samples, peak_bytes));
+    }
+
+    let key = format!("day{:02}{}", day_number, source.suffix());
+    record_timings(&key, &results);
+
+    // Merge with earlier results, so that benchmarking one day keeps the numbers for the others
+    let mut benchmarks = read_json_file(BENCH_FILE);
+    benchmarks.insert(key, Value::Object(results));
+    write_json_file(BENCH_FILE, &benchmarks);
+}
+
+// Appends the median durations to the timing history, showing how they compare to the previous run of the same day.
+fn record_timings(key: &str, results: &Map<String, Value>) {
+    let steps = results.iter()
+        .filter_map(|(step, result)| Some((step.clone(), result.get("median_ms")?.as_f64()?)))
+        .collect();
+    let record = TimingRecord::new(key, steps);
+
+    if let Some(previous) = timings::last_record(timings::HISTORY_FILE, key) {
+        println!("Compared to the previous run ({}):", previous.commit.as_deref().unwrap_or("unknown commit"));
+        let (lines, regressed) = record.compare(&previous, timings::REGRESSION_THRESHOLD);
+        for line in lines {
+            println!("  {}", line);
+        }
+        if regressed {
+            println!("Warning: some steps got more than {:.0}% slower!", (timings::REGRESSION_THRESHOLD - 1.0) * 100.0);
+        }
+    }
+
+    if let Err(err) = timings::append_record(timings::HISTORY_FILE, &record) {
+        eprintln!("{}", err);
+    }
+}
+
+// Runs the step the given number of times (stopping at the first error), returning the last result, the duration of
+// every run, and the peak memory usage if measured.
+fn bench_step<T>(runs: usize, step: impl Fn() -> AocResult<T>) -> (AocResult<T>, Vec<Duration>, Option<usize>) {
+    let mut samples = vec![];
+    let mut result = None;
+    let mut peak_bytes: Option<usize> = None;
+    for _ in 0..runs {
+        let start = Instant::now();
+        let (run_result, run_peak) = run_measured(&step);
+        samples.push(start.elapsed());
+        peak_bytes = peak_bytes.max(run_peak);
+        let failed = run_result.is_err();
+        result = Some(run_result);
+        if failed { break; }
+    }
+    // runs is never 0, so we always have a result
+    (result.unwrap(), samples, peak_bytes)
+}
+
+fn bench_result(samples: &[Duration], peak_bytes: Option<usize>) -> Value {
+    // runs is never 0, so we always have stats
+    let stats = DurationStats::from_samples(samples).unwrap();
+    println!("  {} runs: min {:?}, median {:?}, mean {:?}", stats.runs, stats.min, stats.median, stats.mean);
+
+    let mut result = json!({
+        "runs": stats.runs,
+        "min_ms": as_millis(stats.min),
+        "median_ms": as_millis(stats.median),
+        "mean_ms": as_millis(stats.mean),
+    });
+    if let Some(peak) = peak_bytes {
+        println!("  peak memory: {}", format_bytes(peak));
+        result["peak_bytes"] = json!(peak);
+    }
+    result
+}
+
+// Runs the step, returning its result and (when built with the memory feature) the peak memory it used.
+#[cfg(feature = "memory")]
+fn run_measured<T>(step: impl Fn() -> T) -> (T, Option<usize>) {
+    let (result, peak) = util::allocations::measure_peak_memory(step);
+    (result, Some(peak))
+}
+
+#[cfg(not(feature = "memory"))]
+fn run_measured<T>(step: impl Fn() -> T) -> (T, Option<usize>) {
+    (step(), None)
+}
+
+fn check_day(day_num: &str, source: &InputSource)
+{
+    let (day_number, input, day) = match load_day(day_num, source) {
+        Ok(loaded) => loaded,
+        // Without an input there is nothing to check, which is not a failure of the day itself.
+        Err(AocError::Io(err)) => {
+            eprintln!("Skipping day {}: {}", day_num, err);
+            return;
         }
         Err(err) => {
             eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let start = Instant::now();
+    let parsed = match (day.parse)(&input) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            println!("parse: FAILED ({:?})\n{}", start.elapsed(), err);
+            std::process::exit(1);
         }
+    };
+
+    let mut answers = read_json_file(ANSWERS_FILE);
+    let key = format!("day{:02}{}", day_number, source.suffix());
+    let mut recorded = answers.get(&key).and_then(|v| v.as_object()).cloned().unwrap_or_default();
+    let mut failed = false;
+    let mut actual_answers = vec![];
+
+    for (name, part) in [("puzzle1", 1), ("puzzle2", 2)] {
+        let start = Instant::now();
+        let result = parsed.part(part);
+        let elapsed = start.elapsed();
+        let actual = match result {
+            Ok(actual) => actual,
+            Err(err) => {
+                failed = true;
+                println!("{}: FAILED ({:?})\n{}", name, elapsed, err);
+                continue;
+            }
+        };
+        actual_answers.push(actual.clone());
+
+        match recorded.get(name).and_then(|v| v.as_str()) {
+            Some(expected) if expected == actual => println!("{}: ok ({:?})", name, elapsed),
+            Some(expected) => {
+                failed = true;
+                println!("{}: MISMATCH ({:?})\n{}", name, elapsed, pretty_diff(expected, &actual));
+            }
+            None => {
+                println!("{}: no recorded answer, recording ({:?})\n{}", name, elapsed, actual);
+                recorded.insert(name.to_string(), Value::String(actual));
+            }
+        }
+    }
+
+    answers.insert(key, Value::Object(recorded));
+    write_json_file(ANSWERS_FILE, &answers);
+
+    if failed {
+        std::process::exit(1);
+    }
+    update_answer_cache(day_number, source, &actual_answers);
+}
+
+// Records the answers in the local answer cache, pointing out the ones that changed since the previous run.
+fn update_answer_cache(day_number: i32, source: &InputSource, answers: &[String]) {
+    // Whatever comes in through stdin can differ every time
+    if *source == InputSource::Stdin { return; }
+
+    let mut cache = AnswerCache::load(answers::CACHE_FILE);
+    for change in cache.update(day_number, &format!("day{:02}{}", day_number, source.suffix()), answers) {
+        println!("!! {}", change);
+    }
+    if let Err(err) = cache.store(answers::CACHE_FILE) {
+        eprintln!("{}", err);
+    }
+}
+
+// Solves a part, and submits its answer after confirmation. Every submission is recorded in answers.json (under
+// "submissions"), so the same answer is never judged twice, and the answer is recorded there once it's correct.
+fn submit_day(day_num: &str, source: &InputSource, part: u8)
+{
+    // The site only knows the answers for the account's own input, and stdin is needed to confirm
+    if *source != InputSource::Default {
+        eprintln!("Can only submit the answers for the default input (resources/dayXX.txt)");
+        return;
+    }
+
+    let (day_number, input, day) = match load_day(day_num, source) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+    if day_number == 25 && part == 2 {
+        eprintln!("Day 25 part 2 has no answer, its star is awarded for having all the others");
+        return;
     }
+    let output = match (day.parse)(&input).and_then(|parsed| parsed.part(part)) {
+        Ok(output) => output,
+        Err(err) => {
+            eprintln!("Puzzle {} failed: {}", part, err);
+            return;
+        }
+    };
+    println!("{}", output);
+
+    let Some(answer) = submit::extract_answer(&output) else {
+        eprintln!("There is no number in the output to submit");
+        return;
+    };
+
+    let mut answers = read_json_file(ANSWERS_FILE);
+    let key = format!("day{:02}", day_number);
+    let mut recorded = answers.get(&key).and_then(|v| v.as_object()).cloned().unwrap_or_default();
+    let mut submissions = recorded.get("submissions").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+
+    let judged = submissions.iter().find(|s| s["part"] == json!(part) && s["answer"] == json!(answer) && s["final"] == json!(true));
+    if let Some(previous) = judged {
+        println!("{} was already submitted for day {} part {}: {}", answer, day_number, part, previous["outcome"].as_str().unwrap_or("?"));
+        return;
+    }
+
+    let session = match submit::session_token() {
+        Ok(session) => session,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    eprint!("Submit {} as the answer to day {} part {} of {}? Type 'yes' to submit: ", answer, day_number, part, submit::YEAR);
+    let mut confirmation = String::new();
+    if stdin().lock().read_line(&mut confirmation).is_err() || confirmation.trim() != "yes" {
+        println!("Not submitted");
+        return;
+    }
+
+    let outcome = match submit::submit(day_number, part, &answer, &session) {
+        Ok(outcome) => outcome,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+    println!("{}: {}", answer, outcome);
+
+    submissions.push(json!({ "part": part, "answer": answer, "outcome": outcome.to_string(), "final": outcome.is_final() }));
+    recorded.insert("submissions".to_string(), Value::Array(submissions));
+    if outcome == Outcome::Correct {
+        recorded.insert(format!("puzzle{}", part), Value::String(output));
+    }
+    answers.insert(key, Value::Object(recorded));
+    write_json_file(ANSWERS_FILE, &answers);
+}
+
+fn debug_day(day_num: &str, source: &InputSource)
+{
+    // Commands are read from stdin, so the input can't come from there as well
+    if *source == InputSource::Stdin {
+        eprintln!("Cannot debug with input from stdin, use --input instead");
+        return;
+    }
+
+    let debugger = parse_num::<i32>(day_num).and_then(|d| match d {
+        20 => Ok(days::day20::Debugger::new(&read_input_from(d, source)?)?),
+        _ => Err(AocError::Unsupported(format!("Day {} has no debug mode", d)))
+    });
+    let mut debugger = match debugger {
+        Ok(debugger) => debugger,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    const HELP: &str = "\
+Commands:
+    press [n] (or p) - finish the current button press, or press the button (n times).
+    step [n] (or s) - process the next (n) signal(s), pressing the button when nothing is queued.
+    queue (or q) - show the queued signals.
+    state - show the state of all flip-flops and conjunctions.
+    help - show this help.
+    exit (or quit) - stop debugging.";
+    println!("{}", HELP);
+
+    loop {
+        print!("[{} presses] > ", debugger.presses());
+        let _ = stdout().flush();
+
+        let mut line = String::new();
+        match stdin().lock().read_line(&mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+
+        let words: Vec<_> = line.split_whitespace().collect();
+        let count = match words.get(1).map(|n| parse_num::<usize>(n)) {
+            None => 1,
+            Some(Ok(n)) => n,
+            Some(Err(err)) => {
+                eprintln!("{}", err);
+                continue;
+            }
+        };
+
+        match words.first().copied() {
+            None => continue,
+            Some("p") | Some("press") | Some("s") | Some("step") => {
+                let before = debugger.snapshot();
+                for _ in 0..count {
+                    match words[0] {
+                        "p" | "press" => debugger.press().iter().for_each(|s| println!("{}", s)),
+                        _ => println!("{}", debugger.step()),
+                    }
+                }
+                for change in before.diff(&debugger.snapshot()) {
+                    println!("  {}", change);
+                }
+            }
+            Some("q") | Some("queue") => debugger.queue().iter().for_each(|s| println!("{}", s)),
+            Some("state") => println!("{}", debugger.snapshot()),
+            Some("help") => println!("{}", HELP),
+            Some("exit") | Some("quit") => return,
+            Some(command) => eprintln!("Unknown command: {}, use help to list the commands", command),
+        }
+    }
+}
+
+fn read_json_file(path: &str) -> Map<String, Value> {
+    read_to_string(path).ok()
+        .and_then(|s| serde_json::from_str::<Map<String, Value>>(&s).ok())
+        .unwrap_or_default()
+}
+
+fn write_json_file(path: &str, content: &Map<String, Value>) {
+    let written = serde_json::to_string_pretty(content).map_err(|e| format!("{}", e))
+        .and_then(|json| write(path, json).map_err(|e| format!("{}", e)));
+    if let Err(err) = written {
+        eprintln!("Could not write {}: {}", path, err);
+    }
+}
+
+fn as_millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
 }
 
 fn add_day(input: &str)
 {
     // This is going to be fun. Write code to modify the running code! Woohoo!
-    match parse_i32(input) {
+    match parse_num::<i32>(input) {
         Ok(day) => {
             match util::create_day::create_day(day) {
                 Ok(_) => { println!("Successfully added day {}", day); }
@@ -1,6 +1,40 @@
+#[cfg(not(target_arch = "wasm32"))]
 pub mod input;
 pub mod number;
+pub mod error;
 pub mod geometry;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod create_day;
 pub mod collection;
-pub mod parser;
\ No newline at end of file
+pub mod parser;
+pub mod exact;
+pub mod simulation;
+pub mod hash;
+pub mod stats;
+pub mod cycles;
+pub mod diff;
+pub mod progress;
+pub mod color;
+pub mod visualize;
+pub mod polygon;
+pub mod scanner;
+pub mod date;
+pub mod graph;
+pub mod bitset;
+pub mod dot;
+pub mod log;
+pub mod options;
+pub mod interner;
+pub mod multiset;
+pub mod random;
+pub mod profile;
+pub mod sequence;
+pub mod threads;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod answers;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod timings;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod submit;
+#[cfg(any(test, feature = "memory"))]
+pub mod allocations;
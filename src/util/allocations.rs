@@ -0,0 +1,104 @@
+// Allocation counting: a global allocator that counts the allocations made by the current thread, so that hot loops
+// can be given an allocation budget in tests. (Tests run in parallel, hence counting per thread.)
+// It also keeps track of the bytes in use by the whole process, which the runner uses to report the peak memory of a
+// puzzle when built with the `memory` feature.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+static CURRENT_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+fn count_allocation() {
+    // The thread local might already be gone while a thread is shutting down, those allocations we don't care about.
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+fn grow(bytes: usize) {
+    let current = CURRENT_BYTES.fetch_add(bytes, Ordering::Relaxed) + bytes;
+    PEAK_BYTES.fetch_max(current, Ordering::Relaxed);
+}
+
+fn shrink(bytes: usize) {
+    CURRENT_BYTES.fetch_sub(bytes, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        grow(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        shrink(layout.size());
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        grow(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        if new_size > layout.size() { grow(new_size - layout.size()) } else { shrink(layout.size() - new_size) }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// Runs the given function, returning its result and the number of allocations (including reallocations) it made.
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = ALLOCATIONS.with(|count| count.get());
+    let result = f();
+    let after = ALLOCATIONS.with(|count| count.get());
+    (result, after - before)
+}
+
+// Runs the given function, returning its result and the peak number of bytes allocated on top of what was already in
+// use when it started. This is process-wide, so anything running in parallel is counted as well.
+pub fn measure_peak_memory<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let baseline = CURRENT_BYTES.load(Ordering::Relaxed);
+    PEAK_BYTES.store(baseline, Ordering::Relaxed);
+    let result = f();
+    let peak = PEAK_BYTES.load(Ordering::Relaxed);
+    (result, peak.saturating_sub(baseline))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::allocations::{count_allocations, measure_peak_memory};
+
+    #[test]
+    fn test_count_allocations() {
+        let (_, count) = count_allocations(|| 1 + 1);
+        assert_eq!(count, 0);
+
+        let (result, count) = count_allocations(|| (0..10).map(|i| vec![i]).collect::<Vec<_>>());
+        assert_eq!(result.len(), 10);
+        assert_eq!(count, 11);
+    }
+
+    #[test]
+    fn test_measure_peak_memory() {
+        let (length, peak) = measure_peak_memory(|| {
+            let large = vec![0u8; 1 << 20];
+            large.len()
+        });
+        assert_eq!(length, 1 << 20);
+        // Other tests may allocate in parallel, so we can only check the lower bound
+        assert!(peak >= 1 << 20, "Expected a peak of at least 1MiB, got {} bytes", peak);
+    }
+}
@@ -0,0 +1,84 @@
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::Path;
+use serde_json::{Map, Value};
+
+// The recorded answers for the inputs in resources, by day (e.g. "day05") and part (e.g. "puzzle1"). Recorded by --check
+// and by correct submissions, and checked by --check and tests/real_inputs.rs. Update them when a change in a day's
+// output is intended.
+pub const ANSWERS_FILE: &str = "answers.json";
+
+// Answers of earlier successful runs, so we can point out when refactoring a day changes its answers. Unlike the
+// answers in ANSWERS_FILE, this is just a local cache (resources/answers is git-ignored), updated on every run.
+
+pub const CACHE_FILE: &str = "resources/answers/cache.json";
+
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct AnswerCache {
+    answers: Map<String, Value>,
+}
+
+impl AnswerCache {
+    // Loads the cache from the given file, starting with an empty cache if it's missing or unreadable.
+    pub fn load(path: &str) -> AnswerCache {
+        let answers = read_to_string(path).ok()
+            .and_then(|s| serde_json::from_str::<Map<String, Value>>(&s).ok())
+            .unwrap_or_default();
+        AnswerCache { answers }
+    }
+
+    pub fn store(&self, path: &str) -> Result<(), String> {
+        if let Some(dir) = Path::new(path).parent() {
+            create_dir_all(dir).map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+        }
+        let json = serde_json::to_string_pretty(&self.answers).map_err(|e| format!("{}", e))?;
+        write(path, json).map_err(|e| format!("Could not write {}: {}", path, e))
+    }
+
+    pub fn get(&self, key: &str, part: usize) -> Option<&str> {
+        self.answers.get(key)?.get(format!("puzzle{}", part))?.as_str()
+    }
+
+    // Stores the answers (for part 1, 2, ...) of the given day, returning a message for every answer that changed
+    // since the last time. The key identifies the input the answers are for.
+    pub fn update(&mut self, day: i32, key: &str, answers: &[String]) -> Vec<String> {
+        let mut changes = vec![];
+        let mut recorded = Map::new();
+
+        for (index, answer) in answers.iter().enumerate() {
+            let part = index + 1;
+            if let Some(previous) = self.get(key, part).filter(|previous| answer.ne(previous)) {
+                changes.push(format!("day {} part {} changed from {} to {}", day, part, previous, answer));
+            }
+            recorded.insert(format!("puzzle{}", part), Value::String(answer.clone()));
+        }
+
+        self.answers.insert(key.to_string(), Value::Object(recorded));
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::answers::AnswerCache;
+
+    #[test]
+    fn test_update() {
+        let mut cache = AnswerCache::default();
+
+        assert!(cache.update(12, "day12", &["Puzzle 1: 21".to_string(), "Puzzle 2: 525152".to_string()]).is_empty());
+        assert_eq!(cache.get("day12", 1), Some("Puzzle 1: 21"));
+        assert_eq!(cache.get("day12", 3), None);
+        assert_eq!(cache.get("day13", 1), None);
+
+        assert!(cache.update(12, "day12", &["Puzzle 1: 21".to_string(), "Puzzle 2: 525152".to_string()]).is_empty());
+        assert_eq!(
+            cache.update(12, "day12", &["Puzzle 1: 21".to_string(), "Puzzle 2: 525153".to_string()]),
+            vec!["day 12 part 2 changed from Puzzle 2: 525152 to Puzzle 2: 525153"]
+        );
+        assert_eq!(cache.get("day12", 2), Some("Puzzle 2: 525153"));
+
+        // Other inputs for the same day are kept apart:
+        assert!(cache.update(12, "day12:other.txt", &["Puzzle 1: 1".to_string(), "Puzzle 2: 2".to_string()]).is_empty());
+        assert_eq!(cache.get("day12", 1), Some("Puzzle 1: 21"));
+    }
+}
@@ -0,0 +1,92 @@
+use crate::util::geometry::{Bounds, Point};
+
+// A set of points within known bounds, stored as one bit per point. Way cheaper than a HashSet<Point> to insert into,
+// look up and combine, for puzzles that mark a lot of grid tiles (day 16's energized tiles, day 23's visited tiles, ...).
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct PointSet {
+    bounds: Bounds,
+    bits: Vec<u64>,
+}
+
+impl PointSet {
+    pub fn new(bounds: Bounds) -> Self {
+        Self { bounds, bits: vec![0; (bounds.width * bounds.height).div_ceil(64)] }
+    }
+
+    fn index(&self, point: &Point) -> Option<usize> {
+        if !self.bounds.contains(point) { return None; }
+        Some((point.y - self.bounds.top) as usize * self.bounds.width + (point.x - self.bounds.left) as usize)
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        self.index(point).is_some_and(|index| self.bits[index / 64] & (1 << (index % 64)) != 0)
+    }
+
+    // Adds the point, returning whether it wasn't in the set yet (like HashSet::insert).
+    // Note: panics when the point is outside the bounds of the set, as it cannot be stored.
+    pub fn insert(&mut self, point: Point) -> bool {
+        let index = self.index(&point).unwrap_or_else(|| panic!("Point {} is outside of the set's bounds", point));
+        let mask = 1 << (index % 64);
+        let is_new = self.bits[index / 64] & mask == 0;
+        self.bits[index / 64] |= mask;
+        is_new
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.iter().map(|bits| bits.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|bits| *bits == 0)
+    }
+
+    // Note: combining sets only works for sets with the same bounds, anything else panics.
+    pub fn union_with(&mut self, other: &PointSet) {
+        assert_eq!(self.bounds, other.bounds, "Cannot combine point sets with different bounds");
+        self.bits.iter_mut().zip(&other.bits).for_each(|(bits, other)| *bits |= other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::bitset::PointSet;
+    use crate::util::geometry::{Bounds, Point};
+
+    #[test]
+    fn test_insert() {
+        let mut set = PointSet::new(Bounds { top: -2, left: 3, width: 10, height: 10 });
+
+        assert!(set.is_empty());
+        assert!(set.insert(Point { x: 3, y: -2 }));
+        assert!(set.insert(Point { x: 12, y: 7 }));
+        assert!(!set.insert(Point { x: 3, y: -2 }));
+        assert_eq!(set.len(), 2);
+        assert!(!set.is_empty());
+
+        assert!(set.contains(&Point { x: 12, y: 7 }));
+        assert!(!set.contains(&Point { x: 11, y: 7 }));
+        assert!(!set.contains(&Point { x: 0, y: 0 }));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_insert_out_of_bounds() {
+        PointSet::new(Bounds::from_size(5, 5)).insert(Point { x: 5, y: 0 });
+    }
+
+    #[test]
+    fn test_union_with() {
+        let bounds = Bounds::from_size(100, 3);
+        let mut a = PointSet::new(bounds);
+        let mut b = PointSet::new(bounds);
+        for x in 0..80 { a.insert(Point { x, y: 1 }); }
+        for x in 50..100 { b.insert(Point { x, y: 1 }); }
+        b.insert(Point { x: 0, y: 2 });
+
+        a.union_with(&b);
+        assert_eq!(a.len(), 101);
+        assert!(a.contains(&Point { x: 99, y: 1 }));
+        assert!(a.contains(&Point { x: 0, y: 2 }));
+        assert!(!a.contains(&Point { x: 1, y: 2 }));
+    }
+}
@@ -0,0 +1,30 @@
+// ANSI terminal colors, for highlighting parts of (debug) output.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum AnsiColor {
+    Red,
+    Yellow,
+}
+
+impl AnsiColor {
+    fn code(&self) -> u8 {
+        match self {
+            AnsiColor::Red => 31,
+            AnsiColor::Yellow => 33,
+        }
+    }
+
+    pub fn paint(&self, text: &str) -> String {
+        format!("\x1b[{}m{}\x1b[0m", self.code(), text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::color::AnsiColor;
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(AnsiColor::Red.paint("#"), "\x1b[31m#\x1b[0m");
+        assert_eq!(AnsiColor::Yellow.paint("abc"), "\x1b[33mabc\x1b[0m");
+    }
+}
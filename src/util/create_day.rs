@@ -1,3 +1,7 @@
+// Scaffolding for a new day (`cargo run add <day>` or `cargo run new-day <day>`): the source file rendered from
+// resources/day.rs.hbs, its registration in src/days.rs and tests/real_inputs.rs (at their « add day ... » markers),
+// and an empty input file to paste the puzzle input into.
+
 use crate::days::get_day;
 use std::fs::{read_to_string, write,};
 use std::path::{Path};
@@ -6,74 +10,136 @@ use regex::{Captures, RegexBuilder};
 use handlebars::{Handlebars};
 use serde_json::{json};
 
+const TEMPLATE_FILE: &str = "resources/day.rs.hbs";
+const DAYS_MODULE_FILE: &str = "src/days.rs";
+const REAL_INPUTS_TEST_FILE: &str = "tests/real_inputs.rs";
+
 pub fn create_day(day: i32) -> Result<(), String> {
-    match get_day(day) {
-        Err(_) => {
-            let main_dir = match current_dir() {
-                Ok(dir) => dir,
-                Err(e) => { return Err(format!("Could not get working directory: {}", e)); }
-            };
-            let source_file_name = format!("src/days/day{:02}.rs", day);
-            let source_path = main_dir.join(Path::new(&source_file_name));
-            let template_file_name = format!("resources/day{:02}.txt", day);
-            let input_path = main_dir.join(Path::new(&template_file_name));
-            let module_file_name = "src/days.rs".to_string();
-            let module_path = main_dir.join(Path::new(&module_file_name));
-
-            if source_path.exists() {
-                return Err(format!("Source file for day {} already exists.", day));
-            }
-            if input_path.exists() {
-                return Err(format!("Input file for day {} already exists.", day));
-            }
-
-            let template = match read_to_string(main_dir.join("resources/day.rs.hbs")) {
-                Ok(v) => { v }
-                Err(e) => { return Err(format!("Could not read day template: {}", e)); }
-            };
-            let days_mod_content = match read_to_string(&module_path) {
-                Ok(v) => { v }
-                Err(e) => { return Err(format!("Could not read days module file: {}", e)); }
-            };
-
-            let import_regex = match RegexBuilder::new("^(\\s*)(// « add day import »)").multi_line(true).build() {
-                Ok(r) => { r }
-                Err(e) => { return Err(format!("{}", e)); }
-            };
-            let match_regex = match RegexBuilder::new("^(\\s*)(// « add day match »)").multi_line(true).build() {
-                Ok(r) => { r }
-                Err(e) => { return Err(format!("{}", e)); }
-            };
-
-            if !import_regex.is_match(days_mod_content.as_str()) {
-                println!("{}", days_mod_content);
-                return Err("Could not find import comment in days module".to_string());
-            }
-            if !match_regex.is_match(days_mod_content.as_str()) {
-                return Err("Could not find match comment in days module".to_string());
-            }
-
-            let res1 = import_regex.replace(days_mod_content.as_str(), |caps: &Captures| {
-                format!("{ws}mod day{day:02};\n{ws}use day{day:02}::DAY{day};\n{ws}{comm}", ws = &caps[1], comm = &caps[2], day = day)
-            });
-            let module_content = match_regex.replace(res1.as_ref(), |caps: &Captures| {
-                format!("{ws}{day} => Ok(DAY{day}),\n{ws}{comm}", ws = &caps[1], comm = &caps[2], day = day)
-            });
-
-            let handlebars = Handlebars::new();
-            let day_content = match handlebars.render_template(template.as_str(), &json!({ "day": day })) {
-                Ok(v) => { v }
-                Err(e) => { return Err(format!("{}", e)); }
-            };
-
-            match write(&input_path, "TODO: Add Content Here") { Err(e) => { return Err(format!("Could not write input file: {:?}\nError: {}", input_path, e)); }, _ => {} }
-            match write(&source_path, day_content) { Err(e) => { return Err(format!("Could not write day file: {:?}\nError: {}", source_path, e)); }, _ => {} }
-            match write(&module_path, module_content.to_string()) { Err(e) => { return Err(format!("Could not write module file: {:?}\nError: {}", module_path, e)); }, _ => {} }
-
-            Ok(())
-        }
-        Ok(_) => {
-            Err(format!("Day {} already exists!", day))
+    if day < 1 {
+        return Err(format!("Invalid day {}", day));
+    }
+    if get_day(day).is_ok() {
+        return Err(format!("Day {} already exists!", day));
+    }
+
+    let main_dir = match current_dir() {
+        Ok(dir) => dir,
+        Err(e) => { return Err(format!("Could not get working directory: {}", e)); }
+    };
+    let source_path = main_dir.join(Path::new(&format!("src/days/day{:02}.rs", day)));
+    let input_path = main_dir.join(Path::new(&format!("resources/day{:02}.txt", day)));
+    let module_path = main_dir.join(Path::new(DAYS_MODULE_FILE));
+    let test_path = main_dir.join(Path::new(REAL_INPUTS_TEST_FILE));
+
+    if source_path.exists() {
+        return Err(format!("Source file for day {} already exists.", day));
+    }
+
+    let template = read_to_string(main_dir.join(TEMPLATE_FILE)).map_err(|e| format!("Could not read day template: {}", e))?;
+    let days_mod_content = read_to_string(&module_path).map_err(|e| format!("Could not read days module file: {}", e))?;
+    let test_content = read_to_string(&test_path).map_err(|e| format!("Could not read real inputs test file: {}", e))?;
+
+    // Render and register everything before writing anything, so a failure doesn't leave half a day behind.
+    let day_content = render_day(&template, day)?;
+    let module_content = register_day(&days_mod_content, day)?;
+    let test_content = register_day_test(&test_content, day)?;
+
+    write(&source_path, day_content).map_err(|e| format!("Could not write day file: {:?}\nError: {}", source_path, e))?;
+    write(&module_path, module_content).map_err(|e| format!("Could not write module file: {:?}\nError: {}", module_path, e))?;
+    write(&test_path, test_content).map_err(|e| format!("Could not write real inputs test file: {:?}\nError: {}", test_path, e))?;
+    // The input might have been downloaded already, which we should keep.
+    if !input_path.exists() {
+        write(&input_path, "").map_err(|e| format!("Could not write input file: {:?}\nError: {}", input_path, e))?;
+    }
+
+    Ok(())
+}
+
+fn render_day(template: &str, day: i32) -> Result<String, String> {
+    Handlebars::new().render_template(template, &json!({ "day": day, "padded": format!("{:02}", day) })).map_err(|e| format!("Could not render day template: {}", e))
+}
+
+// Inserts the lines for the day before the line with the given marker comment, with the same indentation.
+fn insert_at_marker(content: &str, marker: &str, lines: &[String]) -> Result<String, String> {
+    let regex = RegexBuilder::new(&format!("^([ \\t]*)({})", regex::escape(marker))).multi_line(true).build().map_err(|e| format!("{}", e))?;
+    if !regex.is_match(content) {
+        return Err(format!("Could not find the '{}' comment", marker));
+    }
+
+    Ok(regex.replace(content, |caps: &Captures| {
+        let mut result = String::new();
+        for line in lines {
+            result += &format!("{}{}\n", &caps[1], line);
         }
+        result + &caps[0]
+    }).to_string())
+}
+
+// Adds the module and its entry in get_day to src/days.rs.
+fn register_day(days_mod_content: &str, day: i32) -> Result<String, String> {
+    let content = insert_at_marker(days_mod_content, "// « add day import »", &[format!("pub mod day{:02};", day), format!("use day{:02}::DAY{};", day, day)])?;
+    insert_at_marker(&content, "// « add day match »", &[format!("{} => Ok(DAY{}),", day, day)])
+}
+
+// Adds a test running the day against its real input to tests/real_inputs.rs.
+fn register_day_test(test_content: &str, day: i32) -> Result<String, String> {
+    insert_at_marker(test_content, "// « add day test »", &[format!("day{:02}: {},", day, day)])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+    use crate::util::create_day::{register_day, register_day_test, render_day, TEMPLATE_FILE};
+
+    #[test]
+    fn test_register_day() {
+        let content = "\
+            pub mod day01;\n\
+            use day01::DAY1;\n\
+            // « add day import »\n\
+            \n\
+            fn get_day() {\n    \
+                match day {\n        \
+                    1 => Ok(DAY1),\n        \
+                    // « add day match »\n    \
+                }\n\
+            }\n";
+
+        assert_eq!(register_day(content, 26), Ok("\
+            pub mod day01;\n\
+            use day01::DAY1;\n\
+            pub mod day26;\n\
+            use day26::DAY26;\n\
+            // « add day import »\n\
+            \n\
+            fn get_day() {\n    \
+                match day {\n        \
+                    1 => Ok(DAY1),\n        \
+                    26 => Ok(DAY26),\n        \
+                    // « add day match »\n    \
+                }\n\
+            }\n".to_string()));
+        assert!(register_day("pub mod day01;", 26).is_err());
+    }
+
+    #[test]
+    fn test_register_day_test() {
+        assert_eq!(
+            register_day_test("real_input_tests! {\n    day25: 25,\n    // « add day test »\n}", 26),
+            Ok("real_input_tests! {\n    day25: 25,\n    day26: 26,\n    // « add day test »\n}".to_string())
+        );
+        // The actual test file should have the marker as well
+        assert!(register_day_test(&read_to_string("tests/real_inputs.rs").unwrap(), 26).is_ok());
+        assert!(register_day(&read_to_string("src/days.rs").unwrap(), 26).is_ok());
+    }
+
+    #[test]
+    fn test_render_day() {
+        let day = render_day(&read_to_string(TEMPLATE_FILE).unwrap(), 26).unwrap();
+
+        assert!(day.contains("pub const DAY26: Day = Day {"));
+        assert!(day.contains("use crate::days::day26::Solution;"));
+        assert!(day.contains("const TEST_INPUT: &str"));
+        assert!(!day.contains("{{"));
     }
-}
\ No newline at end of file
+}
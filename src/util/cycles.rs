@@ -0,0 +1,261 @@
+// Helpers for finding things that repeat (day 8's ghost routes, day 14's spin cycles, day 20's modules), and lining
+// them up (day 8's ghost routes, day 20's conjunction loops).
+// A Cycle describes an event that first happens at `offset`, and then again every `period` steps after that. Aligning
+// cycles finds the moments all events happen at the same time, which is the chinese remainder theorem with the extra
+// rule that we cannot go back before the first occurrence of any of the events.
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Cycle {
+    pub offset: usize,
+    pub period: usize,
+}
+
+impl Cycle {
+    pub fn new(offset: usize, period: usize) -> Cycle {
+        Cycle { offset, period }
+    }
+
+    // The common case where the event happens at every multiple of the period.
+    pub fn from_start(period: usize) -> Cycle {
+        Cycle { offset: period, period }
+    }
+
+    pub fn occurs_at(&self, t: usize) -> bool {
+        t >= self.offset && (t - self.offset).is_multiple_of(self.period)
+    }
+
+    // Maps a step to the earliest step that has the same state, when this cycle describes repeating states.
+    pub fn equivalent_step(&self, steps: usize) -> usize {
+        if steps < self.offset { steps } else { self.offset + (steps - self.offset) % self.period }
+    }
+
+    // Combines two cycles into the cycle of moments both events happen at the same time (if they ever do).
+    pub fn align(&self, other: &Cycle) -> Result<Option<Cycle>, String> {
+        if self.period == 0 || other.period == 0 {
+            return Err("Cannot align cycles with a period of 0".to_string());
+        }
+
+        // Solve t = a1 (mod n1), t = a2 (mod n2) using the extended euclidean algorithm:
+        let (a1, n1) = (self.offset as i128, self.period as i128);
+        let (a2, n2) = (other.offset as i128, other.period as i128);
+        let (g, p, _) = extended_gcd(n1, n2);
+
+        if (a2 - a1) % g != 0 {
+            return Ok(None); // These never line up
+        }
+
+        let period = n1 / g * n2;
+        let k = ((a2 - a1) / g * p).rem_euclid(n2 / g);
+        let mut t = (a1 + k * n1).rem_euclid(period);
+
+        // Move up to the first moment both events have actually started happening:
+        let start = a1.max(a2);
+        if t < start {
+            t += (start - t + period - 1) / period * period;
+        }
+
+        let offset = usize::try_from(t).map_err(|_| format!("Alignment of {:?} and {:?} overflows", self, other))?;
+        let period = usize::try_from(period).map_err(|_| format!("Alignment of {:?} and {:?} overflows", self, other))?;
+        Ok(Some(Cycle { offset, period }))
+    }
+}
+
+// Aligns all given cycles, returning None if they never line up.
+pub fn align_all(cycles: &[Cycle]) -> Result<Option<Cycle>, String> {
+    let (first, rest) = cycles.split_first().ok_or("Cannot align an empty list of cycles".to_string())?;
+
+    let mut result = *first;
+    for cycle in rest {
+        match result.align(cycle)? {
+            Some(aligned) => result = aligned,
+            None => return Ok(None)
+        }
+    }
+    Ok(Some(result))
+}
+
+// Aligns groups of cycles, where the event for a group happens whenever any of its cycles occurs (e.g. a loop that
+// passes multiple end points). Returns the aligned cycle (picking one cycle per group) that starts the earliest, or
+// None if no combination ever lines up.
+pub fn align_any(groups: &[Vec<Cycle>]) -> Result<Option<Cycle>, String> {
+    let (first, rest) = groups.split_first().ok_or("Cannot align an empty list of cycles".to_string())?;
+
+    // Keep all alignments so far, as the earliest one for the first groups is not necessarily part of the earliest
+    // alignment of all of them.
+    let mut alignments: Vec<Cycle> = first.clone();
+    for group in rest {
+        let mut next = vec![];
+        for aligned in &alignments {
+            for cycle in group {
+                if let Some(combined) = aligned.align(cycle)? {
+                    next.push(combined);
+                }
+            }
+        }
+        alignments = next;
+    }
+
+    Ok(alignments.into_iter().min_by_key(|c| c.offset))
+}
+
+// Finds where a sequence of states (initial, step(initial), step(step(initial)), ...) starts repeating itself. The
+// resulting cycle's offset is the first step that is part of the loop, and the period is the length of the loop. States
+// are compared by their key (e.g. just the positions of the moving parts).
+// Uses Brent's algorithm, so only a couple of states are kept around at any time, at the cost of stepping a bit more.
+pub fn find_cycle<S, K>(initial: S, step: impl Fn(&S) -> S, key: impl Fn(&S) -> K) -> Cycle
+    where S: Clone, K: Eq {
+    find_cycle_within(initial, usize::MAX, step, key).unwrap()
+}
+
+// Same as the above, but gives up (returning None) when no loop is found after taking `limit` steps.
+pub fn find_cycle_within<S, K>(initial: S, limit: usize, step: impl Fn(&S) -> S, key: impl Fn(&S) -> K) -> Option<Cycle>
+    where S: Clone, K: Eq {
+    // First find the period: the hare runs ahead, and the tortoise teleports to it every power of two steps. Once the
+    // hare runs into the tortoise, the distance between them is the length of the loop.
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = key(&initial);
+    let mut hare = step(&initial);
+    let mut taken = 1;
+
+    while tortoise != key(&hare) {
+        if taken >= limit { return None; }
+        if power == period {
+            tortoise = key(&hare);
+            power *= 2;
+            period = 0;
+        }
+        hare = step(&hare);
+        period += 1;
+        taken += 1;
+    }
+
+    // Then find the offset: start two states a period apart, and move them along until they meet.
+    let mut tortoise = initial.clone();
+    let mut hare = initial;
+    for _ in 0..period {
+        hare = step(&hare);
+    }
+
+    let mut offset = 0;
+    while key(&tortoise) != key(&hare) {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        offset += 1;
+    }
+
+    Some(Cycle { offset, period })
+}
+
+// The state after the given number of steps, only running the steps up to the matching state in the first loop.
+pub fn state_at<S>(initial: S, steps: usize, step: impl Fn(&S) -> S, cycle: &Cycle) -> S {
+    let mut state = initial;
+    for _ in 0..cycle.equivalent_step(steps) {
+        state = step(&state);
+    }
+    state
+}
+
+// Returns (gcd, x, y) such that a*x + b*y = gcd
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        return (a, 1, 0);
+    }
+    let (g, x, y) = extended_gcd(b, a % b);
+    (g, y, x - (a / b) * y)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::cycles::{align_all, align_any, Cycle, find_cycle, find_cycle_within, state_at};
+
+    fn brute_force(cycles: &[Cycle], limit: usize) -> Option<usize> {
+        (0..limit).find(|t| cycles.iter().all(|c| c.occurs_at(*t)))
+    }
+
+    #[test]
+    fn test_align_from_start() {
+        // Day 20 (and day 8's real input) only has loops that start at 0, this is a plain lcm:
+        let cycles = [Cycle::from_start(4), Cycle::from_start(6), Cycle::from_start(10)];
+        assert_eq!(align_all(&cycles), Ok(Some(Cycle::new(60, 60))));
+    }
+
+    #[test]
+    fn test_align_offsets_differing_from_periods() {
+        // First at 3, then every 2 and first at 2, then every 3: 5, 11, 17, ...
+        assert_eq!(Cycle::new(3, 2).align(&Cycle::new(2, 3)), Ok(Some(Cycle::new(5, 6))));
+
+        // A long lead-in, the first alignment (23) cannot be before the first occurrence of the second event (20):
+        let cycles = [Cycle::new(3, 4), Cycle::new(20, 3)];
+        assert_eq!(align_all(&cycles), Ok(Some(Cycle::new(23, 12))));
+        assert_eq!(brute_force(&cycles, 1000), Some(23));
+
+        // Non-coprime periods:
+        let cycles = [Cycle::new(1, 6), Cycle::new(7, 4), Cycle::new(13, 10)];
+        let aligned = align_all(&cycles).unwrap().unwrap();
+        assert_eq!(Some(aligned.offset), brute_force(&cycles, 1000));
+        assert_eq!(aligned.period, 60);
+    }
+
+    #[test]
+    fn test_align_never() {
+        // Odd and even numbers never meet:
+        assert_eq!(Cycle::new(1, 2).align(&Cycle::new(2, 4)), Ok(None));
+        assert_eq!(align_all(&[Cycle::new(3, 6), Cycle::new(1, 1), Cycle::new(4, 2)]), Ok(None));
+    }
+
+    #[test]
+    fn test_align_invalid() {
+        assert!(align_all(&[]).is_err());
+        assert!(Cycle::new(1, 0).align(&Cycle::new(1, 1)).is_err());
+    }
+
+    #[test]
+    fn test_align_any() {
+        // The first group has an event at 2 and 3 (mod 4), the second at 2 (mod 6), third at 0 or 5 (mod 10):
+        let groups = vec![
+            vec![Cycle::new(2, 4), Cycle::new(3, 4)],
+            vec![Cycle::new(2, 6)],
+            vec![Cycle::new(10, 10), Cycle::new(5, 10)],
+        ];
+        let aligned = align_any(&groups).unwrap().unwrap();
+        let expected = (0..1000).find(|t| groups.iter().all(|g| g.iter().any(|c| c.occurs_at(*t))));
+        assert_eq!(Some(aligned.offset), expected);
+        assert_eq!(aligned.period, 60);
+
+        // A single cycle per group is the same as align_all:
+        let cycles = [Cycle::new(3, 2), Cycle::new(2, 3)];
+        assert_eq!(align_any(&cycles.map(|c| vec![c])), align_all(&cycles));
+
+        assert_eq!(align_any(&[vec![Cycle::new(1, 2)], vec![Cycle::new(2, 4), Cycle::new(4, 4)]]), Ok(None));
+        assert_eq!(align_any(&[vec![Cycle::new(1, 2)], vec![]]), Ok(None));
+        assert!(align_any(&[]).is_err());
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        // Loops 3 -> 4 -> 5 -> 6 -> 3 -> ... after the first two steps
+        let step = |v: &usize| if *v == 6 { 3 } else { v + 1 };
+        let cycle = find_cycle(1, step, |v| *v);
+        assert_eq!(cycle, Cycle::new(2, 4));
+
+        assert_eq!(state_at(1, 0, step, &cycle), 1);
+        assert_eq!(state_at(1, 3, step, &cycle), 4);
+        assert_eq!(state_at(1, 6, step, &cycle), 3);
+        assert_eq!(state_at(1, 1_000_000_000, step, &cycle), 5);
+
+        // Starting in the loop, and a loop of a single state:
+        assert_eq!(find_cycle(4, step, |v| *v), Cycle::new(0, 4));
+        assert_eq!(find_cycle(7, |v: &usize| (*v / 2).max(1), |v| *v), Cycle::new(2, 1));
+
+        // Only comparing the key, the state is the same (mod 3) from the start:
+        assert_eq!(find_cycle(0usize, |v| v + 1, |v| v % 3), Cycle::new(0, 3));
+    }
+
+    #[test]
+    fn test_find_cycle_within() {
+        let step = |v: &usize| if *v == 6 { 3 } else { v + 1 };
+        assert_eq!(find_cycle_within(1, 100, step, |v| *v), Some(Cycle::new(2, 4)));
+        assert_eq!(find_cycle_within(1usize, 100, |v| v + 1, |v| *v), None);
+    }
+}
@@ -0,0 +1,87 @@
+use std::fmt::{Display, Formatter};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Just enough calendar logic to figure out which puzzle day it is, without pulling in a date crate.
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+// CET is UTC+1. The event runs in December, so we don't need to care about summer time.
+const CET_OFFSET_SECONDS: i64 = 60 * 60;
+
+impl Date {
+    // Converts days since 1970-01-01 to a date in the (proleptic) gregorian calendar.
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    pub fn from_days_since_epoch(days: i64) -> Date {
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let day_of_era = z.rem_euclid(146097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153; // March is 0
+        let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+        let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Date { year, month, day }
+    }
+
+    pub fn from_unix_seconds(seconds: i64) -> Date {
+        Date::from_days_since_epoch(seconds.div_euclid(24 * 60 * 60))
+    }
+
+    // The current date in the CET timezone.
+    pub fn today_cet() -> Result<Date, String> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| format!("{}", e))?;
+        Ok(Date::from_unix_seconds(now.as_secs() as i64 + CET_OFFSET_SECONDS))
+    }
+
+    // The puzzle day this date falls on, if it is during the event (December 1st to 25th).
+    pub fn advent_day(&self) -> Option<i32> {
+        if self.month == 12 && (1..=25).contains(&self.day) { Some(self.day as i32) } else { None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::date::Date;
+
+    #[test]
+    fn test_from_days_since_epoch() {
+        assert_eq!(Date::from_days_since_epoch(0), Date { year: 1970, month: 1, day: 1 });
+        assert_eq!(Date::from_days_since_epoch(-1), Date { year: 1969, month: 12, day: 31 });
+        assert_eq!(Date::from_days_since_epoch(11016), Date { year: 2000, month: 2, day: 29 });
+        assert_eq!(Date::from_days_since_epoch(19692), Date { year: 2023, month: 12, day: 1 });
+        assert_eq!(Date::from_days_since_epoch(19716), Date { year: 2023, month: 12, day: 25 });
+    }
+
+    #[test]
+    fn test_from_unix_seconds() {
+        // 2023-12-01T05:00:00Z, the moment the first puzzle unlocked
+        assert_eq!(Date::from_unix_seconds(1701406800), Date { year: 2023, month: 12, day: 1 });
+        assert_eq!(Date::from_unix_seconds(1701406800 - 6 * 60 * 60), Date { year: 2023, month: 11, day: 30 });
+    }
+
+    #[test]
+    fn test_advent_day() {
+        assert_eq!(Date { year: 2023, month: 12, day: 1 }.advent_day(), Some(1));
+        assert_eq!(Date { year: 2023, month: 12, day: 25 }.advent_day(), Some(25));
+        assert_eq!(Date { year: 2023, month: 12, day: 26 }.advent_day(), None);
+        assert_eq!(Date { year: 2023, month: 11, day: 30 }.advent_day(), None);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(Date { year: 2023, month: 12, day: 1 }.to_string(), "2023-12-01");
+    }
+}
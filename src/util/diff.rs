@@ -0,0 +1,38 @@
+// Renders a line-by-line diff of the expected and actual output. Equal lines are printed as-is, differing lines are
+// printed as '-' (expected) and '+' (actual), with a marker below the first differing character.
+pub fn pretty_diff(expected: &str, actual: &str) -> String {
+    let expected_lines: Vec<_> = expected.lines().collect();
+    let actual_lines: Vec<_> = actual.lines().collect();
+    let mut result = vec![];
+
+    for i in 0..expected_lines.len().max(actual_lines.len()) {
+        match (expected_lines.get(i), actual_lines.get(i)) {
+            (Some(e), Some(a)) if e == a => result.push(format!("  {}", e)),
+            (Some(e), Some(a)) => {
+                let position = e.chars().zip(a.chars()).take_while(|(l, r)| l == r).count();
+                result.push(format!("- {}", e));
+                result.push(format!("+ {}", a));
+                result.push(format!("  {}^", " ".repeat(position)));
+            },
+            (Some(e), None) => result.push(format!("- {}", e)),
+            (None, Some(a)) => result.push(format!("+ {}", a)),
+            (None, None) => {}
+        }
+    }
+
+    result.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::diff::pretty_diff;
+
+    #[test]
+    fn test_pretty_diff() {
+        assert_eq!(pretty_diff("Answer: 42", "Answer: 42"), "  Answer: 42");
+        assert_eq!(pretty_diff("Answer: 42", "Answer: 43"), "- Answer: 42\n+ Answer: 43\n           ^");
+        assert_eq!(pretty_diff("Answer: 42", "Answer: 4"), "- Answer: 42\n+ Answer: 4\n           ^");
+        assert_eq!(pretty_diff("Answer: 42\n(extra)", "Answer: 42"), "  Answer: 42\n- (extra)");
+        assert_eq!(pretty_diff("Answer: 42", "Answer: 42\n(extra)"), "  Answer: 42\n+ (extra)");
+    }
+}
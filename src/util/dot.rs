@@ -0,0 +1,47 @@
+// Opt-in export of graphs in the DOT format (graphviz), enabled by --dot <path>. Some puzzles (day 25's wiring) are
+// easiest understood by looking at them, days that support it write their graph to the given path.
+
+use std::sync::Mutex;
+
+static PATH: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn enable(path: &str) {
+    *PATH.lock().unwrap() = Some(path.to_string());
+}
+
+pub fn path() -> Option<String> {
+    PATH.lock().unwrap().clone()
+}
+
+// Quotes an identifier or label, so any name is valid in the output.
+pub fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Writes the graph to the path given by --dot, if any.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(dot: &str) -> Result<(), String> {
+    match path() {
+        Some(path) => std::fs::write(&path, dot).map_err(|e| format!("Could not write graph to '{}': {}", path, e)),
+        None => Ok(())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write(_dot: &str) -> Result<(), String> {
+    match path() {
+        Some(path) => Err(format!("Cannot write graph to '{}', there is no filesystem in wasm", path)),
+        None => Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::dot::quote;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("jqt"), "\"jqt\"");
+        assert_eq!(quote("a \"b\" \\c"), "\"a \\\"b\\\" \\\\c\"");
+    }
+}
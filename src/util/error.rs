@@ -0,0 +1,100 @@
+use std::fmt::{Display, Formatter};
+
+// The errors of the crate, by what went wrong, so that e.g. the runner can tell a missing input file apart from a
+// solver that failed on it. Most of the code still uses String errors, the From shims make `?` work between both. (Errors
+// from parsing a day's input always end up as Parse errors, see days::parse.)
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum AocError {
+    // The input (or part of it) is not what we expected.
+    Parse(String),
+    // Reading (or writing) a file, stdin, ... failed.
+    Io(String),
+    // The input parsed fine, but solving it didn't work out (e.g. an assumption that does not hold).
+    Logic(String),
+    // Asked for something that isn't there, like a day that is not implemented (yet).
+    Unsupported(String),
+}
+
+pub type AocResult<T> = Result<T, AocError>;
+
+impl AocError {
+    pub fn message(&self) -> &str {
+        match self {
+            AocError::Parse(message) | AocError::Io(message) | AocError::Logic(message) | AocError::Unsupported(message) => message
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AocError::Parse(_) => "parse error",
+            AocError::Io(_) => "io error",
+            AocError::Logic(_) => "error",
+            AocError::Unsupported(_) => "unsupported",
+        }
+    }
+}
+
+impl Display for AocError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.kind(), self.message())
+    }
+}
+
+impl std::error::Error for AocError {}
+
+// String errors are mostly from solvers that haven't been migrated yet, so those are considered logic errors.
+impl From<String> for AocError {
+    fn from(message: String) -> Self {
+        AocError::Logic(message)
+    }
+}
+
+impl From<&str> for AocError {
+    fn from(message: &str) -> Self {
+        AocError::Logic(message.to_string())
+    }
+}
+
+// Going back to a String just keeps the message, as before.
+impl From<AocError> for String {
+    fn from(error: AocError) -> Self {
+        error.message().to_string()
+    }
+}
+
+impl From<std::io::Error> for AocError {
+    fn from(error: std::io::Error) -> Self {
+        AocError::Io(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::error::AocError;
+
+    #[test]
+    fn test_conversions() {
+        fn fails_with_string() -> Result<(), String> {
+            Err("nope".to_string())
+        }
+        fn fails_with_error() -> Result<(), AocError> {
+            Err(AocError::Parse("Invalid tile: 'x'".to_string()))
+        }
+        fn string_to_error() -> Result<(), AocError> {
+            Ok(fails_with_string()?)
+        }
+        fn error_to_string() -> Result<(), String> {
+            Ok(fails_with_error()?)
+        }
+
+        assert_eq!(string_to_error(), Err(AocError::Logic("nope".to_string())));
+        assert_eq!(error_to_string(), Err("Invalid tile: 'x'".to_string()));
+        assert_eq!(AocError::from(std::io::Error::new(std::io::ErrorKind::NotFound, "missing")), AocError::Io("missing".to_string()));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(AocError::Unsupported("Day 26 is not implemented".to_string()).to_string(), "unsupported: Day 26 is not implemented");
+        assert_eq!(AocError::Logic("No mirror".to_string()).message(), "No mirror");
+    }
+}
@@ -0,0 +1,86 @@
+// Opt-in 'exact' mode: overflow-prone computations are repeated using u128/BigUint arithmetic and compared to
+// the fast (usize/isize) path. Meant as a correctness oracle while refactoring, not for day-to-day runs.
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use crate::util::error::{AocError, AocResult};
+
+static EXACT: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    EXACT.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    EXACT.load(Ordering::Relaxed)
+}
+
+/// Compares the fast and exact results of a computation by their decimal representation (so e.g. a usize can be
+/// compared against a BigUint), failing with both values when they differ.
+pub fn check(label: &str, fast: impl Display, exact: impl Display) -> AocResult<()> {
+    let fast = fast.to_string();
+    let exact = exact.to_string();
+
+    if fast != exact {
+        return Err(AocError::Logic(format!("Exact check failed for {}: fast path gave {}, exact path gave {}", label, fast, exact)));
+    }
+    Ok(())
+}
+
+pub fn gcd(a: &BigUint, b: &BigUint) -> BigUint {
+    let mut a = a.clone();
+    let mut b = b.clone();
+
+    while !b.is_zero() {
+        let rem = &a % &b;
+        a = b;
+        b = rem;
+    }
+
+    a
+}
+
+pub fn lcm(values: &[usize]) -> BigUint {
+    if values.is_empty() { return BigUint::zero(); }
+
+    values.iter().fold(BigUint::one(), |acc, v| {
+        let value = BigUint::from(*v);
+        let divisor = gcd(&acc, &value);
+        if divisor.is_zero() { BigUint::zero() } else { (&acc * &value) / divisor }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+    use crate::util::error::AocError;
+    use crate::util::exact::{check, gcd, lcm};
+
+    #[test]
+    fn test_gcd() {
+        assert_eq!(gcd(&BigUint::from(36u32), &BigUint::from(12u32)), BigUint::from(12u32));
+        assert_eq!(gcd(&BigUint::from(32u32), &BigUint::from(5u32)), BigUint::from(1u32));
+    }
+
+    #[test]
+    fn test_lcm() {
+        assert_eq!(lcm(&[4, 6, 3]), BigUint::from(12u32));
+        assert_eq!(lcm(&[]), BigUint::from(0u32));
+
+        // Would overflow u64 when multiplied naively
+        let big = [4_294_967_291, 4_294_967_279, 4_294_967_231];
+        assert_eq!(lcm(&big).to_string(), "79228160909397609687688407659");
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(check("equal", 42usize, BigUint::from(42u32)), Ok(()));
+        assert_eq!(check("equal signed", -42isize, -42i128), Ok(()));
+        assert_eq!(
+            check("mismatch", 42usize, 43u128),
+            Err(AocError::Logic("Exact check failed for mismatch: fast path gave 42, exact path gave 43".to_string()))
+        );
+    }
+}
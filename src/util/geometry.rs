@@ -2,13 +2,15 @@
 #![allow(dead_code)]
 
 use std::cmp::{max, Ordering};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::{cmp, fmt};
 use std::hash::Hash;
 use std::ops::{Add, RangeInclusive, Sub};
 use std::str::FromStr;
 use num_traits::{abs, Zero};
+use crate::util::error::{AocError, AocResult};
 use crate::util::number;
+use crate::util::color::AnsiColor;
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Default)]
 pub struct Point {
@@ -16,33 +18,39 @@ pub struct Point {
     pub y: isize,
 }
 
+// The offset for every single direction, clockwise starting at the top left.
+const DIRECTION_OFFSETS: [(Directions, isize, isize); 8] = [
+    (Directions::TopLeft, -1, -1),
+    (Directions::Top, 0, -1),
+    (Directions::TopRight, 1, -1),
+    (Directions::Right, 1, 0),
+    (Directions::BottomRight, 1, 1),
+    (Directions::Bottom, 0, 1),
+    (Directions::BottomLeft, -1, 1),
+    (Directions::Left, -1, 0),
+];
+
 impl Point {
     pub fn get_points_around(&self, directions: Directions) -> Vec<Point> {
-        let mut points = vec![];
-        if directions.has(Directions::TopLeft) { points.push((self.x - 1, self.y - 1).into()) }
-        if directions.has(Directions::Top) { points.push((self.x, self.y - 1).into()) }
-        if directions.has(Directions::TopRight) { points.push((self.x + 1, self.y - 1).into()) }
-        if directions.has(Directions::Right) { points.push((self.x + 1, self.y).into()) }
-        if directions.has(Directions::BottomRight) { points.push((self.x + 1, self.y + 1).into()) }
-        if directions.has(Directions::Bottom) { points.push((self.x, self.y + 1).into()) }
-        if directions.has(Directions::BottomLeft) { points.push((self.x - 1, self.y + 1).into()) }
-        if directions.has(Directions::Left) { points.push((self.x - 1, self.y).into()) }
+        self.points_around(directions).collect()
+    }
 
-        return points;
+    // Non-allocating version of get_points_around, for use in hot loops.
+    pub fn points_around(&self, directions: Directions) -> impl Iterator<Item = Point> {
+        let origin = *self;
+        DIRECTION_OFFSETS.iter()
+            .filter(move |(direction, _, _)| directions.has(*direction))
+            .map(move |(_, dx, dy)| Point { x: origin.x + dx, y: origin.y + dy })
     }
 
     pub fn manhattan_distance(&self, other: &Point) -> isize {
         abs(self.x - other.x) + abs(self.y - other.y)
     }
 
-    pub fn translate_in_direction(&self, directions: Directions, amount: usize) -> Self {
-        match directions {
-            Directions::Top => *self - (0isize, amount as isize),
-            Directions::Left => *self - (amount as isize, 0isize),
-            Directions::Bottom => *self + (0isize, amount as isize),
-            Directions::Right => *self + (amount as isize, 0isize),
-            _ => *self
-        }
+    // The point the given amount of steps away in the direction.
+    pub fn step(&self, direction: Dir, amount: usize) -> Self {
+        let (dx, dy) = direction.offset();
+        Point { x: self.x + dx * amount as isize, y: self.y + dy * amount as isize }
     }
 }
 
@@ -58,28 +66,14 @@ impl From<(isize, isize)> for Point {
     }
 }
 
-impl TryFrom<(usize, usize)> for Point {
-    type Error = String;
-
-    fn try_from(value: (usize, usize)) -> Result<Self, Self::Error> {
-        let x: isize = isize::try_from(value.0).map_err(|e| format!("{}", e))?;
-        let y: isize = isize::try_from(value.1).map_err(|e| format!("{}", e))?;
-        Ok(Point { x, y })
-    }
-}
-
 impl FromStr for Point {
-    type Err = String;
+    type Err = AocError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts_result: Result<Vec<isize>, String> = s.split(",").map(|p| number::parse_isize(p.trim())).collect();
-        let parts = match parts_result {
-            Ok(v) => v,
-            Err(e) => return Err(e)
-        };
+        let parts = s.split(",").map(|p| number::parse_num(p.trim())).collect::<AocResult<Vec<isize>>>()?;
         match parts.len() {
             2 => Ok((parts[0], parts[1]).into()),
-            _ => Err(format!("Invalid str format for Point '{}', expected 'x,y'", s))
+            _ => Err(AocError::Parse(format!("Invalid str format for Point '{}', expected 'x,y'", s)))
         }
     }
 }
@@ -163,7 +157,7 @@ impl Sub<Point> for Vec<Point> {
 
 #[cfg(test)]
 mod point_tests {
-    use crate::util::geometry::{Directions, Point};
+    use crate::util::geometry::{Dir, Directions, Point};
 
     #[test]
     fn test_from_str() {
@@ -172,6 +166,27 @@ mod point_tests {
         assert_eq!("422,-2345".parse(), Ok(Point { x: 422, y: -2345 }));
     }
 
+    #[test]
+    fn test_dir() {
+        assert_eq!(Dir::North.turns(), [Dir::West, Dir::East]);
+        assert_eq!(Dir::West.turns(), [Dir::North, Dir::South]);
+        assert_eq!(Dir::ALL.map(|d| d.turn_left()), [Dir::West, Dir::North, Dir::East, Dir::South]);
+        assert_eq!(Dir::ALL.map(|d| d.turn_right()), [Dir::East, Dir::South, Dir::West, Dir::North]);
+        assert_eq!(Dir::ALL.map(|d| d.opposite()), [Dir::South, Dir::West, Dir::North, Dir::East]);
+        assert!(Dir::South.is_vertical() && Dir::East.is_horizontal());
+
+        // Every direction is a single one of the set
+        assert_eq!(Dir::ALL.map(Directions::from), [Directions::Top, Directions::Right, Directions::Bottom, Directions::Left]);
+        assert_eq!(Point { x: 3, y: 5 }.get_points_around(Dir::East.into()), vec![Point { x: 4, y: 5 }]);
+    }
+
+    #[test]
+    fn test_step() {
+        assert_eq!(Point { x: 3, y: 5 }.step(Dir::North, 2), Point { x: 3, y: 3 });
+        assert_eq!(Point { x: 3, y: 5 }.step(Dir::West, 4), Point { x: -1, y: 5 });
+        assert_eq!(Point { x: 3, y: 5 }.step(Dir::South, 0), Point { x: 3, y: 5 });
+    }
+
     #[test]
     fn test_from() {
         assert_eq!(Point::from((3, 5)), Point { x: 3, y: 5 });
@@ -218,6 +233,89 @@ mod point_tests {
 }
 
 
+// Grids grow downwards: y (and the row index) increases towards Directions::Bottom. To avoid mixing up (x, y) and
+// (row, column) tuples when going from input lines to points, use these types instead of bare tuples.
+
+// Position as (row, column) indices, e.g. the line index and char index in the input.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub struct RowCol {
+    pub row: usize,
+    pub col: usize,
+}
+
+// Position as (x, y) indices, e.g. the index of a column and the index of the tile in that column.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub struct XY {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl From<RowCol> for XY {
+    fn from(value: RowCol) -> Self {
+        XY { x: value.col, y: value.row }
+    }
+}
+
+impl From<XY> for RowCol {
+    fn from(value: XY) -> Self {
+        RowCol { row: value.y, col: value.x }
+    }
+}
+
+impl TryFrom<XY> for Point {
+    type Error = String;
+
+    fn try_from(value: XY) -> Result<Self, Self::Error> {
+        let x: isize = isize::try_from(value.x).map_err(|e| format!("{}", e))?;
+        let y: isize = isize::try_from(value.y).map_err(|e| format!("{}", e))?;
+        Ok(Point { x, y })
+    }
+}
+
+impl TryFrom<RowCol> for Point {
+    type Error = String;
+
+    fn try_from(value: RowCol) -> Result<Self, Self::Error> {
+        Point::try_from(XY::from(value))
+    }
+}
+
+impl TryFrom<Point> for XY {
+    type Error = String;
+
+    fn try_from(value: Point) -> Result<Self, Self::Error> {
+        let x: usize = usize::try_from(value.x).map_err(|e| format!("{}", e))?;
+        let y: usize = usize::try_from(value.y).map_err(|e| format!("{}", e))?;
+        Ok(XY { x, y })
+    }
+}
+
+impl TryFrom<Point> for RowCol {
+    type Error = String;
+
+    fn try_from(value: Point) -> Result<Self, Self::Error> {
+        XY::try_from(value).map(RowCol::from)
+    }
+}
+
+#[cfg(test)]
+mod index_tests {
+    use crate::util::geometry::{Point, RowCol, XY};
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(XY::from(RowCol { row: 3, col: 5 }), XY { x: 5, y: 3 });
+        assert_eq!(RowCol::from(XY { x: 5, y: 3 }), RowCol { row: 3, col: 5 });
+
+        assert_eq!(Point::try_from(RowCol { row: 3, col: 5 }), Ok(Point { x: 5, y: 3 }));
+        assert_eq!(Point::try_from(XY { x: 5, y: 3 }), Ok(Point { x: 5, y: 3 }));
+
+        assert_eq!(RowCol::try_from(Point { x: 5, y: 3 }), Ok(RowCol { row: 3, col: 5 }));
+        assert_eq!(XY::try_from(Point { x: 5, y: 3 }), Ok(XY { x: 5, y: 3 }));
+        assert!(RowCol::try_from(Point { x: -1, y: 3 }).is_err());
+        assert!(Point::try_from(XY { x: usize::MAX, y: 3 }).is_err());
+    }
+}
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
 pub struct Point3D {
     pub x: isize,
@@ -244,12 +342,12 @@ impl From<Point> for Point3D {
 }
 
 impl FromStr for Point3D {
-    type Err = String;
+    type Err = AocError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let points = s.split(",").map(|p| number::parse_isize(p.trim())).collect::<Result<Vec<isize>, String>>()?;
+        let points = s.split(",").map(|p| number::parse_num(p.trim())).collect::<AocResult<Vec<isize>>>()?;
         if points.len() != 3 {
-            Err(format!("Expected three coordinates, but got {}", points.len()))
+            Err(AocError::Parse(format!("Expected three coordinates, but got {}", points.len())))
         } else {
             Ok(Point3D { x: points[0], y: points[1], z: points[2] })
         }
@@ -340,6 +438,89 @@ mod point3d_tests {
     }
 }
 
+// An axis-aligned box of points, both corners included.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct Bounds3D {
+    pub min: Point3D,
+    pub max: Point3D,
+}
+
+impl Bounds3D {
+    // The box between two (opposite) corners, in any order.
+    pub fn from_corners(a: Point3D, b: Point3D) -> Self {
+        Self {
+            min: Point3D { x: a.x.min(b.x), y: a.y.min(b.y), z: a.z.min(b.z) },
+            max: Point3D { x: a.x.max(b.x), y: a.y.max(b.y), z: a.z.max(b.z) },
+        }
+    }
+
+    pub fn contains(&self, p: &Point3D) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x)
+            && (self.min.y..=self.max.y).contains(&p.y)
+            && (self.min.z..=self.max.z).contains(&p.z)
+    }
+
+    pub fn intersects(&self, other: &Bounds3D) -> bool {
+        self.min.x <= other.max.x && other.min.x <= self.max.x
+            && self.min.y <= other.max.y && other.min.y <= self.max.y
+            && self.min.z <= other.max.z && other.min.z <= self.max.z
+    }
+
+    // The (x, y) area of the box, looking at it from above.
+    pub fn footprint(&self) -> Bounds {
+        Bounds::from_tlbr(self.min.y, self.min.x, self.max.y, self.max.x)
+    }
+
+    pub fn translate(&self, by: &Point3D) -> Self {
+        Self { min: self.min.translate(by), max: self.max.translate(by) }
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = Point3D> + '_ {
+        (self.min.z..=self.max.z).flat_map(move |z| self.footprint().points().map(move |p| Point3D { x: p.x, y: p.y, z }))
+    }
+}
+
+#[cfg(test)]
+mod bounds3d_tests {
+    use crate::util::geometry::{Bounds, Bounds3D};
+
+    #[test]
+    fn test_from_corners() {
+        assert_eq!(
+            Bounds3D::from_corners((3, 0, 5).into(), (1, 2, 5).into()),
+            Bounds3D { min: (1, 0, 5).into(), max: (3, 2, 5).into() }
+        );
+    }
+
+    #[test]
+    fn test_contains() {
+        let cube = Bounds3D::from_corners((0, 0, 0).into(), (2, 2, 2).into());
+        assert!(cube.contains(&(0, 0, 0).into()));
+        assert!(cube.contains(&(1, 2, 1).into()));
+        assert!(!cube.contains(&(1, 3, 1).into()));
+        assert!(!cube.contains(&(-1, 0, 0).into()));
+    }
+
+    #[test]
+    fn test_intersects() {
+        let cube = Bounds3D::from_corners((0, 0, 0).into(), (2, 2, 2).into());
+        assert!(cube.intersects(&cube));
+        assert!(cube.intersects(&Bounds3D::from_corners((2, 2, 2).into(), (5, 5, 5).into())));
+        assert!(cube.intersects(&Bounds3D::from_corners((1, -5, 1).into(), (1, 5, 1).into())));
+        assert!(!cube.intersects(&Bounds3D::from_corners((0, 0, 3).into(), (2, 2, 3).into())));
+        assert!(!cube.intersects(&Bounds3D::from_corners((3, 0, 0).into(), (3, 0, 0).into())));
+    }
+
+    #[test]
+    fn test_footprint_and_points() {
+        let block = Bounds3D::from_corners((1, 0, 5).into(), (1, 2, 6).into());
+        assert_eq!(block.footprint(), Bounds::from_tlbr(0, 1, 2, 1));
+        assert_eq!(block.points().count(), 6);
+        assert!(block.points().all(|p| block.contains(&p)));
+        assert_eq!(block.translate(&(0, 0, -4).into()), Bounds3D::from_corners((1, 0, 1).into(), (1, 2, 2).into()));
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct Line {
     pub start: Point,
@@ -534,16 +715,85 @@ impl Bounds {
         self.x().contains(&pixel.x) && self.y().contains(&pixel.y)
     }
 
-    pub fn points(&self) -> Vec<Point> {
-        let mut points = vec![];
+    // All points within the bounds, row by row.
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let xs = self.x();
+        self.y().flat_map(move |y| xs.clone().map(move |x| Point { x, y }))
+    }
 
-        for y in self.y() {
-            for x in self.x() {
-                points.push((x, y).into());
-            }
-        }
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
 
-        points
+    // The bounds covered by both, if any.
+    pub fn intersect(&self, other: &Bounds) -> Option<Bounds> {
+        let top = self.top.max(other.top);
+        let left = self.left.max(other.left);
+        let bottom = self.bottom().min(other.bottom());
+        let right = self.right().min(other.right());
+
+        if bottom < top || right < left { None } else { Some(Bounds::from_tlbr(top, left, bottom, right)) }
+    }
+
+    // The smallest bounds covering both.
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        if self.is_empty() { return *other; }
+        if other.is_empty() { return *self; }
+
+        Bounds::from_tlbr(
+            self.top.min(other.top),
+            self.left.min(other.left),
+            self.bottom().max(other.bottom()),
+            self.right().max(other.right()),
+        )
+    }
+
+    pub fn contains_bounds(&self, other: &Bounds) -> bool {
+        other.is_empty() || self.intersect(other) == Some(*other)
+    }
+}
+
+#[cfg(test)]
+mod bounds_tests {
+    use crate::util::geometry::{Bounds, Point};
+
+    #[test]
+    fn test_points() {
+        let bounds = Bounds { top: -1, left: 2, width: 2, height: 2 };
+        assert_eq!(bounds.points().collect::<Vec<_>>(), vec![
+            Point { x: 2, y: -1 }, Point { x: 3, y: -1 },
+            Point { x: 2, y: 0 }, Point { x: 3, y: 0 },
+        ]);
+        assert_eq!(Bounds::from_size(0, 3).points().count(), 0);
+    }
+
+    #[test]
+    fn test_intersect() {
+        let a = Bounds::from_tlbr(0, 0, 4, 4);
+        assert_eq!(a.intersect(&Bounds::from_tlbr(2, -3, 6, 1)), Some(Bounds::from_tlbr(2, 0, 4, 1)));
+        assert_eq!(a.intersect(&Bounds::from_tlbr(1, 1, 2, 2)), Some(Bounds::from_tlbr(1, 1, 2, 2)));
+        assert_eq!(a.intersect(&Bounds::from_tlbr(4, 4, 8, 8)), Some(Bounds::from_tlbr(4, 4, 4, 4)));
+        assert_eq!(a.intersect(&Bounds::from_tlbr(5, 0, 8, 4)), None);
+        assert_eq!(a.intersect(&Bounds { top: 1, left: 1, width: 0, height: 2 }), None);
+    }
+
+    #[test]
+    fn test_union() {
+        let a = Bounds::from_tlbr(0, 0, 4, 4);
+        assert_eq!(a.union(&Bounds::from_tlbr(2, -3, 6, 1)), Bounds::from_tlbr(0, -3, 6, 4));
+        assert_eq!(a.union(&Bounds::from_tlbr(1, 1, 2, 2)), a);
+        assert_eq!(a.union(&Bounds::default()), a);
+        assert_eq!(Bounds::default().union(&a), a);
+    }
+
+    #[test]
+    fn test_contains_bounds() {
+        let a = Bounds::from_tlbr(0, 0, 4, 4);
+        assert!(a.contains_bounds(&a));
+        assert!(a.contains_bounds(&Bounds::from_tlbr(1, 1, 2, 4)));
+        assert!(!a.contains_bounds(&Bounds::from_tlbr(1, 1, 2, 5)));
+        assert!(!a.contains_bounds(&Bounds::from_tlbr(-1, 1, 2, 4)));
+        assert!(a.contains_bounds(&Bounds { top: 10, left: 10, width: 0, height: 0 }));
     }
 }
 
@@ -590,6 +840,78 @@ impl Directions {
     }
 }
 
+// A single (non-diagonal) direction to go into, as opposed to Directions, which is a set of directions to look at.
+// North is up, so towards lower y values.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Debug, Hash)]
+pub enum Dir {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Dir {
+    // Clockwise, starting at north.
+    pub const ALL: [Dir; 4] = [Dir::North, Dir::East, Dir::South, Dir::West];
+
+    // The (x, y) offset of a single step.
+    pub fn offset(&self) -> (isize, isize) {
+        match self {
+            Dir::North => (0, -1),
+            Dir::East => (1, 0),
+            Dir::South => (0, 1),
+            Dir::West => (-1, 0),
+        }
+    }
+
+    pub fn opposite(&self) -> Dir {
+        match self {
+            Dir::North => Dir::South,
+            Dir::East => Dir::West,
+            Dir::South => Dir::North,
+            Dir::West => Dir::East,
+        }
+    }
+
+    pub fn turn_left(&self) -> Dir {
+        match self {
+            Dir::North => Dir::West,
+            Dir::East => Dir::North,
+            Dir::South => Dir::East,
+            Dir::West => Dir::South,
+        }
+    }
+
+    pub fn turn_right(&self) -> Dir {
+        self.turn_left().opposite()
+    }
+
+    // The directions to turn to, in a fixed order: west and east when going vertically, north and south otherwise.
+    pub fn turns(&self) -> [Dir; 2] {
+        if self.is_vertical() { [Dir::West, Dir::East] } else { [Dir::North, Dir::South] }
+    }
+
+    pub fn is_vertical(&self) -> bool {
+        matches!(self, Dir::North | Dir::South)
+    }
+
+    pub fn is_horizontal(&self) -> bool {
+        !self.is_vertical()
+    }
+}
+
+// The set of just the one direction, e.g. to look around with.
+impl From<Dir> for Directions {
+    fn from(direction: Dir) -> Self {
+        match direction {
+            Dir::North => Directions::Top,
+            Dir::East => Directions::Right,
+            Dir::South => Directions::Bottom,
+            Dir::West => Directions::Left,
+        }
+    }
+}
+
 #[allow(unused)]
 impl<T> Grid<T> where T: Clone {
     pub fn new(cells: HashMap<Point, T>) -> Self {
@@ -638,8 +960,18 @@ impl<T> Grid<T> where T: Clone {
         }
     }
 
+    // The cells of the row from left to right, borrowed (get_row copies them). Empty cells are skipped.
+    pub fn iter_row(&self, row: isize) -> impl Iterator<Item=&T> + '_ {
+        self.bounds.x().filter_map(move |x| self.cells.get(&Point { x, y: row }))
+    }
+
+    // The cells of the column from top to bottom, borrowed (get_column copies them). Empty cells are skipped.
+    pub fn iter_col(&self, column: isize) -> impl Iterator<Item=&T> + '_ {
+        self.bounds.y().filter_map(move |y| self.cells.get(&Point { x: column, y }))
+    }
+
     pub fn get_row(&self, row: isize) -> Vec<T> {
-        self.bounds.x().filter_map(|x| self.get(&Point::from((x, row)))).collect()
+        self.iter_row(row).cloned().collect()
     }
 
     pub fn rows(&self) -> Vec<Vec<T>> {
@@ -647,13 +979,32 @@ impl<T> Grid<T> where T: Clone {
     }
 
     pub fn get_column(&self, column: isize) -> Vec<T> {
-        self.bounds.y().filter_map(|y| self.get(&Point::from((column, y)))).collect()
+        self.iter_col(column).cloned().collect()
     }
 
     pub fn columns(&self) -> Vec<Vec<T>> {
         self.bounds.x().map(|column| self.get_column(column)).collect()
     }
 
+    // Copies the part of this grid within the given bounds, shifted such that the bounds start at (0, 0).
+    // Parts of the bounds outside of this grid are left empty. (Only day 21's tests tile gardens, hence test only.)
+    #[cfg(test)]
+    pub fn view(&self, bounds: &Bounds) -> Grid<T> {
+        let cells = bounds.points()
+            .filter_map(|p| self.cells.get(&p).map(|v| (p - (bounds.left, bounds.top), v.clone())))
+            .collect();
+        Grid { bounds: Bounds::from_size(bounds.width, bounds.height), cells }
+    }
+
+    // Copies all cells of the other grid into this one, moved by the given offset. Cells that were already there are
+    // overwritten, and the bounds grow to fit the pasted cells.
+    #[cfg(test)]
+    pub fn paste(&mut self, offset: Point, other: &Grid<T>) {
+        for (p, v) in &other.cells {
+            self.set(*p + offset, v.clone());
+        }
+    }
+
     pub fn get_adjacent(&self, p: &Point, directions: Directions) -> Vec<T> {
         self.get_adjacent_points(p, directions).iter().filter_map(|p| self.get(p)).collect()
     }
@@ -666,41 +1017,39 @@ impl<T> Grid<T> where T: Clone {
         self.get_adjacent_points(p, directions).into_iter().filter_map(|p| self.get(&p).map(|i| (p, i))).collect()
     }
 
-    pub fn get_in_direction(&self, p: &Point, direction: Directions) -> Vec<T> {
-        self.get_points_in_direction(p, direction).iter().filter_map(|p| self.get(p)).collect()
+    // Non-allocating version of get_adjacent_entries, yielding references to the values instead of clones.
+    pub fn neighbors(&self, p: &Point, directions: Directions) -> impl Iterator<Item = (Point, &T)> {
+        p.points_around(directions).filter_map(move |p| self.cells.get(&p).map(|v| (p, v)))
     }
 
-    pub fn get_points_in_direction(&self, p: &Point, direction: Directions) -> Vec<Point> {
-        match direction {
-            Directions::Top | Directions::Right | Directions::Bottom | Directions::Left => {
-                let mut points = vec![];
-                let mut current = p.clone();
-                loop {
-                    let next = self.get_adjacent_points(&current, direction.clone());
-                    if next.len() != 1 {
-                        break;
-                    }
-                    current = next[0];
-                    points.push(current);
-                }
-                points
-            }
-            _ => vec![]
-        }
+    // Non-allocating version of get_adjacent_points for a single direction, for use in hot loops.
+    pub fn get_adjacent_point(&self, p: &Point, direction: Dir) -> Option<Point> {
+        Some(p.step(direction, 1)).filter(|p| self.bounds.contains(p))
     }
 
-    pub fn points(&self) -> Vec<Point> {
-        let mut points = vec![];
+    pub fn get_adjacent_entry(&self, p: &Point, direction: Dir) -> Option<(Point, T)> {
+        self.get_adjacent_point(p, direction).and_then(|p| self.get(&p).map(|i| (p, i)))
+    }
 
-        for y in self.bounds.y() {
-            for x in self.bounds.x() {
-                points.push((x, y).into());
-            }
-        }
+    pub fn get_in_direction(&self, p: &Point, direction: Dir) -> Vec<T> {
+        self.get_points_in_direction(p, direction).iter().filter_map(|p| self.get(p)).collect()
+    }
 
+    // All points from the given one (exclusive) up to the edge of the grid.
+    pub fn get_points_in_direction(&self, p: &Point, direction: Dir) -> Vec<Point> {
+        let mut points = vec![];
+        let mut current = *p;
+        while let Some(next) = self.get_adjacent_point(&current, direction) {
+            current = next;
+            points.push(current);
+        }
         points
     }
 
+    pub fn points(&self) -> Vec<Point> {
+        self.bounds.points().collect()
+    }
+
     pub fn values(&self) -> Vec<T> {
         self.points().iter().filter_map(|p| self.get(p)).collect()
     }
@@ -708,6 +1057,133 @@ impl<T> Grid<T> where T: Clone {
     pub fn entries(&self) -> Vec<(Point, T)> {
         self.cells.iter().map(|(p, t)| (p.clone(), t.clone())).collect()
     }
+
+    // A grid of the same shape (bounds and empty cells), with every cell transformed.
+    pub fn map<U: Clone>(&self, transform: impl Fn(&Point, &T) -> U) -> Grid<U> {
+        Grid { bounds: self.bounds, cells: self.cells.iter().map(|(p, v)| (*p, transform(p, v))).collect() }
+    }
+
+    // The points of the cells matching the predicate, from top to bottom and left to right.
+    pub fn filter_points(&self, predicate: impl Fn(&T) -> bool) -> Vec<Point> {
+        self.bounds.points().filter(|p| self.cells.get(p).is_some_and(&predicate)).collect()
+    }
+
+    // Renders the grid using the given renderer for every cell (missing cells are rendered as a space).
+    // Unlike Display, cells are not padded to the same width, so the renderer is free to add e.g. ANSI colors.
+    pub fn render_with(&self, render: impl Fn(&Point, &T) -> String) -> String {
+        self.bounds.y().map(|y| {
+            self.bounds.x().map(|x| {
+                let point = Point { x, y };
+                match self.cells.get(&point) {
+                    Some(value) => render(&point, value),
+                    None => " ".to_string()
+                }
+            }).collect::<String>()
+        }).collect::<Vec<_>>().join("\n")
+    }
+
+    // Renders the grid like Display does (without padding), coloring the given points.
+    pub fn render_highlighted(&self, highlight: &HashSet<Point>, color: AnsiColor) -> String where T: fmt::Display {
+        self.render_with(|point, value| {
+            if highlight.contains(point) { color.paint(&value.to_string()) } else { value.to_string() }
+        })
+    }
+}
+
+impl<T> Grid<T> where T: Clone + Default + PartialEq {
+    // Drops all cells holding the default (empty) value, and shifts the remaining cells such that the bounds start at (0, 0).
+    pub fn normalize(&mut self) {
+        let empty = T::default();
+        let cells: Vec<_> = self.cells.drain().filter(|(_, v)| empty.ne(v)).collect();
+
+        let top = cells.iter().map(|(p, _)| p.y).min().unwrap_or(0);
+        let left = cells.iter().map(|(p, _)| p.x).min().unwrap_or(0);
+
+        *self = Self::new(cells.into_iter().map(|(p, v)| (p - (left, top), v)).collect());
+        if self.cells.is_empty() {
+            self.bounds = Bounds::default();
+        }
+    }
+}
+
+// A view on a grid that repeats it infinitely in all directions (like day 21's garden).
+#[derive(Copy, Clone)]
+pub struct WrappingGrid<'a, T> where T: Clone {
+    grid: &'a Grid<T>,
+}
+
+impl<'a, T> WrappingGrid<'a, T> where T: Clone {
+    pub fn new(grid: &'a Grid<T>) -> Self {
+        Self { grid }
+    }
+
+    // The point in the underlying grid the given point maps to, if the grid has any.
+    pub fn wrap(&self, p: &Point) -> Option<Point> {
+        let bounds = &self.grid.bounds;
+        if bounds.width == 0 || bounds.height == 0 { return None; }
+        Some(Point {
+            x: bounds.left + (p.x - bounds.left).rem_euclid(bounds.width as isize),
+            y: bounds.top + (p.y - bounds.top).rem_euclid(bounds.height as isize),
+        })
+    }
+
+    // Which copy of the grid the given point falls in, the underlying grid itself being copy (0, 0), the one to its
+    // right (1, 0), etc. An empty grid has no copies.
+    pub fn copy_of(&self, p: &Point) -> Option<Point> {
+        let bounds = &self.grid.bounds;
+        if bounds.width == 0 || bounds.height == 0 { return None; }
+        Some(Point {
+            x: (p.x - bounds.left).div_euclid(bounds.width as isize),
+            y: (p.y - bounds.top).div_euclid(bounds.height as isize),
+        })
+    }
+
+    pub fn get(&self, p: &Point) -> Option<T> {
+        self.grid.get(&self.wrap(p)?)
+    }
+}
+
+// A maximal horizontal run of digits in a character grid, e.g. a part number in a schematic.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct NumberSpan {
+    pub value: isize,
+    pub bounds: Bounds,
+}
+
+impl NumberSpan {
+    pub fn points(&self) -> Vec<Point> {
+        self.bounds.points().collect()
+    }
+}
+
+#[allow(unused)]
+impl Grid<char> {
+    // Finds all numbers written horizontally in the grid, in reading order (top to bottom, left to right).
+    pub fn extract_number_spans(&self) -> Vec<NumberSpan> {
+        let mut spans = vec![];
+
+        for y in self.bounds.y() {
+            let mut current: Option<(isize, isize)> = None; // (start x, value)
+
+            for x in self.bounds.x() {
+                match (self.get(&Point { x, y }).and_then(|c| c.to_digit(10)), current) {
+                    (Some(digit), None) => current = Some((x, digit as isize)),
+                    (Some(digit), Some((start, value))) => current = Some((start, value * 10 + digit as isize)),
+                    (None, Some((start, value))) => {
+                        spans.push(NumberSpan { value, bounds: Bounds::from_tlbr(y, start, y, x - 1) });
+                        current = None;
+                    }
+                    (None, None) => {}
+                }
+            }
+
+            if let Some((start, value)) = current {
+                spans.push(NumberSpan { value, bounds: Bounds::from_tlbr(y, start, y, self.bounds.right()) });
+            }
+        }
+
+        spans
+    }
 }
 
 impl<T> fmt::Debug for Grid<T> where T: fmt::Display + Clone {
@@ -747,56 +1223,103 @@ impl<T> fmt::Display for Grid<T> where T: fmt::Display + Clone {
     }
 }
 
-impl<T> FromStr for Grid<T> where T: FromStr + Clone + Default {
-    type Err = String;
+// How the cells of a grid are laid out on the lines of the input.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum CellFormat {
+    // Every char is a cell (what FromStr uses).
+    Chars,
+    // Every cell takes the given number of chars, padding around the value is ignored.
+    Width(usize),
+    // Cells are separated by (any amount of) whitespace.
+    Whitespace,
+    // Cells are separated by the given char, whitespace around the values is ignored.
+    Delimited(char),
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parse_result: Result<Vec<Vec<T>>, String> = s.lines()
-            .filter(|l| !l.is_empty())
-            .map(|l| l.chars().map(|c|
-                String::from(c).parse::<T>().map_err(|_| format!("Could not parse '{}' to {}", c, std::any::type_name::<T>())))
-                .collect::<Result<Vec<T>, String>>())
-            .collect();
+impl CellFormat {
+    // Guesses the format of the input: cells separated by commas, or by whitespace, and otherwise a cell per char.
+    pub fn detect(s: &str) -> Self {
+        if s.contains(',') {
+            CellFormat::Delimited(',')
+        } else if s.lines().any(|l| l.trim().contains(char::is_whitespace)) {
+            CellFormat::Whitespace
+        } else {
+            CellFormat::Chars
+        }
+    }
+}
 
-        let cells = match parse_result {
-            Ok(lines) if lines.len() == 0 => {
-                return Ok(Grid::default());
+impl<T> Grid<T> where T: FromStr + Clone + Default {
+    pub fn parse_with(s: &str, format: CellFormat) -> AocResult<Self> {
+        fn parse_cell<T: FromStr>(cell: &str) -> AocResult<T> {
+            cell.parse::<T>().map_err(|_| AocError::Parse(format!("Could not parse '{}' to {}", cell, std::any::type_name::<T>())))
+        }
+
+        fn split_line(line: &str, format: CellFormat) -> AocResult<Vec<&str>> {
+            match format {
+                CellFormat::Chars => Ok(line.char_indices().map(|(i, c)| &line[i..i + c.len_utf8()]).collect()),
+                CellFormat::Width(0) => Err(AocError::Unsupported("Cannot parse cells with a width of 0".to_string())),
+                CellFormat::Width(width) => {
+                    let boundaries: Vec<_> = line.char_indices().map(|(i, _)| i).step_by(width).chain([line.len()]).collect();
+                    Ok(boundaries.windows(2).map(|w| line[w[0]..w[1]].trim()).collect())
+                }
+                CellFormat::Whitespace => Ok(line.split_whitespace().collect()),
+                CellFormat::Delimited(delimiter) => Ok(line.split(delimiter).map(|c| c.trim()).collect()),
             }
-            Ok(lines) => lines,
-            Err(e) => return Err(e)
-        };
+        }
+
+        let lines: Vec<Vec<T>> = s.lines()
+            .filter(|l| !l.is_empty())
+            .map(|l| split_line(l, format)?.into_iter().map(parse_cell).collect::<AocResult<Vec<T>>>())
+            .collect::<Result<_, _>>()?;
+
+        if lines.is_empty() {
+            return Ok(Grid::default());
+        }
+
+        Grid::try_from(lines)
+    }
+}
+
+impl<T> FromStr for Grid<T> where T: FromStr + Clone + Default {
+    type Err = AocError;
 
-        Grid::try_from(cells)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Grid::parse_with(s, CellFormat::Chars)
     }
 }
 
 impl<T> TryFrom<Vec<Vec<T>>> for Grid<T> where T: Clone + Default {
-    type Error = String;
+    type Error = AocError;
 
     fn try_from(data: Vec<Vec<T>>) -> Result<Self, Self::Error> {
         let height = data.len();
-        let width = data[0].len();
+        let width = data.first().map_or(0, |l| l.len());
 
         let bounds = Bounds { top: 0, left: 0, width, height };
 
         if data.iter().all(|l| l.len() == width) {
             let mut cells = HashMap::new();
-            for y in 0..height {
-                for x in 0..width {
-                    cells.insert((x, y).try_into().unwrap(), data[y][x].clone());
+            for row in 0..height {
+                for col in 0..width {
+                    cells.insert(RowCol { row, col }.try_into().unwrap(), data[row][col].clone());
                 }
             }
 
             Ok(Grid { bounds, cells })
         } else {
-            Err(format!("Not all lines in input are the same width"))
+            Err(AocError::Parse("Not all lines in input are the same width".to_string()))
         }
     }
 }
 
 #[cfg(test)]
 mod grid_tests {
-    use crate::util::geometry::{Grid, Directions, Bounds};
+    use std::collections::{HashMap, HashSet};
+    use crate::util::allocations::count_allocations;
+    use crate::util::color::AnsiColor;
+    use crate::util::error::AocError;
+    use crate::util::geometry::{Grid, Dir, Directions, Bounds, CellFormat, NumberSpan, Point, WrappingGrid};
 
     const EXAMPLE_GRID_INPUT: &str = "\
         2199943210\n\
@@ -840,11 +1363,138 @@ mod grid_tests {
             9|8|9|9|9|6|5|6|7|8");
     }
 
+    #[test]
+    fn test_render_with() {
+        let grid = get_example_grid();
+        assert_eq!(grid.render_with(|_, v| v.to_string()), EXAMPLE_GRID_INPUT);
+        assert_eq!(grid.render_with(|p, v| if p.y == 0 && *v > 5 { "#".to_string() } else { ".".to_string() }), "\
+            ..###.....\n\
+            ..........\n\
+            ..........\n\
+            ..........\n\
+            ..........");
+
+        let mut sparse = Grid::empty();
+        sparse.set((0, 0).into(), 'a');
+        sparse.set((2, 1).into(), 'b');
+        assert_eq!(sparse.render_with(|_, v| v.to_string()), "a  \n  b");
+    }
+
+    #[test]
+    fn test_render_highlighted() {
+        let grid: Grid<usize> = vec![vec![1, 2], vec![3, 4]].try_into().unwrap();
+        let highlight = HashSet::from([(1, 0).into(), (0, 1).into()]);
+        assert_eq!(grid.render_highlighted(&highlight, AnsiColor::Red), "1\x1b[31m2\x1b[0m\n\x1b[31m3\x1b[0m4");
+    }
+
     #[test]
     fn test_grid_from_str() {
         assert_eq!(EXAMPLE_GRID_INPUT.parse::<Grid<usize>>(), Ok(get_example_grid()));
     }
 
+    #[test]
+    fn test_map_and_filter() {
+        let grid = get_example_grid();
+
+        let peaks = grid.map(|_, v| *v == 9);
+        assert_eq!(peaks.bounds, grid.bounds);
+        assert_eq!(peaks.get(&(2, 0).into()), Some(true));
+        assert_eq!(peaks.values().into_iter().filter(|p| *p).count(), 15);
+
+        let columns = grid.map(|p, _| p.x);
+        assert_eq!(columns.get(&(2, 3).into()), Some(2));
+
+        assert_eq!(grid.filter_points(|v| *v == 1), vec![(1, 0).into(), (8, 0).into(), (9, 1).into()]);
+        assert_eq!(grid.filter_points(|v| *v > 9), vec![]);
+        // Empty cells never match
+        let mut sparse = Grid::empty();
+        sparse.set((1, 1).into(), 9);
+        sparse.set((3, 0).into(), 9);
+        assert_eq!(sparse.filter_points(|_| true), vec![(3, 0).into(), (1, 1).into()]);
+    }
+
+    #[test]
+    fn test_view_and_paste() {
+        let grid = get_example_grid();
+
+        let view = grid.view(&Bounds { top: 1, left: 2, width: 3, height: 2 });
+        assert_eq!(view.bounds, Bounds::from_size(3, 2));
+        assert_eq!(view.rows(), vec![grid.get_row(1)[2..5].to_vec(), grid.get_row(2)[2..5].to_vec()]);
+
+        // Partially outside of the grid:
+        let view = grid.view(&Bounds { top: -1, left: 8, width: 4, height: 2 });
+        assert_eq!(view.bounds, Bounds::from_size(4, 2));
+        assert_eq!(view.entries().len(), 2);
+        assert!(view.has(&(0, 1).into()) && view.has(&(1, 1).into()));
+
+        let mut tiled = Grid::empty();
+        tiled.paste((0, 0).into(), &grid);
+        tiled.paste((grid.bounds.width as isize, 0).into(), &grid);
+        assert_eq!(tiled.bounds, Bounds::from_size(grid.bounds.width * 2, grid.bounds.height));
+        assert_eq!(tiled.view(&Bounds { left: grid.bounds.width as isize, ..grid.bounds }), grid);
+
+        // Pasting over existing cells:
+        tiled.paste((1, 1).into(), &vec![vec![42]].try_into().unwrap());
+        assert_eq!(tiled.get(&(1, 1).into()), Some(42));
+        assert_eq!(tiled.bounds, Bounds::from_size(grid.bounds.width * 2, grid.bounds.height));
+    }
+
+    #[test]
+    fn test_wrapping_grid() {
+        let grid = get_example_grid();
+        let wrapping = WrappingGrid::new(&grid);
+        let (width, height) = (grid.bounds.width as isize, grid.bounds.height as isize);
+
+        for p in grid.points() {
+            assert_eq!(wrapping.get(&p), grid.get(&p));
+            assert_eq!(wrapping.copy_of(&p), Some((0, 0).into()));
+            assert_eq!(wrapping.get(&(p + (width, 0))), grid.get(&p));
+            assert_eq!(wrapping.get(&(p + (-3 * width, 2 * height))), grid.get(&p));
+            assert_eq!(wrapping.copy_of(&(p + (-3 * width, 2 * height))), Some((-3, 2).into()));
+        }
+
+        assert_eq!(wrapping.wrap(&(-1, -1).into()), Some((width - 1, height - 1).into()));
+        assert_eq!(wrapping.copy_of(&(-1, -1).into()), Some((-1, -1).into()));
+
+        // Grids not starting at (0, 0):
+        let mut moved = Grid::empty();
+        moved.paste((-2, 3).into(), &grid);
+        let wrapping = WrappingGrid::new(&moved);
+        assert_eq!(wrapping.wrap(&(-3, 3).into()), Some((width - 3, 3).into()));
+        assert_eq!(wrapping.copy_of(&(-3, 3).into()), Some((-1, 0).into()));
+        assert_eq!(wrapping.get(&(-2 + width, 3).into()), grid.get(&(0, 0).into()));
+
+        // An empty grid has nothing to wrap to:
+        let empty: Grid<usize> = Grid::empty();
+        let wrapping = WrappingGrid::new(&empty);
+        assert_eq!(wrapping.wrap(&(3, 3).into()), None);
+        assert_eq!(wrapping.copy_of(&(3, 3).into()), None);
+        assert_eq!(wrapping.get(&(3, 3).into()), None);
+    }
+
+    #[test]
+    fn test_grid_parse_with() {
+        let expected: Grid<usize> = vec![vec![1, 23, 4], vec![567, 8, 90]].try_into().unwrap();
+
+        assert_eq!(Grid::parse_with("1 23   4\n567 8 90\n", CellFormat::Whitespace), Ok(expected.clone()));
+        assert_eq!(Grid::parse_with("1,23,4\n567, 8, 90", CellFormat::Delimited(',')), Ok(expected.clone()));
+        assert_eq!(Grid::parse_with("  1 23  4\n567  8 90", CellFormat::Width(3)), Ok(expected.clone()));
+        assert_eq!(Grid::parse_with(EXAMPLE_GRID_INPUT, CellFormat::Chars), Ok(get_example_grid()));
+
+        assert_eq!(Grid::<usize>::parse_with("1,23\n4", CellFormat::Delimited(',')), Err(AocError::Parse("Not all lines in input are the same width".to_string())));
+        assert!(matches!(Grid::<usize>::parse_with("1,,3", CellFormat::Delimited(',')), Err(AocError::Parse(_))));
+        assert!(Grid::<usize>::parse_with("123", CellFormat::Width(0)).is_err());
+        assert_eq!(Grid::<usize>::parse_with("\n", CellFormat::Whitespace), Ok(Grid::default()));
+    }
+
+    #[test]
+    fn test_cell_format_detect() {
+        assert_eq!(CellFormat::detect("1,23,4\n567, 8, 90"), CellFormat::Delimited(','));
+        assert_eq!(CellFormat::detect("1 23   4\n567 8 90"), CellFormat::Whitespace);
+        assert_eq!(CellFormat::detect("1234\r\n5678\r\n"), CellFormat::Chars);
+        assert_eq!(CellFormat::detect(EXAMPLE_GRID_INPUT), CellFormat::Chars);
+    }
+
     #[test]
     fn test_get_adjacent() {
         let grid = get_example_grid();
@@ -868,12 +1518,23 @@ mod grid_tests {
                    vec![(4, 2).into(), (5, 2).into(), (6, 2).into(), (6, 3).into(), (6, 4).into(), (5, 4).into(), (4, 4).into(), (4, 3).into()]);
     }
 
+    #[test]
+    fn test_get_adjacent_point() {
+        let grid = get_example_grid();
+
+        assert_eq!(grid.get_adjacent_point(&(0, 0).into(), Dir::East), Some((1, 0).into()));
+        assert_eq!(grid.get_adjacent_point(&(0, 0).into(), Dir::South), Some((0, 1).into()));
+        assert_eq!(grid.get_adjacent_point(&(0, 0).into(), Dir::North), None);
+        assert_eq!(grid.get_adjacent_point(&(0, 0).into(), Dir::West), None);
+        assert_eq!(grid.get_adjacent_entry(&(5, 3).into(), Dir::North), grid.get_adjacent_entries(&(5, 3).into(), Directions::Top).first().cloned());
+    }
+
     #[test]
     fn test_get_points_in_direction() {
         let grid = get_example_grid();
-        assert_eq!(grid.get_points_in_direction(&(0, 0).into(), Directions::Left), vec![]);
-        assert_eq!(grid.get_points_in_direction(&(1, 0).into(), Directions::Left), vec![(0, 0).into()]);
-        assert_eq!(grid.get_points_in_direction(&(2, 0).into(), Directions::Left), vec![(1, 0).into(), (0, 0).into()]);
+        assert_eq!(grid.get_points_in_direction(&(0, 0).into(), Dir::West), vec![]);
+        assert_eq!(grid.get_points_in_direction(&(1, 0).into(), Dir::West), vec![(0, 0).into()]);
+        assert_eq!(grid.get_points_in_direction(&(2, 0).into(), Dir::West), vec![(1, 0).into(), (0, 0).into()]);
     }
 
     #[test]
@@ -888,6 +1549,24 @@ mod grid_tests {
         assert_eq!(get_example_grid().get_column(5), vec![4, 9, 8, 9, 6]);
     }
 
+    #[test]
+    fn test_iter_row_and_col() {
+        let grid = get_example_grid();
+        assert_eq!(grid.iter_row(3).copied().collect::<Vec<_>>(), vec![8, 7, 6, 7, 8, 9, 6, 7, 8, 9]);
+        assert_eq!(grid.iter_col(5).copied().collect::<Vec<_>>(), vec![4, 9, 8, 9, 6]);
+        assert_eq!(grid.iter_row(5).count(), 0);
+
+        let (sum, allocations) = count_allocations(|| grid.iter_row(1).chain(grid.iter_col(1)).sum::<usize>());
+        assert_eq!(sum, 60 + 33);
+        assert_eq!(allocations, 0);
+
+        // Empty cells are skipped, like get_row and get_column do
+        let mut sparse: Grid<usize> = Grid::new(HashMap::from([(Point { x: 0, y: 0 }, 1), (Point { x: 2, y: 1 }, 2)]));
+        sparse.set(Point { x: 2, y: 0 }, 3);
+        assert_eq!(sparse.iter_row(0).collect::<Vec<_>>(), vec![&1, &3]);
+        assert_eq!(sparse.iter_col(2).collect::<Vec<_>>(), vec![&3, &2]);
+    }
+
     #[test]
     fn test_values() {
         let grid: Grid<usize> = vec![vec![1, 2, 3], vec![9, 8, 7], vec![5, 6, 4]].try_into().unwrap();
@@ -924,4 +1603,62 @@ mod grid_tests {
             (-2, 3).into(), (-1, 3).into(), (0, 3).into(), (1, 3).into(), (2, 3).into(),
         ]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_normalize() {
+        let mut grid: Grid<usize> = Grid::default();
+        grid.set((-3, -2).into(), 0);
+        grid.set((-2, -1).into(), 12);
+        grid.set((1, -1).into(), 0);
+        grid.set((0, 1).into(), 42);
+
+        grid.normalize();
+        assert_eq!(grid.bounds, Bounds { top: 0, left: 0, width: 3, height: 3 });
+        assert_eq!(grid.entries().len(), 2);
+        assert_eq!(grid.get(&(0, 0).into()), Some(12));
+        assert_eq!(grid.get(&(2, 2).into()), Some(42));
+
+        let mut empty: Grid<usize> = Grid::default();
+        empty.set((4, 4).into(), 0);
+        empty.normalize();
+        assert_eq!(empty.bounds, Bounds::default());
+        assert_eq!(empty.points(), vec![]);
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let grid = get_example_grid();
+
+        assert_eq!(grid.neighbors(&(0, 0).into(), Directions::All).collect::<Vec<_>>(), vec![
+            ((1, 0).into(), &1), ((1, 1).into(), &9), ((0, 1).into(), &3),
+        ]);
+        assert_eq!(grid.neighbors(&(2, 2).into(), Directions::NonDiagonal).collect::<Vec<_>>(), vec![
+            ((2, 1).into(), &8), ((3, 2).into(), &6), ((2, 3).into(), &6), ((1, 2).into(), &8),
+        ]);
+        assert_eq!(
+            grid.neighbors(&(2, 2).into(), Directions::All).map(|(p, v)| (p, *v)).collect::<Vec<_>>(),
+            grid.get_adjacent_entries(&(2, 2).into(), Directions::All)
+        );
+
+        let (count, allocations) = count_allocations(|| grid.neighbors(&(4, 3).into(), Directions::All).count());
+        assert_eq!(count, 8);
+        assert_eq!(allocations, 0);
+    }
+
+    #[test]
+    fn test_extract_number_spans() {
+        let grid: Grid<char> = "\
+            467..114\n\
+            ...*....\n\
+            ..35..63\
+        ".parse().unwrap();
+
+        assert_eq!(grid.extract_number_spans(), vec![
+            NumberSpan { value: 467, bounds: Bounds::from_tlbr(0, 0, 0, 2) },
+            NumberSpan { value: 114, bounds: Bounds::from_tlbr(0, 5, 0, 7) },
+            NumberSpan { value: 35, bounds: Bounds::from_tlbr(2, 2, 2, 3) },
+            NumberSpan { value: 63, bounds: Bounds::from_tlbr(2, 6, 2, 7) },
+        ]);
+        assert_eq!(Grid::<char>::empty().extract_number_spans(), vec![]);
+    }
+}
@@ -0,0 +1,233 @@
+// A directed graph, for puzzles that wire things together (day 20's modules, day 19's workflows, day 25's components).
+// Nodes and edges are addressed by their index (in order of adding them), and carry a value of their own.
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Edge<E> {
+    pub from: usize,
+    pub to: usize,
+    pub value: E,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct DiGraph<N, E> {
+    nodes: Vec<N>,
+    edges: Vec<Edge<E>>,
+    // Edge indexes per node
+    outgoing: Vec<Vec<usize>>,
+    incoming: Vec<Vec<usize>>,
+}
+
+impl<N, E> Default for DiGraph<N, E> {
+    fn default() -> Self {
+        Self { nodes: vec![], edges: vec![], outgoing: vec![], incoming: vec![] }
+    }
+}
+
+impl<N, E> DiGraph<N, E> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_node(&mut self, node: N) -> usize {
+        self.nodes.push(node);
+        self.outgoing.push(vec![]);
+        self.incoming.push(vec![]);
+        self.nodes.len() - 1
+    }
+
+    // Note: panics when either node does not exist, as that's a bug in building the graph.
+    pub fn add_edge(&mut self, from: usize, to: usize, value: E) -> usize {
+        assert!(from < self.nodes.len() && to < self.nodes.len(), "Edge {} -> {} between unknown nodes", from, to);
+
+        self.edges.push(Edge { from, to, value });
+        let index = self.edges.len() - 1;
+        self.outgoing[from].push(index);
+        self.incoming[to].push(index);
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, index: usize) -> &N {
+        &self.nodes[index]
+    }
+
+    pub fn node_mut(&mut self, index: usize) -> &mut N {
+        &mut self.nodes[index]
+    }
+
+    pub fn outgoing_edges(&self, node: usize) -> impl Iterator<Item=&Edge<E>> + '_ {
+        self.outgoing[node].iter().map(|e| &self.edges[*e])
+    }
+
+    pub fn incoming_edges(&self, node: usize) -> impl Iterator<Item=&Edge<E>> + '_ {
+        self.incoming[node].iter().map(|e| &self.edges[*e])
+    }
+
+    pub fn successors(&self, node: usize) -> impl Iterator<Item=usize> + '_ {
+        self.outgoing_edges(node).map(|e| e.to)
+    }
+
+    pub fn predecessors(&self, node: usize) -> impl Iterator<Item=usize> + '_ {
+        self.incoming_edges(node).map(|e| e.from)
+    }
+
+    // All nodes that can be reached from the given node (including itself), in breadth-first order.
+    pub fn reachable_from(&self, start: usize) -> Vec<usize> {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut result = vec![start];
+        seen[start] = true;
+
+        let mut index = 0;
+        while index < result.len() {
+            for next in self.successors(result[index]) {
+                if !seen[next] {
+                    seen[next] = true;
+                    result.push(next);
+                }
+            }
+            index += 1;
+        }
+
+        result
+    }
+
+    pub fn can_reach(&self, from: usize, to: usize) -> bool {
+        self.reachable_from(from).contains(&to)
+    }
+
+    // Groups the nodes into strongly connected components, sets of nodes that can all reach each other (Tarjan's
+    // algorithm). Every node is in exactly one component, and components come in reverse topological order: edges
+    // between components only go to components earlier in the list. Nodes within a component are sorted.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        const UNVISITED: usize = usize::MAX;
+
+        let mut order = vec![UNVISITED; self.nodes.len()];
+        let mut low_link = vec![0; self.nodes.len()];
+        let mut on_stack = vec![false; self.nodes.len()];
+        let mut stack = vec![];
+        let mut components = vec![];
+        let mut counter = 0;
+
+        for root in 0..self.nodes.len() {
+            if order[root] != UNVISITED { continue; }
+
+            // Explicit call stack of (node, index of the next outgoing edge to follow), as recursion could get deep.
+            let mut calls = vec![(root, 0)];
+            order[root] = counter;
+            low_link[root] = counter;
+            counter += 1;
+            stack.push(root);
+            on_stack[root] = true;
+
+            while let Some((node, edge)) = calls.pop() {
+                if let Some(next) = self.outgoing[node].get(edge).map(|e| self.edges[*e].to) {
+                    calls.push((node, edge + 1));
+                    if order[next] == UNVISITED {
+                        order[next] = counter;
+                        low_link[next] = counter;
+                        counter += 1;
+                        stack.push(next);
+                        on_stack[next] = true;
+                        calls.push((next, 0));
+                    } else if on_stack[next] {
+                        low_link[node] = low_link[node].min(order[next]);
+                    }
+                    continue;
+                }
+
+                // Done with all edges of this node; pass the low link back to the node we came from.
+                if let Some((parent, _)) = calls.last() {
+                    low_link[*parent] = low_link[*parent].min(low_link[node]);
+                }
+
+                if low_link[node] == order[node] {
+                    let mut component = vec![];
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == node { break; }
+                    }
+                    component.sort();
+                    components.push(component);
+                }
+            }
+        }
+
+        components
+    }
+
+    // The components that contain a cycle, i.e. those with multiple nodes, or a single node with an edge to itself.
+    pub fn cyclic_components(&self) -> Vec<Vec<usize>> {
+        self.strongly_connected_components().into_iter()
+            .filter(|c| c.len() > 1 || self.successors(c[0]).any(|n| n == c[0]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::graph::DiGraph;
+
+    // a -> b -> c -> a, c -> d -> e, e -> e, f
+    fn test_graph() -> DiGraph<&'static str, ()> {
+        let mut graph = DiGraph::new();
+        for name in ["a", "b", "c", "d", "e", "f"] {
+            graph.add_node(name);
+        }
+        for (from, to) in [(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 4)] {
+            graph.add_edge(from, to, ());
+        }
+        graph
+    }
+
+    #[test]
+    fn test_nodes_and_edges() {
+        let mut graph = test_graph();
+
+        assert_eq!(graph.len(), 6);
+        assert_eq!(graph.add_node("x"), 6);
+        assert_eq!(graph.node(6), &"x");
+        *graph.node_mut(6) = "y";
+        assert_eq!(graph.node(6), &"y");
+        assert!(!graph.is_empty());
+
+        assert_eq!(graph.successors(2).collect::<Vec<_>>(), vec![0, 3]);
+        assert_eq!(graph.predecessors(4).collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!(graph.predecessors(5).count(), 0);
+    }
+
+    #[test]
+    fn test_reachable_from() {
+        let graph = test_graph();
+
+        assert_eq!(graph.reachable_from(0), vec![0, 1, 2, 3, 4]);
+        assert_eq!(graph.reachable_from(3), vec![3, 4]);
+        assert_eq!(graph.reachable_from(5), vec![5]);
+        assert!(graph.can_reach(1, 0));
+        assert!(!graph.can_reach(3, 0));
+    }
+
+    #[test]
+    fn test_strongly_connected_components() {
+        let graph = test_graph();
+
+        assert_eq!(graph.strongly_connected_components(), vec![vec![4], vec![3], vec![0, 1, 2], vec![5]]);
+        assert_eq!(graph.cyclic_components(), vec![vec![4], vec![0, 1, 2]]);
+
+        // A long chain should not overflow any stack
+        let mut chain = DiGraph::new();
+        let nodes: Vec<_> = (0..100_000).map(|n| chain.add_node(n)).collect();
+        for pair in nodes.windows(2) {
+            chain.add_edge(pair[0], pair[1], ());
+        }
+        chain.add_edge(nodes[nodes.len() - 1], nodes[0], ());
+        assert_eq!(chain.strongly_connected_components().len(), 1);
+    }
+}
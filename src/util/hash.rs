@@ -0,0 +1,106 @@
+// The HASH (Holiday ASCII String Helper) algorithm from 2023 day 15, with configurable parameters to play around with.
+
+use std::num::NonZeroUsize;
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct HashParams {
+    pub multiplier: usize,
+    // Also the number of boxes of a HashmapBoxes, so there has to be at least one.
+    pub modulus: NonZeroUsize,
+}
+
+impl HashParams {
+    // The parameters as given by the puzzle.
+    pub const HASH: HashParams = HashParams { multiplier: 17, modulus: NonZeroUsize::new(256).unwrap() };
+}
+
+// Hashes the input to a value in 0..params.modulus
+fn hash(input: &str, params: HashParams) -> usize {
+    if !input.is_ascii() { panic!("Can only hash ascii chars") }
+    hash_bytes(input.as_bytes(), params)
+}
+
+pub fn hash_bytes(input: &[u8], params: HashParams) -> usize {
+    input.iter().fold(0, |hash, byte| hash_step(hash, *byte, params))
+}
+
+// Adds a single byte to a hash.
+fn hash_step(hash: usize, byte: u8, params: HashParams) -> usize {
+    ((hash + byte as usize) * params.multiplier) % params.modulus.get()
+}
+
+// The HASHMAP: a box for every possible hash value, each holding labelled values in the order they were put in.
+// Labels can be anything string-like, e.g. String to own them or &str to borrow them from the input.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct HashmapBoxes<L, V> {
+    params: HashParams,
+    boxes: Vec<Vec<(L, V)>>,
+}
+
+impl<L: AsRef<str>, V> HashmapBoxes<L, V> {
+    pub fn new(params: HashParams) -> Self {
+        Self { params, boxes: (0..params.modulus.get()).map(|_| vec![]).collect() }
+    }
+
+    // Replaces the value if the label is already in its box (keeping its place), otherwise adds it to the back.
+    pub fn insert(&mut self, label: L, value: V) {
+        let bucket = &mut self.boxes[hash(label.as_ref(), self.params)];
+        match bucket.iter_mut().find(|(l, _)| label.as_ref() == l.as_ref()) {
+            Some((_, existing)) => *existing = value,
+            None => bucket.push((label, value))
+        }
+    }
+
+    // Takes the value out of its box (if it's there), moving everything behind it forward.
+    pub fn remove(&mut self, label: &str) -> Option<V> {
+        let bucket = &mut self.boxes[hash(label, self.params)];
+        let index = bucket.iter().position(|(l, _)| label == l.as_ref())?;
+        Some(bucket.remove(index).1)
+    }
+
+    // Folds over all values, passing the (0-based) box and slot index of each value.
+    pub fn fold<A>(&self, init: A, f: impl Fn(A, usize, usize, &V) -> A) -> A {
+        self.boxes.iter().enumerate()
+            .flat_map(|(box_index, bucket)| bucket.iter().enumerate().map(move |(slot, (_, v))| (box_index, slot, v)))
+            .fold(init, |acc, (box_index, slot, v)| f(acc, box_index, slot, v))
+    }
+
+    // The sum of (box number) * (slot number) * power of each value, both numbers starting at 1.
+    pub fn focusing_power(&self, power: impl Fn(&V) -> usize) -> usize {
+        self.fold(0, |acc, box_index, slot, v| acc + (box_index + 1) * (slot + 1) * power(v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+    use crate::util::hash::{hash, hash_bytes, hash_step, HashmapBoxes, HashParams};
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(hash("HASH", HashParams::HASH), 52);
+        assert_eq!(hash("", HashParams::HASH), 0);
+        assert_eq!(hash("H", HashParams { multiplier: 1, modulus: NonZeroUsize::new(1000).unwrap() }), 72);
+        assert_eq!(hash_bytes(b"HASH", HashParams::HASH), 52);
+        assert_eq!(b"HASH".iter().fold(0, |h, b| hash_step(h, *b, HashParams::HASH)), 52);
+    }
+
+    #[test]
+    fn test_hashmap_boxes() {
+        // "rn" and "cm" both hash to box 0, "qp" to box 1
+        let mut boxes: HashmapBoxes<&str, usize> = HashmapBoxes::new(HashParams::HASH);
+        boxes.insert("rn", 1);
+        boxes.insert("cm", 2);
+        boxes.insert("qp", 3);
+        assert_eq!(boxes.focusing_power(|v| *v), 1 + 2 * 2 + 2 * 3);
+
+        // Replacing keeps the slot, removing moves the rest forward
+        boxes.insert("rn", 5);
+        assert_eq!(boxes.focusing_power(|v| *v), 5 + 2 * 2 + 2 * 3);
+        assert_eq!(boxes.remove("rn"), Some(5));
+        assert_eq!(boxes.remove("rn"), None);
+        assert_eq!(boxes.focusing_power(|v| *v), 2 + 2 * 3);
+
+        assert_eq!(boxes.fold(vec![], |mut acc, b, s, v| { acc.push((b, s, *v)); acc }), vec![(0, 0, 2), (1, 0, 3)]);
+    }
+}
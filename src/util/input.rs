@@ -1,5 +1,85 @@
-use std::fs::read_to_string;
+use std::fs::{read_dir, read_to_string};
+use std::io::{read_to_string as read_all, stdin};
+use crate::util::error::{AocError, AocResult};
 
-pub fn read_input(day: i32) -> Result<String, String> {
-    read_to_string(format!("resources/day{:02}.txt", day)).map_err(|e| format!("{}", e))
+// Where to read the puzzle input from, by default this is the input file for the day in the resources folder.
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub enum InputSource {
+    #[default]
+    Default,
+    File(String),
+    Stdin,
+}
+
+impl InputSource {
+    // Suffix to tell results for this input apart from results for the default input (e.g. in answers.json).
+    pub fn suffix(&self) -> String {
+        match self {
+            InputSource::Default => String::new(),
+            InputSource::File(path) => format!(":{}", path),
+            InputSource::Stdin => ":stdin".to_string(),
+        }
+    }
+}
+
+pub fn read_input(day: i32) -> AocResult<String> {
+    let path = format!("resources/day{:02}.txt", day);
+    read_to_string(&path).map_err(|e| AocError::Io(format!("Could not read input file '{}': {}", path, e)))
+}
+
+pub fn read_input_from(day: i32, source: &InputSource) -> AocResult<String> {
+    match source {
+        InputSource::Default => read_input(day),
+        InputSource::File(path) => read_to_string(path).map_err(|e| AocError::Io(format!("Could not read input file '{}': {}", path, e))),
+        InputSource::Stdin => read_all(stdin()).map_err(|e| AocError::Io(format!("Could not read input from stdin: {}", e))),
+    }
+}
+
+// Lists the inputs for the given day in a directory (files named dayXX*.txt), sorted by name.
+pub fn list_inputs(dir: &str, day: i32) -> AocResult<Vec<String>> {
+    let prefix = format!("day{:02}", day);
+    let entries = read_dir(dir).map_err(|e| AocError::Io(format!("Could not read input directory '{}': {}", dir, e)))?;
+
+    let mut paths = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        let matches = path.file_name().and_then(|n| n.to_str())
+            .map(|n| n.starts_with(&prefix) && n.ends_with(".txt"))
+            .unwrap_or(false);
+        if matches && path.is_file() {
+            paths.push(path.to_string_lossy().to_string());
+        }
+    }
+
+    if paths.is_empty() {
+        return Err(AocError::Io(format!("No inputs for day {} in '{}' (expected files named {}*.txt)", day, dir, prefix)));
+    }
+
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+    use crate::util::input::list_inputs;
+
+    #[test]
+    fn test_list_inputs() {
+        let dir = temp_dir().join(format!("aoc-2023-list-inputs-{}", std::process::id()));
+        create_dir_all(dir.join("day05-dir.txt")).unwrap();
+        for name in ["day05.txt", "day05-friend.txt", "day05.md", "day15.txt", "day6.txt"] {
+            write(dir.join(name), "").unwrap();
+        }
+        let dir_str = dir.to_string_lossy().to_string();
+
+        let result = list_inputs(&dir_str, 5);
+        let expected = [dir.join("day05-friend.txt"), dir.join("day05.txt")].iter().map(|p| p.to_string_lossy().to_string()).collect::<Vec<_>>();
+        assert_eq!(result, Ok(expected));
+        assert!(list_inputs(&dir_str, 6).is_err());
+        assert!(list_inputs(&dir.join("missing").to_string_lossy(), 5).is_err());
+
+        remove_dir_all(dir).unwrap();
+    }
 }
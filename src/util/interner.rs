@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+// Maps names (of nodes, modules, components, ...) to small ids and back. Puzzles can then pass around and compare
+// plain integers in their hot loops, instead of cloning and hashing strings, and only go back to the names for output.
+// Ids are handed out in order (0, 1, 2, ...), so they can be used to index a Vec as well.
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct Interner {
+    ids: HashMap<String, u32>,
+    names: Vec<String>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The id of the name, adding it when we haven't seen it before.
+    pub fn intern(&mut self, name: &str) -> u32 {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+        let id = self.names.len() as u32;
+        self.ids.insert(name.to_string(), id);
+        self.names.push(name.to_string());
+        id
+    }
+
+    // The id of the name, if it was interned.
+    pub fn get(&self, name: &str) -> Option<u32> {
+        self.ids.get(name).copied()
+    }
+
+    // Note: panics for ids that were not handed out by this interner.
+    pub fn name(&self, id: u32) -> &str {
+        &self.names[id as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    // All ids with their names, in the order they were interned.
+    pub fn iter(&self) -> impl Iterator<Item=(u32, &str)> + '_ {
+        self.names.iter().enumerate().map(|(id, name)| (id as u32, name.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::interner::Interner;
+
+    #[test]
+    fn test_intern() {
+        let mut names = Interner::new();
+        assert!(names.is_empty());
+
+        assert_eq!(names.intern("broadcaster"), 0);
+        assert_eq!(names.intern("a"), 1);
+        assert_eq!(names.intern("broadcaster"), 0);
+        assert_eq!(names.len(), 2);
+
+        assert_eq!(names.get("a"), Some(1));
+        assert_eq!(names.get("b"), None);
+        assert_eq!(names.name(1), "a");
+        assert_eq!(names.iter().collect::<Vec<_>>(), vec![(0, "broadcaster"), (1, "a")]);
+    }
+}
@@ -0,0 +1,75 @@
+// Log output of the solvers, for the things that are interesting while developing (loop sizes, intermediate results,
+// ...) but not part of the answer. Written to stderr, so stdout only has the answers. The runner picks the level:
+// --quiet shows nothing, --verbose shows debug messages as well.
+// Solvers log with the macros, which only format their message when the level is enabled:
+//   log::debug!("Found loop: offset = {}, length = {}", offset, period);
+
+use std::fmt::Arguments;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Copy, Clone)]
+pub enum Level {
+    Quiet,
+    Normal,
+    Debug,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Normal as u8);
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn level() -> Level {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => Level::Quiet,
+        1 => Level::Normal,
+        _ => Level::Debug,
+    }
+}
+
+// Whether a message of the given level should be shown.
+pub fn is_enabled(level: Level) -> bool {
+    shows(self::level(), level)
+}
+
+// Nothing is shown at the quiet level, and messages can't be quiet either.
+fn shows(current: Level, level: Level) -> bool {
+    level != Level::Quiet && level <= current
+}
+
+pub fn write(level: Level, message: Arguments) {
+    if is_enabled(level) {
+        eprintln!("{}", message);
+    }
+}
+
+// Things worth knowing during a normal run, like a sanity check that failed.
+macro_rules! info {
+    ($($arg:tt)*) => { $crate::util::log::write($crate::util::log::Level::Normal, format_args!($($arg)*)) }
+}
+
+// Details of how a solver got to its answer.
+macro_rules! debug {
+    ($($arg:tt)*) => { $crate::util::log::write($crate::util::log::Level::Debug, format_args!($($arg)*)) }
+}
+
+pub(crate) use {debug, info};
+
+#[cfg(test)]
+mod tests {
+    use crate::util::log::{shows, Level};
+
+    #[test]
+    fn test_levels() {
+        assert!(!shows(Level::Quiet, Level::Quiet));
+        assert!(!shows(Level::Quiet, Level::Normal));
+        assert!(!shows(Level::Quiet, Level::Debug));
+
+        assert!(shows(Level::Normal, Level::Normal));
+        assert!(!shows(Level::Normal, Level::Debug));
+
+        assert!(shows(Level::Debug, Level::Normal));
+        assert!(shows(Level::Debug, Level::Debug));
+    }
+}
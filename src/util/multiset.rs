@@ -0,0 +1,99 @@
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+
+// A bag of things, counting how many of each there are (like day 2's cubes of any colour). Things with a count of 0 are
+// not stored, so two sets with the same counts are always equal.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct MultiSet<T: Ord> {
+    counts: BTreeMap<T, usize>,
+}
+
+impl<T: Ord> Default for MultiSet<T> {
+    fn default() -> Self {
+        Self { counts: BTreeMap::new() }
+    }
+}
+
+impl<T: Ord + Clone> MultiSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, item: T, count: usize) {
+        if count > 0 {
+            *self.counts.entry(item).or_default() += count;
+        }
+    }
+
+    pub fn count<Q>(&self, item: &Q) -> usize
+        where T: Borrow<Q>, Q: Ord + ?Sized {
+        self.counts.get(item).copied().unwrap_or_default()
+    }
+
+    // Whether the other set has at least as many of every thing in this set.
+    pub fn is_subset_of(&self, other: &MultiSet<T>) -> bool {
+        self.counts.iter().all(|(item, count)| *count <= other.count(item))
+    }
+
+    // The smallest set both sets are a subset of, i.e. the highest count of every thing.
+    pub fn union(&self, other: &MultiSet<T>) -> MultiSet<T> {
+        let mut result = self.clone();
+        for (item, count) in &other.counts {
+            let current = result.counts.entry(item.clone()).or_default();
+            *current = (*current).max(*count);
+        }
+        result
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<(T, usize)> for MultiSet<T> {
+    fn from_iter<I: IntoIterator<Item=(T, usize)>>(iter: I) -> Self {
+        let mut set = MultiSet::new();
+        for (item, count) in iter {
+            set.add(item, count);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::multiset::MultiSet;
+
+    fn set(counts: &[(&str, usize)]) -> MultiSet<String> {
+        counts.iter().map(|(item, count)| (item.to_string(), *count)).collect()
+    }
+
+    #[test]
+    fn test_add_and_count() {
+        let mut cubes = set(&[("red", 4), ("blue", 3), ("green", 0)]);
+        cubes.add("red".to_string(), 2);
+        cubes.add("purple".to_string(), 1);
+
+        assert_eq!(cubes.count("red"), 6);
+        assert_eq!(cubes.count("green"), 0);
+        assert_eq!(cubes.count("purple"), 1);
+        assert_eq!(set(&[("red", 1), ("green", 0)]), set(&[("red", 1)]));
+        assert_eq!(set(&[("green", 0)]), MultiSet::new());
+    }
+
+    #[test]
+    fn test_is_subset_of() {
+        let bag = set(&[("red", 12), ("green", 13), ("blue", 14)]);
+
+        assert!(set(&[("red", 4), ("blue", 3)]).is_subset_of(&bag));
+        assert!(bag.is_subset_of(&bag));
+        assert!(!set(&[("red", 20), ("blue", 3)]).is_subset_of(&bag));
+        assert!(!set(&[("purple", 1)]).is_subset_of(&bag));
+        assert!(MultiSet::new().is_subset_of(&bag));
+    }
+
+    #[test]
+    fn test_union() {
+        let a = set(&[("red", 4), ("blue", 3)]);
+        let b = set(&[("red", 1), ("green", 2), ("blue", 6)]);
+
+        assert_eq!(a.union(&b), set(&[("red", 4), ("green", 2), ("blue", 6)]));
+        assert_eq!(a.union(&MultiSet::new()), a);
+    }
+}
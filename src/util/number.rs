@@ -1,35 +1,25 @@
 // Allow dead_code since this is a util file copied across years. Later in the AoC we might use everything, or not.
 #![allow(dead_code)]
 
+use std::any::type_name;
+use std::fmt::Display;
+use std::str::FromStr;
 use num_traits::Num;
+use crate::util::error::{AocError, AocResult};
 
-macro_rules! parse_int_impl {
-    ($($t:ty, $name: ident, $name_radix: ident)*) => {$(
-        #[allow(unused)]
-        pub fn $name(input: &str) -> Result<$t, String> {
-            input.to_string().parse().map_err(|e| format!("{} ('{}')", e, input))
-        }
+// Parses any number, with an error telling what we tried to parse into what (e.g. "Invalid u8 '300': number too large
+// to fit in target type").
+pub fn parse_num<T: FromStr>(input: &str) -> AocResult<T> where T::Err: Display {
+    input.parse().map_err(|e| invalid_number::<T>(input, e))
+}
 
-        #[allow(unused)]
-        pub fn $name_radix(input: &str, radix: u32) -> Result<$t, String> {
-            <$t>::from_str_radix(input, radix).map_err(|e| format!("{} ('{}')", e, input))
-        }
-    )*}
+// Same as parse_num, in the given radix. Signed numbers can have a sign, e.g. "-ff" in radix 16 is -255.
+pub fn parse_num_radix<T: Num>(input: &str, radix: u32) -> AocResult<T> where T::FromStrRadixErr: Display {
+    T::from_str_radix(input, radix).map_err(|e| invalid_number::<T>(input, e))
 }
 
-parse_int_impl! {
-    u8, parse_u8, parse_u8_radix
-    u16, parse_u16, parse_u16_radix
-    u32, parse_u32, parse_u32_radix
-    u64, parse_u64, parse_u64_radix
-    u128, parse_u128, parse_u128_radix
-    usize, parse_usize, parse_usize_radix
-    i8, parse_i8, parse_i8_radix
-    i16, parse_i16, parse_i16_radix
-    i32, parse_i32, parse_i32_radix
-    i64, parse_i64, parse_i64_radix
-    i128, parse_i128, parse_i128_radix
-    isize, parse_isize, parse_isize_radix
+fn invalid_number<T>(input: &str, error: impl Display) -> AocError {
+    AocError::Parse(format!("Invalid {} '{}': {}", type_name::<T>(), input, error))
 }
 
 pub fn parse_binary(binary: &str) -> usize {
@@ -86,7 +76,26 @@ impl<T> NumberExtensions<T> for Vec<T> where T: Num + Copy + Clone {
 
 #[cfg(test)]
 mod tests {
-    use crate::util::number::{gcd, lcm, NumberExtensions, parse_binary};
+    use crate::util::error::AocError;
+    use crate::util::number::{gcd, lcm, NumberExtensions, parse_binary, parse_num, parse_num_radix};
+
+    #[test]
+    fn test_parse_num() {
+        assert_eq!(parse_num::<usize>("42"), Ok(42));
+        assert_eq!(parse_num::<isize>("-42"), Ok(-42));
+        assert_eq!(parse_num::<f64>("1.5"), Ok(1.5));
+        assert_eq!(parse_num::<u8>("300"), Err(AocError::Parse("Invalid u8 '300': number too large to fit in target type".to_string())));
+        assert_eq!(parse_num::<usize>("-1"), Err(AocError::Parse("Invalid usize '-1': invalid digit found in string".to_string())));
+        assert_eq!(parse_num::<i32>(""), Err(AocError::Parse("Invalid i32 '': cannot parse integer from empty string".to_string())));
+    }
+
+    #[test]
+    fn test_parse_num_radix() {
+        assert_eq!(parse_num_radix::<usize>("70c71", 16), Ok(0x70c71));
+        assert_eq!(parse_num_radix::<i64>("-ff", 16), Ok(-255));
+        assert_eq!(parse_num_radix::<u8>("101", 2), Ok(5));
+        assert_eq!(parse_num_radix::<u8>("102", 2), Err(AocError::Parse("Invalid u8 '102': invalid digit found in string".to_string())));
+    }
 
     #[test]
     fn test_parse_binary() {
@@ -0,0 +1,69 @@
+// Puzzle parameters that are not part of the input, but differ between the examples and the real puzzle (like day 24's
+// test area). Set with --option <name>=<value> (or directly, when using the library), days fall back to the value of
+// the real puzzle when an option is not set. Names are prefixed with the day they are for, e.g. "day24.area".
+// Tests run in parallel, so they don't touch the shared options: they solve with their own options using `with`.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+static OPTIONS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+thread_local! {
+    static SCOPED: RefCell<Option<BTreeMap<String, String>>> = const { RefCell::new(None) };
+}
+
+pub fn set(name: &str, value: &str) {
+    OPTIONS.lock().unwrap().insert(name.to_string(), value.to_string());
+}
+
+pub fn get(name: &str) -> Option<String> {
+    match SCOPED.with(|scoped| scoped.borrow().as_ref().map(|options| options.get(name).cloned())) {
+        Some(value) => value,
+        None => OPTIONS.lock().unwrap().get(name).cloned(),
+    }
+}
+
+// Runs the function with only the given options set (instead of the shared ones), for the current thread.
+pub fn with<R>(options: &[(&str, &str)], f: impl FnOnce() -> R) -> R {
+    let options = options.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+    let previous = SCOPED.with(|scoped| scoped.replace(Some(options)));
+    let result = f();
+    SCOPED.with(|scoped| scoped.replace(previous));
+    result
+}
+
+// Sets an option from a "<name>=<value>" argument.
+pub fn set_from_arg(arg: &str) -> Result<(), String> {
+    match arg.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            set(name.trim(), value.trim());
+            Ok(())
+        }
+        _ => Err(format!("Invalid option '{}', expected <name>=<value>", arg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::options::{get, set_from_arg, with};
+
+    #[test]
+    fn test_set_from_arg() {
+        assert_eq!(set_from_arg("test.option = 7..27"), Ok(()));
+        assert_eq!(get("test.option"), Some("7..27".to_string()));
+
+        assert!(set_from_arg("7..27").is_err());
+        assert!(set_from_arg("=7..27").is_err());
+    }
+
+    #[test]
+    fn test_with() {
+        assert_eq!(set_from_arg("test.shared = 1"), Ok(()));
+
+        assert_eq!(with(&[("test.scoped", "2")], || (get("test.scoped"), get("test.shared"))), (Some("2".to_string()), None));
+        assert_eq!(with(&[("test.scoped", "2")], || with(&[], || get("test.scoped"))), None);
+        assert_eq!(get("test.scoped"), None);
+        assert_eq!(get("test.shared"), Some("1".to_string()));
+    }
+}
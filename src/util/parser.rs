@@ -1,68 +1,140 @@
-use std::cmp::min;
-use crate::util::number::parse_usize;
+use std::fmt::Debug;
+use std::slice::SliceIndex;
+use crate::util::error::{AocError, AocResult};
+use crate::util::number::parse_num;
 
+// Safe version of `&s[range]`, returning an error instead of panicking when the range is out of bounds (or does not
+// fall on a char boundary).
+pub fn str_get<R>(s: &str, range: R) -> AocResult<&str>
+    where R: SliceIndex<str, Output = str> + Debug + Clone {
+    s.get(range.clone()).ok_or_else(|| AocError::Parse(format!("Cannot get {:?} from '{}'", range, s)))
+}
+
+// Parses tokens (literals, numbers, ...) from the start of the input. How whitespace is handled depends on how the
+// parser was created:
+// - Parser::new is lenient: any whitespace before a token is skipped, so "a = 1" and "a=1" parse the same.
+// - Parser::strict skips nothing: tokens must follow each other exactly, and whitespace has to be consumed with
+//   expect_whitespace. Use it for formats where spacing matters (fixed width fields, "rn=1" style instructions), so a
+//   misaligned input fails to parse instead of silently being read differently.
+// Lines and blocks (line, block, skip_blank_lines) work the same either way.
 pub struct Parser {
     input: String,
-    position: usize
+    // Byte offset into the input, always on a char boundary.
+    position: usize,
+    strict: bool,
 }
 
 #[allow(unused)]
 impl Parser {
     pub fn new<T>(input: T) -> Self
         where T: ToString {
-        Parser { input: input.to_string(), position: 0 }
+        Parser { input: input.to_string(), position: 0, strict: false }
+    }
+
+    pub fn strict<T>(input: T) -> Self
+        where T: ToString {
+        Parser { input: input.to_string(), position: 0, strict: true }
+    }
+
+    fn rest(&self) -> &str {
+        &self.input[self.position..]
     }
 
     fn skip_whitespace(&mut self) {
-        self.position += self.input.chars().skip(self.position).take_while(|c| c.is_whitespace()).count()
+        self.position = self.next_token_position()
+    }
+
+    // Where the next token would start: after any whitespace, unless we're strict.
+    fn next_token_position(&self) -> usize {
+        if self.strict { return self.position; }
+        self.whitespace_end()
+    }
+
+    fn whitespace_end(&self) -> usize {
+        self.position + self.rest().chars().take_while(|c| c.is_whitespace()).map(|c| c.len_utf8()).sum::<usize>()
+    }
+
+    // Consumes the whitespace separating two tokens, of which there should be at least some (in either mode).
+    pub fn expect_whitespace(&mut self) -> AocResult<()> {
+        let end = self.whitespace_end();
+        if end == self.position {
+            let actual: String = self.rest().chars().take(10).collect();
+            return Err(AocError::Parse(format!("Expected whitespace, but got '{}' ('{}':{})", actual, self.input, self.position)));
+        }
+        self.position = end;
+        Ok(())
+    }
+
+    // Whether the upcoming token is the given literal, without consuming anything.
+    pub fn peek_literal(&self, literal: &str) -> bool {
+        self.input.get(self.next_token_position()..).is_some_and(|rest| rest.starts_with(literal))
     }
 
-    pub fn literal(&mut self, literal: &str) -> Result<(), String> {
+    // The upcoming number, if any, without consuming anything.
+    pub fn peek_usize(&self) -> Option<usize> {
+        let digits: String = self.input[self.next_token_position()..].chars().take_while(|c| c.is_ascii_digit()).collect();
+        parse_num(&digits).ok()
+    }
+
+    pub fn literal(&mut self, literal: &str) -> AocResult<()> {
         self.skip_whitespace();
 
-        let actual = &self.input[self.position..min(self.position+literal.len(), self.input.len())];
-        if actual != literal {
-            Err(format!("Expected '{}' to match '{}' ('{}':{})", actual, literal, self.input, self.position))
+        if !self.rest().starts_with(literal) {
+            let actual: String = self.rest().chars().take(literal.chars().count()).collect();
+            Err(AocError::Parse(format!("Expected '{}' to match '{}' ('{}':{})", actual, literal, self.input, self.position)))
         } else {
             self.position += literal.len();
             Ok(())
         }
     }
 
-    pub fn one_of(&mut self, options: Vec<&'static str>) -> Result<&'static str, String> {
+    pub fn one_of(&mut self, options: Vec<&'static str>) -> AocResult<&'static str> {
         for option in &options {
             if self.literal(option).is_ok() {
                 return Ok(option)
             }
         }
 
-        Err(format!("Expected one of {} ('{}':{})", options.iter().map(|o| format!("'{}'",o)).collect::<Vec<_>>().join(", "), self.input, self.position))
+        Err(AocError::Parse(format!("Expected one of {} ('{}':{})", options.iter().map(|o| format!("'{}'",o)).collect::<Vec<_>>().join(", "), self.input, self.position)))
+    }
+
+    // Like one_of, but returns the value mapped to the literal that matched, so matching and converting can't drift
+    // apart. The first matching literal wins, so put longer literals before their prefixes.
+    pub fn enum_of<S, T>(&mut self, options: &[(S, T)]) -> AocResult<T>
+        where S: AsRef<str>, T: Clone {
+        for (literal, value) in options {
+            if self.literal(literal.as_ref()).is_ok() {
+                return Ok(value.clone())
+            }
+        }
+
+        Err(AocError::Parse(format!("Expected one of {} ('{}':{})", options.iter().map(|(o, _)| format!("'{}'", o.as_ref())).collect::<Vec<_>>().join(", "), self.input, self.position)))
     }
 
-    pub fn usize(&mut self) -> Result<usize, String> {
+    pub fn usize(&mut self) -> AocResult<usize> {
         self.skip_whitespace();
 
         let mut result = 0;
 
         // consume at least one numeric character
-        let numbers: Vec<_> = self.input.chars().skip(self.position)
+        let numbers: Vec<_> = self.rest().chars()
             .take_while(|c| c.is_numeric())
             .collect();
-        if numbers.len() == 0 { return Err(format!("Expected to find a number. ('{}':{})", self.input, self.position)) }
+        if numbers.len() == 0 { return Err(AocError::Parse(format!("Expected to find a number. ('{}':{})", self.input, self.position))) }
 
         for char in numbers.iter() {
             result *= 10;
-            result += parse_usize(char.to_string().as_str())?;
+            result += parse_num::<usize>(char.to_string().as_str())?;
         }
 
-        self.position += numbers.len();
+        self.position += numbers.iter().map(|c| c.len_utf8()).sum::<usize>();
         Ok(result)
     }
 
-    pub fn isize(&mut self) -> Result<isize, String> {
+    pub fn isize(&mut self) -> AocResult<isize> {
         self.skip_whitespace();
 
-        let modifier = if self.input.chars().nth(self.position) == Some('-') {
+        let modifier = if self.rest().starts_with('-') {
             self.position += 1;
             -1
         } else {
@@ -72,28 +144,405 @@ impl Parser {
         Ok(modifier * (self.usize()?) as isize)
     }
 
-    pub fn str(&mut self, len: usize) -> Result<String, String> {
+    pub fn str(&mut self, len: usize) -> AocResult<String> {
         self.skip_whitespace();
 
-        let result: Vec<_> = self.input.chars().skip(self.position).take(len).collect();
+        let result: Vec<_> = self.rest().chars().take(len).collect();
         if result.len() != len {
-            Err(format!("Expected to read {} chars, but only got {}. ('{}':{})", len, result.len(), self.input, self.position))
+            Err(AocError::Parse(format!("Expected to read {} chars, but only got {}. ('{}':{})", len, result.len(), self.input, self.position)))
         } else {
-            self.position += len;
+            self.position += result.iter().map(|c| c.len_utf8()).sum::<usize>();
             Ok(result.iter().collect())
         }
     }
 
+    // The next word, a run of (at least one) letters, like a colour or a name.
+    pub fn word(&mut self) -> AocResult<String> {
+        self.skip_whitespace();
+
+        let word: String = self.rest().chars().take_while(|c| c.is_alphabetic()).collect();
+        if word.is_empty() {
+            return Err(AocError::Parse(format!("Expected to find a word. ('{}':{})", self.input, self.position)));
+        }
+        self.position += word.len();
+        Ok(word)
+    }
+
+    // The rest of the current line (without the newline, which is consumed as well).
+    pub fn line(&mut self) -> AocResult<String> {
+        let rest = str_get(&self.input, self.position..)?;
+        if rest.is_empty() {
+            return Err(AocError::Parse(format!("Expected to read a line, but reached the end. ('{}':{})", self.input, self.position)));
+        }
+
+        let (line, consumed) = match rest.find('\n') {
+            Some(index) => (&rest[..index], index + 1),
+            None => (rest, rest.len()),
+        };
+        let line = line.strip_suffix('\r').unwrap_or(line).to_string();
+        self.position += consumed;
+        Ok(line)
+    }
+
+    // Skips over any lines that are empty or contain only whitespace, leaving the parser at the start of the next line
+    // with content (or at the end of the input).
+    pub fn skip_blank_lines(&mut self) {
+        while let Some(rest) = self.input.get(self.position..).filter(|rest| !rest.is_empty()) {
+            match rest.find('\n') {
+                Some(index) if rest[..index].trim().is_empty() => self.position += index + 1,
+                None if rest.trim().is_empty() => self.position = self.input.len(),
+                _ => break,
+            }
+        }
+    }
+
+    // Everything until the next blank line (or the end of the input), e.g. one grid out of a list of grids.
+    // Any blank lines before the block are skipped, the blank line after it is consumed.
+    pub fn block(&mut self) -> AocResult<String> {
+        self.skip_blank_lines();
+
+        let mut lines = vec![];
+        while self.input.get(self.position..).is_some_and(|rest| !rest.is_empty()) {
+            let line = self.line()?;
+            if line.trim().is_empty() { break; }
+            lines.push(line);
+        }
+
+        if lines.is_empty() {
+            Err(AocError::Parse(format!("Expected to read a block, but reached the end. ('{}':{})", self.input, self.position)))
+        } else {
+            Ok(lines.join("\n"))
+        }
+    }
+
+    // Whether there's nothing left to parse. Trailing whitespace is fine, unless we're strict.
     pub fn is_exhausted(&self) -> bool {
-        let rest = &self.input[self.position..self.input.len()];
-        rest.is_empty() || rest.chars().all(|c| c.is_whitespace())
+        if self.strict { return self.rest().is_empty(); }
+        self.rest().chars().all(|c| c.is_whitespace())
     }
     
-    pub fn ensure_exhausted(&self) -> Result<(), String> {
+    pub fn ensure_exhausted(&self) -> AocResult<()> {
         if self.is_exhausted() { 
             Ok(())
         } else {
-            Err(format!("Unexpected extra content: '{}'", self.input[self.position..].trim()))
+            Err(AocError::Parse(format!("Unexpected extra content: '{}'", self.input[self.position..].trim())))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::error::AocError;
+    use crate::util::parser::{str_get, Parser};
+
+    #[test]
+    fn test_str_get() {
+        assert_eq!(str_get("a<123", 0..1), Ok("a"));
+        assert_eq!(str_get("a<123", 1..=1), Ok("<"));
+        assert_eq!(str_get("a<123", 2..), Ok("123"));
+        assert_eq!(str_get("", ..), Ok(""));
+
+        assert!(str_get("", 0..1).is_err());
+        assert!(str_get("ab", 1..=2).is_err());
+        assert!(str_get("ab", 3..).is_err());
+        // Not on a char boundary:
+        assert!(str_get("™ab", 1..).is_err());
+    }
+
+    #[test]
+    fn test_peek() {
+        let mut parser = Parser::new("Card 12: 3 |");
+
+        assert_eq!(parser.peek_literal("Card"), true);
+        assert_eq!(parser.peek_literal("Game"), false);
+        assert_eq!(parser.peek_usize(), None);
+        assert_eq!(parser.literal("Card"), Ok(()));
+
+        // Peeking skips whitespace, but does not consume anything
+        assert_eq!(parser.peek_usize(), Some(12));
+        assert_eq!(parser.peek_usize(), Some(12));
+        assert_eq!(parser.usize(), Ok(12));
+
+        assert_eq!(parser.peek_literal(":"), true);
+        assert_eq!(parser.literal(":"), Ok(()));
+        assert_eq!(parser.usize(), Ok(3));
+        assert_eq!(parser.peek_literal("|"), true);
+        assert_eq!(parser.peek_literal("| and more"), false);
+        assert_eq!(parser.literal("|"), Ok(()));
+
+        assert_eq!(parser.peek_literal(""), true);
+        assert_eq!(parser.peek_usize(), None);
+        assert_eq!(parser.is_exhausted(), true);
+    }
+
+    #[test]
+    fn test_enum_of() {
+        let mut parser = Parser::new("3 blue, 4 purple");
+        let colours = [("red", 'r'), ("green", 'g'), ("blue", 'b')];
+
+        assert_eq!(parser.usize(), Ok(3));
+        assert_eq!(parser.enum_of(&colours), Ok('b'));
+        assert_eq!(parser.literal(","), Ok(()));
+        assert_eq!(parser.usize(), Ok(4));
+        assert_eq!(
+            parser.enum_of(&colours),
+            Err(AocError::Parse("Expected one of 'red', 'green', 'blue' ('3 blue, 4 purple':10)".to_string()))
+        );
+
+        // Owned literals work as well, e.g. when the options are only known at runtime:
+        let cards: Vec<_> = "AKQ".chars().enumerate().map(|(i, c)| (c.to_string(), i)).collect();
+        assert_eq!(Parser::new("Q").enum_of(&cards), Ok(2));
+    }
+
+    #[test]
+    fn test_word() {
+        let mut parser = Parser::new("3 blue, 4 purple;");
+
+        assert_eq!(parser.usize(), Ok(3));
+        assert_eq!(parser.word(), Ok("blue".to_string()));
+        assert!(parser.word().is_err());
+        assert_eq!(parser.literal(","), Ok(()));
+        assert_eq!(parser.usize(), Ok(4));
+        assert_eq!(parser.word(), Ok("purple".to_string()));
+        assert_eq!(parser.literal(";"), Ok(()));
+        assert!(parser.word().is_err());
+    }
+
+    #[test]
+    fn test_whitespace_policy() {
+        // Lenient parsers skip whitespace before any token
+        let mut parser = Parser::new(" AAA = ( BBB, CCC) ");
+        assert_eq!(parser.str(3), Ok("AAA".to_string()));
+        assert_eq!(parser.literal("="), Ok(()));
+        assert_eq!(parser.literal("("), Ok(()));
+        assert_eq!(parser.str(3), Ok("BBB".to_string()));
+        assert_eq!(parser.literal(","), Ok(()));
+        assert_eq!(parser.expect_whitespace(), Ok(()));
+        assert_eq!(parser.str(3), Ok("CCC".to_string()));
+        assert_eq!(parser.literal(")"), Ok(()));
+        assert_eq!(parser.is_exhausted(), true);
+
+        // Strict parsers only skip whitespace when asked to
+        let mut parser = Parser::strict("AAA = ( BBB");
+        assert_eq!(parser.str(3), Ok("AAA".to_string()));
+        assert!(!parser.peek_literal("="));
+        assert!(parser.literal("=").is_err());
+        assert_eq!(parser.expect_whitespace(), Ok(()));
+        assert_eq!(parser.literal("="), Ok(()));
+        assert_eq!(parser.expect_whitespace(), Ok(()));
+        assert_eq!(parser.literal("("), Ok(()));
+        assert_eq!(parser.str(3), Ok(" BB".to_string()));
+        assert!(parser.expect_whitespace().is_err());
+
+        let mut parser = Parser::strict("rn= 1 ");
+        assert_eq!(parser.word(), Ok("rn".to_string()));
+        assert_eq!(parser.literal("="), Ok(()));
+        assert!(parser.usize().is_err());
+        assert_eq!(parser.expect_whitespace(), Ok(()));
+        assert_eq!(parser.usize(), Ok(1));
+        assert_eq!(parser.is_exhausted(), false);
+        assert!(parser.ensure_exhausted().is_err());
+    }
+
+    #[test]
+    fn test_line() {
+        let mut parser = Parser::new("seeds: 1 2\r\n\nfoo");
+
+        assert_eq!(parser.literal("seeds:"), Ok(()));
+        assert_eq!(parser.line(), Ok(" 1 2".to_string()));
+        assert_eq!(parser.line(), Ok("".to_string()));
+        assert_eq!(parser.line(), Ok("foo".to_string()));
+        assert!(parser.line().is_err());
+    }
+
+    #[test]
+    fn test_block() {
+        let mut parser = Parser::new("\n  \n#.#\n.#.\n\n\n  \nab\ncd\n\n");
+
+        assert_eq!(parser.block(), Ok("#.#\n.#.".to_string()));
+        assert_eq!(parser.block(), Ok("ab\ncd".to_string()));
+        assert_eq!(parser.is_exhausted(), true);
+        assert!(parser.block().is_err());
+
+        let mut parser = Parser::new("header\n\nlast block");
+        assert_eq!(parser.line(), Ok("header".to_string()));
+        parser.skip_blank_lines();
+        assert_eq!(parser.peek_literal("last"), true);
+        assert_eq!(parser.block(), Ok("last block".to_string()));
+        assert!(parser.block().is_err());
+    }
+}
+
+// Round trip tests: random token sequences are rendered to a string (with random whitespace in between), after which
+// parsing them back should give the same tokens. Uses a tiny seeded generator, so failures are reproducible.
+#[cfg(test)]
+mod property_tests {
+    use crate::util::error::AocResult;
+    use crate::util::parser::Parser;
+
+    const CASES: usize = 500;
+
+    // xorshift64*, good enough for picking test cases.
+    struct Generator(u64);
+
+    impl Generator {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545F4914F6CDD1D)
+        }
+
+        fn below(&mut self, max: usize) -> usize {
+            (self.next() % max as u64) as usize
+        }
+
+        fn pick<'a, T>(&mut self, options: &'a [T]) -> &'a T {
+            &options[self.below(options.len())]
+        }
+
+        fn chars(&mut self, alphabet: &str, len: usize) -> String {
+            let chars: Vec<_> = alphabet.chars().collect();
+            (0..len).map(|_| *self.pick(&chars)).collect()
         }
     }
-}
\ No newline at end of file
+
+    #[derive(Eq, PartialEq, Debug, Clone)]
+    enum Token {
+        Literal(String),
+        Usize(usize),
+        Isize(isize),
+        Str(String),
+    }
+
+    impl Token {
+        fn generate(generator: &mut Generator) -> Token {
+            match generator.below(4) {
+                0 => {
+                    let len = 1 + generator.below(4);
+                    Token::Literal(generator.chars("abcXYZé:|,;=()", len))
+                }
+                1 => Token::Usize(generator.below(1_000_000)),
+                2 => Token::Isize(generator.below(2_000_000) as isize - 1_000_000),
+                _ => {
+                    let len = 1 + generator.below(5);
+                    Token::Str(generator.chars("abcdefxyzé™", len))
+                }
+            }
+        }
+
+        fn render(&self) -> String {
+            match self {
+                Token::Literal(s) | Token::Str(s) => s.clone(),
+                Token::Usize(n) => n.to_string(),
+                Token::Isize(n) => n.to_string(),
+            }
+        }
+
+        fn parse(&self, parser: &mut Parser) -> AocResult<Token> {
+            match self {
+                Token::Literal(s) => parser.literal(s).map(|_| self.clone()),
+                Token::Usize(_) => parser.usize().map(Token::Usize),
+                Token::Isize(_) => parser.isize().map(Token::Isize),
+                Token::Str(s) => parser.str(s.chars().count()).map(Token::Str),
+            }
+        }
+    }
+
+    fn whitespace(generator: &mut Generator, allow_empty: bool) -> String {
+        let options: &[&str] = if allow_empty { &["", " ", "  ", "\t", "\n", "\r\n", " \n "] } else { &[" ", "  ", "\t", "\n", "\r\n", " \n "] };
+        generator.pick(options).to_string()
+    }
+
+    // Renders the tokens, making sure numbers don't run into each other.
+    fn render(generator: &mut Generator, tokens: &[Token]) -> String {
+        let mut result = whitespace(generator, true);
+        for (index, token) in tokens.iter().enumerate() {
+            let rendered = token.render();
+            let touches_number = index > 0 && result.ends_with(|c: char| c.is_ascii_digit()) && rendered.starts_with(|c: char| c.is_ascii_digit() || c == '-');
+            if index > 0 {
+                result += &whitespace(generator, !touches_number);
+            }
+            result += &rendered;
+        }
+        result + &whitespace(generator, true)
+    }
+
+    #[test]
+    fn test_tokens_round_trip() {
+        let mut generator = Generator(0x5EED_2023);
+
+        for _ in 0..CASES {
+            let len = generator.below(8);
+            let tokens: Vec<_> = (0..len).map(|_| Token::generate(&mut generator)).collect();
+            let input = render(&mut generator, &tokens);
+
+            let mut parser = Parser::new(&input);
+            for token in &tokens {
+                match token {
+                    Token::Literal(s) => assert!(parser.peek_literal(s), "Expected to peek {:?} in {:?}", s, input),
+                    Token::Usize(n) => assert_eq!(parser.peek_usize(), Some(*n), "Peeking in {:?}", input),
+                    _ => {}
+                }
+                assert_eq!(token.parse(&mut parser), Ok(token.clone()), "Parsing {:?} from {:?}", tokens, input);
+            }
+            assert!(parser.is_exhausted(), "Expected {:?} to be exhausted after {:?}", input, tokens);
+            assert_eq!(parser.ensure_exhausted(), Ok(()));
+            assert_eq!(parser.peek_usize(), None);
+        }
+    }
+
+    #[test]
+    fn test_trailing_content_is_reported() {
+        let mut generator = Generator(0xC0FFEE);
+
+        for _ in 0..CASES {
+            let len = 1 + generator.below(5);
+            let tokens: Vec<_> = (0..len).map(|_| Token::generate(&mut generator)).collect();
+            let input = render(&mut generator, &tokens);
+
+            // Parsing all but the last token leaves something behind.
+            let mut parser = Parser::new(&input);
+            for token in &tokens[..len - 1] {
+                assert_eq!(token.parse(&mut parser), Ok(token.clone()), "Parsing {:?} from {:?}", tokens, input);
+            }
+            assert!(!parser.is_exhausted(), "Expected {:?} to have content left after {:?}", input, &tokens[..len - 1]);
+            assert!(parser.ensure_exhausted().is_err());
+        }
+    }
+
+    #[test]
+    fn test_blocks_round_trip() {
+        let mut generator = Generator(0xB10C);
+
+        for _ in 0..CASES {
+            let blocks: Vec<Vec<String>> = (0..1 + generator.below(4))
+                .map(|_| (0..1 + generator.below(4)).map(|_| {
+                    // Lines may contain whitespace, as long as they are not blank.
+                    let len = generator.below(3);
+                    generator.chars("#. ", len) + &generator.chars("#.ab", 1)
+                }).collect())
+                .collect();
+
+            let newline = *generator.pick(&["\n", "\r\n"]);
+            let separators: Vec<_> = (0..blocks.len()).map(|_| {
+                let blank_lines = 1 + generator.below(3);
+                (0..blank_lines).map(|_| generator.pick(&["", " ", "\t"]).to_string() + newline).collect::<String>()
+            }).collect();
+
+            let mut input = whitespace(&mut generator, true).replace(|c: char| c != '\n', "");
+            for (block, separator) in blocks.iter().zip(separators.iter()) {
+                input += &block.join(newline);
+                input += newline;
+                input += separator;
+            }
+
+            let mut parser = Parser::new(&input);
+            for block in &blocks {
+                assert_eq!(parser.block(), Ok(block.join("\n")), "Parsing {:?} from {:?}", blocks, input);
+            }
+            assert!(parser.is_exhausted(), "Expected {:?} to be exhausted", input);
+            assert!(parser.block().is_err());
+        }
+    }
+}
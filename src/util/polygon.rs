@@ -0,0 +1,77 @@
+// Area helpers for polygons on a grid, given by their corners (in order, the last corner may or may not repeat the
+// first one). Corner points lie on the polygon's boundary, so for a trench the polygon goes through the cell middles.
+
+use crate::util::geometry::Point;
+
+fn edges(corners: &[Point]) -> impl Iterator<Item = (&Point, &Point)> {
+    corners.iter().zip(corners.iter().cycle().skip(1))
+}
+
+// Shoelace formula: twice the (geometric) area enclosed by the polygon, which is always a whole number.
+fn twice_area(corners: &[Point]) -> isize {
+    edges(corners).map(|(a, b)| a.x * b.y - b.x * a.y).sum::<isize>().abs()
+}
+
+// The number of grid points on the boundary of the polygon (assuming horizontal, vertical, or diagonal edges).
+pub fn boundary_points(corners: &[Point]) -> usize {
+    edges(corners).map(|(a, b)| (a.x - b.x).abs().max((a.y - b.y).abs()) as usize).sum()
+}
+
+// Pick's theorem: area = interior + boundary / 2 - 1, so interior = area - boundary / 2 + 1.
+pub fn interior_points(corners: &[Point]) -> usize {
+    let boundary = boundary_points(corners) as isize;
+    ((twice_area(corners) - boundary) / 2 + 1) as usize
+}
+
+// All grid points on or inside the polygon, e.g. the cells of a dug-out lagoon including its trench.
+pub fn enclosed_points(corners: &[Point]) -> usize {
+    interior_points(corners) + boundary_points(corners)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::geometry::Point;
+    use crate::util::polygon::{boundary_points, enclosed_points, interior_points, twice_area};
+
+    fn points(corners: &[(isize, isize)]) -> Vec<Point> {
+        corners.iter().map(|c| (*c).into()).collect()
+    }
+
+    #[test]
+    fn test_square() {
+        // 3x3 square of grid points, with a single point inside
+        let square = points(&[(0, 0), (2, 0), (2, 2), (0, 2)]);
+        assert_eq!(twice_area(&square), 8);
+        assert_eq!(boundary_points(&square), 8);
+        assert_eq!(interior_points(&square), 1);
+        assert_eq!(enclosed_points(&square), 9);
+
+        // Repeating the first corner, or going the other way around, does not matter:
+        let closed = points(&[(0, 0), (0, 2), (2, 2), (2, 0), (0, 0)]);
+        assert_eq!(twice_area(&closed), 8);
+        assert_eq!(enclosed_points(&closed), 9);
+    }
+
+    #[test]
+    fn test_concave() {
+        // An L-shape:
+        // ###
+        // #.#
+        // #.###
+        // #...#
+        // #####
+        let shape = points(&[(0, 0), (2, 0), (2, 2), (4, 2), (4, 4), (0, 4)]);
+        assert_eq!(twice_area(&shape), 24);
+        assert_eq!(boundary_points(&shape), 16);
+        assert_eq!(interior_points(&shape), 5);
+        assert_eq!(enclosed_points(&shape), 21);
+    }
+
+    #[test]
+    fn test_triangle() {
+        let triangle = points(&[(0, 0), (4, 0), (0, 4)]);
+        assert_eq!(twice_area(&triangle), 16);
+        assert_eq!(boundary_points(&triangle), 12);
+        assert_eq!(interior_points(&triangle), 3);
+    }
+}
@@ -0,0 +1,150 @@
+// Opt-in tracing of where the time goes, enabled by --profile <path>. The runner wraps every day, parse and part in a
+// span, heavier days add spans for their own phases:
+//   let _span = profile::span!("build graph");
+// Spans end when they are dropped. The trace is written in the Chrome tracing format, which can be loaded in
+// chrome://tracing, https://ui.perfetto.dev or speedscope to see the spans as a flame graph (a row per thread).
+// When not profiling, a span is only a check of a flag: its name isn't even formatted.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+#[cfg(any(test, not(target_arch = "wasm32")))]
+use serde_json::{json, Value};
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+struct Event {
+    name: String,
+    thread: u64,
+    // Both in microseconds, the start since the profile was enabled.
+    start: u64,
+    duration: u64,
+}
+
+struct Trace {
+    path: String,
+    start: Instant,
+    events: Vec<Event>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TRACE: Mutex<Option<Trace>> = Mutex::new(None);
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    // Threads are numbered in the order they record their first span, the main thread usually being 1.
+    static THREAD: Cell<u64> = const { Cell::new(0) };
+}
+
+pub fn enable(path: &str) {
+    *TRACE.lock().unwrap() = Some(Trace { path: path.to_string(), start: Instant::now(), events: vec![] });
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn thread_id() -> u64 {
+    THREAD.with(|thread| {
+        if thread.get() == 0 {
+            thread.set(NEXT_THREAD.fetch_add(1, Ordering::Relaxed));
+        }
+        thread.get()
+    })
+}
+
+// A span of time, from its creation until it's dropped.
+pub struct Span {
+    started: Option<(String, Instant)>,
+}
+
+impl Span {
+    // Starts a span, the name is only built when profiling.
+    pub fn begin(name: impl FnOnce() -> String) -> Self {
+        Self { started: is_enabled().then(|| (name(), Instant::now())) }
+    }
+
+    // Runs the step within the span, ending the span right after it.
+    pub fn run<T>(self, step: impl FnOnce() -> T) -> T {
+        step()
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let Some((name, start)) = self.started.take() else { return };
+        let duration = start.elapsed();
+        if let Some(trace) = TRACE.lock().unwrap().as_mut() {
+            let start = start.saturating_duration_since(trace.start);
+            trace.events.push(Event { name, thread: thread_id(), start: start.as_micros() as u64, duration: duration.as_micros() as u64 });
+        }
+    }
+}
+
+macro_rules! span {
+    ($($arg:tt)*) => { $crate::util::profile::Span::begin(|| format!($($arg)*)) }
+}
+
+pub(crate) use span;
+
+// All spans as "complete" events, which viewers nest by their times.
+#[cfg(any(test, not(target_arch = "wasm32")))]
+fn to_chrome_trace(events: &[Event]) -> Value {
+    let events: Vec<_> = events.iter().map(|e| json!({
+        "name": e.name,
+        "ph": "X",
+        "ts": e.start,
+        "dur": e.duration,
+        "pid": 1,
+        "tid": e.thread,
+    })).collect();
+    json!({ "traceEvents": events, "displayTimeUnit": "ms" })
+}
+
+// Writes the spans recorded so far to the path given by --profile, if any.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write() -> Result<(), String> {
+    match TRACE.lock().unwrap().as_ref() {
+        Some(trace) => std::fs::write(&trace.path, to_chrome_trace(&trace.events).to_string())
+            .map_err(|e| format!("Could not write profile to '{}': {}", trace.path, e)),
+        None => Ok(())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write() -> Result<(), String> {
+    match TRACE.lock().unwrap().as_ref() {
+        Some(trace) => Err(format!("Cannot write profile to '{}', there is no filesystem in wasm", trace.path)),
+        None => Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::util::profile::{to_chrome_trace, Event, Span};
+
+    #[test]
+    fn test_to_chrome_trace() {
+        let events = vec![
+            Event { name: "day 5".to_string(), thread: 1, start: 10, duration: 500 },
+            Event { name: "part 1".to_string(), thread: 2, start: 20, duration: 30 },
+        ];
+
+        assert_eq!(to_chrome_trace(&events), json!({
+            "traceEvents": [
+                { "name": "day 5", "ph": "X", "ts": 10, "dur": 500, "pid": 1, "tid": 1 },
+                { "name": "part 1", "ph": "X", "ts": 20, "dur": 30, "pid": 1, "tid": 2 },
+            ],
+            "displayTimeUnit": "ms",
+        }));
+    }
+
+    #[test]
+    fn test_disabled_span() {
+        // Profiling is never enabled in tests, so the name should not be built at all
+        let span = Span::begin(|| panic!("Should not format the name"));
+        assert!(span.started.is_none());
+    }
+}
@@ -0,0 +1,128 @@
+// Progress reporting for long-running solvers. Solvers get a reporter passed in, the runner decides what it does with
+// it: show progress on the console, or nothing at all (--quiet, or when measuring/comparing answers).
+
+use std::io::{stderr, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::util::log::{self, Level};
+
+pub trait ProgressReporter: Sync {
+    // Progress on a (long-running) task, total being the final value of done, if it is known up front.
+    fn progress(&self, task: &str, done: usize, total: Option<usize>);
+    // Something noteworthy happened, e.g. a candidate solution was found.
+    fn message(&self, message: &str);
+    // The solver is done, clean up whatever was shown.
+    fn finish(&self) {}
+}
+
+pub struct Silent;
+
+impl ProgressReporter for Silent {
+    fn progress(&self, _task: &str, _done: usize, _total: Option<usize>) {}
+    fn message(&self, _message: &str) {}
+}
+
+// Shows a single (overwritten) progress line on stderr with a spinner, and an ETA if the total is known.
+pub struct Console {
+    state: Mutex<Option<ConsoleState>>,
+}
+
+struct ConsoleState {
+    task: String,
+    started: Instant,
+    last_update: Instant,
+    frame: usize,
+}
+
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+impl Console {
+    pub const fn new() -> Self {
+        Console { state: Mutex::new(None) }
+    }
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressReporter for Console {
+    fn progress(&self, task: &str, done: usize, total: Option<usize>) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+
+        let current = match state.as_mut() {
+            Some(current) if current.task == task => {
+                if now - current.last_update < UPDATE_INTERVAL { return; }
+                current
+            }
+            _ => state.insert(ConsoleState { task: task.to_string(), started: now, last_update: now, frame: 0 })
+        };
+
+        current.last_update = now;
+        current.frame = (current.frame + 1) % SPINNER.len();
+
+        let mut line = format!("{} {}: {}", SPINNER[current.frame], task, done);
+        if let Some(total) = total {
+            line += &format!("/{}", total);
+            if let Some(eta) = eta(now - current.started, done, total) {
+                line += &format!(" (ETA {:.1?})", eta);
+            }
+        }
+        eprint!("\r\x1b[K{}", line);
+        let _ = stderr().flush();
+    }
+
+    fn message(&self, message: &str) {
+        // Messages are details of how the solver is doing, those are only interesting with --verbose.
+        if !log::is_enabled(Level::Debug) { return; }
+        let state = self.state.lock().unwrap();
+        if state.is_some() {
+            eprint!("\r\x1b[K");
+        }
+        eprintln!("{}", message);
+    }
+
+    fn finish(&self) {
+        if self.state.lock().unwrap().take().is_some() {
+            eprint!("\r\x1b[K");
+            let _ = stderr().flush();
+        }
+    }
+}
+
+fn eta(elapsed: Duration, done: usize, total: usize) -> Option<Duration> {
+    if done == 0 || done > total { return None; }
+    Some(elapsed.mul_f64((total - done) as f64 / done as f64))
+}
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+static SILENT: Silent = Silent;
+static CONSOLE: Console = Console::new();
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+// The reporter the runner selected for the puzzles.
+pub fn reporter() -> &'static dyn ProgressReporter {
+    if QUIET.load(Ordering::Relaxed) { &SILENT } else { &CONSOLE }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::util::progress::eta;
+
+    #[test]
+    fn test_eta() {
+        assert_eq!(eta(Duration::from_secs(10), 0, 100), None);
+        assert_eq!(eta(Duration::from_secs(10), 25, 100), Some(Duration::from_secs(30)));
+        assert_eq!(eta(Duration::from_secs(10), 100, 100), Some(Duration::ZERO));
+        assert_eq!(eta(Duration::from_secs(10), 101, 100), None);
+    }
+}
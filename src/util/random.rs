@@ -0,0 +1,92 @@
+// A small seedable pseudo random number generator (SplitMix64), for randomized algorithms like day 25's min-cut. Not
+// fit for anything that needs proper randomness, but fast and reproducible: the same seed always gives the same numbers.
+// The seed of a run can be set with --seed <n>, so a run that misbehaves can be repeated exactly.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub const DEFAULT_SEED: u64 = 2023;
+
+static SEED: AtomicU64 = AtomicU64::new(DEFAULT_SEED);
+
+pub fn set_seed(seed: u64) {
+    SEED.store(seed, Ordering::Relaxed);
+}
+
+pub fn seed() -> u64 {
+    SEED.load(Ordering::Relaxed)
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    // A generator seeded with the seed of this run.
+    pub fn seeded() -> Self {
+        Self::new(seed())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    // A number in 0..bound. Scales the random number instead of taking the modulo, which is (a tiny bit) less biased.
+    pub fn below(&mut self, bound: usize) -> usize {
+        if bound == 0 { panic!("Cannot pick a number below 0") }
+        ((self.next_u64() as u128 * bound as u128) >> 64) as usize
+    }
+
+    // Puts the items in a random order (Fisher-Yates).
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::random::Random;
+
+    #[test]
+    fn test_reproducible() {
+        let numbers = |seed| { let mut random = Random::new(seed); (0..5).map(|_| random.next_u64()).collect::<Vec<_>>() };
+
+        assert_eq!(numbers(1), numbers(1));
+        assert_ne!(numbers(1), numbers(2));
+        // The reference values of SplitMix64 with seed 1234567
+        assert_eq!(&numbers(1234567)[0..2], &[6457827717110365317, 3203168211198807973]);
+    }
+
+    #[test]
+    fn test_below() {
+        let mut random = Random::new(42);
+        let mut seen = [0; 6];
+        for _ in 0..6000 {
+            seen[random.below(6)] += 1;
+        }
+        assert!(seen.iter().all(|count| (800..1200).contains(count)), "{:?}", seen);
+        assert_eq!(random.below(1), 0);
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let mut random = Random::new(42);
+        let mut items: Vec<_> = (0..20).collect();
+        random.shuffle(&mut items);
+
+        assert_ne!(items, (0..20).collect::<Vec<_>>());
+        items.sort();
+        assert_eq!(items, (0..20).collect::<Vec<_>>());
+    }
+}
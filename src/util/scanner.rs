@@ -0,0 +1,73 @@
+// Finds words from a fixed word -> value table in a string. Matches may overlap (e.g. "oneight" contains both "one"
+// and "eight"), since every position in the string is checked against every word.
+pub struct WordScanner<T> where T: Copy {
+    words: Vec<(String, T)>,
+}
+
+impl<T> WordScanner<T> where T: Copy {
+    pub fn new(words: &[(&str, T)]) -> Self {
+        Self { words: words.iter().filter(|(w, _)| !w.is_empty()).map(|(w, v)| (w.to_string(), *v)).collect() }
+    }
+
+    // Creates a scanner from multiple tables, e.g. digits and their spelled-out versions.
+    pub fn from_tables(tables: &[&[(&str, T)]]) -> Self {
+        Self::new(&tables.concat())
+    }
+
+    // All matches in the string, as (byte index, value), in order of occurrence.
+    // When multiple words match at the same index, the first one in the table wins.
+    #[cfg(test)]
+    pub fn matches(&self, s: &str) -> Vec<(usize, T)> {
+        s.char_indices().filter_map(|(i, _)| self.match_at(s, i).map(|v| (i, v))).collect()
+    }
+
+    // The values of the first and the last match in the string, found in a single pass.
+    pub fn first_and_last(&self, s: &str) -> Option<(T, T)> {
+        let mut result: Option<(T, T)> = None;
+
+        for (i, _) in s.char_indices() {
+            if let Some(value) = self.match_at(s, i) {
+                result = match result {
+                    None => Some((value, value)),
+                    Some((first, _)) => Some((first, value)),
+                };
+            }
+        }
+
+        result
+    }
+
+    fn match_at(&self, s: &str, index: usize) -> Option<T> {
+        let rest = &s[index..];
+        self.words.iter().find(|(w, _)| rest.starts_with(w.as_str())).map(|(_, v)| *v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::scanner::WordScanner;
+
+    const GERMAN: &[(&str, u32)] = &[("eins", 1), ("zwei", 2), ("drei", 3), ("vier", 4), ("fünf", 5)];
+
+    #[test]
+    fn test_matches() {
+        let scanner = WordScanner::new(&[("one", 1), ("eight", 8), ("1", 1)]);
+        assert_eq!(scanner.matches("xoneight1"), vec![(1, 1), (3, 8), (8, 1)]);
+        assert_eq!(scanner.matches("nothing"), vec![]);
+    }
+
+    #[test]
+    fn test_first_and_last() {
+        let scanner = WordScanner::new(&[("one", 1), ("eight", 8), ("1", 1)]);
+        assert_eq!(scanner.first_and_last("oneight"), Some((1, 8)));
+        assert_eq!(scanner.first_and_last("abc1def"), Some((1, 1)));
+        assert_eq!(scanner.first_and_last("abcdef"), None);
+    }
+
+    #[test]
+    fn test_custom_word_set() {
+        let scanner = WordScanner::from_tables(&[GERMAN, &[("5", 5)]]);
+        assert_eq!(scanner.first_and_last("xzweifünfzig"), Some((2, 5)));
+        assert_eq!(scanner.matches("fünfeins5"), vec![(0, 5), (5, 1), (9, 5)]);
+    }
+}
@@ -0,0 +1,68 @@
+// Working with sequences of numbers through their difference tables, like 2023 day 9's sensor histories.
+
+// The values right before and right after a sequence, following the pattern of its differences.
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Default)]
+pub struct Extrapolation {
+    pub previous: isize,
+    pub next: isize,
+}
+
+// Extrapolates the sequence in both directions at once.
+pub fn extrapolate(sequence: &[isize]) -> Extrapolation {
+    // To get the next value, we need to compute sub-lists based on the differences between the numbers.
+    // We repeat that until the differences list is all zeroes, from which we can calculate back by
+    // adding a 0, which means adding the same number to the previous list, which mean adding that to
+    // the one before that, etc.
+    // 10 13 16 21 30 45 *68
+    //   3  3  5  9  15 *23
+    //     0  2  4  6  *8
+    //       2  2  2  *2
+    //         0  0  *0
+    // That means the next value is just the sum of the last values of every list. Going back works the same, but
+    // subtracting instead: 5 = 10 - (3 - (0 - (2 - 0))), so the previous value is the alternating sum of the first values.
+    // Since we only need the first and last values, we can compute the differences in place in a single list, which
+    // gets one shorter for every level.
+    // Sequences that run out of values before their differences are all zeroes are extrapolated as if the last
+    // difference stays the same.
+
+    let mut values = sequence.to_vec();
+    let mut length = values.len();
+    let mut result = Extrapolation::default();
+    let mut sign = 1;
+
+    while length > 0 && values[..length].iter().any(|v| 0.ne(v)) {
+        result.next += values[length - 1];
+        result.previous += sign * values[0];
+        sign = -sign;
+
+        for i in 0..length - 1 {
+            values[i] = values[i + 1] - values[i];
+        }
+        length -= 1;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::sequence::{extrapolate, Extrapolation};
+
+    fn extrapolation(previous: isize, next: isize) -> Extrapolation {
+        Extrapolation { previous, next }
+    }
+
+    #[test]
+    fn test_extrapolate() {
+        assert_eq!(extrapolate(&[0, 3, 6, 9, 12, 15]), extrapolation(-3, 18));
+        assert_eq!(extrapolate(&[0, -3, -6, -9, -12, -15]), extrapolation(3, -18));
+        assert_eq!(extrapolate(&[1, 3, 6, 10, 15, 21]), extrapolation(0, 28));
+        assert_eq!(extrapolate(&[10, 13, 16, 21, 30, 45]), extrapolation(5, 68));
+
+        // Constant and single value lists, and lists that never reach all zeroes before running out of values
+        assert_eq!(extrapolate(&[7, 7, 7]), extrapolation(7, 7));
+        assert_eq!(extrapolate(&[4]), extrapolation(4, 4));
+        assert_eq!(extrapolate(&[1, 2, 4]), extrapolation(1, 7));
+        assert_eq!(extrapolate(&[]), extrapolation(0, 0));
+    }
+}
@@ -0,0 +1,47 @@
+// Helpers for 'run this N times, what does it look like after?' style puzzles (day 14's spin cycles, day 21's steps).
+// N is usually way too large to simulate, but the state tends to start looping at some point. Once we see a state
+// again, we know where in the loop we'd end up after N steps. (A state that alternates between two states, e.g. the
+// reachable plots flipping parity every step, is just a loop of length 2.)
+
+use crate::util::cycles::{Cycle, find_cycle_within, state_at};
+
+// Runs `step` on the initial state `steps` times (skipping ahead once a loop is found), and returns the measurement of
+// the resulting state. The key is used to recognize states we've already seen.
+pub fn measure_after_steps<S, K, M>(initial: S, steps: usize, step: impl Fn(&S) -> S, key: impl Fn(&S) -> K, measure: impl Fn(&S) -> M) -> M
+    where S: Clone, K: Eq {
+    let cycle = find_cycle_within(initial.clone(), steps, &step, key).unwrap_or(Cycle::new(steps, 1));
+    measure(&state_at(initial, steps, step, &cycle))
+}
+
+// Same as the above for states made up of cells (like a platform or a garden), counting the cells matching the
+// predicate after the given number of steps. The cells (in a fixed order) are used to recognize states.
+pub fn count_after_steps<S, T>(initial: S, steps: usize, step: impl Fn(&S) -> S, cells: impl Fn(&S) -> Vec<T>, predicate: impl Fn(&T) -> bool) -> usize
+    where S: Clone, T: Eq {
+    measure_after_steps(initial, steps, step, &cells, |state| cells(state).iter().filter(|c| predicate(c)).count())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::simulation::{count_after_steps, measure_after_steps};
+
+    #[test]
+    fn test_measure_after_steps() {
+        // Loops 3 -> 4 -> 5 -> 6 -> 3 -> ... after the first two steps
+        let step = |v: &usize| if *v == 6 { 3 } else { v + 1 };
+
+        assert_eq!(measure_after_steps(1, 0, step, |v| *v, |v| *v), 1);
+        assert_eq!(measure_after_steps(1, 3, step, |v| *v, |v| *v), 4);
+        assert_eq!(measure_after_steps(1, 6, step, |v| *v, |v| *v), 3);
+        assert_eq!(measure_after_steps(1, 1_000_000_000, step, |v| *v, |v| *v * 10), 50);
+    }
+
+    #[test]
+    fn test_count_after_steps() {
+        // A light moving to the right through 4 cells, wrapping around at the end
+        let step = |cells: &Vec<bool>| { let mut next = cells.clone(); next.rotate_right(1); next };
+        let lit = |cell: &bool| *cell;
+
+        assert_eq!(count_after_steps(vec![true, false, false, false], 1_000_000_000, step, |c| c.clone(), lit), 1);
+        assert_eq!(count_after_steps(vec![true, true, false, false], 7, step, |c| c.clone(), |c| !c), 2);
+    }
+}
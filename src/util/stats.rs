@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct DurationStats {
+    pub runs: usize,
+    pub min: Duration,
+    pub median: Duration,
+    pub mean: Duration,
+}
+
+impl DurationStats {
+    // Returns None for an empty list of samples, as there's nothing sensible to report then.
+    pub fn from_samples(samples: &[Duration]) -> Option<DurationStats> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+
+        let runs = sorted.len();
+        let median = if runs.is_multiple_of(2) {
+            (sorted[runs / 2 - 1] + sorted[runs / 2]) / 2
+        } else {
+            sorted[runs / 2]
+        };
+        let mean = sorted.iter().sum::<Duration>() / runs as u32;
+
+        Some(DurationStats { runs, min: sorted[0], median, mean })
+    }
+}
+
+// Formats a number of bytes using binary units, e.g. 1536 => "1.5 KiB".
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1024f64;
+    let mut unit = 0;
+    while value >= 1024f64 && unit < UNITS.len() - 1 {
+        value /= 1024f64;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::util::stats::{format_bytes, DurationStats};
+
+    #[test]
+    fn test_from_samples() {
+        assert_eq!(DurationStats::from_samples(&[]), None);
+
+        let odd = [5, 1, 3].map(Duration::from_millis);
+        assert_eq!(DurationStats::from_samples(&odd), Some(DurationStats {
+            runs: 3,
+            min: Duration::from_millis(1),
+            median: Duration::from_millis(3),
+            mean: Duration::from_millis(3),
+        }));
+
+        let even = [10, 2, 4, 8].map(Duration::from_millis);
+        assert_eq!(DurationStats::from_samples(&even), Some(DurationStats {
+            runs: 4,
+            min: Duration::from_millis(2),
+            median: Duration::from_millis(6),
+            mean: Duration::from_millis(6),
+        }));
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
@@ -0,0 +1,178 @@
+// Submitting answers to adventofcode.com (`cargo run day <n> --submit <part>`). The request is made with curl, logged in
+// with the session cookie of the site: set AOC_SESSION, or put it in a .aoc-session file in the working directory (which
+// is ignored by git). The site rate-limits wrong answers, so the runner always asks before submitting.
+
+use std::env;
+use std::fmt::{Display, Formatter};
+use std::fs::read_to_string;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+pub const YEAR: u32 = 2023;
+pub const SESSION_ENV: &str = "AOC_SESSION";
+pub const SESSION_FILE: &str = ".aoc-session";
+// The site asks automated requests to say where they come from.
+const USER_AGENT: &str = "github.com/fvanderveen/advent-of-code-2023";
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum Outcome {
+    Correct,
+    TooHigh,
+    TooLow,
+    // Wrong, without a hint which way.
+    Incorrect,
+    // Answered too recently, with how long to wait (e.g. "4m 30s").
+    Wait(String),
+    // The part was solved before (or part 1 isn't yet, for part 2).
+    WrongLevel,
+    // Any other response, with its text.
+    Unknown(String),
+}
+
+impl Outcome {
+    // Whether the answer itself was judged, i.e. submitting it again won't tell anything new.
+    pub fn is_final(&self) -> bool {
+        matches!(self, Outcome::Correct | Outcome::TooHigh | Outcome::TooLow | Outcome::Incorrect)
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Correct => write!(f, "correct"),
+            Outcome::TooHigh => write!(f, "too high"),
+            Outcome::TooLow => write!(f, "too low"),
+            Outcome::Incorrect => write!(f, "incorrect"),
+            Outcome::Wait(time) => write!(f, "wait {}", time),
+            Outcome::WrongLevel => write!(f, "already solved"),
+            Outcome::Unknown(text) => write!(f, "unknown response: {}", text),
+        }
+    }
+}
+
+// The value to submit from the output of a part. Our answers are sentences on the first line, like "Sum of gear ratios:
+// 75312571" or "There are 3758 tiles reachable with 64 steps": the answer is the first number after the last colon, or
+// the first number in the line when there is no colon.
+pub fn extract_answer(output: &str) -> Option<String> {
+    let line = output.lines().next()?;
+    let value = line.rsplit_once(':').map(|(_, value)| value).unwrap_or(line);
+
+    let mut chars = value.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let negative = c == '-' && chars.peek().is_some_and(|(_, n)| n.is_ascii_digit());
+        if !negative && !c.is_ascii_digit() { continue; }
+
+        let mut end = start + 1;
+        while let Some((index, _)) = chars.next_if(|(_, n)| n.is_ascii_digit()) {
+            end = index + 1;
+        }
+        return Some(value[start..end].to_string());
+    }
+    None
+}
+
+// The outcome from the page the site responds with. The message is in the page's <article>.
+pub fn parse_response(html: &str) -> Outcome {
+    let article = match (html.find("<article"), html.find("</article>")) {
+        (Some(start), Some(end)) if start < end => &html[start..end],
+        _ => html,
+    };
+    let text = strip_tags(article);
+
+    if text.contains("That's the right answer") {
+        Outcome::Correct
+    } else if text.contains("You gave an answer too recently") {
+        let wait = text.split_once("You have ").and_then(|(_, rest)| rest.split_once(" left to wait")).map(|(time, _)| time.to_string());
+        Outcome::Wait(wait.unwrap_or_else(|| "a bit".to_string()))
+    } else if text.contains("your answer is too high") {
+        Outcome::TooHigh
+    } else if text.contains("your answer is too low") {
+        Outcome::TooLow
+    } else if text.contains("That's not the right answer") {
+        Outcome::Incorrect
+    } else if text.contains("You don't seem to be solving the right level") {
+        Outcome::WrongLevel
+    } else {
+        Outcome::Unknown(text)
+    }
+}
+
+// The text of the html, with whitespace collapsed.
+fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn session_token() -> Result<String, String> {
+    let token = env::var(SESSION_ENV).ok()
+        .or_else(|| read_to_string(SESSION_FILE).ok())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+    token.ok_or(format!("No session token, set {} or put it in {}", SESSION_ENV, SESSION_FILE))
+}
+
+pub fn submit(day: i32, part: u8, answer: &str, session: &str) -> Result<Outcome, String> {
+    let url = format!("https://adventofcode.com/{}/day/{}/answer", YEAR, day);
+    // The cookie header is passed through stdin, so the token doesn't show up in the process list.
+    let mut curl = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--header", "@-", "--user-agent", USER_AGENT])
+        .args(["--data-urlencode", &format!("level={}", part), "--data-urlencode", &format!("answer={}", answer), &url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Could not run curl: {}", e))?;
+
+    if let Some(mut stdin) = curl.stdin.take() {
+        writeln!(stdin, "Cookie: session={}", session).map_err(|e| format!("Could not pass the session to curl: {}", e))?;
+    }
+    let output = curl.wait_with_output().map_err(|e| format!("Could not run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Submitting to {} failed: {}", url, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    Ok(parse_response(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::submit::{extract_answer, parse_response, Outcome};
+
+    #[test]
+    fn test_extract_answer() {
+        assert_eq!(extract_answer("Sum of gear ratios: 75312571"), Some("75312571".to_string()));
+        assert_eq!(extract_answer("North beam load after 1.000.000.000 spins: 100310"), Some("100310".to_string()));
+        assert_eq!(extract_answer("Your cards resulted in a pile of 8063216 cards."), Some("8063216".to_string()));
+        assert_eq!(extract_answer("Puzzle 2: -1087"), Some("-1087".to_string()));
+        assert_eq!(extract_answer("It takes 6738 steps.\n(Ignored loop of length 4)"), Some("6738".to_string()));
+        assert_eq!(extract_answer("There are 3758 tiles reachable with 64 steps"), Some("3758".to_string()));
+        assert_eq!(extract_answer("Stone: Hailstone { position: Point3D { x: 19, y: 13 } }, result: 47"), Some("47".to_string()));
+        assert_eq!(extract_answer("No number here"), None);
+        assert_eq!(extract_answer(""), None);
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let page = |message: &str| format!("<html><main>\n<article><p>{}</p></article>\n</main></html>", message);
+
+        assert_eq!(parse_response(&page("That's the right answer!  You are <span class=\"day-success\">one gold star</span> closer.")), Outcome::Correct);
+        assert_eq!(parse_response(&page("That's not the right answer; your answer is too high.  If you're stuck, ...")), Outcome::TooHigh);
+        assert_eq!(parse_response(&page("That's not the right answer; your answer is too low.")), Outcome::TooLow);
+        assert_eq!(parse_response(&page("That's not the right answer.  If you're stuck, ...")), Outcome::Incorrect);
+        assert_eq!(parse_response(&page("You gave an answer too recently; you have to wait after submitting an answer before trying again.  You have 4m 30s left to wait.")), Outcome::Wait("4m 30s".to_string()));
+        assert_eq!(parse_response(&page("You don't seem to be solving the right level.  Did you already complete it?")), Outcome::WrongLevel);
+        assert_eq!(parse_response("<p>Something <b>else</b></p>"), Outcome::Unknown("Something else".to_string()));
+    }
+}
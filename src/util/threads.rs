@@ -0,0 +1,12 @@
+// The number of threads the solvers that split up their work can use. wasm32-unknown-unknown has no threads (spawning
+// one panics), so there we always get a single one, and the solvers do all the work on the calling thread.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn available() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn available() -> usize {
+    1
+}
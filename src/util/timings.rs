@@ -0,0 +1,154 @@
+use std::fs::{create_dir_all, read_to_string, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde_json::{json, Map, Value};
+
+// A history of benchmark timings, one JSON line per benchmarked day, so we can see whether a refactoring actually made
+// things faster (or slower). Lives in target/, as it's only meaningful for the machine (and build) it was made on.
+
+pub const HISTORY_FILE: &str = "target/aoc-timings.jsonl";
+
+// Steps that take this much longer than before are flagged as a regression (1.2 = 20% slower).
+pub const REGRESSION_THRESHOLD: f64 = 1.2;
+
+// Steps faster than this are all noise, we don't flag them even when they double.
+const MIN_FLAGGED_MS: f64 = 0.1;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct TimingRecord {
+    pub commit: Option<String>,
+    pub timestamp: u64,
+    // Identifies the day and input the timings are for, like the keys in benchmarks.json.
+    pub key: String,
+    // The (median) duration of each step (parse, puzzle1, ...) in milliseconds.
+    pub steps: Vec<(String, f64)>,
+}
+
+impl TimingRecord {
+    pub fn new(key: &str, steps: Vec<(String, f64)>) -> TimingRecord {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        TimingRecord { commit: current_commit(), timestamp, key: key.to_string(), steps }
+    }
+
+    fn to_json(&self) -> Value {
+        let steps: Map<String, Value> = self.steps.iter().map(|(step, ms)| (step.clone(), json!(ms))).collect();
+        json!({ "commit": self.commit, "timestamp": self.timestamp, "key": self.key, "steps_ms": steps })
+    }
+
+    fn from_json(value: &Value) -> Option<TimingRecord> {
+        Some(TimingRecord {
+            commit: value.get("commit")?.as_str().map(|c| c.to_string()),
+            timestamp: value.get("timestamp")?.as_u64()?,
+            key: value.get("key")?.as_str()?.to_string(),
+            steps: value.get("steps_ms")?.as_object()?.iter()
+                .filter_map(|(step, ms)| Some((step.clone(), ms.as_f64()?)))
+                .collect(),
+        })
+    }
+
+    pub fn step(&self, name: &str) -> Option<f64> {
+        self.steps.iter().find(|(step, _)| step == name).map(|(_, ms)| *ms)
+    }
+
+    // Describes the difference with an earlier record for every step, flagging the ones that got slower by more than
+    // the threshold. Returns the lines to show, and whether any step regressed.
+    pub fn compare(&self, previous: &TimingRecord, threshold: f64) -> (Vec<String>, bool) {
+        let mut regressed = false;
+        let lines = self.steps.iter().map(|(step, ms)| {
+            match previous.step(step) {
+                Some(previous_ms) => {
+                    let delta = ms - previous_ms;
+                    let percentage = if previous_ms > 0.0 { delta / previous_ms * 100.0 } else { 0.0 };
+                    let is_regression = *ms > MIN_FLAGGED_MS && *ms > previous_ms * threshold;
+                    regressed |= is_regression;
+                    format!(
+                        "{}: {:.3}ms -> {:.3}ms ({:+.3}ms, {:+.1}%){}",
+                        step, previous_ms, ms, delta, percentage, if is_regression { " REGRESSION" } else { "" }
+                    )
+                }
+                None => format!("{}: {:.3}ms (not recorded before)", step, ms)
+            }
+        }).collect();
+        (lines, regressed)
+    }
+}
+
+// The commit we're benchmarking, if we're in a git repository (and git is available).
+pub fn current_commit() -> Option<String> {
+    let output = Command::new("git").args(["rev-parse", "--short", "HEAD"]).output().ok()?;
+    if !output.status.success() { return None; }
+    let commit = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    // Timings of uncommitted changes are not really for that commit.
+    let dirty = Command::new("git").args(["status", "--porcelain", "--untracked-files=no"]).output().ok()
+        .is_some_and(|o| !o.stdout.is_empty());
+    Some(if dirty { format!("{}-dirty", commit) } else { commit })
+}
+
+// The last record in the history for the given key, skipping any lines we can't read.
+pub fn last_record(path: &str, key: &str) -> Option<TimingRecord> {
+    read_to_string(path).ok()?
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter_map(|value| TimingRecord::from_json(&value))
+        .rfind(|record| record.key == key)
+}
+
+pub fn append_record(path: &str, record: &TimingRecord) -> Result<(), String> {
+    if let Some(dir) = Path::new(path).parent() {
+        create_dir_all(dir).map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)
+        .map_err(|e| format!("Could not open {}: {}", path, e))?;
+    writeln!(file, "{}", record.to_json()).map_err(|e| format!("Could not write {}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::timings::{append_record, last_record, TimingRecord, REGRESSION_THRESHOLD};
+
+    fn record(key: &str, steps: &[(&str, f64)]) -> TimingRecord {
+        TimingRecord {
+            commit: Some("abc1234".to_string()),
+            timestamp: 1702000000,
+            key: key.to_string(),
+            steps: steps.iter().map(|(step, ms)| (step.to_string(), *ms)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let previous = record("day05", &[("parse", 0.01), ("puzzle1", 10.0), ("puzzle2", 100.0)]);
+        let current = record("day05", &[("parse", 0.05), ("puzzle1", 11.0), ("puzzle2", 150.0), ("puzzle3", 1.0)]);
+
+        let (lines, regressed) = current.compare(&previous, REGRESSION_THRESHOLD);
+        assert!(regressed);
+        assert_eq!(lines, vec![
+            "parse: 0.010ms -> 0.050ms (+0.040ms, +400.0%)",
+            "puzzle1: 10.000ms -> 11.000ms (+1.000ms, +10.0%)",
+            "puzzle2: 100.000ms -> 150.000ms (+50.000ms, +50.0%) REGRESSION",
+            "puzzle3: 1.000ms (not recorded before)",
+        ]);
+
+        let (_, regressed) = previous.compare(&current, REGRESSION_THRESHOLD);
+        assert!(!regressed);
+    }
+
+    #[test]
+    fn test_history() {
+        let path = std::env::temp_dir().join(format!("aoc-timings-test-{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        assert_eq!(last_record(path, "day05"), None);
+        append_record(path, &record("day05", &[("puzzle1", 10.0)])).unwrap();
+        append_record(path, &record("day06", &[("puzzle1", 1.0)])).unwrap();
+        append_record(path, &record("day05", &[("puzzle1", 12.5)])).unwrap();
+
+        assert_eq!(last_record(path, "day05"), Some(record("day05", &[("puzzle1", 12.5)])));
+        assert_eq!(last_record(path, "day06"), Some(record("day06", &[("puzzle1", 1.0)])));
+        assert_eq!(last_record(path, "day07"), None);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
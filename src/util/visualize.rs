@@ -0,0 +1,34 @@
+// Terminal animation of intermediate (grid) states, enabled by --visualize. Days opt in by implementing Visualizer for
+// their state, and calling show() at the interesting moments. Without --visualize, show() does nothing.
+
+use std::io::{stdout, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread::sleep;
+use std::time::Duration;
+
+pub trait Visualizer {
+    fn render_frame(&self) -> String;
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static FRAME_DELAY_MS: AtomicU64 = AtomicU64::new(DEFAULT_FRAME_DELAY_MS);
+
+pub const DEFAULT_FRAME_DELAY_MS: u64 = 50;
+
+pub fn enable(frame_delay: Duration) {
+    FRAME_DELAY_MS.store(frame_delay.as_millis() as u64, Ordering::Relaxed);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// Clears the terminal, draws the frame with the given title, and waits for the frame delay.
+pub fn show(title: &str, state: &impl Visualizer) {
+    if !is_enabled() { return; }
+
+    print!("\x1b[2J\x1b[H{}\n{}\n", title, state.render_frame());
+    let _ = stdout().flush();
+    sleep(Duration::from_millis(FRAME_DELAY_MS.load(Ordering::Relaxed)));
+}
@@ -0,0 +1,59 @@
+// Runs every day against its real input (resources/dayXX.txt) through the library API, so that a refactoring that
+// breaks (or changes the answers of) any day fails `cargo test`. Days without an input are skipped, as not everyone
+// has all of them (a new day starts out with an empty input), and so are parts that are not implemented yet. When
+// answers.json has answers for a day (see ANSWERS_FILE), the answers have to match as well.
+
+use std::fs::read_to_string;
+use serde_json::{Map, Value};
+use aoc2023::solve_both;
+use aoc2023::util::answers::ANSWERS_FILE;
+use aoc2023::util::error::AocError;
+
+fn check_day(day: u8) {
+    let path = format!("resources/day{:02}.txt", day);
+    let input = match read_to_string(&path) {
+        Ok(input) if !input.trim().is_empty() => input,
+        _ => {
+            eprintln!("Skipping day {}, there is no input in {}", day, path);
+            return;
+        }
+    };
+    let answers = read_to_string(ANSWERS_FILE).ok()
+        .map(|s| serde_json::from_str::<Map<String, Value>>(&s).unwrap_or_else(|e| panic!("Could not read {}: {}", ANSWERS_FILE, e)))
+        .unwrap_or_default();
+    let expected = answers.get(&format!("day{:02}", day));
+
+    let (part1, part2) = solve_both(day, &input).unwrap_or_else(|e| panic!("Day {} failed to parse: {}", day, e));
+    for (part, answer) in [(1, part1), (2, part2)] {
+        let answer = match answer {
+            Ok(answer) => answer,
+            Err(AocError::Unsupported(message)) => {
+                eprintln!("Skipping day {} part {}: {}", day, part, message);
+                continue;
+            }
+            Err(e) => panic!("Day {} part {} failed: {}", day, part, e),
+        };
+        if let Some(expected) = expected.and_then(|a| a.get(format!("puzzle{}", part))).and_then(|a| a.as_str()) {
+            assert_eq!(answer, expected, "Day {} part {} has a different answer than recorded in {}", day, part, ANSWERS_FILE);
+        }
+    }
+}
+
+// A test per day, so they run in parallel and a failure points at the day right away.
+macro_rules! real_input_tests {
+    ($($name:ident: $day:expr),* $(,)?) => {
+        $(
+            #[test]
+            fn $name() {
+                check_day($day);
+            }
+        )*
+    };
+}
+
+real_input_tests! {
+    day01: 1, day02: 2, day03: 3, day04: 4, day05: 5, day06: 6, day07: 7, day08: 8, day09: 9, day10: 10,
+    day11: 11, day12: 12, day13: 13, day14: 14, day15: 15, day16: 16, day17: 17, day18: 18, day19: 19, day20: 20,
+    day21: 21, day22: 22, day23: 23, day24: 24, day25: 25,
+    // « add day test »
+}